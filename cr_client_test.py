@@ -12,10 +12,15 @@ proc = subprocess.Popen(
     bufsize=1,
 )
 
-def send(cmd: str):
-    """Send a command and read one JSON line back, skipping debug lines."""
-    print(">>>", cmd)
-    proc.stdin.write(cmd + "\n")
+next_id = 0
+
+def call(method: str, params=None):
+    """Send a JSON-RPC request and read its response, skipping debug lines."""
+    global next_id
+    next_id += 1
+    request = json.dumps({"jsonrpc": "2.0", "id": next_id, "method": method, "params": params or {}})
+    print(">>>", request)
+    proc.stdin.write(request + "\n")
     proc.stdin.flush()
 
     while True:
@@ -30,25 +35,33 @@ def send(cmd: str):
             print("SERVER:", line, file=sys.stderr)
             continue
 
-        # JSON state line
+        # JSON response line
         print("<<<", line[:120] + ("..." if len(line) > 120 else ""))
-        return json.loads(line)
+        response = json.loads(line)
+        if "error" in response:
+            raise RuntimeError(response["error"]["message"])
+        return response["result"]
+
+def play(slot: int, tile: int):
+    return {"actions": [{"player": "Player1", "action": {"Play": {"slot": slot, "tile": tile}}}]}
 
 # ---- Test sequence ----
 
+print("Protocol:", call("version")["protocol"])
+
 # Reset game
-state = send("RESET 0")
+state = call("reset", {"seed": 0})
 print("Ally elixir:", state["ally_elixir"])
 print("Ally towers:", state["ally_towers"])
 print("time_left:", state["time_left"])
 
 # Take a couple of dummy steps
-state = send("STEP 0 10")
-print("after STEP 0 10, time_left:", state["time_left"])
+state = call("step", play(0, 10))
+print("after step (0, 10), time_left:", state["time_left"])
 
-state = send("STEP 1 20")
-print("after STEP 1 20, time_left:", state["time_left"])
+state = call("step", play(1, 20))
+print("after step (1, 20), time_left:", state["time_left"])
 
 # Exit
-send("EXIT")
-proc.wait()
\ No newline at end of file
+call("exit")
+proc.wait()
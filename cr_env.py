@@ -28,10 +28,24 @@ def _read_json_line(proc):
         else:
             print("SERVER:", line, file=sys.stderr)
 
-def _send(proc, cmd: str):
-    proc.stdin.write(cmd + "\n")
+_next_id = 0
+
+def _call(proc, method: str, params=None):
+    """One JSON-RPC request (see engine/src/rpc.rs); returns its result."""
+    global _next_id
+    _next_id += 1
+    request = {"jsonrpc": "2.0", "id": _next_id, "method": method, "params": params or {}}
+    proc.stdin.write(json.dumps(request) + "\n")
     proc.stdin.flush()
-    return _read_json_line(proc)
+    response = _read_json_line(proc)
+    if "error" in response:
+        raise RuntimeError(f"{method} failed ({response['error']['code']}): {response['error']['message']}")
+    return response["result"]
+
+def _space_size(state):
+    """Number of flat action ids: the no-op, then every hand slot's tiles."""
+    legal = state["legal"]
+    return 1 + len(legal["cards"]) * legal["grid_w"] * legal["grid_h"]
 
 class CRSimEnv(gym.Env):
     metadata = {"render_modes": []}
@@ -39,9 +53,14 @@ class CRSimEnv(gym.Env):
     def __init__(self, render_mode=None):
         super().__init__()
         self.render_mode = render_mode
+        self.proc = _start_server()
+        self.last_state = None
 
-        # 8 hand slots, 16x9 tile grid (same as LegalMasks placeholder)
-        self.action_space = spaces.MultiDiscrete([8, 16 * 9])
+        # Flat action ids (AgentAction in engine/src/action.rs): 0 passes,
+        # 1 + slot * tiles + tile plays, sized from the match's placement
+        # grid. Tiles are in the acting player's frame; the server
+        # un-mirrors Player 2's indices (Arena::pov_tile_to_world).
+        self.action_space = spaces.Discrete(_space_size(_call(self.proc, "state")))
 
         # Observation: [ally_elixir, time_left,
         #               3 ally tower hp, 3 enemy tower hp]  => 8 floats
@@ -49,9 +68,6 @@ class CRSimEnv(gym.Env):
             low=0.0, high=1e4, shape=(8,), dtype=np.float32
         )
 
-        self.proc = _start_server()
-        self.last_state = None
-
     def _obs_from_state(self, s):
         ally_elixir = s["ally_elixir"]
         time_left = s["time_left"]
@@ -72,31 +88,42 @@ class CRSimEnv(gym.Env):
     def reset(self, *, seed=None, options=None):
         if seed is None:
             seed = 0
-        s = _send(self.proc, f"RESET {seed}")
+        s = _call(self.proc, "reset", {"seed": seed})
         self.last_state = s
         obs = self._obs_from_state(s)
         info = {}
         return obs, info
 
     def step(self, action):
-        card_idx, tile_idx = int(action[0]), int(action[1])
-        s = _send(self.proc, f"STEP {card_idx} {tile_idx}")
+        prev_crowns = len(self.last_state.get("crowns", [])) if self.last_state else 0
+        s = _call(self.proc, "step", {"actions": [{"player": "Player1", "action_id": int(action)}]})
         self.last_state = s
         obs = self._obs_from_state(s)
 
-        # Very basic reward: damage dealt - 0.5 * damage taken (per tick)
-        r = float(s["enemy_tower_hp_drop"] - 0.5 * s["ally_tower_hp_drop"])
+        # The server's reward for the step (weights set with "set_reward")
+        r = float(s.get("reward", 0.0))
+        damage_dealt = float(s["enemy_tower_hp_drop"])
+        damage_taken = float(s["ally_tower_hp_drop"])
 
         terminated = bool(s["win"] or s["lose"])
         truncated = False  # you could also truncate when time_left == 0
-        info = {}
+        # Crowns taken since the last step (tick, tower, and the card that
+        # landed the last hit), for crediting the plays behind each one
+        info = {
+            "reward_breakdown": {
+                "damage_dealt": damage_dealt,
+                "damage_taken": damage_taken,
+                "crowns": s.get("crowns", [])[prev_crowns:],
+            }
+        }
+        if "result" in s:
+            info["match_stats"] = s["result"]
         return obs, r, terminated, truncated, info
 
     def close(self):
         if self.proc is not None:
             try:
-                self.proc.stdin.write("EXIT\n")
-                self.proc.stdin.flush()
+                _call(self.proc, "exit")
                 self.proc.wait(timeout=1)
             except Exception:
                 pass
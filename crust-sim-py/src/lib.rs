@@ -0,0 +1,460 @@
+//! Python bindings: a gym-style environment running the engine in-process,
+//! so training loops don't need the `crust_sim_server` subprocess.
+//!
+//! ```python
+//! import crust_sim
+//!
+//! env = crust_sim.Env(frame_skip=30)
+//! obs, info = env.reset(seed=7)
+//! obs, reward, terminated, truncated, info = env.step(0)
+//! ```
+//!
+//! Matches start like the server's `reset` (see `engine::rpc`). Actions are
+//! flat agent action ids (see [`AgentAction`]); `info["action_mask"]` marks
+//! the legal ones: plays of a card in hand the POV can afford, on a tile that
+//! card may be placed on, plus the always-legal no-op. Observations are the engine's observation grid as a
+//! `float32` array of shape `observation_shape`, handed to numpy without a
+//! copy. Rewards come from `engine::reward`, weighted by a `reward` dict of
+//! `RewardConfig` fields (`Env(reward={"win": 1.0, "elixir_leak": 0.1})`;
+//! fields left out keep their defaults).
+//!
+//! `VecEnv` runs many matches at once (see [`BatchSim`]), with everything
+//! stacked along a leading environment axis.
+//!
+//! With `two_v_two=True` matches are 2v2, and `pov` may also be one of the
+//! teammates, `"Player3"` or `"Player4"`; self-play's opponent is always
+//! the other team's lead.
+
+use engine::arena::PlacementGrid;
+use engine::observation::OBS_SHAPE;
+use engine::rpc::Session;
+use engine::state::{step_with_agent_actions, GameState};
+use engine::{AgentAction, BatchSim, RewardConfig, RewardTracker};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArray3, PyArray4, PyArrayMethods};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use shared::{Error, PlayerId};
+
+fn to_py_err(error: Error) -> PyErr {
+    match error {
+        Error::InvalidAction(_) | Error::Configuration(_) => PyValueError::new_err(error.to_string()),
+        Error::Serialization(_) | Error::Internal(_) => PyRuntimeError::new_err(error.to_string()),
+    }
+}
+
+fn parse_player(name: &str) -> PyResult<PlayerId> {
+    match name {
+        "Player1" => Ok(PlayerId::Player1),
+        "Player2" => Ok(PlayerId::Player2),
+        "Player3" => Ok(PlayerId::Player3),
+        "Player4" => Ok(PlayerId::Player4),
+        _ => Err(PyValueError::new_err(format!("Unknown player: {}", name))),
+    }
+}
+
+/// Errors unless `pov` plays in `game` (Player3 and Player4 only do in 2v2).
+fn check_seat(game: &GameState, pov: PlayerId) -> PyResult<()> {
+    if !game.players.contains_key(&pov) {
+        return Err(PyValueError::new_err(format!("{:?} only plays in 2v2 matches (two_v_two=True)", pov)));
+    }
+    Ok(())
+}
+
+/// Reward weights from a dict of `RewardConfig` fields (the defaults for
+/// `None`).
+fn reward_config(py: Python<'_>, weights: Option<&Bound<'_, PyAny>>) -> PyResult<RewardConfig> {
+    let Some(weights) = weights else {
+        return Ok(RewardConfig::default());
+    };
+    let json: String = py.import("json")?.call_method1("dumps", (weights,))?.extract()?;
+    serde_json::from_str(&json).map_err(|e| PyValueError::new_err(format!("Invalid reward weights: {}", e)))
+}
+
+/// The match behind an [`Env`], free of Python types so it can be tested
+/// without an interpreter.
+struct Episode {
+    session: Session,
+    pov: PlayerId,
+    frame_skip: Option<u32>,
+    reward: RewardTracker,
+}
+
+impl Episode {
+    fn reset(&mut self, seed: u64) -> shared::Result<()> {
+        self.session.reset(seed)?;
+        let game = self.session.game_mut();
+        game.set_headless(true);
+        if let Some(ticks) = self.frame_skip {
+            game.set_frame_skip(ticks);
+        }
+        self.reward.reset(game);
+        Ok(())
+    }
+
+    /// Plays `action` (and the opponent's, for self-play) and runs one
+    /// agent step. Returns the reward.
+    fn step(&mut self, action: usize, opponent: Option<usize>) -> shared::Result<f32> {
+        let grid = self.session.game().placement_grid;
+        let mut actions = vec![(self.pov, AgentAction::decode(action, grid)?)];
+        if let Some(id) = opponent {
+            actions.push((self.pov.opponent(), AgentAction::decode(id, grid)?));
+        }
+        step_with_agent_actions(self.session.game_mut(), &actions)?;
+
+        Ok(self.reward.step(self.session.game()))
+    }
+
+    fn action_mask(&self) -> Vec<bool> {
+        self.session.game().export_cr_state(self.pov).legal.action_mask()
+    }
+}
+
+/// A gym-style environment over one match, played from `pov`'s side.
+#[pyclass(unsendable)]
+struct Env {
+    episode: Episode,
+}
+
+#[pymethods]
+impl Env {
+    /// `frame_skip` sets the engine ticks per step (the engine default
+    /// otherwise); `reward` the reward weights; `cards`/`towers` are JSON
+    /// files to build matches from instead of the built-in cards;
+    /// `two_v_two` plays 2v2 matches.
+    #[new]
+    #[pyo3(signature = (pov = "Player1", frame_skip = None, reward = None, cards = None, towers = None, two_v_two = false))]
+    fn new(
+        py: Python<'_>,
+        pov: &str,
+        frame_skip: Option<u32>,
+        reward: Option<&Bound<'_, PyAny>>,
+        cards: Option<&str>,
+        towers: Option<&str>,
+        two_v_two: bool,
+    ) -> PyResult<Self> {
+        let mut session = Session::new();
+        if let Some(cards) = cards {
+            session.reload(cards, towers).map_err(to_py_err)?;
+        }
+        session.set_two_v_two(two_v_two);
+        session.reset(0).map_err(to_py_err)?;
+        let pov = parse_player(pov)?;
+        check_seat(session.game(), pov)?;
+        let reward = RewardTracker::new(reward_config(py, reward)?.build(), pov, session.game());
+        let mut episode = Episode {
+            session,
+            pov,
+            frame_skip,
+            reward,
+        };
+        episode.reset(0).map_err(to_py_err)?;
+        Ok(Self { episode })
+    }
+
+    /// Starts a new match. Returns `(observation, info)`.
+    #[pyo3(signature = (seed = 0))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: u64) -> PyResult<(Bound<'py, PyArray3<f32>>, Bound<'py, PyDict>)> {
+        self.episode.reset(seed).map_err(to_py_err)?;
+        Ok((self.observation(py)?, self.info(py)?))
+    }
+
+    /// Plays action id `action` (0 waits) and runs one step; in self-play,
+    /// `opponent_action` is the other side's id, from its own frame.
+    /// Returns `(observation, reward, terminated, truncated, info)`.
+    #[pyo3(signature = (action, opponent_action = None))]
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: usize,
+        opponent_action: Option<usize>,
+    ) -> PyResult<(Bound<'py, PyArray3<f32>>, f32, bool, bool, Bound<'py, PyDict>)> {
+        let reward = self.episode.step(action, opponent_action).map_err(to_py_err)?;
+        let terminated = self.episode.session.game().is_match_over();
+        Ok((self.observation(py)?, reward, terminated, false, self.info(py)?))
+    }
+
+    /// Replaces the reward weights from the next step on.
+    fn set_reward(&mut self, py: Python<'_>, weights: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.episode.reward.set_reward(reward_config(py, Some(weights))?.build());
+        Ok(())
+    }
+
+    /// The observation grid from `pov`'s side, shaped `observation_shape`.
+    fn observation<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray3<f32>>> {
+        let grid = self.episode.session.game().export_observation_grid(self.episode.pov);
+        // The Vec's buffer becomes the array's; reshaping it is a view
+        grid.into_pyarray(py).reshape(OBS_SHAPE)
+    }
+
+    /// The full exported state (`shared::CRState`) from `pov`'s side, as a
+    /// dict.
+    fn state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let state = self.episode.session.game().export_cr_state(self.episode.pov);
+        let json = serde_json::to_string(&state).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        py.import("json")?.call_method1("loads", (json,))
+    }
+
+    /// The finished match (`engine::MatchResult`) as a dict, or `None`
+    /// while it's running.
+    fn result<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let Some(result) = self.episode.session.game().result() else {
+            return Ok(py.None().into_bound(py));
+        };
+        let json = serde_json::to_string(&result).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        py.import("json")?.call_method1("loads", (json,))
+    }
+
+    /// Number of action ids.
+    #[getter]
+    fn action_space_size(&self) -> usize {
+        AgentAction::space_size(self.episode.session.game().placement_grid)
+    }
+
+    /// `(channels, height, width)` of observations.
+    #[getter]
+    fn observation_shape(&self) -> (usize, usize, usize) {
+        (OBS_SHAPE[0], OBS_SHAPE[1], OBS_SHAPE[2])
+    }
+
+    /// Engine ticks each step runs.
+    #[getter]
+    fn frame_skip(&self) -> u32 {
+        self.episode.session.game().frame_skip
+    }
+}
+
+impl Env {
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let game = self.episode.session.game();
+        let info = PyDict::new(py);
+        info.set_item("tick", game.tick)?;
+        info.set_item("match_time", game.match_time)?;
+        info.set_item("crowns", game.crowns_against(self.episode.pov.opponent()))?;
+        info.set_item("crowns_lost", game.crowns_against(self.episode.pov))?;
+        info.set_item("action_mask", self.episode.action_mask().into_pyarray(py))?;
+        Ok(info)
+    }
+}
+
+fn decode_actions(ids: &[usize], grid: PlacementGrid) -> PyResult<Vec<AgentAction>> {
+    ids.iter()
+        .map(|&id| AgentAction::decode(id, grid))
+        .collect::<shared::Result<_>>()
+        .map_err(to_py_err)
+}
+
+/// `num_envs` matches stepped together, for vectorized training. Matches
+/// that end restart on their own (with fresh seeds), so every step returns
+/// `num_envs` live environments; `terminated` marks the restarted ones.
+#[pyclass(unsendable)]
+struct VecEnv {
+    sim: BatchSim,
+    grid: PlacementGrid,
+}
+
+#[pymethods]
+impl VecEnv {
+    /// Environment `i` starts from seed `seed + i`. `threads` worker
+    /// threads share each step; the other arguments are as for `Env`.
+    #[new]
+    #[pyo3(signature = (num_envs, seed = 0, pov = "Player1", frame_skip = None, threads = 1, reward = None, cards = None, towers = None, two_v_two = false))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        py: Python<'_>,
+        num_envs: usize,
+        seed: u64,
+        pov: &str,
+        frame_skip: Option<u32>,
+        threads: usize,
+        reward: Option<&Bound<'_, PyAny>>,
+        cards: Option<&str>,
+        towers: Option<&str>,
+        two_v_two: bool,
+    ) -> PyResult<Self> {
+        if num_envs == 0 {
+            return Err(PyValueError::new_err("num_envs must be at least 1"));
+        }
+        let mut session = Session::new();
+        if let Some(cards) = cards {
+            session.reload(cards, towers).map_err(to_py_err)?;
+        }
+        session.set_two_v_two(two_v_two);
+        let pov = parse_player(pov)?;
+        check_seat(&session.new_match(seed).map_err(to_py_err)?, pov)?;
+        let seeds: Vec<u64> = (0..num_envs as u64).map(|i| seed.wrapping_add(i)).collect();
+        let mut sim = BatchSim::new(&seeds, pov, move |seed| {
+            let mut game = session.new_match(seed)?;
+            game.set_headless(true);
+            if let Some(ticks) = frame_skip {
+                game.set_frame_skip(ticks);
+            }
+            Ok(game)
+        })
+        .map_err(to_py_err)?;
+        sim.set_threads(threads);
+        sim.set_reward(reward_config(py, reward)?.build());
+        let grid = sim.envs()[0].placement_grid;
+        Ok(Self { sim, grid })
+    }
+
+    /// Restarts every match, environment `i` from seed `seed + i`. Returns
+    /// `(observations, info)`.
+    #[pyo3(signature = (seed = 0))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: u64) -> PyResult<(Bound<'py, PyArray4<f32>>, Bound<'py, PyDict>)> {
+        let seeds: Vec<u64> = (0..self.sim.len() as u64).map(|i| seed.wrapping_add(i)).collect();
+        let observations = self.sim.reset(&seeds).map_err(to_py_err)?;
+        Ok((self.stack(py, observations)?, self.info(py)?))
+    }
+
+    /// Plays `actions[i]` (flat ids) in environment `i`, and
+    /// `opponent_actions[i]` for the other side in self-play. Returns
+    /// `(observations, rewards, terminated, truncated, info)`, each with a
+    /// leading environment axis.
+    #[pyo3(signature = (actions, opponent_actions = None))]
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        actions: Vec<usize>,
+        opponent_actions: Option<Vec<usize>>,
+    ) -> PyResult<(
+        Bound<'py, PyArray4<f32>>,
+        Bound<'py, PyArray1<f32>>,
+        Bound<'py, PyArray1<bool>>,
+        Bound<'py, PyArray1<bool>>,
+        Bound<'py, PyDict>,
+    )> {
+        let actions = decode_actions(&actions, self.grid)?;
+        let opponent_actions = opponent_actions.map(|ids| decode_actions(&ids, self.grid)).transpose()?;
+        let step = self.sim.step(&actions, opponent_actions.as_deref()).map_err(to_py_err)?;
+        let truncated = vec![false; self.sim.len()];
+        Ok((
+            self.stack(py, step.observations)?,
+            step.rewards.into_pyarray(py),
+            step.dones.into_pyarray(py),
+            truncated.into_pyarray(py),
+            self.info(py)?,
+        ))
+    }
+
+    /// Replaces the reward weights from the next step on.
+    fn set_reward(&mut self, py: Python<'_>, weights: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.sim.set_reward(reward_config(py, Some(weights))?.build());
+        Ok(())
+    }
+
+    /// Number of environments.
+    #[getter]
+    fn num_envs(&self) -> usize {
+        self.sim.len()
+    }
+
+    /// Number of action ids.
+    #[getter]
+    fn action_space_size(&self) -> usize {
+        AgentAction::space_size(self.grid)
+    }
+
+    /// `(channels, height, width)` of each environment's observation.
+    #[getter]
+    fn observation_shape(&self) -> (usize, usize, usize) {
+        (OBS_SHAPE[0], OBS_SHAPE[1], OBS_SHAPE[2])
+    }
+}
+
+impl VecEnv {
+    fn stack<'py>(&self, py: Python<'py>, observations: Vec<f32>) -> PyResult<Bound<'py, PyArray4<f32>>> {
+        observations
+            .into_pyarray(py)
+            .reshape([self.sim.len(), OBS_SHAPE[0], OBS_SHAPE[1], OBS_SHAPE[2]])
+    }
+
+    /// `tick` per environment and a `(num_envs, action_space_size)`
+    /// `action_mask`.
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let pov = self.sim.pov();
+        let ticks: Vec<u64> = self.sim.envs().iter().map(|env| env.tick).collect();
+        let masks: Vec<bool> = self
+            .sim
+            .envs()
+            .iter()
+            .flat_map(|env| env.export_cr_state(pov).legal.action_mask())
+            .collect();
+        let masks: Bound<'py, PyArray2<bool>> =
+            masks.into_pyarray(py).reshape([self.sim.len(), AgentAction::space_size(self.grid)])?;
+        let info = PyDict::new(py);
+        info.set_item("tick", ticks.into_pyarray(py))?;
+        info.set_item("action_mask", masks)?;
+        Ok(info)
+    }
+}
+
+#[pymodule]
+fn crust_sim(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Env>()?;
+    m.add_class::<VecEnv>()?;
+    m.add("PROTOCOL_VERSION", engine::rpc::PROTOCOL_VERSION)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_episode_rewards_tower_damage_per_step() {
+        let session = Session::new();
+        let reward = RewardTracker::new(RewardConfig::default().build(), PlayerId::Player2, session.game());
+        let mut episode = Episode {
+            session,
+            pov: PlayerId::Player2,
+            frame_skip: Some(10),
+            reward,
+        };
+        episode.reset(3).unwrap();
+        assert_eq!(episode.session.game().frame_skip, 10);
+        let mask = episode.action_mask();
+        assert_eq!(mask.len(), AgentAction::space_size(episode.session.game().placement_grid));
+        assert!(mask[0]);
+
+        assert_eq!(episode.step(0, Some(0)).unwrap(), 0.0);
+        assert_eq!(episode.session.game().tick, 10);
+
+        // Knock 100 HP off the opponent's King: rewarded once, then nothing
+        let king = episode
+            .session
+            .game_mut()
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player1 && e.tower_type() == Some(engine::state::TowerType::King))
+            .unwrap();
+        king.hp -= 100.0;
+        assert!(episode.step(0, None).unwrap() > 0.0);
+        assert_eq!(episode.step(0, None).unwrap(), 0.0);
+        assert!(episode.step(usize::MAX, None).is_err());
+    }
+
+    #[test]
+    fn test_teammates_train_in_2v2_only() {
+        let mut session = Session::new();
+        assert!(check_seat(session.game(), PlayerId::Player3).is_err());
+        session.set_two_v_two(true);
+        session.reset(0).unwrap();
+        check_seat(session.game(), PlayerId::Player3).unwrap();
+
+        let reward = RewardTracker::new(RewardConfig::default().build(), PlayerId::Player3, session.game());
+        let mut episode = Episode {
+            session,
+            pov: PlayerId::Player3,
+            frame_skip: None,
+            reward,
+        };
+        episode.reset(4).unwrap();
+        let play = episode.action_mask().iter().skip(1).position(|&legal| legal).unwrap() + 1;
+        episode.step(play, Some(0)).unwrap();
+        let plays = &episode.session.game().card_plays;
+        assert_eq!(plays.iter().map(|p| p.player).collect::<Vec<_>>(), [PlayerId::Player3]);
+    }
+}
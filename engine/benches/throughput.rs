@@ -0,0 +1,52 @@
+//! Throughput benchmark: exported vs. headless playouts.
+//!
+//! Run with `cargo bench -p engine --bench throughput`.
+
+use engine::{step_and_export, Action, GameState};
+use shared::{PlayerId, Position};
+use std::time::Instant;
+
+const MATCHES: u64 = 20;
+const TICKS_PER_MATCH: u64 = 60 * 60; // One minute of match time
+
+/// Plays `MATCHES` short matches and returns ticks per second.
+fn run(headless: bool) -> f64 {
+    let start = Instant::now();
+    let mut bytes = 0usize;
+
+    for seed in 0..MATCHES {
+        let mut state = GameState::new(seed);
+        state.set_headless(headless);
+
+        for tick in 0..TICKS_PER_MATCH {
+            let actions = if tick % 300 == 0 {
+                vec![Action::PlayCard {
+                    player: PlayerId::Player1,
+                    card_name: "Knight".to_string(),
+                    level: 11,
+                    position: Position::new(10.0, 9.0),
+                }]
+            } else {
+                Vec::new()
+            };
+
+            if let Some(snapshot) = step_and_export(&mut state, &actions, PlayerId::Player1).unwrap() {
+                // Serialize like the stdio server does
+                bytes += serde_json::to_string(&snapshot).unwrap().len();
+            }
+        }
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    std::hint::black_box(bytes);
+    (MATCHES * TICKS_PER_MATCH) as f64 / elapsed
+}
+
+fn main() {
+    let exported = run(false);
+    let headless = run(true);
+
+    println!("exported: {:>12.0} ticks/s", exported);
+    println!("headless: {:>12.0} ticks/s", headless);
+    println!("speedup:  {:>12.2}x", headless / exported);
+}
@@ -1,8 +1,12 @@
 //! Player actions that can be applied to the game state.
 
-use crate::state::GameState;
+use crate::arena::PlacementGrid;
+use crate::card::Card;
+use crate::state::{GameState, PlayerState};
 use serde::{Deserialize, Serialize};
-use shared::{Error, PlayerId, Position, Result};
+use shared::{dims, Error, PlayerId, Position, Result};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
 
 /// Actions that players can take during the game.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,22 +20,153 @@ pub enum Action {
         position: Position,
     },
 
-    /// Play a card from the player's hand.
+    /// Play a card from the player's hand, at its level in the deck.
     /// This uses the deck/hand cycling system.
     PlayCardFromHand {
         player: PlayerId,
         hand_index: usize, // Index in hand (0-3)
-        level: u32,        // Card level to play at
         position: Position,
     },
 
+    /// Pick up a hand card without playing it, as a UI does while the
+    /// player drags or taps it. Nothing cycles and no elixir is spent; the
+    /// card stays selected until it is played or the selection is
+    /// cancelled or replaced.
+    SelectCard { player: PlayerId, hand_index: usize },
+
+    /// Put the selected card back. It keeps its hand slot and does not
+    /// cycle, as in the live game.
+    CancelSelection { player: PlayerId },
+
+    /// Play the selected card, exactly like `PlayCardFromHand` on its slot.
+    PlaySelected { player: PlayerId, position: Position },
+
+    /// Cycle the cheapest card in hand (the leftmost on ties) without
+    /// deploying it, paying its elixir cost. Only allowed in matches with
+    /// the `cycle_cheapest_card` event modifier (see
+    /// [`EventModifiers`](crate::state::EventModifiers)).
+    CycleCheapestCard { player: PlayerId },
+
     /// Emote (for replay purposes, no game effect).
     Emote { player: PlayerId, emote_id: u32 },
 }
 
+/// Flat id of the no-op action in the agent action space.
+pub const NO_OP_ACTION_ID: usize = 0;
+
+/// One choice in the flat agent action space used by RL consumers (the
+/// Python env, the server's `ACT` command, bindings).
+///
+/// Ids are laid out as `0` for [`AgentAction::NoOp`], then every hand slot's
+/// tiles in turn: `1 + slot * grid.len() + tile`, for `slot` below
+/// `dims::HAND_SLOTS` and `tile` a row-major index into the match's
+/// placement grid, in the acting player's frame (see
+/// [`Arena::pov_tile_to_world`](crate::arena::Arena::pov_tile_to_world)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgentAction {
+    /// Let the tick pass without playing.
+    NoOp,
+    /// Play the card in hand slot `slot` at placement tile `tile`.
+    Play { slot: usize, tile: usize },
+}
+
+impl AgentAction {
+    /// Number of ids in the action space over `grid`.
+    pub fn space_size(grid: PlacementGrid) -> usize {
+        1 + dims::HAND_SLOTS * grid.len()
+    }
+
+    /// The flat id for this action. Errors if the slot or tile is outside
+    /// the action space.
+    pub fn encode(&self, grid: PlacementGrid) -> Result<usize> {
+        match *self {
+            AgentAction::NoOp => Ok(NO_OP_ACTION_ID),
+            AgentAction::Play { slot, tile } => {
+                if slot >= dims::HAND_SLOTS || tile >= grid.len() {
+                    return Err(Error::InvalidAction(format!(
+                        "Slot {} / tile {} outside the {}x{} action space",
+                        slot,
+                        tile,
+                        dims::HAND_SLOTS,
+                        grid.len()
+                    )));
+                }
+                Ok(1 + slot * grid.len() + tile)
+            }
+        }
+    }
+
+    /// The action a flat id stands for. Errors if `id` is outside the
+    /// action space.
+    pub fn decode(id: usize, grid: PlacementGrid) -> Result<Self> {
+        if id == NO_OP_ACTION_ID {
+            return Ok(AgentAction::NoOp);
+        }
+        if id >= Self::space_size(grid) {
+            return Err(Error::InvalidAction(format!(
+                "Action id {} outside the action space (size {})",
+                id,
+                Self::space_size(grid)
+            )));
+        }
+        let index = id - 1;
+        Ok(AgentAction::Play {
+            slot: index / grid.len(),
+            tile: index % grid.len(),
+        })
+    }
+
+    /// The engine action `player` takes by choosing this in `state`;
+    /// `None` for the no-op.
+    pub fn to_action(&self, state: &GameState, player: PlayerId) -> Result<Option<Action>> {
+        let AgentAction::Play { slot, tile } = *self else {
+            return Ok(None);
+        };
+        let position = state
+            .arena
+            .pov_tile_to_world(player, tile, state.placement_grid)
+            .ok_or_else(|| Error::InvalidAction(format!("Invalid tile index: {}", tile)))?;
+        Ok(Some(Action::PlayCardFromHand {
+            player,
+            hand_index: slot,
+            position,
+        }))
+    }
+}
+
 impl Action {
+    /// The player taking this action.
+    pub fn player(&self) -> PlayerId {
+        match self {
+            Action::PlayCard { player, .. }
+            | Action::PlayCardFromHand { player, .. }
+            | Action::SelectCard { player, .. }
+            | Action::CancelSelection { player }
+            | Action::PlaySelected { player, .. }
+            | Action::CycleCheapestCard { player }
+            | Action::Emote { player, .. } => *player,
+        }
+    }
+
+    /// Returns true for card plays (everything that counts toward the
+    /// per-tick action cap). Selecting and cancelling only touch the hand,
+    /// so they aren't plays.
+    pub fn is_play(&self) -> bool {
+        !matches!(
+            self,
+            Action::SelectCard { .. } | Action::CancelSelection { .. } | Action::Emote { .. }
+        )
+    }
+
     /// Applies this action to the game state.
     pub(crate) fn apply(&self, state: &mut GameState) -> Result<()> {
+        apply_all(state, std::slice::from_ref(self))
+    }
+
+    /// Checks this action against `state`, taking hand, selection and
+    /// elixir changes out of `ledger` (the players touched so far this
+    /// group) rather than the match. Returns the card to deploy, if any.
+    fn resolve(&self, state: &GameState, ledger: &mut Ledger) -> Result<Option<Play>> {
         match self {
             Action::PlayCard {
                 player,
@@ -39,18 +174,18 @@ impl Action {
                 level,
                 position,
             } => {
+                // Direct plays bypass deck setup, so check strict mode here too
+                state.check_supported(std::slice::from_ref(card_name))?;
+
                 // Get the card by name (clone to avoid borrow issues)
                 let card = state
                     .get_card_by_name(card_name)
                     .ok_or_else(|| Error::InvalidAction(format!("Card '{}' not found", card_name)))?
                     .clone();
+                state.check_placement(*player, &card, *position)?;
 
                 // Check if player has enough elixir
-                let player_state = state
-                    .players
-                    .get_mut(player)
-                    .ok_or_else(|| Error::InvalidAction("Player not found".to_string()))?;
-
+                let player_state = ledger_entry(state, ledger, *player)?;
                 if !player_state.spend_elixir(card.elixir_cost) {
                     return Err(Error::InvalidAction(format!(
                         "Not enough elixir. Need {}, have {}",
@@ -58,56 +193,276 @@ impl Action {
                     )));
                 }
 
-                // Spawn the card's entities at the specified level
-                card.spawn(state, *player, *position, *level)?;
-
-                Ok(())
+                Ok(Some(Play {
+                    player: *player,
+                    card,
+                    level: *level,
+                    position: *position,
+                }))
             }
             Action::PlayCardFromHand {
                 player,
                 hand_index,
-                level,
                 position,
-            } => {
-                // Get the player's state
-                let player_state = state
-                    .players
-                    .get_mut(player)
-                    .ok_or_else(|| Error::InvalidAction("Player not found".to_string()))?;
-
-                // Get the card name from the hand and cycle it
-                let card_name = player_state
-                    .play_card_from_hand(*hand_index)
-                    .ok_or_else(|| Error::InvalidAction(format!("Invalid hand index: {}", hand_index)))?;
-
-                // Get the card definition
-                let card = state
-                    .get_card_by_name(&card_name)
-                    .ok_or_else(|| Error::InvalidAction(format!("Card '{}' not found", card_name)))?
-                    .clone();
+            } => play_from_hand(state, ledger, *player, *hand_index, *position).map(Some),
+            Action::SelectCard { player, hand_index } => {
+                let player_state = ledger_entry(state, ledger, *player)?;
+                if !player_state.select_card(*hand_index) {
+                    return Err(Error::InvalidAction(format!("Invalid hand index: {}", hand_index)));
+                }
+                Ok(None)
+            }
+            Action::CancelSelection { player } => {
+                ledger_entry(state, ledger, *player)?.cancel_selection();
+                Ok(None)
+            }
+            Action::PlaySelected { player, position } => {
+                let hand_index = ledger_entry(state, ledger, *player)?
+                    .selected
+                    .ok_or_else(|| Error::InvalidAction("No card selected".to_string()))?;
+                play_from_hand(state, ledger, *player, hand_index, *position).map(Some)
+            }
+            Action::CycleCheapestCard { player } => {
+                if !state.event_modifiers.cycle_cheapest_card {
+                    return Err(Error::InvalidAction(
+                        "Cycling without playing needs the cycle_cheapest_card event modifier".to_string(),
+                    ));
+                }
 
-                // Check if player has enough elixir
-                let player_state = state
-                    .players
-                    .get_mut(player)
-                    .ok_or_else(|| Error::InvalidAction("Player not found".to_string()))?;
+                // Cheapest card in hand, leftmost on ties
+                let player_state = ledger_entry(state, ledger, *player)?;
+                let (hand_index, cost) = (0..player_state.hand.len())
+                    .filter_map(|i| player_state.get_hand_card(i).map(|name| (i, name)))
+                    .filter_map(|(i, name)| state.get_card_by_name(name).map(|card| (i, card.elixir_cost)))
+                    .fold(None, |cheapest: Option<(usize, f32)>, (i, cost)| match cheapest {
+                        Some((_, best)) if best <= cost => cheapest,
+                        _ => Some((i, cost)),
+                    })
+                    .ok_or_else(|| Error::InvalidAction("No card in hand to cycle".to_string()))?;
 
-                if !player_state.spend_elixir(card.elixir_cost) {
+                if !player_state.spend_elixir(cost) {
                     return Err(Error::InvalidAction(format!(
                         "Not enough elixir. Need {}, have {}",
-                        card.elixir_cost, player_state.elixir
+                        cost, player_state.elixir
                     )));
                 }
-
-                // Spawn the card's entities at the specified level
-                card.spawn(state, *player, *position, *level)?;
-
-                Ok(())
+                player_state.play_card_from_hand(hand_index);
+                // The opponent sees the card leave the hand, so it counts
+                // toward their elixir estimate like a play
+                player_state.observe_play(cost);
+                Ok(None)
             }
             Action::Emote { .. } => {
                 // Emotes have no game effect
-                Ok(())
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Working copies of the players a group of actions has touched, so the
+/// group can be checked without staging a copy of the whole match.
+type Ledger = BTreeMap<PlayerId, PlayerState>;
+
+/// A card an action deploys once its whole group has checked out.
+struct Play {
+    player: PlayerId,
+    card: Card,
+    level: u32,
+    position: Position,
+}
+
+/// `player`'s working state in `ledger`, copied in from the match on first
+/// use, or an error if they aren't in it.
+fn ledger_entry<'a>(state: &GameState, ledger: &'a mut Ledger, player: PlayerId) -> Result<&'a mut PlayerState> {
+    match ledger.entry(player) {
+        Entry::Occupied(entry) => Ok(entry.into_mut()),
+        Entry::Vacant(entry) => {
+            let player_state = state
+                .players
+                .get(&player)
+                .ok_or_else(|| Error::InvalidAction("Player not found".to_string()))?;
+            Ok(entry.insert(player_state.clone()))
+        }
+    }
+}
+
+/// Checks `actions` in order, each seeing the hands and elixir the ones
+/// before it left, without touching `state`. Returns the players' updated
+/// states and the cards to deploy.
+fn resolve_all(state: &GameState, actions: &[Action]) -> Result<(Ledger, Vec<Play>)> {
+    let mut ledger = Ledger::new();
+    let mut plays = Vec::new();
+    for action in actions {
+        if let Some(play) = action.resolve(state, &mut ledger)? {
+            play.card.check_spawn(state, play.level)?;
+            plays.push(play);
+        }
+    }
+    Ok((ledger, plays))
+}
+
+/// Errors if applying `actions` as a group would.
+pub(crate) fn check_all(state: &GameState, actions: &[Action]) -> Result<()> {
+    resolve_all(state, actions).map(|_| ())
+}
+
+/// Applies `actions` as a group: every one is checked first, so a failure
+/// leaves `state` untouched.
+pub(crate) fn apply_all(state: &mut GameState, actions: &[Action]) -> Result<()> {
+    let (ledger, plays) = resolve_all(state, actions)?;
+    state.players.extend(ledger);
+    for play in plays {
+        play.card.spawn(state, play.player, play.position, play.level)?;
+        state.record_play(play.player, &play.card, play.position);
+    }
+    Ok(())
+}
+
+/// Plays the card in `player`'s hand slot `hand_index` at its deck level
+/// and cycles in the next one.
+fn play_from_hand(
+    state: &GameState,
+    ledger: &mut Ledger,
+    player: PlayerId,
+    hand_index: usize,
+    position: Position,
+) -> Result<Play> {
+    // Get the card from the hand and cycle it
+    let entry = ledger_entry(state, ledger, player)?
+        .play_card_from_hand(hand_index)
+        .ok_or_else(|| Error::InvalidAction(format!("Invalid hand index: {}", hand_index)))?;
+
+    // Get the card definition
+    let card = state
+        .get_card_by_name(&entry.name)
+        .ok_or_else(|| Error::InvalidAction(format!("Card '{}' not found", entry.name)))?
+        .clone();
+    state.check_placement(player, &card, position)?;
+
+    // Check if player has enough elixir
+    let player_state = ledger_entry(state, ledger, player)?;
+    if !player_state.spend_elixir(card.elixir_cost) {
+        return Err(Error::InvalidAction(format!(
+            "Not enough elixir. Need {}, have {}",
+            card.elixir_cost, player_state.elixir
+        )));
+    }
+
+    Ok(Play {
+        player,
+        card,
+        level: entry.level,
+        position,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_action_ids_round_trip() {
+        for grid in [PlacementGrid::default(), PlacementGrid { width: 8, height: 6 }] {
+            let size = AgentAction::space_size(grid);
+            for id in 0..size {
+                let action = AgentAction::decode(id, grid).unwrap();
+                assert_eq!(action.encode(grid).unwrap(), id);
             }
+            assert!(AgentAction::decode(size, grid).is_err());
         }
+
+        let grid = PlacementGrid::default();
+        assert_eq!(AgentAction::decode(NO_OP_ACTION_ID, grid).unwrap(), AgentAction::NoOp);
+        assert_eq!(AgentAction::Play { slot: 1, tile: 0 }.encode(grid).unwrap(), 1 + grid.len());
+        assert!(AgentAction::Play { slot: 0, tile: grid.len() }.encode(grid).is_err());
+        assert!(AgentAction::Play { slot: dims::HAND_SLOTS, tile: 0 }.encode(grid).is_err());
+
+        // Player2's tiles are in its mirrored frame
+        let state = GameState::new(1);
+        let play = AgentAction::Play { slot: 2, tile: 0 };
+        let Some(Action::PlayCardFromHand { position, hand_index, .. }) =
+            play.to_action(&state, PlayerId::Player2).unwrap()
+        else {
+            panic!("expected a hand play");
+        };
+        assert_eq!(hand_index, 2);
+        assert!(position.x > 16.0 && position.y > 9.0);
+        assert!(AgentAction::NoOp.to_action(&state, PlayerId::Player1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_played_cards_go_to_the_back_of_the_cycle() {
+        let mut player = PlayerState::new(PlayerId::Player1);
+        let deck = (0..dims::DECK_SIZE).map(|i| crate::state::DeckCard::new(format!("Card {}", i), 11)).collect();
+        player.set_deck(deck, &mut crate::rng::Rng::new(5));
+
+        let mut played = Vec::new();
+        for _ in 0..2 * dims::DECK_SIZE {
+            let next = player.next_card_index;
+            assert!(!player.hand.contains(&next), "next card {} is already in hand {:?}", next, player.hand);
+            played.push(player.hand[0]);
+            player.play_card_from_hand(0);
+            assert_eq!(player.hand[0], next);
+            let mut cards: Vec<usize> = player.hand.iter().chain(&player.cycle).copied().collect();
+            cards.sort_unstable();
+            assert_eq!(cards, (0..dims::DECK_SIZE).collect::<Vec<_>>());
+        }
+        // A card comes back once the four waiting ahead of it have been drawn
+        assert_eq!(played[5], played[0]);
+    }
+
+    #[test]
+    fn test_selecting_and_cancelling_never_cycles() {
+        let mut state = GameState::new(3);
+        let deck = ["Knight", "Archers", "Giant", "Musketeer", "Arrows", "Baby Dragon", "Fireball", "Knight"]
+            .map(String::from)
+            .to_vec();
+        state.set_player_deck(PlayerId::Player1, deck).unwrap();
+        let player = PlayerId::Player1;
+        state.players.get_mut(&player).unwrap().elixir = 10.0;
+        let hand = state.players[&player].hand.clone();
+
+        // Pick a card up and put it back: same hand, same elixir, no play
+        crate::step(&mut state, &[Action::SelectCard { player, hand_index: 1 }]).unwrap();
+        assert_eq!(state.export_cr_state(player).ally_cycle.selected, Some(1));
+        crate::step(&mut state, &[Action::CancelSelection { player }]).unwrap();
+        assert_eq!(state.players[&player].hand, hand);
+        assert_eq!(state.players[&player].selected, None);
+        assert!(state.card_plays.is_empty());
+        assert!(crate::step(&mut state, &[Action::SelectCard { player, hand_index: dims::HAND_SIZE }]).is_err());
+
+        // Committing plays the held slot and drops the selection
+        let play = Action::PlaySelected {
+            player,
+            position: Position::new(8.5, 9.5),
+        };
+        assert!(crate::step(&mut state, std::slice::from_ref(&play)).is_err());
+        crate::step(&mut state, &[Action::SelectCard { player, hand_index: 2 }]).unwrap();
+        let held = state.players[&player].get_hand_card(2).cloned().unwrap();
+        crate::step(&mut state, &[play]).unwrap();
+        assert_eq!(state.card_plays.last().unwrap().card, held);
+        assert_ne!(state.players[&player].hand[2], hand[2]);
+        assert_eq!(state.players[&player].selected, None);
+
+        // Cycling without a play is an event-only rule
+        let cycle = Action::CycleCheapestCard { player };
+        assert!(cycle.is_play());
+        assert!(crate::step(&mut state, std::slice::from_ref(&cycle)).is_err());
+        state.set_event_modifiers(crate::state::EventModifiers {
+            cycle_cheapest_card: true,
+            ..Default::default()
+        });
+        let player_state = &state.players[&player];
+        let costs: Vec<f32> = (0..player_state.hand.len())
+            .map(|i| state.get_card_by_name(player_state.get_hand_card(i).unwrap()).unwrap().elixir_cost)
+            .collect();
+        let cheapest = (0..costs.len()).fold(0, |best, i| if costs[i] < costs[best] { i } else { best });
+        let (elixir, plays, next) = (player_state.elixir, state.card_plays.len(), player_state.next_card_index);
+        crate::step(&mut state, &[cycle]).unwrap();
+        let player_state = &state.players[&player];
+        assert_eq!(player_state.hand[cheapest], next);
+        assert!((player_state.elixir - (elixir - costs[cheapest] + player_state.elixir_regen_rate * crate::DELTA_TIME)).abs() < 1e-4);
+        assert_eq!(state.card_plays.len(), plays);
     }
 }
@@ -1,7 +1,21 @@
 //! Arena geometry, tile system, and spatial utilities.
 
+use crate::state::TowerType;
 use serde::{Deserialize, Serialize};
-use shared::Position;
+use shared::{dims, Error, PlayerId, Position, Result};
+
+/// Tile columns covered by the river (between the two halves).
+pub const RIVER_TILES_X: std::ops::Range<usize> = 15..17;
+
+/// Tile rows covered by the left and right lane bridges.
+pub const BRIDGE_TILES_Y: [std::ops::Range<usize>; 2] = [2..5, 13..16];
+
+/// Tile columns past the river that a player's deploy zone reaches into a
+/// lane once the enemy princess tower guarding it has fallen.
+pub const POCKET_DEPTH_TILES: usize = 4;
+
+/// Sampling step (tiles) when checking whether a line crosses water.
+const LINE_SAMPLE_STEP: f32 = 0.25;
 
 /// The game arena containing tile layout and dimensions.
 ///
@@ -12,23 +26,36 @@ pub struct Arena {
     pub height: u32,
     pub tile_size: f32,
     pub tiles: Vec<Vec<TileType>>,
+    /// Damaging or slowing zones laid over the tiles (event arenas).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hazards: Vec<Hazard>,
 }
 
 impl Arena {
     /// Creates a default arena (32x18 tiles).
     pub fn new() -> Self {
-        let width = 32;
-        let height = 18;
+        let width = dims::ARENA_WIDTH;
+        let height = dims::ARENA_HEIGHT;
         let tile_size = 1.0;
 
-        // Initialize with grass tiles (will be configurable later)
-        let tiles = vec![vec![TileType::Grass; width as usize]; height as usize];
+        // Grass everywhere except the river, which is crossed by two bridges
+        let mut tiles = vec![vec![TileType::Grass; width as usize]; height as usize];
+        for (y, row) in tiles.iter_mut().enumerate() {
+            for x in RIVER_TILES_X {
+                row[x] = if BRIDGE_TILES_Y.iter().any(|rows| rows.contains(&y)) {
+                    TileType::Bridge
+                } else {
+                    TileType::River
+                };
+            }
+        }
 
         Self {
             width,
             height,
             tile_size,
             tiles,
+            hazards: Vec::new(),
         }
     }
 
@@ -40,6 +67,135 @@ impl Arena {
             .copied()
     }
 
+    /// Returns true if `pos` is over river water (bridges don't count).
+    pub fn is_river(&self, pos: &Position) -> bool {
+        if !self.is_in_bounds(pos) {
+            return false;
+        }
+        let (x, y) = self.world_to_tile(pos);
+        self.get_tile(x, y) == Some(TileType::River)
+    }
+
+    /// Adds a hazard to the layout. Errors if it covers no tiles, reaches
+    /// outside the arena, or pulses damage at a non-positive interval.
+    pub fn add_hazard(&mut self, hazard: Hazard) -> Result<()> {
+        let fits = hazard.width > 0
+            && hazard.height > 0
+            && hazard.x.checked_add(hazard.width).is_some_and(|end| end <= self.width)
+            && hazard.y.checked_add(hazard.height).is_some_and(|end| end <= self.height);
+        if !fits {
+            return Err(Error::Configuration(format!(
+                "Hazard {}x{} at tile ({}, {}) must lie within the arena's {}x{} tiles",
+                hazard.width, hazard.height, hazard.x, hazard.y, self.width, self.height
+            )));
+        }
+        if hazard.damage > 0.0 && hazard.interval <= 0.0 {
+            return Err(Error::Configuration(format!(
+                "Hazard damage interval must be positive, got {}",
+                hazard.interval
+            )));
+        }
+        self.hazards.push(hazard);
+        Ok(())
+    }
+
+    /// The hazards covering `pos`, in the order they were added.
+    pub fn hazards_at<'a>(&'a self, pos: &Position) -> impl Iterator<Item = &'a Hazard> + 'a {
+        let tile = self.is_in_bounds(pos).then(|| self.world_to_tile(pos));
+        self.hazards.iter().filter(move |h| tile.is_some_and(|(x, y)| h.covers(x, y)))
+    }
+
+    /// Returns true if the straight line from `a` to `b` passes over river
+    /// water, i.e. the two points are separated by the river rather than
+    /// connected along a bridge.
+    pub fn line_crosses_river(&self, a: &Position, b: &Position) -> bool {
+        let steps = (a.distance_to(b) / (LINE_SAMPLE_STEP * self.tile_size)).ceil().max(1.0) as u32;
+        (0..=steps).any(|i| self.is_river(&a.lerp(b, i as f32 / steps as f32)))
+    }
+
+    /// Returns true if a ground unit can walk the straight line from `a` to
+    /// `b`: every tile the segment touches, including both tiles at a
+    /// corner it passes exactly through, is walkable.
+    pub fn segment_is_walkable(&self, a: &Position, b: &Position) -> bool {
+        self.segment_passes(a, b, TileType::is_walkable)
+    }
+
+    /// Returns true if every tile the segment from `a` to `b` touches
+    /// satisfies `passable` (see [`segment_is_walkable`]).
+    ///
+    /// [`segment_is_walkable`]: Arena::segment_is_walkable
+    fn segment_passes(&self, a: &Position, b: &Position, passable: fn(&TileType) -> bool) -> bool {
+        let ts = self.tile_size;
+        let tile = |p: &Position| ((p.x / ts).floor() as i64, (p.y / ts).floor() as i64);
+        let walkable =
+            |(x, y): (i64, i64)| x >= 0 && y >= 0 && self.get_tile(x as u32, y as u32).is_some_and(|t| passable(&t));
+
+        // Grid traversal (Amanatides & Woo)
+        let (mut current, end) = (tile(a), tile(b));
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let (step_x, step_y) = (dx.signum() as i64, dy.signum() as i64);
+        let boundary = |i: i64, step: i64| (i + (step > 0) as i64) as f32 * ts;
+        let mut t_max_x = if dx != 0.0 { (boundary(current.0, step_x) - a.x) / dx } else { f32::INFINITY };
+        let mut t_max_y = if dy != 0.0 { (boundary(current.1, step_y) - a.y) / dy } else { f32::INFINITY };
+        let (t_delta_x, t_delta_y) = (ts / dx.abs(), ts / dy.abs());
+
+        for _ in 0..=(self.width + self.height) * 2 {
+            if !walkable(current) {
+                return false;
+            }
+            if current == end || (t_max_x > 1.0 && t_max_y > 1.0) {
+                return true;
+            }
+            if (t_max_x - t_max_y).abs() < 1e-6 {
+                // Through a corner: both side tiles must be walkable too
+                if !walkable((current.0 + step_x, current.1)) || !walkable((current.0, current.1 + step_y)) {
+                    return false;
+                }
+                current = (current.0 + step_x, current.1 + step_y);
+                t_max_x += t_delta_x;
+                t_max_y += t_delta_y;
+            } else if t_max_x < t_max_y {
+                current.0 += step_x;
+                t_max_x += t_delta_x;
+            } else {
+                current.1 += step_y;
+                t_max_y += t_delta_y;
+            }
+        }
+        walkable(current)
+    }
+
+    /// Like [`segment_is_walkable`], but for a body of radius `clearance`:
+    /// the lines `clearance` to either side of the segment must be walkable
+    /// too, so a troop crossing a bridge keeps its whole body over it.
+    ///
+    /// [`segment_is_walkable`]: Arena::segment_is_walkable
+    pub fn segment_is_clear(&self, a: &Position, b: &Position, clearance: f32) -> bool {
+        self.segment_clear_with(a, b, clearance, TileType::is_walkable)
+    }
+
+    /// Like [`segment_is_clear`], but the river counts as passable, for
+    /// units that jump it (Hog Rider, Ram Rider).
+    ///
+    /// [`segment_is_clear`]: Arena::segment_is_clear
+    pub fn segment_is_clear_jumping(&self, a: &Position, b: &Position, clearance: f32) -> bool {
+        self.segment_clear_with(a, b, clearance, |t| t.is_walkable() || *t == TileType::River)
+    }
+
+    fn segment_clear_with(&self, a: &Position, b: &Position, clearance: f32, passable: fn(&TileType) -> bool) -> bool {
+        if !self.segment_passes(a, b, passable) {
+            return false;
+        }
+        let (dx, dy) = a.direction_to(b);
+        if clearance <= 0.0 || (dx, dy) == (0.0, 0.0) {
+            return true;
+        }
+        let offset = Position::new(-dy * clearance, dx * clearance);
+        let side = offset.scale(-1.0);
+        self.segment_passes(&a.add(&offset), &b.add(&offset), passable)
+            && self.segment_passes(&a.add(&side), &b.add(&side), passable)
+    }
+
     /// Converts world position to tile coordinates.
     pub fn world_to_tile(&self, pos: &Position) -> (u32, u32) {
         let x = (pos.x / self.tile_size).floor() as u32;
@@ -62,6 +218,145 @@ impl Arena {
             && pos.x < self.width as f32 * self.tile_size
             && pos.y < self.height as f32 * self.tile_size
     }
+
+    /// Converts a flat placement-grid tile index into a world position: the
+    /// center of the arena tile under the middle of that placement cell.
+    ///
+    /// The placement grid covers the whole arena at a coarser resolution,
+    /// indexed row-major. A cell middle on a tile edge takes the tile
+    /// farther from the arena's midline, so the default 16x9 grid's cells
+    /// next to the river land on the bank rather than in the water. Returns
+    /// `None` if the index is outside the grid.
+    pub fn placement_tile_to_world(&self, tile_idx: usize, grid: PlacementGrid) -> Option<Position> {
+        if tile_idx >= grid.len() {
+            return None;
+        }
+        let snap = |middle: f32, tiles: u32| {
+            let middle = if middle.fract() == 0.0 && middle <= tiles as f32 / 2.0 { middle - 1.0 } else { middle };
+            (middle.max(0.0) as u32).min(tiles - 1)
+        };
+        let gx = (tile_idx % grid.width) as f32 + 0.5;
+        let gy = (tile_idx / grid.width) as f32 + 0.5;
+        let x = snap(gx * self.width as f32 / grid.width as f32, self.width);
+        let y = snap(gy * self.height as f32 / grid.height as f32, self.height);
+        Some(self.tile_to_world(x, y))
+    }
+
+    /// Converts a placement tile index as `pov` sees the arena into a world
+    /// position. Player2's view is point-reflected so its own half is on
+    /// the left like Player1's, so its tiles are mirrored back here; every
+    /// caller that turns an agent's tile into a play goes through this.
+    pub fn pov_tile_to_world(&self, pov: PlayerId, tile_idx: usize, grid: PlacementGrid) -> Option<Position> {
+        let position = self.placement_tile_to_world(tile_idx, grid)?;
+        Some(self.pov_position(pov, &position))
+    }
+
+    /// Inverse of [`pov_tile_to_world`]: the index of the placement tile
+    /// containing `pos`, as `pov` sees it. `None` outside the arena.
+    ///
+    /// [`pov_tile_to_world`]: Arena::pov_tile_to_world
+    pub fn world_to_pov_tile(&self, pov: PlayerId, pos: &Position, grid: PlacementGrid) -> Option<usize> {
+        if !self.is_in_bounds(pos) || grid.is_empty() {
+            return None;
+        }
+        let pos = self.pov_position(pov, pos);
+        let cell_w = self.width as f32 * self.tile_size / grid.width as f32;
+        let cell_h = self.height as f32 * self.tile_size / grid.height as f32;
+        let gx = ((pos.x / cell_w) as usize).min(grid.width - 1);
+        let gy = ((pos.y / cell_h) as usize).min(grid.height - 1);
+        Some(gy * grid.width + gx)
+    }
+
+    /// `pos` as `pov` sees the arena: unchanged for Player1, point-reflected
+    /// through the center for Player2. Its own inverse.
+    pub fn pov_position(&self, pov: PlayerId, pos: &Position) -> Position {
+        match pov {
+            PlayerId::Player1 | PlayerId::Player3 => *pos,
+            PlayerId::Player2 | PlayerId::Player4 => Position::new(
+                self.width as f32 * self.tile_size - pos.x,
+                self.height as f32 * self.tile_size - pos.y,
+            ),
+        }
+    }
+
+    /// Returns true if `owner` may place a troop or building at `pos`: a
+    /// grass tile on their own half, or up to [`POCKET_DEPTH_TILES`] into
+    /// the enemy half in one of `open_lanes` (lanes whose enemy princess
+    /// tower is down).
+    pub fn in_deploy_zone(&self, owner: PlayerId, pos: &Position, open_lanes: &[usize]) -> bool {
+        if !self.is_in_bounds(pos) {
+            return false;
+        }
+        let (x, y) = self.world_to_tile(pos);
+        if self.get_tile(x, y) != Some(TileType::Grass) {
+            return false;
+        }
+        // Columns past the river on the enemy side (None on the own side)
+        let x = x as usize;
+        let past_river = match owner {
+            PlayerId::Player1 | PlayerId::Player3 => x.checked_sub(RIVER_TILES_X.end),
+            PlayerId::Player2 | PlayerId::Player4 => RIVER_TILES_X.start.checked_sub(x + 1),
+        };
+        match past_river {
+            None => true,
+            Some(depth) => depth < POCKET_DEPTH_TILES && open_lanes.contains(&self.lane_of(pos)),
+        }
+    }
+
+    /// Returns the center of a player's tower footprint.
+    ///
+    /// Player1 defends the left half of the arena and Player2 the right half;
+    /// Player2's towers are the point reflection of Player1's.
+    pub fn tower_position(&self, player: PlayerId, tower: TowerType) -> Position {
+        let (x, y) = match tower {
+            TowerType::King => (3.0, 9.0),
+            TowerType::LeftPrincess => (6.5, 3.5),
+            TowerType::RightPrincess => (6.5, 14.5),
+        };
+        let (x, y) = (x * self.tile_size, y * self.tile_size);
+
+        match player {
+            PlayerId::Player1 | PlayerId::Player3 => Position::new(x, y),
+            PlayerId::Player2 | PlayerId::Player4 => Position::new(
+                self.width as f32 * self.tile_size - x,
+                self.height as f32 * self.tile_size - y,
+            ),
+        }
+    }
+
+    /// Lane (index into [`BRIDGE_TILES_Y`]) whose bridge is nearest to `pos`
+    /// across the arena.
+    pub fn lane_of(&self, pos: &Position) -> usize {
+        let center = |lane: usize| {
+            let rows = &BRIDGE_TILES_Y[lane];
+            (rows.start + rows.end) as f32 / 2.0 * self.tile_size
+        };
+        (0..BRIDGE_TILES_Y.len())
+            .min_by(|&a, &b| (center(a) - pos.y).abs().total_cmp(&(center(b) - pos.y).abs()))
+            .unwrap_or(0)
+    }
+
+    /// Route a troop of `owner` takes down `lane` when it has nothing to
+    /// attack: to the near end of the lane's bridge, off the far end, then
+    /// on to the enemy King tower.
+    pub fn march_waypoints(&self, owner: PlayerId, lane: usize) -> [Position; 3] {
+        let rows = &BRIDGE_TILES_Y[lane.min(BRIDGE_TILES_Y.len() - 1)];
+        let y = (rows.start + rows.end) as f32 / 2.0 * self.tile_size;
+        // One tile short of the river on either side
+        let (west, east) = (
+            (RIVER_TILES_X.start as f32 - 0.5) * self.tile_size,
+            (RIVER_TILES_X.end as f32 + 0.5) * self.tile_size,
+        );
+        let (near, far) = match owner {
+            PlayerId::Player1 | PlayerId::Player3 => (west, east),
+            PlayerId::Player2 | PlayerId::Player4 => (east, west),
+        };
+        [
+            Position::new(near, y),
+            Position::new(far, y),
+            self.tower_position(owner.opponent(), TowerType::King),
+        ]
+    }
 }
 
 impl Default for Arena {
@@ -70,6 +365,109 @@ impl Default for Arena {
     }
 }
 
+/// A rectangle of tiles that hurts or slows the troops standing on it
+/// (lava, mud and the like in event arenas). Hazards sit over the tiles
+/// rather than replacing them, so walkability and placement are unchanged;
+/// [`crate::systems::environment`] applies their effects.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hazard {
+    /// First tile column and row covered.
+    pub x: u32,
+    pub y: u32,
+    /// Tiles covered across and down.
+    pub width: u32,
+    pub height: u32,
+    /// Damage each troop on the hazard takes per pulse.
+    #[serde(default)]
+    pub damage: f32,
+    /// Seconds between damage pulses, on the match clock (the first lands
+    /// one interval in).
+    #[serde(default = "Hazard::default_interval")]
+    pub interval: f32,
+    /// Whether troops on the hazard are slowed (see [`StatusKind::Slow`]).
+    ///
+    /// [`StatusKind::Slow`]: crate::entities::StatusKind::Slow
+    #[serde(default)]
+    pub slows: bool,
+    /// Whether air troops are affected too, not just ground ones.
+    #[serde(default)]
+    pub hits_air: bool,
+}
+
+impl Hazard {
+    fn default_interval() -> f32 {
+        1.0
+    }
+
+    /// A hazard over `width` x `height` tiles from (`x`, `y`) with no
+    /// effects yet.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            damage: 0.0,
+            interval: Self::default_interval(),
+            slows: false,
+            hits_air: false,
+        }
+    }
+
+    /// Returns true if tile (`x`, `y`) is part of the hazard.
+    pub fn covers(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// Returns true if damage pulses on the step that ends with `tick`
+    /// ticks played (see [`interval`](Self::interval)).
+    pub fn pulses_at(&self, tick: u64) -> bool {
+        let every = (self.interval / crate::DELTA_TIME).round().max(1.0) as u64;
+        self.damage > 0.0 && tick.is_multiple_of(every) && tick > 0
+    }
+}
+
+/// Resolution of the grid agents pick placement tiles on. It spans the
+/// whole arena, each cell covering at least one arena tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlacementGrid {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for PlacementGrid {
+    fn default() -> Self {
+        Self {
+            width: dims::PLACEMENT_GRID_W,
+            height: dims::PLACEMENT_GRID_H,
+        }
+    }
+}
+
+impl PlacementGrid {
+    /// A `width` x `height` grid over `arena`. Errors if either side is zero
+    /// or finer than the arena's tiles.
+    pub fn new(width: usize, height: usize, arena: &Arena) -> Result<Self> {
+        if width == 0 || height == 0 || width > arena.width as usize || height > arena.height as usize {
+            return Err(Error::Configuration(format!(
+                "Placement grid {}x{} must be between 1x1 and the arena's {}x{} tiles",
+                width, height, arena.width, arena.height
+            )));
+        }
+        Ok(Self { width, height })
+    }
+
+    /// Number of placement tiles (the length of `LegalMasks::tiles_flat`).
+    pub fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 /// Types of tiles in the arena.
 ///
 /// Based on the legacy engine's 6 tile types.
@@ -89,3 +487,58 @@ impl TileType {
         matches!(self, TileType::Grass | TileType::Bridge | TileType::Tower)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pov_tiles_round_trip_and_mirror() {
+        let arena = Arena::new();
+        let grid = PlacementGrid::default();
+        for pov in [PlayerId::Player1, PlayerId::Player2] {
+            for tile in 0..grid.len() {
+                let pos = arena.pov_tile_to_world(pov, tile, grid).unwrap();
+                assert_eq!(arena.world_to_pov_tile(pov, &pos, grid), Some(tile));
+            }
+            assert_eq!(arena.pov_tile_to_world(pov, grid.len(), grid), None);
+        }
+
+        // The same index lands on mirrored spots, each in its player's half
+        let p1 = arena.pov_tile_to_world(PlayerId::Player1, 0, grid).unwrap();
+        let p2 = arena.pov_tile_to_world(PlayerId::Player2, 0, grid).unwrap();
+        assert_eq!(p2, Position::new(arena.width as f32 - p1.x, arena.height as f32 - p1.y));
+        let king = arena.tower_position(PlayerId::Player2, TowerType::King);
+        let seen = arena.world_to_pov_tile(PlayerId::Player2, &king, grid).unwrap();
+        let mine = arena.world_to_pov_tile(PlayerId::Player1, &arena.tower_position(PlayerId::Player1, TowerType::King), grid);
+        assert_eq!(Some(seen), mine);
+    }
+
+    #[test]
+    fn test_placement_grids_map_onto_arena_tiles() {
+        let arena = Arena::new();
+
+        // At full resolution a placement tile is an arena tile
+        let full = PlacementGrid::new(32, 18, &arena).unwrap();
+        let pos = arena.placement_tile_to_world(2 * 32 + 5, full).unwrap();
+        assert_eq!(pos, arena.tile_to_world(5, 2));
+
+        // Default-grid cells beside the river stay on the banks
+        let grid = PlacementGrid::default();
+        for column in [7, 8] {
+            let pos = arena.placement_tile_to_world(4 * grid.width + column, grid).unwrap();
+            let (x, y) = arena.world_to_tile(&pos);
+            assert_eq!(arena.get_tile(x, y), Some(TileType::Grass));
+        }
+
+        // Grids that don't divide the arena evenly still round trip
+        let odd = PlacementGrid::new(10, 7, &arena).unwrap();
+        for tile in 0..odd.len() {
+            let pos = arena.placement_tile_to_world(tile, odd).unwrap();
+            assert_eq!(arena.world_to_pov_tile(PlayerId::Player1, &pos, odd), Some(tile));
+        }
+
+        assert!(PlacementGrid::new(0, 9, &arena).is_err());
+        assert!(PlacementGrid::new(64, 9, &arena).is_err());
+    }
+}
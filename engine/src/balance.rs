@@ -0,0 +1,311 @@
+//! Monte Carlo balance sweeps.
+//!
+//! A sweep scales one stat of one card by each of a range of multipliers
+//! (say Knight HP from 90% to 110%) and, at every setting, plays a
+//! tournament of bot-vs-bot matches: a random deck with the card against a
+//! random deck without it. The card's win rate per setting is the curve a
+//! proposed balance change moves along.
+//!
+//! Every setting replays the same seeds, so the decks, sides and bot noise
+//! are identical across settings and only the stat differs; the curve
+//! shows far less noise than independent tournaments of the same size.
+//! The card's side alternates with the seed to cancel out side bias.
+
+use crate::bot::BotMatch;
+use crate::card::Card;
+use crate::rng::Rng;
+use crate::state::GameState;
+use crate::tournament::{DeckEntry, MatchRecord, TournamentReport};
+use serde::{Deserialize, Serialize};
+use shared::{dims, Error, PlayerId, Result};
+
+/// A card stat a sweep can scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceStat {
+    /// Hitpoints at every level.
+    Hp,
+    /// Every damage figure at every level (hit, area, spawn damage, DPS).
+    Damage,
+    /// Seconds between hits (above 1 is slower).
+    HitSpeed,
+    /// Attack range.
+    Range,
+}
+
+impl BalanceStat {
+    /// Every stat, by name.
+    pub const ALL: [(&'static str, BalanceStat); 4] = [
+        ("hp", BalanceStat::Hp),
+        ("damage", BalanceStat::Damage),
+        ("hit_speed", BalanceStat::HitSpeed),
+        ("range", BalanceStat::Range),
+    ];
+
+    /// The stat called `name` (as in [`ALL`](Self::ALL)).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|(n, _)| *n == name).map(|&(_, stat)| stat)
+    }
+
+    /// Scales this stat of `card` by `multiplier`. Stats the card doesn't
+    /// have are left alone.
+    pub fn apply(self, card: &mut Card, multiplier: f32) {
+        let scale = |value: &mut Option<f32>| {
+            if let Some(value) = value {
+                *value *= multiplier;
+            }
+        };
+        match self {
+            BalanceStat::Hp => card.levels.iter_mut().for_each(|level| scale(&mut level.hp)),
+            BalanceStat::Damage => {
+                for level in &mut card.levels {
+                    scale(&mut level.damage);
+                    scale(&mut level.dps);
+                    scale(&mut level.area_damage);
+                    scale(&mut level.spawn_damage);
+                }
+            }
+            BalanceStat::HitSpeed => scale(&mut card.attack_speed),
+            BalanceStat::Range => scale(&mut card.range),
+        }
+    }
+}
+
+/// What to sweep.
+#[derive(Debug, Clone)]
+pub struct SweepConfig {
+    pub card: String,
+    pub stat: BalanceStat,
+    /// Settings to try, each applied to the card's unmodified stat.
+    pub multipliers: Vec<f32>,
+    /// Matches per setting, on seeds `first_seed..first_seed + matches`.
+    pub matches: u64,
+    pub first_seed: u64,
+    /// Tick limit per match; a match still running then counts as a draw.
+    pub max_ticks_per_match: u64,
+    /// Worker threads the matches are spread over.
+    pub threads: usize,
+    /// Card pool decks are drawn from. `None` uses the built-in cards.
+    pub cards: Option<Vec<Card>>,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            card: "Knight".to_string(),
+            stat: BalanceStat::Hp,
+            multipliers: vec![0.9, 0.95, 1.0, 1.05, 1.1],
+            matches: 200,
+            first_seed: 0,
+            max_ticks_per_match: 200 * 60,
+            threads: 4,
+            cards: None,
+        }
+    }
+}
+
+/// Results at one multiplier.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SweepPoint {
+    pub multiplier: f32,
+    /// Matches played (timeouts excluded, as in [`TournamentReport`]).
+    pub matches: u32,
+    pub wins: u32,
+    pub draws: u32,
+    /// Wins over matches, for the side with the card.
+    pub win_rate: f32,
+    /// Average crowns per player per match.
+    pub average_crowns: f32,
+}
+
+/// A full sweep: the card's win rate at each multiplier, in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub card: String,
+    pub stat: BalanceStat,
+    pub points: Vec<SweepPoint>,
+}
+
+/// Runs the sweep on the calling thread (spread over `config.threads`
+/// workers). Results don't depend on the thread count.
+///
+/// # Errors
+/// If the card isn't in the pool, or the pool has too few other cards to
+/// build a deck without it.
+pub fn run_sweep(config: &SweepConfig) -> Result<SweepReport> {
+    let cards = config.cards.clone().unwrap_or_else(crate::card::get_test_cards);
+    let base = cards
+        .iter()
+        .find(|c| c.name == config.card)
+        .ok_or_else(|| Error::Configuration(format!("Card {} is not in the pool", config.card)))?;
+    let others = playable(&cards).filter(|name| *name != config.card).count();
+    if base.type_name == "tower troop" || others == 0 {
+        return Err(Error::Configuration(format!(
+            "Card {} needs to be playable, alongside others to build decks from",
+            config.card
+        )));
+    }
+
+    let pools: Vec<Vec<Card>> = config
+        .multipliers
+        .iter()
+        .map(|&multiplier| {
+            let mut pool = cards.clone();
+            for card in pool.iter_mut().filter(|c| c.name == config.card) {
+                config.stat.apply(card, multiplier);
+            }
+            pool
+        })
+        .collect();
+    let jobs: Vec<(usize, u64)> = (0..pools.len())
+        .flat_map(|point| (config.first_seed..config.first_seed + config.matches).map(move |seed| (point, seed)))
+        .collect();
+
+    let chunk = jobs.len().div_ceil(config.threads.max(1)).max(1);
+    let mut records: Vec<Vec<MatchRecord>> = vec![Vec::new(); pools.len()];
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = jobs
+            .chunks(chunk)
+            .map(|jobs| {
+                let pools = &pools;
+                scope.spawn(move || {
+                    jobs.iter()
+                        .map(|&(point, seed)| {
+                            let record = sweep_match(seed, &pools[point], &config.card, config.max_ticks_per_match);
+                            record.map(|record| (point, record))
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+            })
+            .collect();
+        for worker in workers {
+            let results = worker
+                .join()
+                .map_err(|_| Error::Internal("sweep worker panicked".to_string()))??;
+            for (point, record) in results {
+                records[point].push(record);
+            }
+        }
+        Ok::<(), Error>(())
+    })?;
+
+    let with = format!("with {}", config.card);
+    let without = format!("without {}", config.card);
+    let points = config
+        .multipliers
+        .iter()
+        .zip(&records)
+        .map(|(&multiplier, records)| {
+            let report = TournamentReport::from_records(records);
+            let matchup = report
+                .matchups
+                .get(&with)
+                .and_then(|row| row.get(&without))
+                .cloned()
+                .unwrap_or_default();
+            SweepPoint {
+                multiplier,
+                matches: matchup.games,
+                wins: matchup.wins,
+                draws: matchup.draws,
+                win_rate: matchup.win_rate,
+                average_crowns: report.average_crowns,
+            }
+        })
+        .collect();
+
+    Ok(SweepReport {
+        card: config.card.clone(),
+        stat: config.stat,
+        points,
+    })
+}
+
+fn playable(cards: &[Card]) -> impl Iterator<Item = &str> {
+    cards.iter().filter(|c| c.type_name != "tower troop").map(|c| c.name.as_str())
+}
+
+/// Plays one match of the sweep: a random deck with `card` against one
+/// without it, Player1 holding the card on even seeds.
+fn sweep_match(seed: u64, cards: &[Card], card: &str, max_ticks: u64) -> Result<MatchRecord> {
+    let mut state = GameState::new(seed);
+    state.set_headless(true);
+    state.load_cards(cards.to_vec())?;
+
+    // Decks come from their own stream so they don't perturb the match RNG
+    let mut deck_rng = Rng::new(seed ^ 0xba1a_2ce5);
+    let mut others: Vec<String> = playable(cards).filter(|name| *name != card).map(str::to_string).collect();
+    let mut deal = |with_card: bool| {
+        deck_rng.shuffle(&mut others);
+        let rest = others.iter().cycle().take(dims::DECK_SIZE - usize::from(with_card)).cloned();
+        let cards: Vec<String> = with_card.then(|| card.to_string()).into_iter().chain(rest).collect();
+        let name = if with_card { "with" } else { "without" };
+        DeckEntry {
+            name: format!("{} {}", name, card),
+            cards,
+        }
+    };
+    let (holder, opponent) = if seed.is_multiple_of(2) {
+        (PlayerId::Player1, PlayerId::Player2)
+    } else {
+        (PlayerId::Player2, PlayerId::Player1)
+    };
+    let decks = [(holder, deal(true)), (opponent, deal(false))];
+    for (player, deck) in &decks {
+        state.set_player_deck(*player, deck.cards.clone())?;
+    }
+
+    let mut game = BotMatch::new(state, seed);
+    game.run(max_ticks)?;
+
+    let [(_, deck1), (_, deck2)] = decks;
+    Ok(if holder == PlayerId::Player1 {
+        MatchRecord::from_state(&game.state, deck1, deck2)
+    } else {
+        MatchRecord::from_state(&game.state, deck2, deck1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_scale_every_level() {
+        let mut knight = crate::card::get_test_cards().into_iter().find(|c| c.name == "Knight").unwrap();
+        let hp = knight.levels[0].hp.unwrap();
+        let damage = knight.levels[0].damage.unwrap();
+        BalanceStat::Hp.apply(&mut knight, 1.1);
+        BalanceStat::from_name("damage").unwrap().apply(&mut knight, 0.5);
+        assert!((knight.levels[0].hp.unwrap() - hp * 1.1).abs() < 1e-3);
+        assert!((knight.levels[0].damage.unwrap() - damage * 0.5).abs() < 1e-3);
+        assert_eq!(BalanceStat::from_name("mass"), None);
+    }
+
+    #[test]
+    fn test_sweep_replays_the_same_matches_per_setting() {
+        let config = SweepConfig {
+            card: "Giant".to_string(),
+            multipliers: vec![1.0, 1.0, 3.0],
+            matches: 4,
+            max_ticks_per_match: 40 * 60,
+            threads: 3,
+            ..SweepConfig::default()
+        };
+        let report = run_sweep(&config).unwrap();
+        assert_eq!(report.points.len(), 3);
+        // Equal settings play out identically
+        assert_eq!(report.points[0], report.points[1]);
+        for point in &report.points {
+            assert_eq!(point.matches, 4);
+            assert!(point.wins + point.draws <= 4);
+        }
+        assert_eq!(run_sweep(&SweepConfig { threads: 1, ..config.clone() }).unwrap(), report);
+
+        let missing = SweepConfig {
+            card: "Goblin".to_string(),
+            ..config
+        };
+        assert!(run_sweep(&missing).is_err());
+    }
+}
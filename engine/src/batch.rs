@@ -0,0 +1,424 @@
+//! Batches of independent simulations stepped together.
+//!
+//! Each environment owns its entire state (including its RNG), and the
+//! engine has no global mutable state, so stepping environments on worker
+//! threads produces exactly the same results as stepping them serially.
+//!
+//! [`SimBatch`] advances raw engine ticks; [`BatchSim`] is the training
+//! view on top, taking one agent action per environment and returning
+//! stacked observations, rewards and done flags.
+
+use crate::action::{Action, AgentAction};
+use crate::observation::OBS_LEN;
+use crate::reward::{RewardConfig, RewardFn, RewardTally};
+use crate::state::{step_with_agent_actions, GameState};
+use shared::{Error, PlayerId, Result};
+
+/// A set of independent game states advanced in lockstep.
+#[derive(Debug, Clone, Default)]
+pub struct SimBatch {
+    envs: Vec<GameState>,
+}
+
+impl SimBatch {
+    /// Creates one fresh environment per seed.
+    pub fn new(seeds: &[u64]) -> Self {
+        Self::from_states(seeds.iter().map(|&seed| GameState::new(seed)).collect())
+    }
+
+    /// Wraps already-configured states.
+    pub fn from_states(envs: Vec<GameState>) -> Self {
+        Self { envs }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[GameState] {
+        &self.envs
+    }
+
+    pub fn envs_mut(&mut self) -> &mut [GameState] {
+        &mut self.envs
+    }
+
+    /// Steps every environment once, one after another.
+    ///
+    /// `actions` holds one action list per environment, or is empty for a
+    /// tick without actions.
+    pub fn step(&mut self, actions: &[Vec<Action>]) -> Result<()> {
+        self.check_actions(actions)?;
+        for (i, env) in self.envs.iter_mut().enumerate() {
+            crate::step(env, actions.get(i).map(Vec::as_slice).unwrap_or(&[]))?;
+        }
+        Ok(())
+    }
+
+    /// Steps every environment once, spreading them over up to `threads`
+    /// worker threads. Results are identical to [`SimBatch::step`].
+    pub fn step_parallel(&mut self, actions: &[Vec<Action>], threads: usize) -> Result<()> {
+        self.check_actions(actions)?;
+        for_each_env(&mut self.envs, threads, |i, env| {
+            crate::step(env, actions.get(i).map(Vec::as_slice).unwrap_or(&[]))
+        })?;
+        Ok(())
+    }
+
+    /// Returns [`GameState::state_hash`] for every environment.
+    pub fn hashes(&self) -> Vec<u64> {
+        self.envs.iter().map(GameState::state_hash).collect()
+    }
+
+    fn check_actions(&self, actions: &[Vec<Action>]) -> Result<()> {
+        if !actions.is_empty() && actions.len() != self.envs.len() {
+            return Err(Error::InvalidAction(format!(
+                "Expected actions for {} environments, got {}",
+                self.envs.len(),
+                actions.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f` on every environment over up to `threads` worker threads (on
+/// this one for a single thread) and returns the results in environment
+/// order. Stops at the first error.
+fn for_each_env<T: Send>(
+    envs: &mut [GameState],
+    threads: usize,
+    f: impl Fn(usize, &mut GameState) -> Result<T> + Sync,
+) -> Result<Vec<T>> {
+    if envs.is_empty() {
+        return Ok(Vec::new());
+    }
+    if threads <= 1 {
+        return envs.iter_mut().enumerate().map(|(i, env)| f(i, env)).collect();
+    }
+    let total = envs.len();
+    let chunk = total.div_ceil(threads);
+    let f = &f;
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = envs
+            .chunks_mut(chunk)
+            .enumerate()
+            .map(|(c, envs)| {
+                scope.spawn(move || -> Result<Vec<T>> {
+                    envs.iter_mut()
+                        .enumerate()
+                        .map(|(j, env)| f(c * chunk + j, env))
+                        .collect()
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(total);
+        for worker in workers {
+            let chunk_results = worker
+                .join()
+                .map_err(|_| Error::Internal("batch worker panicked".to_string()))??;
+            results.extend(chunk_results);
+        }
+        Ok(results)
+    })
+}
+
+/// Builds the match an environment starts (or restarts) with from a seed.
+pub type NewMatch = dyn Fn(u64) -> Result<GameState> + Send + Sync;
+
+/// What every environment of a [`BatchSim`] did in one step.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchStep {
+    /// Each environment's observation grid (see
+    /// [`GameState::export_observation_grid`]) after the step, one after
+    /// another: [`OBS_LEN`] values per environment.
+    pub observations: Vec<f32>,
+    /// Each environment's reward for the step.
+    pub rewards: Vec<f32>,
+    /// Whether each environment's match ended during the step. Those
+    /// environments have already been restarted, and their observations
+    /// are of the new match.
+    pub dones: Vec<bool>,
+}
+
+/// Vectorized training environments: independent matches stepped together
+/// at the agent level, one [`AgentAction`] per environment per step (see
+/// [`step_with_agent_actions`]), played from the `pov` side.
+///
+/// Rewards come from a [`RewardFn`] ([`RewardConfig::default`] unless
+/// [`set_reward`](Self::set_reward) says otherwise). A match that ends is replaced straight away by a fresh
+/// one, so every step returns a full batch of live environments; the
+/// replacements take seeds counting up from one past the highest seed the
+/// batch started with.
+pub struct BatchSim {
+    envs: Vec<GameState>,
+    new_match: Box<NewMatch>,
+    next_seed: u64,
+    pov: PlayerId,
+    threads: usize,
+    reward: Box<dyn RewardFn>,
+    /// Each environment's reward tally as of its last step.
+    tallies: Vec<RewardTally>,
+}
+
+impl BatchSim {
+    /// One environment per seed, each built by `new_match` (which sets up
+    /// decks, frame skip and so on), played from `pov`'s side. Steps run on
+    /// this thread until [`set_threads`](Self::set_threads) says otherwise.
+    pub fn new(
+        seeds: &[u64],
+        pov: PlayerId,
+        new_match: impl Fn(u64) -> Result<GameState> + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let envs = seeds.iter().map(|&seed| new_match(seed)).collect::<Result<Vec<_>>>()?;
+        let tallies = envs.iter().map(|env| RewardTally::of(env, pov)).collect();
+        Ok(Self {
+            envs,
+            new_match: Box::new(new_match),
+            next_seed: seeds.iter().max().map_or(0, |&seed| seed.wrapping_add(1)),
+            pov,
+            threads: 1,
+            reward: Box::new(RewardConfig::default().build()),
+            tallies,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[GameState] {
+        &self.envs
+    }
+
+    /// The side the agent plays.
+    pub fn pov(&self) -> PlayerId {
+        self.pov
+    }
+
+    /// Spreads steps over up to `threads` worker threads (1 steps them on
+    /// the calling thread). Results don't depend on it.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Scores steps with `reward` from now on.
+    pub fn set_reward(&mut self, reward: impl RewardFn + 'static) {
+        self.reward = Box::new(reward);
+    }
+
+    /// Restarts every environment with the given seeds (one per
+    /// environment) and returns the stacked observations.
+    pub fn reset(&mut self, seeds: &[u64]) -> Result<Vec<f32>> {
+        if seeds.len() != self.envs.len() {
+            return Err(Error::InvalidAction(format!(
+                "Expected seeds for {} environments, got {}",
+                self.envs.len(),
+                seeds.len()
+            )));
+        }
+        for (i, &seed) in seeds.iter().enumerate() {
+            self.restart(i, seed)?;
+        }
+        self.next_seed = seeds.iter().max().map_or(0, |&seed| seed.wrapping_add(1));
+        Ok(self.observations())
+    }
+
+    /// Every environment's current observation, stacked as in
+    /// [`BatchStep::observations`].
+    pub fn observations(&self) -> Vec<f32> {
+        let mut observations = Vec::with_capacity(self.envs.len() * OBS_LEN);
+        for env in &self.envs {
+            observations.extend(env.export_observation_grid(self.pov));
+        }
+        observations
+    }
+
+    /// Plays `actions[i]` in environment `i` (and, for self-play,
+    /// `opponent_actions[i]` for the other side, from its own frame) and
+    /// runs one agent step in each, restarting matches that end.
+    ///
+    /// # Errors
+    /// If an action list doesn't have one action per environment (nothing
+    /// is stepped then), or on an internal engine error.
+    pub fn step(&mut self, actions: &[AgentAction], opponent_actions: Option<&[AgentAction]>) -> Result<BatchStep> {
+        for list in std::iter::once(actions).chain(opponent_actions) {
+            if list.len() != self.envs.len() {
+                return Err(Error::InvalidAction(format!(
+                    "Expected actions for {} environments, got {}",
+                    self.envs.len(),
+                    list.len()
+                )));
+            }
+        }
+
+        let pov = self.pov;
+        let (reward, tallies) = (&self.reward, &self.tallies);
+        let outcomes = for_each_env(&mut self.envs, self.threads, |i, env| {
+            let mut agents = vec![(pov, actions[i])];
+            if let Some(opponent_actions) = opponent_actions {
+                agents.push((pov.opponent(), opponent_actions[i]));
+            }
+            step_with_agent_actions(env, &agents)?;
+            let tally = RewardTally::of(env, pov);
+            let done = env.is_match_over();
+            let observation = if done { Vec::new() } else { env.export_observation_grid(pov) };
+            Ok((reward.reward(&tallies[i], &tally), tally, done, observation))
+        })?;
+
+        let mut step = BatchStep {
+            observations: Vec::with_capacity(self.envs.len() * OBS_LEN),
+            rewards: Vec::with_capacity(self.envs.len()),
+            dones: Vec::with_capacity(self.envs.len()),
+        };
+        for (i, (reward, tally, done, observation)) in outcomes.into_iter().enumerate() {
+            step.rewards.push(reward);
+            step.dones.push(done);
+            if done {
+                let seed = self.next_seed;
+                self.next_seed = seed.wrapping_add(1);
+                self.restart(i, seed)?;
+                step.observations.extend(self.envs[i].export_observation_grid(pov));
+            } else {
+                self.tallies[i] = tally;
+                step.observations.extend(observation);
+            }
+        }
+        Ok(step)
+    }
+
+    /// Replaces environment `i` with a fresh match from `seed`.
+    fn restart(&mut self, i: usize, seed: u64) -> Result<()> {
+        let env = (self.new_match)(seed)?;
+        self.tallies[i] = RewardTally::of(&env, self.pov);
+        self.envs[i] = env;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::Position;
+
+    fn batch() -> SimBatch {
+        let mut batch = SimBatch::new(&[1, 2, 3, 4, 5]);
+        let deck: Vec<String> = ["Knight", "Archers", "Giant", "Musketeer"]
+            .iter()
+            .cycle()
+            .take(8)
+            .map(|c| c.to_string())
+            .collect();
+        for env in batch.envs_mut() {
+            env.set_player_deck(PlayerId::Player1, deck.clone()).unwrap();
+            env.set_player_deck(PlayerId::Player2, deck.clone()).unwrap();
+        }
+        batch
+    }
+
+    fn run(batch: &mut SimBatch, parallel: bool) {
+        for tick in 0..600u32 {
+            let actions: Vec<Vec<Action>> = (0..batch.len())
+                .map(|i| {
+                    if tick % 300 != 0 {
+                        return Vec::new();
+                    }
+                    let player = if i % 2 == 0 { PlayerId::Player1 } else { PlayerId::Player2 };
+                    let x = if player == PlayerId::Player1 { 10.0 } else { 22.0 };
+                    vec![Action::PlayCard {
+                        player,
+                        card_name: "Knight".to_string(),
+                        level: 11,
+                        position: Position::new(x, 4.0 + i as f32),
+                    }]
+                })
+                .collect();
+            if parallel {
+                batch.step_parallel(&actions, 3).unwrap();
+            } else {
+                batch.step(&actions).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        let mut serial = batch();
+        let mut parallel = batch();
+        run(&mut serial, false);
+        run(&mut parallel, true);
+
+        assert_eq!(serial.hashes(), parallel.hashes());
+        assert!(serial.envs().iter().all(|env| env.tick > 0));
+    }
+
+    #[test]
+    fn test_action_count_must_match() {
+        let mut batch = SimBatch::new(&[1, 2]);
+        assert!(batch.step(&[Vec::new()]).is_err());
+        assert!(batch.step_parallel(&[Vec::new(), Vec::new()], 4).is_ok());
+    }
+
+    fn batch_sim(seeds: &[u64], threads: usize) -> BatchSim {
+        let mut sim = BatchSim::new(seeds, PlayerId::Player1, |seed| {
+            let mut env = GameState::new(seed);
+            let deck = ["Knight", "Archers", "Giant", "Musketeer", "Arrows", "Baby Dragon", "Fireball", "Knight"];
+            env.set_player_deck(PlayerId::Player1, deck.to_vec())?;
+            env.set_player_deck(PlayerId::Player2, deck.to_vec())?;
+            env.set_headless(true);
+            env.set_frame_skip(30);
+            // Twenty steps to a match
+            env.max_match_time = 10.0;
+            Ok(env)
+        })
+        .unwrap();
+        sim.set_threads(threads);
+        sim
+    }
+
+    #[test]
+    fn test_batch_sim_steps_restart_finished_matches() {
+        let mut serial = batch_sim(&[1, 2, 3, 4], 1);
+        let mut parallel = batch_sim(&[1, 2, 3, 4], 3);
+        assert_eq!(serial.observations().len(), 4 * OBS_LEN);
+
+        let mut returns = [0.0; 4];
+        for n in 0..20 {
+            let play = AgentAction::Play { slot: n % 4, tile: 40 + n };
+            let actions = [play, AgentAction::NoOp, play, AgentAction::NoOp];
+            let opponent = [AgentAction::NoOp, play, AgentAction::NoOp, play];
+            let step = serial.step(&actions, Some(&opponent)).unwrap();
+            assert_eq!(step, parallel.step(&actions, Some(&opponent)).unwrap());
+            assert_eq!(step.observations.len(), 4 * OBS_LEN);
+            assert_eq!(step.dones, [n == 19; 4]);
+            for (total, reward) in returns.iter_mut().zip(&step.rewards) {
+                *total += reward;
+            }
+        }
+        assert!(returns.iter().any(|&total| total != 0.0));
+
+        // Every match ended on the last step and was replaced
+        let seeds: Vec<u64> = serial.envs().iter().map(|env| env.match_metadata(PlayerId::Player1).seed).collect();
+        assert_eq!(seeds, [5, 6, 7, 8]);
+        assert!(serial.envs().iter().all(|env| env.tick == 0));
+        assert_eq!(serial.observations(), parallel.observations());
+
+        // Any reward function plugs in
+        serial.set_reward(|before: &RewardTally, after: &RewardTally| after.match_time - before.match_time);
+        let step = serial.step(&[AgentAction::NoOp; 4], None).unwrap();
+        assert!(step.rewards.iter().all(|&reward| (reward - 0.5).abs() < 1e-4));
+
+        assert!(serial.step(&[AgentAction::NoOp], None).is_err());
+        assert_eq!(serial.reset(&[9, 9, 9, 9]).unwrap().len(), 4 * OBS_LEN);
+    }
+}
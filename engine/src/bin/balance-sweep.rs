@@ -0,0 +1,88 @@
+//! Sweeps one card stat over a range of multipliers and reports the card's
+//! win rate at each, as JSON on stdout and a table on stderr.
+//!
+//! Usage: balance-sweep <card> <stat> <from> <to> [--steps N] [--matches N]
+//!        [--seed N] [--threads N] [--max-ticks N] [--cards cards.json]
+//!
+//! `stat` is hp, damage, hit_speed or range; `from`/`to` are multipliers
+//! (0.9 1.1 sweeps 90% to 110%), tried at `--steps` evenly spaced settings.
+
+use engine::balance::{run_sweep, BalanceStat, SweepConfig};
+use engine::load_cards_from_json;
+
+const DEFAULT_STEPS: usize = 5;
+
+fn usage(problem: &str) -> ! {
+    eprintln!("{}", problem);
+    eprintln!(
+        "usage: balance-sweep <card> <stat> <from> <to> [--steps N] [--matches N] [--seed N] [--threads N] [--max-ticks N] [--cards cards.json]"
+    );
+    let stats: Vec<&str> = BalanceStat::ALL.iter().map(|(name, _)| *name).collect();
+    eprintln!("stats: {}", stats.join(", "));
+    std::process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (positional, flags): (Vec<&String>, Vec<&String>) = {
+        let first_flag = args.iter().position(|a| a.starts_with("--")).unwrap_or(args.len());
+        (args[..first_flag].iter().collect(), args[first_flag..].iter().collect())
+    };
+    let [card, stat, from, to] = positional[..] else {
+        usage("expected a card, a stat and a multiplier range");
+    };
+    let stat = BalanceStat::from_name(stat).unwrap_or_else(|| usage(&format!("unknown stat: {}", stat)));
+    let multiplier = |value: &str| value.parse::<f32>().unwrap_or_else(|_| usage(&format!("not a multiplier: {}", value)));
+    let (from, to) = (multiplier(from), multiplier(to));
+
+    let flag = |name: &str| {
+        let i = flags.iter().position(|a| *a == name)?;
+        Some(flags.get(i + 1).map(|v| v.as_str()).unwrap_or_else(|| usage(&format!("{} needs a value", name))))
+    };
+    let number = |name: &str| {
+        flag(name).map(|v| v.parse::<u64>().unwrap_or_else(|_| usage(&format!("{} needs a number", name))))
+    };
+
+    let defaults = SweepConfig::default();
+    let steps = number("--steps").map_or(DEFAULT_STEPS, |n| n as usize).max(1);
+    let multipliers = (0..steps)
+        .map(|i| if steps == 1 { from } else { from + (to - from) * i as f32 / (steps - 1) as f32 })
+        .collect();
+    let cards = flag("--cards").map(|path| {
+        load_cards_from_json(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        })
+    });
+    let config = SweepConfig {
+        card: card.clone(),
+        stat,
+        multipliers,
+        matches: number("--matches").unwrap_or(defaults.matches),
+        first_seed: number("--seed").unwrap_or(defaults.first_seed),
+        max_ticks_per_match: number("--max-ticks").unwrap_or(defaults.max_ticks_per_match),
+        threads: number("--threads").map_or(defaults.threads, |n| n as usize),
+        cards,
+    };
+
+    let report = match run_sweep(&config) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    eprintln!("{} {:?}, {} matches per setting", report.card, report.stat, config.matches);
+    for point in &report.points {
+        eprintln!(
+            "  x{:<6.3} win rate {:>5.1}%  ({} wins, {} draws of {})",
+            point.multiplier,
+            point.win_rate * 100.0,
+            point.wins,
+            point.draws,
+            point.matches
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Runs the card coverage checks over a patch and prints a JSON report.
+//!
+//! Usage: card-coverage [cards.json]
+
+use engine::coverage::run_coverage;
+use engine::load_cards_from_json;
+
+const DEFAULT_CARDS: &str = "config/patches/v2025_current/cards_complete.json";
+
+fn main() {
+    let path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_CARDS.to_string());
+    let cards = match load_cards_from_json(&path) {
+        Ok(cards) => cards,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // Silence per-card panic messages; they are captured in the report
+    std::panic::set_hook(Box::new(|_| {}));
+    let report = run_coverage(&cards);
+
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+
+    eprintln!("{}/{} cards passed", report.passed(), report.cards.len());
+    for (card_type, tally) in &report.by_type {
+        eprintln!("  type {:<12} {}/{}", card_type, tally.passed, tally.total);
+    }
+    for (effect, tally) in &report.by_effect {
+        eprintln!("  effect {:<10} {}/{}", effect, tally.passed, tally.total);
+    }
+}
@@ -1,82 +1,139 @@
 use std::io::{self, BufRead, Write};
-use engine::state::{GameState, step_with_action};
-use engine::card;
-use shared::PlayerId;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+use engine::rpc::{Session, PROTOCOL_VERSION};
+#[cfg(feature = "server")]
+use engine::ws::LiveServer;
 
+/// Interface the live server listens on unless `--host` says otherwise.
+const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// Ticks between streamed states in live mode unless `--stream-every` says
+/// otherwise (a quarter of a second).
+const DEFAULT_STREAM_EVERY: u64 = 15;
+
+/// Serves one match over stdio: a JSON-RPC message per line in, a response
+/// per line out (see `engine::rpc` for the methods).
+///
+/// With `--websocket <port>` it serves a live match to WebSocket clients
+/// instead (see `engine::ws`), streaming state every `--stream-every
+/// <ticks>` ticks, 2v2 with `--two-v-two`. It only listens on localhost
+/// unless `--host <addr>` names another interface. Live mode needs the
+/// engine's `server` feature (`cargo run --features server --bin
+/// crust_sim_server`).
+///
+/// A live match only ticks while a client is connected, and the stdio one
+/// only on `step`, so an idle server costs no CPU either way.
+///
+/// `--idle-timeout <secs>` (off by default) makes the process exit once it
+/// has been idle that long. That shuts down the server and its one match;
+/// it isn't per-session eviction, which needs a multi-session server (see
+/// `DEFERRED_FEATURES.md`). In live mode idle means no client connected.
+/// Over stdio it means no request line: a client that can go longer than
+/// the timeout between requests (a slow training step, say) would find the
+/// server gone, so only pass it when the client keeps to a known cadence.
 fn main() {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .map(|i| args.get(i + 1).and_then(|v| v.parse::<u64>().ok()).unwrap_or_else(|| usage(name)))
+    };
 
-    // Start with some default game; we'll replace it on RESET.
-    let mut game = GameState::new(0);
+    let host = match args.iter().position(|a| a == "--host") {
+        Some(i) => args.get(i + 1).cloned().unwrap_or_else(|| usage("--host")),
+        None => DEFAULT_HOST.to_string(),
+    };
 
-    eprintln!("crust_sim_server ready. Commands: RESET <seed>, STATE, EXIT");
+    let idle_timeout = flag("--idle-timeout").map(Duration::from_secs);
+    match flag("--websocket") {
+        Some(port) => {
+            let two_v_two = args.iter().any(|a| a == "--two-v-two");
+            let stream_every = flag("--stream-every").unwrap_or(DEFAULT_STREAM_EVERY);
+            serve_websocket(&host, port, stream_every, two_v_two, idle_timeout)
+        }
+        None => serve_stdio(idle_timeout),
+    }
+}
 
-    for line in stdin.lock().lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => break,
-        };
-        let parts: Vec<_> = line.trim().split_whitespace().collect();
-        if parts.is_empty() {
-            continue;
+fn usage(flag: &str) -> ! {
+    eprintln!("{} needs a value", flag);
+    eprintln!(
+        "usage: crust_sim_server [--idle-timeout <secs>] [--websocket <port> [--host <addr>] [--stream-every <ticks>] [--two-v-two]]"
+    );
+    std::process::exit(2);
+}
+
+#[cfg(not(feature = "server"))]
+fn serve_websocket(_host: &str, _port: u64, _stream_every: u64, _two_v_two: bool, _idle_timeout: Option<Duration>) {
+    eprintln!("crust_sim_server: built without live mode; rebuild with --features server");
+    std::process::exit(2);
+}
+
+#[cfg(feature = "server")]
+fn serve_websocket(host: &str, port: u64, stream_every: u64, two_v_two: bool, idle_timeout: Option<Duration>) {
+    let Ok(port) = u16::try_from(port) else { usage("--websocket") };
+    let result = LiveServer::bind((host, port), 0, stream_every).and_then(|mut server| {
+        if two_v_two {
+            server.session_mut().set_two_v_two(true);
+            server.session_mut().reset(0)?;
         }
+        server.set_idle_timeout(idle_timeout);
+        eprintln!(
+            "crust_sim_server ready ({}, JSON-RPC 2.0 over WebSocket on {}:{}, state every {} ticks)",
+            PROTOCOL_VERSION, host, port, stream_every
+        );
+        server.run()
+    });
+    if let Err(e) = result {
+        eprintln!("crust_sim_server: {}", e);
+        std::process::exit(1);
+    }
+}
 
-        match parts[0] {
-            "RESET" => {
-                let seed: u64 = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                game = GameState::new(seed);
-                
-                // Set up decks for both players using test cards
-                // Cycle through test cards to fill 8-card deck
-                let test_cards = card::get_test_cards();
-                let player1_deck: Vec<String> = test_cards.iter().cycle().take(8).map(|c| c.name.clone()).collect();
-                let player2_deck: Vec<String> = test_cards.iter().rev().cycle().take(8).map(|c| c.name.clone()).collect();
-                
-                game.set_player_deck(shared::PlayerId::Player1, player1_deck)
-                    .expect("Failed to set Player1 deck");
-                game.set_player_deck(shared::PlayerId::Player2, player2_deck)
-                    .expect("Failed to set Player2 deck");
-                
-                eprintln!(
-                    "RESET: Player1 hand size = {}, Player2 hand size = {}",
-                    game.players.get(&shared::PlayerId::Player1).unwrap().hand.len(),
-                    game.players.get(&shared::PlayerId::Player2).unwrap().hand.len()
-                );
-                
-                let snapshot = game.export_cr_state(PlayerId::Player1);
-                let json = serde_json::to_string(&snapshot).unwrap();
-                writeln!(stdout, "{}", json).unwrap();
-                stdout.flush().unwrap();
-            }
-            "STATE" => {
-                let snapshot = game.export_cr_state(PlayerId::Player1);
-                let json = serde_json::to_string(&snapshot).unwrap();
-                writeln!(stdout, "{}", json).unwrap();
-                stdout.flush().unwrap();
+fn serve_stdio(idle_timeout: Option<Duration>) {
+    let mut stdout = io::stdout();
+    let mut session = Session::new();
+
+    // Lines are read on their own thread so waiting for one can time out
+    let (sender, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if sender.send(line).is_err() {
+                break;
             }
-            "STEP" => {
-                let card_idx: usize = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                let tile_idx: usize = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
+        }
+    });
+
+    eprintln!("crust_sim_server ready ({}, JSON-RPC 2.0 over stdio)", PROTOCOL_VERSION);
 
+    loop {
+        let next = match idle_timeout {
+            Some(timeout) => lines.recv_timeout(timeout),
+            None => lines.recv().map_err(RecvTimeoutError::from),
+        };
+        let line = match next {
+            Ok(Ok(l)) => l,
+            Err(RecvTimeoutError::Timeout) => {
                 eprintln!(
-                    "DEBUG: STEP command received card_idx={}, tile_idx={}",
-                    card_idx, tile_idx
+                    "crust_sim_server: no request for {}s (--idle-timeout), exiting",
+                    idle_timeout.unwrap_or_default().as_secs()
                 );
-
-                step_with_action(&mut game, PlayerId::Player1, card_idx, tile_idx);
-
-                let snapshot = game.export_cr_state(PlayerId::Player1);
-                let json = serde_json::to_string(&snapshot).unwrap();
-                writeln!(stdout, "{}", json).unwrap();
-                stdout.flush().unwrap();
-            }
-            "EXIT" => {
                 break;
             }
-            _ => {
-                eprintln!("Unknown command: {}", parts[0]);
+            Ok(Err(_)) | Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(response) = session.handle_message(&line) {
+            if writeln!(stdout, "{}", response).and_then(|_| stdout.flush()).is_err() {
+                break;
             }
         }
+        if session.is_exiting() {
+            break;
+        }
     }
-}
\ No newline at end of file
+}
@@ -15,7 +15,7 @@ fn main() {
     println!("Loading test cards...");
     // Note: cards_complete.json has a parsing issue, will fix in next iteration
     // let cards = load_cards_from_json("config/patches/v2025_current/cards_complete.json").expect("Failed to load cards");
-    println!("Loaded 5 test cards\n");
+    println!("Loaded {} test cards\n", engine::card::get_test_cards().len());
 
     println!("Game initialized with seed: {}", seed);
     println!("Player 1 starting elixir: {}", state.players[&PlayerId::Player1].elixir);
@@ -75,14 +75,13 @@ fn main() {
         step(&mut state, &[]).unwrap();
     }
     let p1_card = state.players[&PlayerId::Player1].get_hand_card(0).unwrap().clone();
-    println!("[Tick {}] Player 1 plays {} (hand slot 0) at (16, 8)", state.tick, p1_card);
+    println!("[Tick {}] Player 1 plays {} (hand slot 0) at (12, 8)", state.tick, p1_card);
     step(
         &mut state,
         &[Action::PlayCardFromHand {
             player: PlayerId::Player1,
             hand_index: 0,
-            level: 11,
-            position: Position::new(16.0, 8.0),
+            position: Position::new(12.0, 8.0),
         }],
     )
     .unwrap();
@@ -99,14 +98,13 @@ fn main() {
         step(&mut state, &[]).unwrap();
     }
     let p2_card = state.players[&PlayerId::Player2].get_hand_card(1).unwrap().clone();
-    println!("\n[Tick {}] Player 2 plays {} (hand slot 1) at (16, 10)", state.tick, p2_card);
+    println!("\n[Tick {}] Player 2 plays {} (hand slot 1) at (20, 10)", state.tick, p2_card);
     step(
         &mut state,
         &[Action::PlayCardFromHand {
             player: PlayerId::Player2,
             hand_index: 1,
-            level: 11,
-            position: Position::new(16.0, 10.0),
+            position: Position::new(20.0, 10.0),
         }],
     )
     .unwrap();
@@ -243,8 +241,9 @@ fn main() {
     println!("\n=== Projectile System Test ===");
     println!("Testing ranged attacks with Archers shooting at Knight...\n");
 
-    // Clear entities and reset
+    // Clear entities and reset (the Knight is staged across the river)
     state.entities.clear();
+    state.set_deploy_zones(false);
     state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
     state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 10.0;
 
@@ -0,0 +1,346 @@
+//! Rule-based bot with tunable difficulty.
+//!
+//! The bot defends against enemy troops that cross onto its half and
+//! otherwise pushes a lane once it has banked enough elixir. Its knobs
+//! (reaction delay, elixir threshold, placement noise, card subset) let a
+//! single implementation serve as a family of calibrated opponents.
+
+use crate::action::Action;
+use crate::arena::RIVER_TILES_X;
+use crate::entities::EntityKind;
+use crate::rng::Rng;
+use crate::state::GameState;
+use serde::{Deserialize, Serialize};
+use shared::{PlayerId, Position, Result};
+
+/// Tunable parameters for the rule-based bot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BotConfig {
+    /// Ticks between first seeing a threat and responding to it.
+    pub reaction_delay_ticks: u64,
+
+    /// Elixir banked before starting an attack.
+    pub elixir_threshold: f32,
+
+    /// Maximum placement offset (tiles) in each axis.
+    pub placement_noise: f32,
+
+    /// Seed for the bot's own noise stream (independent of the match RNG).
+    pub seed: u64,
+
+    /// Cards the bot may play. `None` allows every card.
+    pub allowed_cards: Option<Vec<String>>,
+}
+
+impl BotConfig {
+    /// Slow, sloppy, and spends elixir as soon as it has any.
+    pub fn easy() -> Self {
+        Self {
+            reaction_delay_ticks: 90,
+            elixir_threshold: 4.0,
+            placement_noise: 2.0,
+            ..Self::default()
+        }
+    }
+
+    /// The default bot.
+    pub fn medium() -> Self {
+        Self::default()
+    }
+
+    /// Fast, precise, and saves up for bigger pushes.
+    pub fn hard() -> Self {
+        Self {
+            reaction_delay_ticks: 6,
+            elixir_threshold: 9.0,
+            placement_noise: 0.0,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            reaction_delay_ticks: 30,
+            elixir_threshold: 7.0,
+            placement_noise: 0.75,
+            seed: 0,
+            allowed_cards: None,
+        }
+    }
+}
+
+/// A rule-based opponent controlling one player.
+#[derive(Debug, Clone)]
+pub struct RuleBot {
+    player: PlayerId,
+    config: BotConfig,
+    rng: Rng,
+
+    /// Tick a threat was first seen on our half, if one is active.
+    threat_seen_at: Option<u64>,
+}
+
+impl RuleBot {
+    pub fn new(player: PlayerId, config: BotConfig) -> Self {
+        let rng = Rng::new(config.seed);
+        Self {
+            player,
+            config,
+            rng,
+            threat_seen_at: None,
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    pub fn config(&self) -> &BotConfig {
+        &self.config
+    }
+
+    /// Decides on at most one action for the current tick.
+    pub fn decide(&mut self, state: &GameState) -> Option<Action> {
+        let threat = self.find_threat(state);
+
+        match threat {
+            Some(threat_pos) => {
+                // A sighting from later than now belongs to a match since reset
+                if self.threat_seen_at.is_some_and(|seen_at| seen_at > state.tick) {
+                    self.threat_seen_at = None;
+                }
+                let seen_at = *self.threat_seen_at.get_or_insert(state.tick);
+                if state.tick - seen_at < self.config.reaction_delay_ticks {
+                    return None;
+                }
+
+                // Defend slightly in front of the threat (toward our King)
+                let back = match self.player {
+                    PlayerId::Player1 | PlayerId::Player3 => -1.5,
+                    PlayerId::Player2 | PlayerId::Player4 => 1.5,
+                };
+                let target = Position::new(threat_pos.x + back, threat_pos.y);
+                let action = self.play_card(state, target, 0.0);
+                if action.is_some() {
+                    self.threat_seen_at = None;
+                }
+                action
+            }
+            None => {
+                self.threat_seen_at = None;
+
+                // Push a lane once enough elixir is banked
+                let lane_y = if self.rng.rand_float() < 0.5 { 3.5 } else { 14.5 };
+                let mid = state.arena.width as f32 * state.arena.tile_size / 2.0;
+                let x = match self.player {
+                    PlayerId::Player1 | PlayerId::Player3 => mid - 3.0,
+                    PlayerId::Player2 | PlayerId::Player4 => mid + 3.0,
+                };
+                self.play_card(state, Position::new(x, lane_y), self.config.elixir_threshold)
+            }
+        }
+    }
+
+    /// Returns the position of the enemy troop deepest into our half.
+    fn find_threat(&self, state: &GameState) -> Option<Position> {
+        let mid = state.arena.width as f32 * state.arena.tile_size / 2.0;
+        state
+            .entities
+            .values()
+            .filter(|e| !e.owner.is_ally_of(self.player) && matches!(e.kind, EntityKind::Troop(_)))
+            .filter(|e| match self.player {
+                PlayerId::Player1 | PlayerId::Player3 => e.position.x < mid,
+                PlayerId::Player2 | PlayerId::Player4 => e.position.x > mid,
+            })
+            .map(|e| e.position)
+            .min_by(|a, b| {
+                let (da, db) = match self.player {
+                    PlayerId::Player1 | PlayerId::Player3 => (a.x, b.x),
+                    PlayerId::Player2 | PlayerId::Player4 => (-a.x, -b.x),
+                };
+                da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Plays the cheapest allowed, affordable card from hand at `target`,
+    /// provided the player has at least `min_elixir`.
+    fn play_card(&mut self, state: &GameState, target: Position, min_elixir: f32) -> Option<Action> {
+        let player = state.players.get(&self.player)?;
+        if player.elixir < min_elixir {
+            return None;
+        }
+
+        let (hand_index, _) = (0..player.hand.len())
+            .filter_map(|i| {
+                let name = player.get_hand_card(i)?;
+                if let Some(allowed) = &self.config.allowed_cards {
+                    if !allowed.contains(name) {
+                        return None;
+                    }
+                }
+                let cost = state.get_card_by_name(name)?.elixir_cost;
+                (cost <= player.elixir).then_some((i, cost))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))?;
+
+        let noise = self.config.placement_noise;
+        // Keep troops on our side of the river, where they may be deployed
+        let ts = state.arena.tile_size;
+        let (min_x, max_x) = match self.player {
+            PlayerId::Player1 | PlayerId::Player3 => (0.5, RIVER_TILES_X.start as f32 - 0.5),
+            PlayerId::Player2 | PlayerId::Player4 => (RIVER_TILES_X.end as f32 + 0.5, state.arena.width as f32 - 0.5),
+        };
+        let position = Position::new(
+            (target.x + self.rng.rand_range(-noise, noise)).clamp(min_x * ts, max_x * ts),
+            (target.y + self.rng.rand_range(-noise, noise)).clamp(0.5 * ts, (state.arena.height as f32 - 0.5) * ts),
+        );
+
+        Some(Action::PlayCardFromHand {
+            player: self.player,
+            hand_index,
+            position,
+        })
+    }
+}
+
+/// Two default bots playing a 1v1 match out, as soak tests and balance
+/// sweeps run them.
+pub(crate) struct BotMatch {
+    pub state: GameState,
+    bots: [RuleBot; 2],
+}
+
+impl BotMatch {
+    /// Seats a bot on each side of `state`, each with its own noise stream
+    /// derived from `seed`.
+    pub fn new(state: GameState, seed: u64) -> Self {
+        let bots = [PlayerId::Player1, PlayerId::Player2].map(|player| {
+            RuleBot::new(
+                player,
+                BotConfig {
+                    seed: seed.wrapping_add(player as u64),
+                    ..BotConfig::default()
+                },
+            )
+        });
+        Self { state, bots }
+    }
+
+    /// Runs one tick with whatever the bots decide on.
+    pub fn step(&mut self) -> Result<()> {
+        let actions: Vec<_> = self.bots.iter_mut().filter_map(|bot| bot.decide(&self.state)).collect();
+        // A bot's misjudged play mustn't cost the other bot its own
+        crate::step_keeping_valid(&mut self.state, &actions).map(|_| ())
+    }
+
+    /// Steps until the match ends or reaches `max_ticks`.
+    pub fn run(&mut self, max_ticks: u64) -> Result<()> {
+        while self.state.tick < max_ticks && !self.state.is_match_over() {
+            self.step()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> GameState {
+        let mut state = GameState::new(2);
+        let deck: Vec<String> = ["Knight", "Archers", "Giant", "Musketeer"]
+            .iter()
+            .cycle()
+            .take(8)
+            .map(|c| c.to_string())
+            .collect();
+        state.set_player_deck(PlayerId::Player1, deck.clone()).unwrap();
+        state.set_player_deck(PlayerId::Player2, deck).unwrap();
+        state
+    }
+
+    #[test]
+    fn test_reaction_delay() {
+        let mut state = setup();
+        // An enemy Knight already deep in Player2's half
+        state.set_deploy_zones(false);
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        Action::PlayCard {
+            player: PlayerId::Player1,
+            card_name: "Knight".to_string(),
+            level: 11,
+            position: Position::new(20.0, 9.0),
+        }
+        .apply(&mut state)
+        .unwrap();
+
+        let config = BotConfig {
+            reaction_delay_ticks: 10,
+            ..BotConfig::default()
+        };
+        let mut bot = RuleBot::new(PlayerId::Player2, config);
+
+        let mut responded_at = None;
+        for _ in 0..20 {
+            if bot.decide(&state).is_some() {
+                responded_at = Some(state.tick);
+                break;
+            }
+            state.tick += 1;
+        }
+        assert_eq!(responded_at, Some(10));
+
+        // Ticks going backwards (a reset) restart the delay instead of underflowing
+        let mut bot = RuleBot::new(PlayerId::Player2, bot.config().clone());
+        state.tick = 5;
+        assert!(bot.decide(&state).is_none());
+        state.tick = 0;
+        assert!(bot.decide(&state).is_none());
+        state.tick = 10;
+        assert!(bot.decide(&state).is_some());
+    }
+
+    #[test]
+    fn test_allowed_cards_and_threshold() {
+        let mut state = setup();
+        let allowed = state.players[&PlayerId::Player1].get_hand_card(2).unwrap().clone();
+        let config = BotConfig {
+            elixir_threshold: 8.0,
+            allowed_cards: Some(vec![allowed.clone()]),
+            ..BotConfig::default()
+        };
+        let mut bot = RuleBot::new(PlayerId::Player1, config);
+
+        // Starting elixir (5) is below the threshold
+        assert!(bot.decide(&state).is_none());
+
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        let action = bot.decide(&state);
+        let hand_index = match action {
+            Some(Action::PlayCardFromHand { hand_index, .. }) => hand_index,
+            other => panic!("expected a play, got {:?}", other),
+        };
+        let card = state.players[&PlayerId::Player1].get_hand_card(hand_index).unwrap();
+        assert_eq!(card, &allowed);
+    }
+
+    #[test]
+    fn test_teammates_push_from_their_own_side() {
+        let mut state = GameState::new_2v2(2);
+        let deck: Vec<String> = ["Knight", "Archers"].iter().cycle().take(8).map(|c| c.to_string()).collect();
+        state.set_player_deck(PlayerId::Player3, deck).unwrap();
+        state.players.get_mut(&PlayerId::Player3).unwrap().elixir = 10.0;
+        let mut bot = RuleBot::new(PlayerId::Player3, BotConfig::default());
+
+        let mid = state.arena.width as f32 * state.arena.tile_size / 2.0;
+        match bot.decide(&state) {
+            Some(Action::PlayCardFromHand { player, position, .. }) => {
+                assert_eq!(player, PlayerId::Player3);
+                assert!(position.x < mid, "{:?} is across the river", position);
+            }
+            other => panic!("expected a play, got {:?}", other),
+        }
+    }
+}
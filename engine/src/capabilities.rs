@@ -0,0 +1,172 @@
+//! Feature support matrix for the current build.
+//!
+//! Card data can reference mechanics the engine does not simulate yet.
+//! [`capabilities()`] lists what is implemented so loaders can warn (or
+//! refuse) instead of silently simulating such cards as vanilla troops.
+
+use crate::card::Card;
+use serde::Serialize;
+
+/// What the engine implements.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    /// Supported `card_type` values.
+    pub card_types: Vec<&'static str>,
+    /// Supported entries of a card's `effects` list.
+    pub effects: Vec<&'static str>,
+    /// Supported entries of a card's `targets` list.
+    pub target_types: Vec<&'static str>,
+    /// Supported `transport` values.
+    pub transports: Vec<&'static str>,
+    /// Engine mechanics that are simulated.
+    pub mechanics: Vec<&'static str>,
+}
+
+/// Returns the feature support matrix for this build.
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        card_types: vec!["troop", "tower troop", "spell", "building"],
+        effects: vec!["damage", "area", "knockback", "heal"],
+        target_types: vec!["air", "ground", "buildings"],
+        transports: vec!["ground", "air"],
+        mechanics: vec![
+            "tower_entities",
+            "melee_attacks",
+            "homing_projectiles",
+            "splash_projectiles",
+            "aimed_projectiles",
+            "piercing_projectiles",
+            "chaining_projectiles",
+            "area_spells",
+            "spell_travel_time",
+            "first_hit_speed",
+            "shield_hp",
+            "knockback",
+            "river_line_of_sight",
+            "bridge_pathing",
+            "lane_marching",
+            "river_jumping",
+            "soft_collisions",
+            "mass_collisions",
+            "tile_pathfinding",
+            "flying_movement",
+            "damage_zones",
+            "healing",
+            "spawner_buildings",
+            "building_decay",
+            "building_attacks",
+            "minimum_range",
+            "spawn_damage",
+            "deploy_travel_time",
+            "deploy_zones",
+            "retarget_policies",
+            "air_ground_targeting",
+            "ownership_transfer",
+            "friendly_fire",
+        ],
+    }
+}
+
+impl Capabilities {
+    pub fn supports_card_type(&self, card_type: &str) -> bool {
+        self.card_types.contains(&card_type)
+    }
+
+    pub fn supports_effect(&self, effect: &str) -> bool {
+        self.effects.contains(&effect)
+    }
+
+    pub fn supports_mechanic(&self, mechanic: &str) -> bool {
+        self.mechanics.contains(&mechanic)
+    }
+
+    /// Lists the features `card` relies on that this build does not simulate.
+    /// An empty list means the card is fully supported.
+    pub fn unsupported_features(&self, card: &Card) -> Vec<String> {
+        let mut missing = Vec::new();
+
+        if !self.supports_card_type(&card.type_name) {
+            missing.push(format!("card type '{}'", card.type_name));
+        }
+        for effect in card.effects.iter().flatten() {
+            // "spawn" is only simulated for buildings that name what they spawn
+            let periodic_spawn = effect == "spawn" && card.spawn_card.is_some();
+            if !self.supports_effect(effect) && !periodic_spawn {
+                missing.push(format!("effect '{}'", effect));
+            }
+        }
+        for target in card.targets.iter().flatten() {
+            if !self.target_types.contains(&target.as_str()) {
+                missing.push(format!("target type '{}'", target));
+            }
+        }
+        if let Some(transport) = &card.transport {
+            if !self.transports.contains(&transport.as_str()) {
+                missing.push(format!("transport '{}'", transport));
+            }
+        }
+
+        // Stats that only matter for mechanics we don't model yet
+        let mut stat = |name: &str, used: bool| {
+            if used && !self.supports_mechanic(name) {
+                missing.push(format!("mechanic '{}'", name));
+            }
+        };
+        stat("shield_hp", card.levels.iter().any(|l| l.shield_hp.is_some()));
+        stat("healing", card.levels.iter().any(|l| l.healing.is_some()));
+        stat("spawn_damage", card.levels.iter().any(|l| l.spawn_damage.is_some()));
+        // Lingering spells are only simulated as damage zones (with a dps)
+        stat(
+            "lingering_spells",
+            card.type_name == "spell"
+                && card.duration.is_some_and(|d| d > 0.0)
+                && card.levels.iter().all(|l| l.dps.is_none() && l.healing.is_none()),
+        );
+
+        missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_cards_are_supported() {
+        let caps = capabilities();
+        for card in crate::card::get_test_cards() {
+            assert!(caps.unsupported_features(&card).is_empty(), "{}", card.name);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_features_are_listed() {
+        let mut card = crate::card::get_test_cards().remove(0);
+        card.effects = Some(vec!["freeze".to_string()]);
+        // A spell lingering without a dps is only simulated as a zone
+        card.type_name = "spell".to_string();
+        card.duration = Some(5.0);
+
+        let missing = capabilities().unsupported_features(&card);
+        assert_eq!(missing, vec!["effect 'freeze'", "mechanic 'lingering_spells'"]);
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unsupported_deck() {
+        use crate::GameState;
+        use shared::PlayerId;
+
+        let mut cards = crate::card::get_test_cards();
+        cards[0].effects = Some(vec!["freeze".to_string()]);
+        let frozen = cards[0].name.clone();
+        let deck: Vec<String> = cards.iter().take(4).cycle().take(8).map(|c| c.name.clone()).collect();
+
+        let mut state = GameState::new(1);
+        state.load_cards(cards).unwrap();
+        assert!(state.set_player_deck(PlayerId::Player1, deck.clone()).is_ok());
+
+        state.set_strict(true);
+        let err = state.set_player_deck(PlayerId::Player1, deck).unwrap_err().to_string();
+        assert!(err.contains(&frozen) && err.contains("freeze"), "{}", err);
+    }
+}
@@ -1,16 +1,81 @@
 //! Card definitions and behaviors.
 
-use crate::entities::{Entity, EntityKind, TargetType, TroopData};
-use crate::state::GameState;
+use crate::entities::{
+    BuildingAttack, BuildingData, DamageFilter, Entity, EntityKind, HealOnHit, SpawnerData, SpellData, TargetType, Transport,
+    TroopData, ZoneData,
+};
+use crate::formation::Formation;
+use crate::state::{GameState, TowerType};
+use crate::systems::knockback;
 use serde::{Deserialize, Serialize};
 use shared::{PlayerId, Position, Result};
 
+/// Fraction of a spell's area damage dealt to crown towers when the card
+/// does not specify tower damage explicitly.
+pub const CROWN_TOWER_DAMAGE_MULTIPLIER: f32 = 0.3;
+
+/// Travel speed (tiles per second) for spells without a projectile speed.
+pub const DEFAULT_SPELL_SPEED: f32 = 10.0;
+
+/// Knockback distance (tiles) for "knockback" cards that don't set one.
+pub const DEFAULT_KNOCKBACK_DISTANCE: f32 = 1.0;
+
+/// Radius (tiles) around a healer troop that its on-hit healing reaches.
+pub const DEFAULT_HEAL_RADIUS: f32 = 4.0;
+
+/// Radius (tiles) of a landing troop's spawn damage when the card doesn't
+/// set one.
+pub const DEFAULT_SPAWN_DAMAGE_RADIUS: f32 = 2.0;
+
+/// Collision mass for troops that don't set one (`mass_default` in the
+/// legacy mechanics config).
+pub const DEFAULT_MASS: f32 = 5.0;
+
+/// Lifetime (seconds) for buildings that don't list a duration.
+pub const DEFAULT_BUILDING_LIFETIME: f32 = 30.0;
+
+/// Tiles per second for each `movement_speed` tier, used for cards that
+/// don't give a `movement_speed_value`. Defaults follow the legacy
+/// mechanics config (45/60/90/120 at 60 units per tile).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeedTiers {
+    pub slow: f32,
+    pub medium: f32,
+    pub fast: f32,
+    pub very_fast: f32,
+}
+
+impl Default for SpeedTiers {
+    fn default() -> Self {
+        Self {
+            slow: 0.75,
+            medium: 1.0,
+            fast: 1.5,
+            very_fast: 2.0,
+        }
+    }
+}
+
+impl SpeedTiers {
+    /// Speed for a tier name ("slow", "medium", "fast", "very_fast").
+    pub fn resolve(&self, tier: &str) -> Option<f32> {
+        match tier {
+            "slow" => Some(self.slow),
+            "medium" => Some(self.medium),
+            "fast" => Some(self.fast),
+            "very_fast" => Some(self.very_fast),
+            _ => None,
+        }
+    }
+}
+
 /// A card that can be played by a player.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Card {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+    #[serde(default)] // Tower troops have no elixir cost
     pub elixir_cost: f32,
     pub rarity: Rarity,
     #[serde(rename = "card_type")]
@@ -43,6 +108,52 @@ pub struct Card {
     pub radius: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub effects: Option<Vec<String>>, // ["freeze", "knockback", "spawn", etc.]
+    /// Knockback distance (tiles) for cards with the "knockback" effect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub knockback_distance: Option<f32>,
+    /// Card a spawner building produces every `spawn_interval` seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_card: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_interval: Option<f32>,
+    /// Copies of `spawn_card` per wave (default 1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_count: Option<u32>,
+    /// Deploy spread for multi-unit cards (defaults by `count`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formation: Option<Formation>,
+    /// Seconds between casting a spell and it leaving the King tower.
+    /// Falls back to the game's `spell_cast_delay`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cast_delay: Option<f32>,
+    /// May be placed anywhere in the arena, ignoring deploy zones (Miner,
+    /// Goblin Barrel).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deploy_anywhere: bool,
+    /// Seconds a troop spends burrowing or in flight before it appears at
+    /// the deploy point.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub travel_time: Option<f32>,
+    /// Blind spot (tiles) inside which an attacking building can't fire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_range: Option<f32>,
+    /// Siege buildings: target buildings over troops when both are in range.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prefers_buildings: bool,
+    /// Radius (tiles) of the `spawn_damage` blast when the troop lands.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_damage_radius: Option<f32>,
+    /// Knockback distance (tiles) of the spawn blast; none if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_knockback: Option<f32>,
+    /// Collision mass of each troop; heavier troops shove lighter ones.
+    /// Defaults to [`DEFAULT_MASS`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass: Option<f32>,
+    /// Ground troop that leaps the river instead of walking to a bridge
+    /// (Hog Rider, Ram Rider).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub jumps_river: bool,
 
     // Level-based stats
     pub levels: Vec<CardLevelStats>,
@@ -71,10 +182,20 @@ pub struct CardLevelStats {
 impl Card {
     /// Spawns entities when this card is played at a specific level.
     pub fn spawn(&self, state: &mut GameState, owner: PlayerId, position: Position, level: u32) -> Result<()> {
-        // Get stats for the requested level
+        self.check_spawn(state, level)?;
         let level_stats = self.get_level_stats(level)?;
 
         match self.type_name.as_str() {
+            "troop" | "tower troop" if self.travel_time.is_some_and(|t| t > 0.0) => {
+                // Units arrive later; the spawner system lands them
+                state.pending_deploys.push(PendingDeploy {
+                    owner,
+                    card: self.name.clone(),
+                    level,
+                    position,
+                    remaining: self.travel_time.unwrap_or(0.0),
+                });
+            }
             "troop" | "tower troop" => {
                 self.spawn_troop(state, owner, position, level_stats)?;
             }
@@ -94,6 +215,48 @@ impl Card {
         Ok(())
     }
 
+    /// Errors if [`spawn`](Self::spawn) would reject this card at `level`:
+    /// a missing level, an unknown card type, a spell with nothing to deal
+    /// or a building spawning an unknown card. Plays check this before they
+    /// commit.
+    pub fn check_spawn(&self, state: &GameState, level: u32) -> Result<()> {
+        let level_stats = self.get_level_stats(level)?;
+        match self.type_name.as_str() {
+            "troop" | "tower troop" => Ok(()),
+            "spell" => {
+                let lingers = self.duration.unwrap_or(0.0) > 0.0
+                    && (level_stats.dps.is_some() || level_stats.healing.is_some());
+                if lingers || Self::spell_damage(level_stats).is_some() {
+                    return Ok(());
+                }
+                Err(shared::Error::InvalidAction(format!(
+                    "Spell {} has no damage at level {}",
+                    self.name, level_stats.level
+                )))
+            }
+            "building" => {
+                // Catch a bad reference at deploy time rather than mid-match
+                if let Some(card_name) = &self.spawn_card {
+                    let spawned = state.get_card_by_name(card_name).ok_or_else(|| {
+                        shared::Error::InvalidAction(format!("{} spawns unknown card {}", self.name, card_name))
+                    })?;
+                    spawned.get_level_stats(level_stats.level)?;
+                }
+                Ok(())
+            }
+            _ => Err(shared::Error::InvalidAction(format!(
+                "Unknown card type: {}",
+                self.type_name
+            ))),
+        }
+    }
+
+    /// Spawns a travelling troop card's units once it reaches its deploy point.
+    pub(crate) fn land(&self, state: &mut GameState, deploy: &PendingDeploy) -> Result<()> {
+        let level_stats = self.get_level_stats(deploy.level)?;
+        self.spawn_troop(state, deploy.owner, deploy.position, level_stats)
+    }
+
     /// Get stats for a specific card level.
     pub fn get_level_stats(&self, level: u32) -> Result<&CardLevelStats> {
         self.levels
@@ -104,25 +267,41 @@ impl Card {
             })
     }
 
+    /// Knockback distance if this card has the "knockback" effect.
+    pub fn knockback(&self) -> Option<f32> {
+        let has_effect = self.effects.iter().flatten().any(|e| e == "knockback");
+        has_effect.then(|| self.knockback_distance.unwrap_or(DEFAULT_KNOCKBACK_DISTANCE))
+    }
+
     /// Get the target type from the targets list.
-    fn get_target_type(&self) -> TargetType {
-        match &self.targets {
-            Some(targets) => {
-                let has_air = targets.iter().any(|t| t == "air");
-                let has_ground = targets.iter().any(|t| t == "ground");
-                let has_buildings = targets.iter().any(|t| t == "buildings");
-
-                if has_buildings {
-                    TargetType::Buildings
-                } else if has_air && has_ground {
-                    TargetType::Both
-                } else if has_air {
-                    TargetType::Air
-                } else {
-                    TargetType::Ground
-                }
-            }
-            None => TargetType::Both, // Default
+    pub(crate) fn get_target_type(&self) -> TargetType {
+        TargetType::from_targets(self.targets.as_deref())
+    }
+
+    /// Tiles per second the card's troops move at: an explicit value wins
+    /// over the tier. `None` if neither resolves.
+    pub(crate) fn resolved_movement_speed(&self, tiers: &SpeedTiers) -> Option<f32> {
+        self.movement_speed_value
+            .or_else(|| self.movement_speed.as_deref().and_then(|tier| tiers.resolve(tier)))
+    }
+
+    /// A spell's `(damage, crown tower damage)` at `level_stats`. Cards with
+    /// both values list area damage for troops and a separate (reduced)
+    /// crown tower damage; otherwise the tower damage is derived. `None` if
+    /// the level has no damage at all.
+    pub(crate) fn spell_damage(level_stats: &CardLevelStats) -> Option<(f32, f32)> {
+        match (level_stats.area_damage, level_stats.damage) {
+            (Some(area), Some(tower)) => Some((area, tower)),
+            (Some(area), None) | (None, Some(area)) => Some((area, area * CROWN_TOWER_DAMAGE_MULTIPLIER)),
+            (None, None) => None,
+        }
+    }
+
+    /// Get the transport from the transport string (defaults to ground).
+    pub(crate) fn get_transport(&self) -> Transport {
+        match self.transport.as_deref() {
+            Some("air") => Transport::Air,
+            _ => Transport::Ground,
         }
     }
 
@@ -135,41 +314,132 @@ impl Card {
     ) -> Result<()> {
         let count = self.count.unwrap_or(1);
         let hp = level_stats.hp.unwrap_or(100.0);
-        let damage = level_stats.damage.unwrap_or(10.0);
+        // Area-damage troops list their hit as area_damage and splash over `radius`
+        let damage = level_stats.damage.or(level_stats.area_damage).unwrap_or(10.0);
+        let splash_radius = level_stats.area_damage.and(self.radius);
         let range = self.range.unwrap_or(1.0);
 
         // Determine if this is a ranged unit based on attack range
         // Melee units have range <= 2.0, ranged units have range > 2.0
         let is_ranged = range > 2.0;
 
-        for _ in 0..count {
-            let entity = Entity::new(
+        // Spread multi-unit cards out, facing the enemy side
+        let forward = match owner {
+            PlayerId::Player1 | PlayerId::Player3 => (1.0, 0.0),
+            PlayerId::Player2 | PlayerId::Player4 => (-1.0, 0.0),
+        };
+        let min = Position::new(0.5, 0.5);
+        let max = Position::new(
+            state.arena.width as f32 * state.arena.tile_size - 0.5,
+            state.arena.height as f32 * state.arena.tile_size - 0.5,
+        );
+        let lane = state.arena.lane_of(&position);
+        let movement_speed = self.resolved_movement_speed(&state.speed_tiers).unwrap_or(60.0);
+        let positions = self
+            .formation
+            .unwrap_or_else(|| Formation::default_for(count))
+            .positions(count, position, forward);
+
+        for unit_position in positions {
+            let mut entity = Entity::new(
                 owner,
-                position,
+                unit_position.clamp_to_rect(&min, &max),
                 EntityKind::Troop(TroopData {
                     base_hp: hp,
                     damage,
                     range,
                     attack_speed: self.attack_speed.unwrap_or(1.0),
-                    movement_speed: self.movement_speed_value.unwrap_or(60.0),
+                    first_hit_speed: self.first_hit_speed.unwrap_or(0.0),
+                    movement_speed,
                     target_type: self.get_target_type(),
+                    transport: self.get_transport(),
                     is_ranged,
+                    splash_radius,
+                    knockback: self.knockback(),
+                    heal_on_hit: level_stats.healing.map(|amount| HealOnHit {
+                        amount,
+                        radius: DEFAULT_HEAL_RADIUS,
+                    }),
+                    mass: self.mass.unwrap_or(DEFAULT_MASS),
+                    jumps_river: self.jumps_river,
+                    lane: Some(lane),
+                    card: self.name.clone(),
+                    level: level_stats.level,
                 }),
-            );
+            )
+            .with_shield(level_stats.shield_hp.unwrap_or(0.0));
+            entity.position = state.free_spawn_position(&entity);
             state.add_entity(entity);
         }
+
+        if let Some(damage) = level_stats.spawn_damage {
+            self.apply_spawn_damage(state, owner, position, damage);
+        }
         Ok(())
     }
 
+    /// Damages (and optionally knocks back) every enemy around the deploy
+    /// point as the troop lands (Mega Knight, Electro Giant).
+    fn apply_spawn_damage(&self, state: &mut GameState, owner: PlayerId, position: Position, damage: f32) {
+        let radius = self.spawn_damage_radius.unwrap_or(DEFAULT_SPAWN_DAMAGE_RADIUS);
+        for id in state.targets_in_radius(owner, DamageFilter::Enemies, &position, radius) {
+            state.damage(id, damage);
+            if let Some(distance) = self.spawn_knockback {
+                knockback::push_from(state, id, &position, distance);
+            }
+        }
+    }
+
     fn spawn_building(
         &self,
         state: &mut GameState,
         owner: PlayerId,
         position: Position,
-        _level_stats: &CardLevelStats,
+        level_stats: &CardLevelStats,
     ) -> Result<()> {
-        // TODO: Implement building spawning
-        let _ = (state, owner, position);
+        let spawner = match &self.spawn_card {
+            Some(card_name) => {
+                let interval = self.spawn_interval.unwrap_or(1.0).max(crate::DELTA_TIME);
+                Some(SpawnerData {
+                    card: card_name.clone(),
+                    level: level_stats.level,
+                    count: self.spawn_count.unwrap_or(1),
+                    interval,
+                    timer: interval,
+                })
+            }
+            None => None,
+        };
+
+        // Spawners don't attack; anything else with damage and a range does
+        let damage = level_stats.damage.or(level_stats.area_damage);
+        let attack = match (damage, self.range) {
+            (Some(damage), Some(range)) if spawner.is_none() => Some(BuildingAttack {
+                damage,
+                range,
+                min_range: self.min_range.unwrap_or(0.0),
+                attack_speed: self.attack_speed.unwrap_or(1.0),
+                first_hit_speed: self.first_hit_speed.unwrap_or(0.0),
+                target_type: self.get_target_type(),
+                splash_radius: level_stats.area_damage.and(self.radius),
+                prefers_buildings: self.prefers_buildings,
+            }),
+            _ => None,
+        };
+
+        let mut entity = Entity::new(
+            owner,
+            position,
+            EntityKind::Building(BuildingData {
+                base_hp: level_stats.hp.unwrap_or(100.0),
+                lifetime: self.duration.unwrap_or(DEFAULT_BUILDING_LIFETIME),
+                spawner,
+                attack,
+                card: self.name.clone(),
+            }),
+        );
+        entity.position = state.free_spawn_position(&entity);
+        state.add_entity(entity);
         Ok(())
     }
 
@@ -180,12 +450,71 @@ impl Card {
         position: Position,
         level_stats: &CardLevelStats,
     ) -> Result<()> {
-        // TODO: Implement spell effects using level_stats.area_damage or .damage
-        let _ = (state, owner, position, level_stats);
+        // Spells with a duration and a dps (or healing) leave a lingering zone
+        let duration = self.duration.unwrap_or(0.0);
+        if duration > 0.0 && (level_stats.dps.is_some() || level_stats.healing.is_some()) {
+            let dps = level_stats.dps.unwrap_or(0.0);
+            let entity = Entity::new(
+                owner,
+                position,
+                EntityKind::Zone(ZoneData {
+                    dps,
+                    tower_dps: dps * CROWN_TOWER_DAMAGE_MULTIPLIER,
+                    heal_per_second: level_stats.healing.unwrap_or(0.0),
+                    radius: self.radius.unwrap_or(1.0),
+                    remaining: duration,
+                    target_type: self.get_target_type(),
+                    affects: DamageFilter::Enemies,
+                    card: self.name.clone(),
+                }),
+            );
+            state.add_entity(entity);
+            return Ok(());
+        }
+
+        let Some((damage, tower_damage)) = Self::spell_damage(level_stats) else {
+            return Err(shared::Error::InvalidAction(format!(
+                "Spell {} has no damage at level {}",
+                self.name, level_stats.level
+            )));
+        };
+
+        // Spells are launched from the caster's King tower after the cast delay
+        let origin = state.arena.tower_position(owner, TowerType::King);
+        let speed = self.projectile_speed.unwrap_or(DEFAULT_SPELL_SPEED);
+        let cast_delay = self.cast_delay.unwrap_or(state.spell_cast_delay);
+        let time_to_impact = cast_delay + origin.distance_to(&position) / speed;
+
+        let entity = Entity::new(
+            owner,
+            position,
+            EntityKind::Spell(SpellData {
+                damage,
+                tower_damage,
+                radius: self.radius.unwrap_or(1.0),
+                duration: self.duration.unwrap_or(0.0),
+                time_to_impact,
+                knockback: self.knockback(),
+                affects: DamageFilter::Enemies,
+                card: self.name.clone(),
+            }),
+        );
+        state.add_entity(entity);
         Ok(())
     }
 }
 
+/// A troop card on its way to the deploy point (Miner burrowing, Goblin
+/// Barrel in flight). Its units appear when `remaining` runs out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDeploy {
+    pub owner: PlayerId,
+    pub card: String,
+    pub level: u32,
+    pub position: Position,
+    pub remaining: f32,
+}
+
 /// Card rarity.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -230,6 +559,20 @@ pub fn get_test_cards() -> Vec<Card> {
             duration: None,
             radius: None,
             effects: None,
+            knockback_distance: None,
+            spawn_card: None,
+            spawn_interval: None,
+            spawn_count: None,
+            formation: None,
+            cast_delay: None,
+            deploy_anywhere: false,
+            travel_time: None,
+            min_range: None,
+            prefers_buildings: false,
+            spawn_damage_radius: None,
+            spawn_knockback: None,
+            mass: None,
+            jumps_river: false,
             levels: vec![
                 CardLevelStats {
                     level: 11,
@@ -263,6 +606,20 @@ pub fn get_test_cards() -> Vec<Card> {
             duration: None,
             radius: None,
             effects: None,
+            knockback_distance: None,
+            spawn_card: None,
+            spawn_interval: None,
+            spawn_count: None,
+            formation: None,
+            cast_delay: None,
+            deploy_anywhere: false,
+            travel_time: None,
+            min_range: None,
+            prefers_buildings: false,
+            spawn_damage_radius: None,
+            spawn_knockback: None,
+            mass: None,
+            jumps_river: false,
             levels: vec![
                 CardLevelStats {
                     level: 11,
@@ -296,6 +653,20 @@ pub fn get_test_cards() -> Vec<Card> {
             duration: None,
             radius: None,
             effects: None,
+            knockback_distance: None,
+            spawn_card: None,
+            spawn_interval: None,
+            spawn_count: None,
+            formation: None,
+            cast_delay: None,
+            deploy_anywhere: false,
+            travel_time: None,
+            min_range: None,
+            prefers_buildings: false,
+            spawn_damage_radius: None,
+            spawn_knockback: None,
+            mass: None,
+            jumps_river: false,
             levels: vec![
                 CardLevelStats {
                     level: 11,
@@ -309,6 +680,100 @@ pub fn get_test_cards() -> Vec<Card> {
                 }
             ],
         },
+        // Musketeer - 4 elixir ranged single-target (hits air)
+        Card {
+            name: "Musketeer".to_string(),
+            url: None,
+            elixir_cost: 4.0,
+            rarity: Rarity::Rare,
+            type_name: "troop".to_string(),
+            attack_speed: Some(1.0),
+            first_hit_speed: None,
+            movement_speed: Some("medium".to_string()),
+            movement_speed_value: Some(1.0),
+            deploy_time: Some(1.0),
+            range: Some(6.0),
+            projectile_speed: None,
+            targets: Some(vec!["air".to_string(), "ground".to_string()]),
+            count: Some(1),
+            transport: Some("ground".to_string()),
+            duration: None,
+            radius: None,
+            effects: None,
+            knockback_distance: None,
+            spawn_card: None,
+            spawn_interval: None,
+            spawn_count: None,
+            formation: None,
+            cast_delay: None,
+            deploy_anywhere: false,
+            travel_time: None,
+            min_range: None,
+            prefers_buildings: false,
+            spawn_damage_radius: None,
+            spawn_knockback: None,
+            mass: None,
+            jumps_river: false,
+            levels: vec![
+                CardLevelStats {
+                    level: 11,
+                    hp: Some(721.0),
+                    damage: Some(217.0),
+                    dps: None,
+                    area_damage: None,
+                    spawn_damage: None,
+                    shield_hp: None,
+                    healing: None,
+                }
+            ],
+        },
+        // Baby Dragon - 4 elixir flying troop
+        Card {
+            name: "Baby Dragon".to_string(),
+            url: None,
+            elixir_cost: 4.0,
+            rarity: Rarity::Epic,
+            type_name: "troop".to_string(),
+            attack_speed: Some(1.5),
+            first_hit_speed: None,
+            movement_speed: Some("fast".to_string()),
+            movement_speed_value: Some(1.5),
+            deploy_time: Some(1.0),
+            range: Some(3.5),
+            projectile_speed: None,
+            targets: Some(vec!["air".to_string(), "ground".to_string()]),
+            count: Some(1),
+            transport: Some("air".to_string()),
+            duration: None,
+            radius: Some(1.5),
+            effects: None,
+            knockback_distance: None,
+            spawn_card: None,
+            spawn_interval: None,
+            spawn_count: None,
+            formation: None,
+            cast_delay: None,
+            deploy_anywhere: false,
+            travel_time: None,
+            min_range: None,
+            prefers_buildings: false,
+            spawn_damage_radius: None,
+            spawn_knockback: None,
+            mass: None,
+            jumps_river: false,
+            levels: vec![
+                CardLevelStats {
+                    level: 11,
+                    hp: Some(1152.0),
+                    damage: None,
+                    dps: None,
+                    area_damage: Some(161.0),
+                    spawn_damage: None,
+                    shield_hp: None,
+                    healing: None,
+                }
+            ],
+        },
         // Fireball - 4 elixir damage spell
         Card {
             name: "Fireball".to_string(),
@@ -329,6 +794,20 @@ pub fn get_test_cards() -> Vec<Card> {
             duration: None,
             radius: Some(2.5),
             effects: Some(vec!["damage".to_string()]),
+            knockback_distance: None,
+            spawn_card: None,
+            spawn_interval: None,
+            spawn_count: None,
+            formation: None,
+            cast_delay: None,
+            deploy_anywhere: false,
+            travel_time: None,
+            min_range: None,
+            prefers_buildings: false,
+            spawn_damage_radius: None,
+            spawn_knockback: None,
+            mass: None,
+            jumps_river: false,
             levels: vec![
                 CardLevelStats {
                     level: 11,
@@ -362,6 +841,20 @@ pub fn get_test_cards() -> Vec<Card> {
             duration: None,
             radius: Some(4.0),
             effects: Some(vec!["damage".to_string()]),
+            knockback_distance: None,
+            spawn_card: None,
+            spawn_interval: None,
+            spawn_count: None,
+            formation: None,
+            cast_delay: None,
+            deploy_anywhere: false,
+            travel_time: None,
+            min_range: None,
+            prefers_buildings: false,
+            spawn_damage_radius: None,
+            spawn_knockback: None,
+            mass: None,
+            jumps_river: false,
             levels: vec![
                 CardLevelStats {
                     level: 11,
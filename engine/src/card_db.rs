@@ -0,0 +1,235 @@
+//! Cards from several patches or card packs at once.
+//!
+//! A match knows its cards by bare name ([`GameState::load_cards`]), so
+//! loading two sets with overlapping names into one list would let the
+//! later silently replace the earlier. A [`CardDb`] keeps every set apart,
+//! keyed by (patch, name), and resolves deck references against it: a bare
+//! name when only one set has the card, or `patch/name` to pick one. A
+//! reference more than one set could mean is an error, never a guess.
+//!
+//! [`GameState::set_decks_from_db`] loads the cards a match's decks pick
+//! and deals the decks.
+//!
+//! [`GameState::load_cards`]: crate::state::GameState::load_cards
+//! [`GameState::set_decks_from_db`]: crate::state::GameState::set_decks_from_db
+
+use crate::card::{self, Card};
+use serde::{Deserialize, Serialize};
+use shared::{Error, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Separates the patch from the name in a qualified reference.
+pub const PATCH_SEPARATOR: char = '/';
+
+/// Where a card comes from: its patch (or pack) and its name there.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CardKey {
+    pub patch: String,
+    pub name: String,
+}
+
+impl CardKey {
+    pub fn new(patch: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            patch: patch.into(),
+            name: name.into(),
+        }
+    }
+}
+
+/// The qualified reference, `patch/name`.
+impl fmt::Display for CardKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.patch, PATCH_SEPARATOR, self.name)
+    }
+}
+
+/// Cards of any number of patches, keyed by (patch, name).
+#[derive(Debug, Clone, Default)]
+pub struct CardDb {
+    cards: BTreeMap<CardKey, Card>,
+}
+
+impl CardDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the cards of `patch`. Errors, adding nothing, if the patch is
+    /// already loaded, its id contains [`PATCH_SEPARATOR`], or two of its
+    /// cards share a name.
+    pub fn add_patch(&mut self, patch: &str, cards: Vec<Card>) -> Result<()> {
+        if patch.is_empty() || patch.contains(PATCH_SEPARATOR) {
+            return Err(Error::Configuration(format!(
+                "Patch id '{}' must be non-empty and free of '{}'",
+                patch, PATCH_SEPARATOR
+            )));
+        }
+        if self.patches().any(|p| p == patch) {
+            return Err(Error::Configuration(format!("Patch '{}' is already loaded", patch)));
+        }
+        let mut added = BTreeMap::new();
+        for card in cards {
+            let key = CardKey::new(patch, card.name.clone());
+            if added.insert(key.clone(), card).is_some() {
+                return Err(Error::Configuration(format!("Card {} appears more than once", key)));
+            }
+        }
+        self.cards.append(&mut added);
+        Ok(())
+    }
+
+    /// Reads a cards JSON file (see [`card::load_cards_from_json`]) in as
+    /// `patch`.
+    pub fn load_patch(&mut self, patch: &str, path: &str) -> Result<()> {
+        self.add_patch(patch, card::load_cards_from_json(path)?)
+    }
+
+    /// Number of cards across all patches.
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Loaded patch ids, in order.
+    pub fn patches(&self) -> impl Iterator<Item = &str> {
+        let mut last: Option<&str> = None;
+        self.cards.keys().filter_map(move |key| {
+            let new = last != Some(key.patch.as_str());
+            last = Some(key.patch.as_str());
+            new.then_some(key.patch.as_str())
+        })
+    }
+
+    /// Every card of `patch`, in name order.
+    pub fn patch_cards<'a>(&'a self, patch: &'a str) -> impl Iterator<Item = &'a Card> + 'a {
+        self.cards.iter().filter(move |(key, _)| key.patch == patch).map(|(_, card)| card)
+    }
+
+    pub fn get(&self, key: &CardKey) -> Option<&Card> {
+        self.cards.get(key)
+    }
+
+    /// Every loaded card called `name`, one per patch that has it: the
+    /// choices a bare reference to it is ambiguous between.
+    pub fn candidates<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a CardKey> + 'a {
+        self.cards.keys().filter(move |key| key.name == name)
+    }
+
+    /// The card `reference` names: `patch/name`, or a bare name that only
+    /// one patch has.
+    ///
+    /// # Errors
+    /// If nothing matches, or a bare name is in several patches (the error
+    /// lists the qualified references to choose from).
+    pub fn resolve(&self, reference: &str) -> Result<(&CardKey, &Card)> {
+        if let Some((patch, name)) = reference.split_once(PATCH_SEPARATOR) {
+            if self.patches().any(|p| p == patch) {
+                return self
+                    .cards
+                    .get_key_value(&CardKey::new(patch, name))
+                    .ok_or_else(|| Error::InvalidAction(format!("Card '{}' not found in patch '{}'", name, patch)));
+            }
+        }
+        let mut matches = self.cards.iter().filter(|(key, _)| key.name == reference);
+        match (matches.next(), matches.next()) {
+            (Some(card), None) => Ok(card),
+            (None, _) => Err(Error::InvalidAction(format!("Card '{}' not found in any patch", reference))),
+            (Some(_), Some(_)) => {
+                let choices: Vec<String> = self.candidates(reference).map(CardKey::to_string).collect();
+                Err(Error::InvalidAction(format!(
+                    "Card '{}' is ambiguous; use one of {}",
+                    reference,
+                    choices.join(", ")
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{DeckCard, GameState};
+    use shared::PlayerId;
+
+    /// The test cards, with every Knight's HP scaled by `hp_scale`.
+    fn pack(hp_scale: f32) -> Vec<Card> {
+        let mut cards = card::get_test_cards();
+        for card in cards.iter_mut().filter(|c| c.name == "Knight") {
+            for level in &mut card.levels {
+                level.hp = level.hp.map(|hp| hp * hp_scale);
+            }
+        }
+        cards
+    }
+
+    fn knight_hp(card: &Card) -> f32 {
+        card.levels[0].hp.unwrap()
+    }
+
+    #[test]
+    fn test_overlapping_patches_stay_apart() {
+        let mut db = CardDb::new();
+        db.add_patch("base", pack(1.0)).unwrap();
+        db.add_patch("buffed", pack(2.0)).unwrap();
+        assert_eq!(db.patches().collect::<Vec<_>>(), ["base", "buffed"]);
+        assert_eq!(db.len(), 2 * card::get_test_cards().len());
+
+        let (key, buffed) = db.resolve("buffed/Knight").unwrap();
+        assert_eq!(key, &CardKey::new("buffed", "Knight"));
+        let base = db.get(&CardKey::new("base", "Knight")).unwrap();
+        assert_eq!(knight_hp(buffed), 2.0 * knight_hp(base));
+
+        let error = db.resolve("Knight").unwrap_err().to_string();
+        assert!(error.contains("base/Knight") && error.contains("buffed/Knight"), "{}", error);
+        assert!(db.resolve("buffed/Goblin").is_err());
+        assert!(db.resolve("Goblin").is_err());
+
+        // Names only one patch has need no qualifying
+        let mut solo = card::get_test_cards().into_iter().find(|c| c.name == "Archers").unwrap();
+        solo.name = "Crossbows".to_string();
+        db.add_patch("pack", vec![solo]).unwrap();
+        assert_eq!(db.resolve("Crossbows").unwrap().0.patch, "pack");
+
+        assert!(db.add_patch("base", Vec::new()).is_err());
+        assert!(db.add_patch("a/b", Vec::new()).is_err());
+        let doubled = [pack(1.0), pack(1.0)].concat();
+        assert!(db.add_patch("doubled", doubled).is_err());
+        assert!(db.patches().all(|p| p != "doubled"));
+    }
+
+    #[test]
+    fn test_decks_pick_cards_by_patch() {
+        let mut db = CardDb::new();
+        db.add_patch("base", pack(1.0)).unwrap();
+        db.add_patch("buffed", pack(2.0)).unwrap();
+        let deck = |knight: &str| -> Vec<DeckCard> {
+            let rest = ["Archers", "Giant", "Musketeer", "Arrows", "Baby Dragon", "Fireball"];
+            let mut deck: Vec<DeckCard> = rest.iter().map(|name| DeckCard::new(format!("base/{}", name), 11)).collect();
+            deck.push(DeckCard::new(knight, 11));
+            deck.push(DeckCard::new("base/Archers", 11));
+            deck
+        };
+
+        let mut state = GameState::new(3);
+        state
+            .set_decks_from_db(&db, &[(PlayerId::Player1, deck("buffed/Knight")), (PlayerId::Player2, deck("buffed/Knight"))])
+            .unwrap();
+        let buffed = db.get(&CardKey::new("buffed", "Knight")).unwrap();
+        assert_eq!(knight_hp(state.get_card_by_name("Knight").unwrap()), knight_hp(buffed));
+        assert!(state.players[&PlayerId::Player1].deck.iter().any(|c| c.name == "Knight"));
+
+        // A bare Knight could be either, and a match can't hold both
+        let before = serde_json::to_string(&state).unwrap();
+        let ambiguous = state.set_decks_from_db(&db, &[(PlayerId::Player1, deck("Knight"))]);
+        assert!(ambiguous.unwrap_err().to_string().contains("ambiguous"));
+        let mixed = [(PlayerId::Player1, deck("base/Knight")), (PlayerId::Player2, deck("buffed/Knight"))];
+        assert!(state.set_decks_from_db(&db, &mixed).is_err());
+        assert_eq!(serde_json::to_string(&state).unwrap(), before);
+    }
+}
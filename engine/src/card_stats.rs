@@ -0,0 +1,310 @@
+//! Effective card stats: what the engine will actually simulate for each
+//! loaded card, for UIs and analysis.
+//!
+//! Raw [`Card`] data leaves a lot implicit (speed tiers, missing values the
+//! spawn code defaults, spell damage split between troops and towers).
+//! [`Card::stats`] resolves all of it the same way spawning does, and
+//! rejects cards the engine would fail on or misplay, so the numbers here
+//! are the ones a match uses.
+
+use crate::capabilities::capabilities;
+use crate::card::{Card, CardLevelStats, Rarity, CROWN_TOWER_DAMAGE_MULTIPLIER, DEFAULT_BUILDING_LIFETIME, DEFAULT_MASS};
+use crate::entities::{TargetType, Transport};
+use crate::formation::Formation;
+use crate::state::GameState;
+use serde::Serialize;
+use shared::{Error, Result};
+
+/// A card as the engine simulates it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveCardStats {
+    pub name: String,
+    pub card_type: String,
+    pub elixir_cost: f32,
+    pub rarity: Rarity,
+    /// Units per play (troops; 1 for everything else).
+    pub count: u32,
+    /// How a multi-unit troop card spreads out.
+    pub formation: Option<Formation>,
+    pub transport: Transport,
+    pub target_type: TargetType,
+    /// Tiles per second (troops).
+    pub movement_speed: Option<f32>,
+    /// Attack range in tiles (troops, attacking buildings).
+    pub range: Option<f32>,
+    pub min_range: f32,
+    /// Ranged troops fire projectiles; the rest hit in melee.
+    pub is_ranged: bool,
+    pub attack_speed: f32,
+    pub first_hit_speed: f32,
+    /// Collision mass (troops).
+    pub mass: Option<f32>,
+    pub jumps_river: bool,
+    pub knockback: Option<f32>,
+    /// Seconds a building stands or a zone lingers.
+    pub lifetime: Option<f32>,
+    /// Area radius of a spell or zone, in tiles.
+    pub radius: Option<f32>,
+    /// Seconds before a travelling troop lands.
+    pub travel_time: Option<f32>,
+    /// What a spawner building produces.
+    pub spawner: Option<SpawnerStats>,
+    /// Card data the engine ignores (see [`crate::capabilities`]); empty
+    /// when everything is simulated.
+    pub unsupported: Vec<String>,
+    /// One entry per level, in the card's order.
+    pub levels: Vec<LevelStats>,
+}
+
+/// A spawner building's waves.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpawnerStats {
+    pub card: String,
+    pub interval: f32,
+    pub count: u32,
+}
+
+/// Level-dependent stats, with the defaults filled in (compare the raw
+/// [`CardLevelStats`]).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LevelStats {
+    pub level: u32,
+    /// Per unit, for troops and buildings.
+    pub hp: Option<f32>,
+    pub shield: f32,
+    /// Per hit for troops and attacking buildings; on impact for spells.
+    pub damage: Option<f32>,
+    /// What an area spell (or, per second, a zone) deals to crown towers.
+    pub tower_damage: Option<f32>,
+    /// Damage per second of a lingering zone.
+    pub dps: Option<f32>,
+    /// Splash radius of each hit (area-damage troops and buildings).
+    pub splash_radius: Option<f32>,
+    /// Healing per hit (healer troops) or per second (zones).
+    pub healing: Option<f32>,
+    /// Blast when the troop lands.
+    pub spawn_damage: Option<f32>,
+}
+
+impl LevelStats {
+    fn new(level: u32) -> Self {
+        Self {
+            level,
+            hp: None,
+            shield: 0.0,
+            damage: None,
+            tower_damage: None,
+            dps: None,
+            splash_radius: None,
+            healing: None,
+            spawn_damage: None,
+        }
+    }
+}
+
+impl Card {
+    /// The stats this card plays with in `state` (its speed tiers, its other
+    /// cards). Errors if the engine can't simulate the card as written: an
+    /// unknown type, no levels, a troop with no usable movement speed, a
+    /// spell level with no damage, or a spawner naming a card (or level)
+    /// that isn't loaded.
+    pub fn stats(&self, state: &GameState) -> Result<EffectiveCardStats> {
+        let invalid = |reason: String| Error::Configuration(format!("{}: {}", self.name, reason));
+        if self.levels.is_empty() {
+            return Err(invalid("no levels".to_string()));
+        }
+        let mut stats = EffectiveCardStats {
+            name: self.name.clone(),
+            card_type: self.type_name.clone(),
+            elixir_cost: self.elixir_cost,
+            rarity: self.rarity,
+            count: 1,
+            formation: None,
+            transport: self.get_transport(),
+            target_type: self.get_target_type(),
+            movement_speed: None,
+            range: None,
+            min_range: 0.0,
+            is_ranged: false,
+            attack_speed: self.attack_speed.unwrap_or(1.0),
+            first_hit_speed: self.first_hit_speed.unwrap_or(0.0),
+            mass: None,
+            jumps_river: false,
+            knockback: self.knockback(),
+            lifetime: None,
+            radius: None,
+            travel_time: None,
+            spawner: None,
+            unsupported: capabilities().unsupported_features(self),
+            levels: Vec::new(),
+        };
+
+        match self.type_name.as_str() {
+            "troop" | "tower troop" => {
+                let count = self.count.unwrap_or(1);
+                let range = self.range.unwrap_or(1.0);
+                let movement_speed = self
+                    .resolved_movement_speed(&state.speed_tiers)
+                    .ok_or_else(|| invalid(format!("unknown movement speed {:?}", self.movement_speed)))?;
+                stats.count = count;
+                stats.formation = (count > 1).then(|| self.formation.unwrap_or_else(|| Formation::default_for(count)));
+                stats.movement_speed = Some(movement_speed);
+                stats.range = Some(range);
+                stats.is_ranged = range > 2.0;
+                stats.mass = Some(self.mass.unwrap_or(DEFAULT_MASS));
+                stats.jumps_river = self.jumps_river && stats.transport == Transport::Ground;
+                stats.travel_time = self.travel_time.filter(|&t| t > 0.0);
+                stats.levels = self.levels.iter().map(|l| self.troop_level(l)).collect();
+            }
+            "building" => {
+                stats.lifetime = Some(self.duration.unwrap_or(DEFAULT_BUILDING_LIFETIME));
+                if let Some(card) = &self.spawn_card {
+                    let spawned = state
+                        .get_card_by_name(card)
+                        .ok_or_else(|| invalid(format!("spawns unknown card {}", card)))?;
+                    if let Some(level) = self.levels.iter().find(|l| spawned.get_level_stats(l.level).is_err()) {
+                        return Err(invalid(format!("spawns {} with no level {}", card, level.level)));
+                    }
+                    stats.spawner = Some(SpawnerStats {
+                        card: card.clone(),
+                        interval: self.spawn_interval.unwrap_or(1.0).max(crate::DELTA_TIME),
+                        count: self.spawn_count.unwrap_or(1),
+                    });
+                } else if self.range.is_some() {
+                    stats.range = self.range;
+                    stats.min_range = self.min_range.unwrap_or(0.0);
+                }
+                stats.levels = self.levels.iter().map(|l| self.building_level(l, stats.range.is_some())).collect();
+            }
+            "spell" => {
+                stats.radius = Some(self.radius.unwrap_or(1.0));
+                stats.levels = self
+                    .levels
+                    .iter()
+                    .map(|l| self.spell_level(l).ok_or_else(|| invalid(format!("no damage at level {}", l.level))))
+                    .collect::<Result<_>>()?;
+                if stats.levels.iter().any(|l| l.dps.is_some()) {
+                    stats.lifetime = self.duration;
+                }
+            }
+            other => return Err(invalid(format!("unknown card type {}", other))),
+        }
+        Ok(stats)
+    }
+
+    /// Mirrors [`Card::spawn`] for troops.
+    fn troop_level(&self, raw: &CardLevelStats) -> LevelStats {
+        LevelStats {
+            hp: Some(raw.hp.unwrap_or(100.0)),
+            shield: raw.shield_hp.unwrap_or(0.0),
+            damage: Some(raw.damage.or(raw.area_damage).unwrap_or(10.0)),
+            splash_radius: raw.area_damage.and(self.radius),
+            healing: raw.healing,
+            spawn_damage: raw.spawn_damage,
+            ..LevelStats::new(raw.level)
+        }
+    }
+
+    /// Mirrors [`Card::spawn`] for buildings; only attacking ones deal damage.
+    fn building_level(&self, raw: &CardLevelStats, attacks: bool) -> LevelStats {
+        let damage = raw.damage.or(raw.area_damage).filter(|_| attacks);
+        LevelStats {
+            hp: Some(raw.hp.unwrap_or(100.0)),
+            damage,
+            splash_radius: damage.and(raw.area_damage).and(self.radius),
+            ..LevelStats::new(raw.level)
+        }
+    }
+
+    /// Mirrors [`Card::spawn`] for spells: a lingering zone at levels with
+    /// a duration and a dps (or healing), an area hit otherwise. `None` for
+    /// an area hit with no damage.
+    fn spell_level(&self, raw: &CardLevelStats) -> Option<LevelStats> {
+        let mut stats = LevelStats::new(raw.level);
+        if self.duration.unwrap_or(0.0) > 0.0 && (raw.dps.is_some() || raw.healing.is_some()) {
+            let dps = raw.dps.unwrap_or(0.0);
+            stats.dps = Some(dps);
+            stats.tower_damage = Some(dps * CROWN_TOWER_DAMAGE_MULTIPLIER);
+            stats.healing = raw.healing;
+        } else {
+            let (damage, tower_damage) = Self::spell_damage(raw)?;
+            stats.damage = Some(damage);
+            stats.tower_damage = Some(tower_damage);
+        }
+        Some(stats)
+    }
+}
+
+impl GameState {
+    /// Effective stats of every loaded card, by name: each card's
+    /// [`Card::stats`], or why the engine can't simulate it.
+    pub fn cards(&self) -> impl Iterator<Item = Result<EffectiveCardStats>> + '_ {
+        self.loaded_cards().map(move |card| card.stats(self))
+    }
+
+    /// Effective stats of the card called `name`.
+    pub fn card_stats(&self, name: &str) -> Result<EffectiveCardStats> {
+        self.get_card_by_name(name)
+            .ok_or_else(|| Error::Configuration(format!("Card '{}' not found", name)))?
+            .stats(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_resolve_defaults_and_tiers() {
+        let mut state = GameState::new(1);
+        let mut cards: Vec<Card> = crate::card::get_test_cards();
+        let mut slow = cards.iter().find(|c| c.name == "Knight").unwrap().clone();
+        slow.name = "Slow Knight".to_string();
+        slow.movement_speed_value = None;
+        slow.movement_speed = Some("slow".to_string());
+        slow.mass = None;
+        let mut lost = slow.clone();
+        lost.name = "Lost Knight".to_string();
+        lost.movement_speed = Some("glacial".to_string());
+        cards.extend([slow, lost]);
+        state.load_cards(cards).unwrap();
+
+        // Every loaded card is listed, in name order; only the broken one fails
+        assert_eq!(state.cards().count(), state.loaded_cards().count());
+        let names: Vec<String> = state.cards().filter_map(|c| c.ok()).map(|c| c.name).collect();
+        assert!(names.windows(2).all(|w| w[0] < w[1]));
+        assert!(!names.contains(&"Lost Knight".to_string()));
+        assert_eq!(names.len() + 1, state.loaded_cards().count());
+
+        // Tier names become tiles per second; missing values take the engine's defaults
+        let slow = state.card_stats("Slow Knight").unwrap();
+        assert_eq!(slow.movement_speed, Some(state.speed_tiers.slow));
+        assert_eq!(slow.mass, Some(DEFAULT_MASS));
+        let knight = state.card_stats("Knight").unwrap();
+        let raw = state.get_card_by_name("Knight").unwrap();
+        assert_eq!(knight.levels.len(), raw.levels.len());
+        assert_eq!(knight.levels[0].hp, raw.levels[0].hp);
+        assert!(!knight.is_ranged && knight.count == 1 && knight.formation.is_none());
+        // An unknown tier would silently run at a made-up speed, so it's rejected
+        assert!(matches!(state.card_stats("Lost Knight"), Err(Error::Configuration(_))));
+        assert!(state.card_stats("Nonexistent").is_err());
+
+        // Area spells split troop and crown tower damage
+        let arrows = state.card_stats("Arrows").unwrap();
+        let level = &arrows.levels[0];
+        let raw = &state.get_card_by_name("Arrows").unwrap().levels[0];
+        let area = raw.area_damage.or(raw.damage).unwrap();
+        assert_eq!(level.damage, Some(area));
+        assert!(level.tower_damage.unwrap() < area);
+        assert_eq!(arrows.radius, state.get_card_by_name("Arrows").unwrap().radius.or(Some(1.0)));
+
+        // The stats are what spawning actually uses
+        state.set_deploy_zones(false);
+        let spawned = crate::fixtures::spawn(&mut state, shared::PlayerId::Player1, "Slow Knight", shared::Position::new(8.0, 9.0)).unwrap();
+        let crate::entities::EntityKind::Troop(troop) = &state.entities[&spawned].kind else {
+            panic!("not a troop");
+        };
+        assert_eq!(Some(troop.movement_speed), slow.movement_speed);
+        assert_eq!(Some(troop.range), slow.range);
+    }
+}
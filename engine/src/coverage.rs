@@ -0,0 +1,255 @@
+//! Card coverage checks.
+//!
+//! Spawns every card of a patch into an empty arena against a dummy target
+//! and records basic sanity results, producing a report of which card
+//! types and effects the engine actually handles.
+
+use crate::action::Action;
+use crate::card::Card;
+use crate::entities::{Entity, EntityKind, TargetType, TowerData};
+use crate::state::{EntityId, GameState, TowerType};
+use serde::{Deserialize, Serialize};
+use shared::{PlayerId, Position};
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Preferred level to test cards at (falls back to the highest available).
+pub const COVERAGE_LEVEL: u32 = 11;
+
+/// How long each card scenario runs (ticks).
+pub const COVERAGE_TICKS: u64 = 20 * 60;
+
+/// Extra time allowed past a spell's duration before it must be gone (seconds).
+const SPELL_DESPAWN_GRACE: f32 = 5.0;
+
+const DUMMY_HP: f32 = 1_000_000.0;
+
+/// Result of checking a single card.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardCoverage {
+    pub name: String,
+    pub card_type: String,
+    pub effects: Vec<String>,
+    pub level: Option<u32>,
+    pub entities_spawned: usize,
+    pub damage_dealt: f32,
+    /// Tick the card's last entity despawned on, if it did.
+    pub despawned_at: Option<u64>,
+    /// Sanity failures; empty means the card passed.
+    pub issues: Vec<String>,
+}
+
+impl CardCoverage {
+    pub fn passed(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Pass counts for a group of cards.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageTally {
+    pub passed: u32,
+    pub total: u32,
+}
+
+/// Coverage results for a whole patch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub cards: Vec<CardCoverage>,
+    pub by_type: BTreeMap<String, CoverageTally>,
+    pub by_effect: BTreeMap<String, CoverageTally>,
+}
+
+impl CoverageReport {
+    pub fn passed(&self) -> usize {
+        self.cards.iter().filter(|c| c.passed()).count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &CardCoverage> {
+        self.cards.iter().filter(|c| !c.passed())
+    }
+}
+
+/// Checks every card and aggregates the results by card type and effect.
+pub fn run_coverage(cards: &[Card]) -> CoverageReport {
+    let mut report = CoverageReport::default();
+
+    for card in cards {
+        let result = check_card(card);
+        let passed = result.passed() as u32;
+
+        let tally = report.by_type.entry(result.card_type.clone()).or_default();
+        tally.total += 1;
+        tally.passed += passed;
+        for effect in &result.effects {
+            let tally = report.by_effect.entry(effect.clone()).or_default();
+            tally.total += 1;
+            tally.passed += passed;
+        }
+
+        report.cards.push(result);
+    }
+
+    report
+}
+
+/// Plays a card against a dummy target in an otherwise empty arena.
+///
+/// Checks that the play is accepted, spawns at least one entity, damages
+/// the dummy if the card has damage stats, does not panic, and (for spells)
+/// despawns within its duration plus a grace period.
+pub fn check_card(card: &Card) -> CardCoverage {
+    let mut result = CardCoverage {
+        name: card.name.clone(),
+        card_type: card.type_name.clone(),
+        effects: card.effects.clone().unwrap_or_default(),
+        level: None,
+        entities_spawned: 0,
+        damage_dealt: 0.0,
+        despawned_at: None,
+        issues: Vec::new(),
+    };
+
+    let level = card
+        .levels
+        .iter()
+        .map(|l| l.level)
+        .find(|&l| l == COVERAGE_LEVEL)
+        .or_else(|| card.levels.iter().map(|l| l.level).max());
+    let level = match level {
+        Some(level) => level,
+        None => {
+            result.issues.push("no level stats".to_string());
+            return result;
+        }
+    };
+    result.level = Some(level);
+
+    let outcome = catch_unwind(AssertUnwindSafe(|| simulate(card, level, &mut result)));
+    if outcome.is_err() {
+        result.issues.push("panicked during simulation".to_string());
+    }
+
+    result
+}
+
+fn simulate(card: &Card, level: u32, result: &mut CardCoverage) {
+    let mut state = GameState::new(0);
+    state.set_headless(true);
+    state.entities.clear();
+    if let Err(e) = state.load_cards(vec![card.clone()]) {
+        result.issues.push(e.to_string());
+        return;
+    }
+    state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+
+    let dummy = state.add_entity(Entity::new(
+        PlayerId::Player2,
+        Position::new(14.0, 9.0),
+        EntityKind::Tower(TowerData {
+            tower_type: TowerType::King,
+            base_hp: DUMMY_HP,
+            damage: 0.0,
+            range: 0.0,
+            attack_speed: 1.0,
+            first_hit_speed: 0.0,
+            target_type: TargetType::Both,
+        }),
+    ));
+
+    let play = Action::PlayCard {
+        player: PlayerId::Player1,
+        card_name: card.name.clone(),
+        level,
+        position: Position::new(11.0, 9.0),
+    };
+    if let Err(e) = crate::step(&mut state, &[play]) {
+        result.issues.push(format!("play rejected: {}", e));
+        return;
+    }
+
+    let owned = |state: &GameState| -> Vec<EntityId> {
+        state
+            .entities
+            .iter()
+            .filter(|(_, e)| e.owner == PlayerId::Player1)
+            .map(|(id, _)| *id)
+            .collect()
+    };
+    result.entities_spawned = owned(&state).len();
+    if result.entities_spawned == 0 {
+        result.issues.push("no entities spawned".to_string());
+    }
+
+    for _ in 0..COVERAGE_TICKS {
+        if let Err(e) = crate::step(&mut state, &[]) {
+            result.issues.push(format!("step failed: {}", e));
+            return;
+        }
+        if result.despawned_at.is_none() && result.entities_spawned > 0 && owned(&state).is_empty() {
+            result.despawned_at = Some(state.tick);
+        }
+    }
+
+    result.damage_dealt = state
+        .entities
+        .get(&dummy)
+        .map(|d| d.max_hp - d.hp)
+        .unwrap_or(DUMMY_HP);
+
+    let stats = card.get_level_stats(level).ok();
+    let has_damage = stats.is_some_and(|s| {
+        s.damage.unwrap_or(0.0) > 0.0 || s.area_damage.unwrap_or(0.0) > 0.0 || s.dps.unwrap_or(0.0) > 0.0
+    });
+    if has_damage && result.damage_dealt <= 0.0 {
+        result.issues.push("no damage dealt to dummy".to_string());
+    }
+
+    if card.type_name == "spell" && result.entities_spawned > 0 {
+        let limit = card.duration.unwrap_or(0.0) + SPELL_DESPAWN_GRACE;
+        let in_time = result
+            .despawned_at
+            .is_some_and(|tick| tick as f32 * crate::DELTA_TIME <= limit + crate::DELTA_TIME);
+        if !in_time {
+            result.issues.push(format!("spell did not despawn within {:.1}s", limit));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_cards_pass_coverage() {
+        let report = run_coverage(&crate::card::get_test_cards());
+        let failures: Vec<_> = report.failed().map(|c| (&c.name, &c.issues)).collect();
+        assert!(failures.is_empty(), "{:?}", failures);
+        assert_eq!(report.by_type["spell"].total, 2);
+    }
+
+    #[test]
+    fn test_unsupported_card_is_reported() {
+        let mut card = crate::card::get_test_cards().remove(0);
+        card.name = "Cannon".to_string();
+        card.type_name = "building".to_string();
+
+        let result = check_card(&card);
+        assert!(!result.passed());
+        // A Knight turned building keeps its melee reach, and a building
+        // can't walk over to the dummy
+        assert!(result.issues.iter().any(|i| i.contains("no damage dealt")));
+    }
+
+    #[test]
+    fn test_attacking_building_passes_coverage() {
+        let mut card = crate::card::get_test_cards().into_iter().find(|c| c.name == "Archers").unwrap();
+        card.name = "Cannon".to_string();
+        card.type_name = "building".to_string();
+        card.count = None;
+        card.range = Some(5.5);
+
+        let result = check_card(&card);
+        assert!(result.passed(), "{:?}", result.issues);
+    }
+}
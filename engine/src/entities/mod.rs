@@ -1,37 +1,107 @@
 //! Entity definitions (troops, towers, projectiles, spells).
 
+use crate::fp;
+use crate::state::TowerType;
+use crate::systems::pathfinding::Path;
 use serde::{Deserialize, Serialize};
 use shared::{PlayerId, Position, Velocity};
+use std::collections::VecDeque;
+
+/// Ticks of damage an entity remembers (one second).
+pub const DAMAGE_HISTORY_TICKS: usize = 60;
+
+/// Fraction of its speed a slowed troop keeps (a 35% slow).
+pub const SLOW_SPEED_MULTIPLIER: f32 = 0.65;
+
+/// How much faster a raged unit moves and attacks (a 35% boost).
+pub const RAGE_SPEED_MULTIPLIER: f32 = 1.35;
 
 /// An entity in the game world.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entity {
     pub owner: PlayerId,
     pub position: Position,
+    /// Position at the start of the current tick (for render interpolation).
+    pub prev_position: Position,
     pub velocity: Velocity,
+
+    /// Facing direction in radians (0 = +x, counter-clockwise). Follows
+    /// movement, and turns toward the target while attacking.
+    #[serde(default)]
+    pub facing: f32,
+
     pub hp: f32,
     pub max_hp: f32,
+
+    /// Remaining shield, absorbing damage before `hp` (Dark Prince, Guards).
+    /// Healing never restores it.
+    #[serde(default)]
+    pub shield: f32,
+    #[serde(default)]
+    pub max_shield: f32,
+
+    /// Fractional HP change not yet applied in integer HP mode (negative
+    /// for pending damage). Always within (-1, 1).
+    #[serde(default)]
+    pub hp_carry: f32,
+
     pub kind: EntityKind,
 
     /// Time until next attack (in seconds). 0 = ready to attack.
     pub attack_cooldown: f32,
 
+    /// Remaining windup (seconds) before the first hit on the current target.
+    /// Counts down only while the target is in range; `None` once it has hit.
+    pub first_hit_timer: Option<f32>,
+
     /// Current target entity ID (if any).
     pub target: Option<u32>,
+
+    /// Active timed status effects (stun, rage, ...).
+    #[serde(default)]
+    pub statuses: Vec<StatusEffect>,
+
+    /// Cached route for ground units detouring around unwalkable tiles.
+    #[serde(default)]
+    pub path: Option<Path>,
+
+    /// Entity whose damage landed last (a projectile's shooter rather than
+    /// the projectile), for crediting tower kills.
+    #[serde(default)]
+    pub last_hit_by: Option<u32>,
+
+    /// Damage taken over the last [`DAMAGE_HISTORY_TICKS`] ticks.
+    #[serde(default, skip_serializing_if = "DamageHistory::is_empty")]
+    pub recent_damage: DamageHistory,
 }
 
 impl Entity {
     pub fn new(owner: PlayerId, position: Position, kind: EntityKind) -> Self {
         let max_hp = kind.base_hp();
+        // Start out facing the enemy side
+        let facing = match owner {
+            PlayerId::Player1 | PlayerId::Player3 => 0.0,
+            PlayerId::Player2 | PlayerId::Player4 => std::f32::consts::PI,
+        };
         Self {
             owner,
             position,
+            prev_position: position,
             velocity: Velocity::zero(),
+            facing,
             hp: max_hp,
             max_hp,
+            shield: 0.0,
+            max_shield: 0.0,
+            hp_carry: 0.0,
             kind,
             attack_cooldown: 0.0,
+            first_hit_timer: None,
             target: None,
+            statuses: Vec::new(),
+            path: None,
+            last_hit_by: None,
+            recent_damage: DamageHistory::default(),
         }
     }
 
@@ -39,19 +109,124 @@ impl Entity {
         self.hp > 0.0
     }
 
+    /// Unit vector of the facing direction.
+    pub fn facing_vector(&self) -> (f32, f32) {
+        (fp::cos(self.facing), fp::sin(self.facing))
+    }
+
+    /// Turns to face `point`. Does nothing if already on top of it.
+    pub fn face_toward(&mut self, point: &Position) {
+        let dx = point.x - self.position.x;
+        let dy = point.y - self.position.y;
+        if dx != 0.0 || dy != 0.0 {
+            self.facing = fp::atan2(dy, dx);
+        }
+    }
+
+    /// Gives the entity a full shield of `shield` points.
+    pub fn with_shield(mut self, shield: f32) -> Self {
+        self.shield = shield;
+        self.max_shield = shield;
+        self
+    }
+
+    /// Applies damage, draining the shield first. As in the live game, the
+    /// hit that breaks a shield is fully absorbed and does not carry over.
     pub fn take_damage(&mut self, amount: f32) {
+        if self.shield > 0.0 {
+            self.shield = (self.shield - amount).max(0.0);
+            return;
+        }
         self.hp = (self.hp - amount).max(0.0);
     }
 
+    /// Adds `delta` to the fractional HP carry and takes out its whole
+    /// part, which is returned for the caller to apply.
+    pub fn carry_hp_change(&mut self, delta: f32) -> f32 {
+        self.hp_carry += delta;
+        let whole = self.hp_carry.trunc();
+        self.hp_carry -= whole;
+        whole
+    }
+
+    /// Restores up to `amount` HP (never above `max_hp`, never the shield).
+    /// Returns how much was actually healed.
+    pub fn heal(&mut self, amount: f32) -> f32 {
+        if !self.is_alive() || amount <= 0.0 {
+            return 0.0;
+        }
+        let healed = amount.min(self.max_hp - self.hp);
+        self.hp += healed;
+        healed
+    }
+
+    /// Applies a status for `duration` seconds. Reapplying an active status
+    /// keeps whichever duration is longer.
+    pub fn apply_status(&mut self, kind: StatusKind, duration: f32) {
+        match self.statuses.iter_mut().find(|s| s.kind == kind) {
+            Some(status) => status.remaining = status.remaining.max(duration),
+            None => self.statuses.push(StatusEffect { kind, remaining: duration }),
+        }
+    }
+
+    /// Returns true if `kind` is currently active.
+    pub fn has_status(&self, kind: StatusKind) -> bool {
+        self.statuses.iter().any(|s| s.kind == kind)
+    }
+
+    /// Counts status durations down and drops expired ones.
+    pub fn tick_statuses(&mut self, dt: f32) {
+        for status in &mut self.statuses {
+            status.remaining -= dt;
+        }
+        self.statuses.retain(|s| s.remaining > 0.0);
+    }
+
+    /// Healing this entity's attacks trigger, if any.
+    pub fn heal_on_hit(&self) -> Option<HealOnHit> {
+        match &self.kind {
+            EntityKind::Troop(data) => data.heal_on_hit,
+            _ => None,
+        }
+    }
+
+    /// Knockback distance this entity's attacks apply, if any.
+    pub fn knockback(&self) -> Option<f32> {
+        match &self.kind {
+            EntityKind::Troop(data) => data.knockback,
+            _ => None,
+        }
+    }
+
+    /// Attack stats if this is a building that attacks.
+    fn building_attack(&self) -> Option<&BuildingAttack> {
+        match &self.kind {
+            EntityKind::Building(data) => data.attack.as_ref(),
+            _ => None,
+        }
+    }
+
     /// Returns the attack range for this entity.
     pub fn attack_range(&self) -> f32 {
         match &self.kind {
             EntityKind::Tower(data) => data.range,
             EntityKind::Troop(data) => data.range,
+            EntityKind::Building(_) => self.building_attack().map_or(0.0, |a| a.range),
             _ => 0.0,
         }
     }
 
+    /// Returns the minimum attack range; closer targets can't be hit (Mortar).
+    pub fn min_attack_range(&self) -> f32 {
+        self.building_attack().map_or(0.0, |a| a.min_range)
+    }
+
+    /// Returns true if this entity picks buildings over troops when both are
+    /// valid targets (siege buildings).
+    pub fn prefers_buildings(&self) -> bool {
+        self.building_attack().is_some_and(|a| a.prefers_buildings)
+    }
+
     /// Returns the damage this entity deals.
     pub fn damage(&self) -> f32 {
         match &self.kind {
@@ -59,35 +234,127 @@ impl Entity {
             EntityKind::Troop(data) => data.damage,
             EntityKind::Projectile(data) => data.damage,
             EntityKind::Spell(data) => data.damage,
+            EntityKind::Zone(data) => data.dps,
+            EntityKind::Building(_) => self.building_attack().map_or(0.0, |a| a.damage),
         }
     }
 
-    /// Returns the attack speed (seconds between attacks).
+    /// Returns the attack speed (seconds between attacks), shorter while
+    /// [`StatusKind::Rage`] is active.
     pub fn attack_speed(&self) -> f32 {
-        match &self.kind {
+        let base = match &self.kind {
             EntityKind::Tower(data) => data.attack_speed,
             EntityKind::Troop(data) => data.attack_speed,
+            EntityKind::Building(_) => self.building_attack().map_or(1.0, |a| a.attack_speed),
             _ => 1.0,
+        };
+        if self.has_status(StatusKind::Rage) {
+            base / RAGE_SPEED_MULTIPLIER
+        } else {
+            base
+        }
+    }
+
+    /// Returns the windup before the first hit on a new target (seconds).
+    pub fn first_hit_speed(&self) -> f32 {
+        match &self.kind {
+            EntityKind::Tower(data) => data.first_hit_speed,
+            EntityKind::Troop(data) => data.first_hit_speed,
+            EntityKind::Building(_) => self.building_attack().map_or(0.0, |a| a.first_hit_speed),
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the splash radius of this entity's attacks, if they splash.
+    pub fn splash_radius(&self) -> Option<f32> {
+        match &self.kind {
+            EntityKind::Troop(data) => data.splash_radius,
+            EntityKind::Building(_) => self.building_attack().and_then(|a| a.splash_radius),
+            _ => None,
         }
     }
 
-    /// Returns true if this entity can attack (troops and towers).
+    /// Returns true if this entity can attack (troops, towers, and
+    /// buildings with an attack).
     pub fn can_attack(&self) -> bool {
-        matches!(self.kind, EntityKind::Tower(_) | EntityKind::Troop(_))
+        matches!(self.kind, EntityKind::Tower(_) | EntityKind::Troop(_)) || self.building_attack().is_some()
     }
 
     /// Returns the target type for this entity.
     pub fn target_type(&self) -> Option<TargetType> {
         match &self.kind {
             EntityKind::Troop(data) => Some(data.target_type),
+            EntityKind::Tower(data) => Some(data.target_type),
+            EntityKind::Building(_) => self.building_attack().map(|a| a.target_type),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this entity is on the ground or flying.
+    /// Everything other than air troops is treated as ground.
+    pub fn transport(&self) -> Transport {
+        match &self.kind {
+            EntityKind::Troop(data) => data.transport,
+            _ => Transport::Ground,
+        }
+    }
+
+    /// Returns true if this entity is a building (crown towers count as buildings).
+    pub fn is_building(&self) -> bool {
+        matches!(self.kind, EntityKind::Tower(_) | EntityKind::Building(_))
+    }
+
+    /// Returns true if this entity can be targeted by attacks (troops, towers
+    /// and buildings).
+    pub fn is_targetable(&self) -> bool {
+        matches!(
+            self.kind,
+            EntityKind::Tower(_) | EntityKind::Troop(_) | EntityKind::Building(_)
+        )
+    }
+
+    /// Returns true if `target` is within this entity's attack range (and
+    /// not inside its minimum range).
+    /// Range is measured to the edge of the target's collision radius.
+    pub fn in_attack_range(&self, target: &Entity) -> bool {
+        let distance = self.position.distance_to(&target.position) - target.radius();
+        distance <= self.attack_range() && distance >= self.min_attack_range()
+    }
+
+    /// Name of the card that put this entity on the field, if any.
+    pub fn card(&self) -> Option<&str> {
+        let card = match &self.kind {
+            EntityKind::Troop(data) => &data.card,
+            EntityKind::Building(data) => &data.card,
+            EntityKind::Spell(data) => &data.card,
+            EntityKind::Zone(data) => &data.card,
+            _ => return None,
+        };
+        (!card.is_empty()).then_some(card.as_str())
+    }
+
+    /// Returns the tower type if this entity is a tower.
+    pub fn tower_type(&self) -> Option<TowerType> {
+        match &self.kind {
+            EntityKind::Tower(data) => Some(data.tower_type),
             _ => None,
         }
     }
 
-    /// Returns the movement speed (tiles per second).
+    /// Returns the movement speed (tiles per second), adjusted while
+    /// [`StatusKind::Slow`] or [`StatusKind::Rage`] is active.
     pub fn movement_speed(&self) -> f32 {
         match &self.kind {
-            EntityKind::Troop(data) => data.movement_speed,
+            EntityKind::Troop(data) => {
+                let mut speed = data.movement_speed;
+                if self.has_status(StatusKind::Slow) {
+                    speed *= SLOW_SPEED_MULTIPLIER;
+                }
+                if self.has_status(StatusKind::Rage) {
+                    speed *= RAGE_SPEED_MULTIPLIER;
+                }
+                speed
+            }
             _ => 0.0, // Towers and projectiles don't move
         }
     }
@@ -101,9 +368,36 @@ impl Entity {
     pub fn radius(&self) -> f32 {
         match &self.kind {
             EntityKind::Tower(_) => 1.5,      // Towers are large
+            EntityKind::Building(_) => 1.0,    // Placed buildings cover about 2x2 tiles
             EntityKind::Troop(_) => 0.4,       // Troops are medium (about 1 tile wide for 2 side-by-side)
             EntityKind::Projectile(_) => 0.1,  // Projectiles are small
             EntityKind::Spell(_) => 0.0,       // Spells have no collision
+            EntityKind::Zone(_) => 0.0,
+        }
+    }
+
+    /// Collision mass. Towers and buildings never give way, so they count
+    /// as infinitely heavy.
+    pub fn mass(&self) -> f32 {
+        match &self.kind {
+            EntityKind::Troop(data) => data.mass,
+            _ => f32::INFINITY,
+        }
+    }
+
+    /// Returns true if this is a ground troop that jumps the river.
+    pub fn jumps_river(&self) -> bool {
+        match &self.kind {
+            EntityKind::Troop(data) => data.jumps_river && data.transport == Transport::Ground,
+            _ => false,
+        }
+    }
+
+    /// Lane a troop marches down when idle (see [`TroopData::lane`]).
+    pub fn lane(&self) -> Option<usize> {
+        match &self.kind {
+            EntityKind::Troop(data) => data.lane,
+            _ => None,
         }
     }
 
@@ -112,9 +406,10 @@ impl Entity {
     pub fn collision_shape(&self) -> CollisionShape {
         match &self.kind {
             EntityKind::Tower(_) => CollisionShape::Rectangle { half_width: 2.0, half_height: 2.0 },
+            EntityKind::Building(_) => CollisionShape::Rectangle { half_width: 1.0, half_height: 1.0 },
             EntityKind::Troop(_) => CollisionShape::Circle { radius: 0.4 },
             EntityKind::Projectile(_) => CollisionShape::Circle { radius: 0.1 },
-            EntityKind::Spell(_) => CollisionShape::None,
+            EntityKind::Spell(_) | EntityKind::Zone(_) => CollisionShape::None,
         }
     }
 
@@ -123,6 +418,7 @@ impl Entity {
         match &self.kind {
             EntityKind::Troop(data) => data.is_ranged,
             EntityKind::Tower(_) => true, // Towers always shoot projectiles
+            EntityKind::Building(_) => self.building_attack().is_some(),
             _ => false,
         }
     }
@@ -150,25 +446,95 @@ pub enum EntityKind {
 
     /// Spell effects (area damage, etc.).
     Spell(SpellData),
+
+    /// Lingering area effects (Poison, Earthquake).
+    Zone(ZoneData),
+
+    /// Player-placed buildings (Goblin Hut, Tombstone).
+    Building(BuildingData),
 }
 
 impl EntityKind {
+    /// Lowercase name of the kind ("troop", "tower", ...).
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntityKind::Tower(_) => "tower",
+            EntityKind::Troop(_) => "troop",
+            EntityKind::Projectile(_) => "projectile",
+            EntityKind::Spell(_) => "spell",
+            EntityKind::Zone(_) => "zone",
+            EntityKind::Building(_) => "building",
+        }
+    }
+
     fn base_hp(&self) -> f32 {
         match self {
             EntityKind::Tower(data) => data.base_hp,
             EntityKind::Troop(data) => data.base_hp,
             EntityKind::Projectile(_) => 1.0,
             EntityKind::Spell(_) => 1.0,
+            EntityKind::Zone(_) => 1.0,
+            EntityKind::Building(data) => data.base_hp,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TowerData {
+    pub tower_type: TowerType,
     pub base_hp: f32,
     pub damage: f32,
     pub range: f32,
     pub attack_speed: f32,
+    pub first_hit_speed: f32,
+    /// What the tower may shoot at (both air and ground by default).
+    #[serde(default)]
+    pub target_type: TargetType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildingData {
+    pub base_hp: f32,
+    /// Total lifetime (seconds). HP decays linearly to zero over it.
+    pub lifetime: f32,
+    /// Periodic troop production, for spawner buildings.
+    pub spawner: Option<SpawnerData>,
+    /// Attack stats, for defensive and siege buildings.
+    #[serde(default)]
+    pub attack: Option<BuildingAttack>,
+    /// Card that placed the building.
+    #[serde(default)]
+    pub card: String,
+}
+
+/// How an attacking building (Cannon, X-Bow, Mortar) fires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildingAttack {
+    pub damage: f32,
+    pub range: f32,
+    /// Targets closer than this can't be hit (Mortar's blind spot).
+    pub min_range: f32,
+    pub attack_speed: f32,
+    pub first_hit_speed: f32,
+    pub target_type: TargetType,
+    pub splash_radius: Option<f32>,
+    /// Picks buildings over troops when both are in range.
+    pub prefers_buildings: bool,
+}
+
+/// Troops a spawner building produces on a timer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnerData {
+    /// Name of the card spawned each wave.
+    pub card: String,
+    /// Level the spawned card is played at (the building's level).
+    pub level: u32,
+    /// Copies of the card per wave.
+    pub count: u32,
+    /// Seconds between waves.
+    pub interval: f32,
+    /// Seconds until the next wave.
+    pub timer: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -177,29 +543,457 @@ pub struct TroopData {
     pub damage: f32,
     pub range: f32,
     pub attack_speed: f32,
+    pub first_hit_speed: f32,
     pub movement_speed: f32,
     pub target_type: TargetType,
+    pub transport: Transport,
     pub is_ranged: bool, // true = spawns projectiles, false = instant melee damage
+    /// Splash radius for area-damage attackers (Baby Dragon, Wizard).
+    pub splash_radius: Option<f32>,
+    /// Knockback distance applied by each hit (Bowler).
+    #[serde(default)]
+    pub knockback: Option<f32>,
+    /// HP restored to itself and nearby allies on each hit (Battle Healer).
+    #[serde(default)]
+    pub heal_on_hit: Option<HealOnHit>,
+    /// Collision mass; overlapping troops give way in inverse proportion.
+    #[serde(default = "default_mass")]
+    pub mass: f32,
+    /// Crosses the river in a straight jump rather than by bridge.
+    #[serde(default)]
+    pub jumps_river: bool,
+    /// Lane (see [`crate::arena::Arena::lane_of`]) the troop was deployed
+    /// in, and marches down when it has nothing to attack. `None` uses the
+    /// lane it is standing in.
+    #[serde(default)]
+    pub lane: Option<usize>,
+    /// Card that deployed the troop, and the level it was played at.
+    #[serde(default)]
+    pub card: String,
+    #[serde(default)]
+    pub level: u32,
+}
+
+fn default_mass() -> f32 {
+    crate::card::DEFAULT_MASS
+}
+
+/// Healing triggered by a troop's attacks.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HealOnHit {
+    pub amount: f32,
+    /// Allies within this radius of the attacker are healed too.
+    pub radius: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectileData {
     pub damage: f32,
     pub speed: f32,
+    /// Entity the projectile homes on.
     pub target_id: Option<u32>,
+    /// Fixed aim point for non-homing projectiles (used when `target_id` is `None`).
+    pub target_position: Option<Position>,
+    /// Distance after which the projectile detonates (splash) or despawns.
+    pub max_distance: Option<f32>,
+    /// Distance traveled so far.
+    pub traveled: f32,
+    /// If set, the projectile explodes on impact and damages every enemy
+    /// within this radius of the impact point instead of just its target.
+    pub splash_radius: Option<f32>,
+    /// Multiplier applied to damage dealt to crown towers.
+    pub tower_damage_multiplier: f32,
+    /// How the projectile deals damage along its flight.
+    pub behavior: ProjectileBehavior,
+    /// Knockback distance applied to troops it damages.
+    #[serde(default)]
+    pub knockback: Option<f32>,
+    /// Whose units it can damage.
+    #[serde(default)]
+    pub affects: DamageFilter,
+    /// Entity that fired it, credited with its hits.
+    #[serde(default)]
+    pub source: Option<u32>,
+}
+
+/// Whose units a source of damage can hit, relative to its owner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageFilter {
+    #[default]
+    Enemies,
+    Allies,
+    /// Both sides (friendly fire).
+    All,
+}
+
+impl DamageFilter {
+    /// Whether damage from `owner`'s source may land on a unit of `target`.
+    pub fn hits(self, owner: PlayerId, target: PlayerId) -> bool {
+        match self {
+            DamageFilter::Enemies => !owner.is_ally_of(target),
+            DamageFilter::Allies => owner.is_ally_of(target),
+            DamageFilter::All => true,
+        }
+    }
+}
+
+/// Damage behavior of a projectile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProjectileBehavior {
+    /// Stops at the first thing it hits (or splashes around it).
+    Standard,
+
+    /// Flies in a straight line for `max_distance`, damaging each enemy it
+    /// passes within `width / 2` of its path once (Log, Arrows volley).
+    Piercing {
+        direction: (f32, f32),
+        width: f32,
+        hit: Vec<u32>,
+    },
+
+    /// After hitting its target, jumps to up to `max_jumps` further enemies
+    /// within `jump_radius` of the last one hit, multiplying damage by
+    /// `falloff` on each jump (Electro Wizard, Zap-like chains).
+    Chaining {
+        max_jumps: u32,
+        jump_radius: f32,
+        falloff: f32,
+    },
+}
+
+impl ProjectileData {
+    /// A projectile that homes on `target_id` and cannot miss.
+    pub fn homing(damage: f32, speed: f32, target_id: u32) -> Self {
+        Self {
+            damage,
+            speed,
+            target_id: Some(target_id),
+            target_position: None,
+            max_distance: None,
+            traveled: 0.0,
+            splash_radius: None,
+            tower_damage_multiplier: 1.0,
+            behavior: ProjectileBehavior::Standard,
+            knockback: None,
+            affects: DamageFilter::Enemies,
+            source: None,
+        }
+    }
+
+    /// A projectile flying to a fixed point; it only hits what is there
+    /// when it lands, so moving targets can dodge it.
+    pub fn aimed(damage: f32, speed: f32, target_position: Position) -> Self {
+        Self {
+            target_id: None,
+            target_position: Some(target_position),
+            ..Self::homing(damage, speed, 0)
+        }
+    }
+
+    /// A projectile flying straight from `from` toward `toward` for `range`
+    /// tiles, damaging every enemy along a `width`-wide path once.
+    pub fn piercing(damage: f32, speed: f32, from: Position, toward: Position, width: f32, range: f32) -> Self {
+        Self {
+            target_id: None,
+            max_distance: Some(range),
+            behavior: ProjectileBehavior::Piercing {
+                direction: from.direction_to(&toward),
+                width,
+                hit: Vec::new(),
+            },
+            ..Self::homing(damage, speed, 0)
+        }
+    }
+
+    /// Makes a homing projectile chain to nearby enemies after its first hit.
+    pub fn with_chain(mut self, max_jumps: u32, jump_radius: f32, falloff: f32) -> Self {
+        self.behavior = ProjectileBehavior::Chaining {
+            max_jumps,
+            jump_radius,
+            falloff,
+        };
+        self
+    }
+
+    pub fn with_knockback(mut self, distance: Option<f32>) -> Self {
+        self.knockback = distance;
+        self
+    }
+
+    pub fn with_splash(mut self, radius: Option<f32>) -> Self {
+        self.splash_radius = radius;
+        self
+    }
+
+    pub fn with_max_distance(mut self, distance: f32) -> Self {
+        self.max_distance = Some(distance);
+        self
+    }
+
+    pub fn with_affects(mut self, affects: DamageFilter) -> Self {
+        self.affects = affects;
+        self
+    }
+
+    pub fn with_source(mut self, source: u32) -> Self {
+        self.source = Some(source);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpellData {
+    /// Damage dealt to troops in the radius.
     pub damage: f32,
+    /// Reduced damage dealt to crown towers in the radius.
+    pub tower_damage: f32,
     pub radius: f32,
     pub duration: f32,
+    /// Seconds until the spell lands (cast delay plus travel time from the
+    /// caster's King tower).
+    pub time_to_impact: f32,
+    /// Knockback distance applied to troops in the radius (Fireball, Log).
+    #[serde(default)]
+    pub knockback: Option<f32>,
+    /// Whose units it can damage.
+    #[serde(default)]
+    pub affects: DamageFilter,
+    /// Card that cast it.
+    #[serde(default)]
+    pub card: String,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// A persistent area dealing damage over time to enemies inside it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneData {
+    /// Damage per second to troops inside the radius.
+    pub dps: f32,
+    /// Damage per second to crown towers inside the radius.
+    pub tower_dps: f32,
+    /// HP per second restored to allies inside the radius.
+    #[serde(default)]
+    pub heal_per_second: f32,
+    pub radius: f32,
+    /// Seconds left before the zone expires.
+    pub remaining: f32,
+    /// Which enemies the zone affects.
+    pub target_type: TargetType,
+    /// Whose units it can damage (healing always goes to allies).
+    #[serde(default)]
+    pub affects: DamageFilter,
+    /// Card that cast it.
+    #[serde(default)]
+    pub card: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TargetType {
     Ground,
     Air,
+    #[default]
     Both,
     Buildings,
 }
+
+impl TargetType {
+    /// Parses a config `targets` list (["air", "ground", "buildings"]).
+    /// A missing list means the attacker hits both air and ground.
+    pub fn from_targets(targets: Option<&[String]>) -> Self {
+        match targets {
+            Some(targets) => {
+                let has_air = targets.iter().any(|t| t == "air");
+                let has_ground = targets.iter().any(|t| t == "ground");
+                let has_buildings = targets.iter().any(|t| t == "buildings");
+
+                if has_buildings {
+                    TargetType::Buildings
+                } else if has_air && has_ground {
+                    TargetType::Both
+                } else if has_air {
+                    TargetType::Air
+                } else {
+                    TargetType::Ground
+                }
+            }
+            None => TargetType::Both,
+        }
+    }
+}
+
+/// Kinds of timed status effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusKind {
+    Stun,
+    Freeze,
+    /// Moves at [`SLOW_SPEED_MULTIPLIER`] of its usual speed.
+    Slow,
+    /// Moves and attacks [`RAGE_SPEED_MULTIPLIER`] times as fast.
+    Rage,
+    /// Mid-air over the river (units that jump it).
+    Jumping,
+}
+
+/// A status effect with its remaining duration (seconds).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub remaining: f32,
+}
+
+/// Damage taken per tick, newest first, over the last
+/// [`DAMAGE_HISTORY_TICKS`] ticks. Empty for entities that haven't been
+/// hit in that long, so most of them never allocate.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DamageHistory {
+    ticks: VecDeque<f32>,
+}
+
+impl DamageHistory {
+    /// Adds `amount` to the current tick's damage.
+    pub fn record(&mut self, amount: f32) {
+        if amount <= 0.0 {
+            return;
+        }
+        match self.ticks.front_mut() {
+            Some(current) => *current += amount,
+            None => self.ticks.push_front(amount),
+        }
+    }
+
+    /// Starts a new tick, forgetting the oldest.
+    pub fn advance(&mut self) {
+        if self.ticks.is_empty() {
+            return;
+        }
+        self.ticks.push_front(0.0);
+        self.ticks.truncate(DAMAGE_HISTORY_TICKS);
+        if self.ticks.iter().all(|&d| d == 0.0) {
+            self.ticks.clear();
+        }
+    }
+
+    /// Damage taken over the window.
+    pub fn total(&self) -> f32 {
+        // Fold from +0.0: `sum` starts at -0.0, which leaks into exports
+        self.ticks.iter().fold(0.0, |total, d| total + d)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+}
+
+/// Whether a unit walks on the ground or flies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Transport {
+    Ground,
+    Air,
+}
+
+/// How an attacker keeps or drops its current target.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RetargetPolicy {
+    /// Drop the current target as soon as it leaves attack range.
+    pub drop_out_of_range: bool,
+
+    /// While the current target is still out of range, switch to a
+    /// strictly closer valid target. Once in range, the target is locked.
+    pub switch_while_approaching: bool,
+}
+
+/// Retarget policies by entity kind.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RetargetPolicies {
+    pub tower: RetargetPolicy,
+    pub troop: RetargetPolicy,
+}
+
+impl RetargetPolicies {
+    /// Returns the policy for an entity, or `None` if it can't attack.
+    pub fn for_entity(&self, entity: &Entity) -> Option<RetargetPolicy> {
+        match &entity.kind {
+            EntityKind::Tower(_) => Some(self.tower),
+            EntityKind::Troop(_) => Some(self.troop),
+            // Attacking buildings are stationary, so they lock on like towers
+            EntityKind::Building(data) if data.attack.is_some() => Some(self.tower),
+            _ => None,
+        }
+    }
+}
+
+impl Default for RetargetPolicies {
+    fn default() -> Self {
+        Self {
+            // Towers lock on until the target dies or walks out of range
+            tower: RetargetPolicy {
+                drop_out_of_range: true,
+                switch_while_approaching: false,
+            },
+            // Troops chase their target, but pick a closer one while walking
+            troop: RetargetPolicy {
+                drop_out_of_range: false,
+                switch_while_approaching: true,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::GameState;
+
+    #[test]
+    fn test_shield_absorbs_damage_before_hp() {
+        let mut state = GameState::new(22);
+        let mut card = state.get_card_by_name("Knight").unwrap().clone();
+        card.name = "Shielded Knight".to_string();
+        for level in &mut card.levels {
+            level.shield_hp = Some(200.0);
+        }
+        state.load_cards(vec![card.clone()]).unwrap();
+        card.spawn(&mut state, PlayerId::Player1, Position::new(10.0, 9.0), 11).unwrap();
+
+        let entity = state.entities.values_mut().find(|e| e.max_shield > 0.0).unwrap();
+        entity.take_damage(150.0);
+        assert_eq!((entity.shield, entity.hp), (50.0, entity.max_hp));
+
+        // The breaking hit does not spill over into HP
+        entity.take_damage(120.0);
+        assert_eq!((entity.shield, entity.hp), (0.0, entity.max_hp));
+
+        entity.take_damage(100.0);
+        assert_eq!(entity.hp, entity.max_hp - 100.0);
+
+        entity.shield = 30.0;
+        let exported = state.export_cr_state(PlayerId::Player1);
+        assert_eq!(exported.ally_units[0].shield, 30.0);
+    }
+
+    #[test]
+    fn test_statuses_expire_and_are_exported() {
+        let mut state = GameState::new(24);
+        let card = state.get_card_by_name("Knight").unwrap().clone();
+        card.spawn(&mut state, PlayerId::Player1, Position::new(10.0, 9.0), 11).unwrap();
+        let id = *state.entities.iter().find(|(_, e)| e.tower_type().is_none()).unwrap().0;
+
+        let knight = state.entities.get_mut(&id).unwrap();
+        knight.apply_status(StatusKind::Stun, 0.5);
+        knight.apply_status(StatusKind::Rage, 2.0);
+        knight.apply_status(StatusKind::Stun, 0.25); // shorter reapply keeps 0.5s
+        assert_eq!(knight.statuses.len(), 2);
+
+        let unit = &state.export_cr_state(PlayerId::Player1).ally_units[0];
+        assert!(unit.is_stunned && unit.is_raged);
+        let debug = serde_json::to_string(&state.entities[&id]).unwrap();
+        assert!(debug.contains(r#"{"kind":"stun","remaining":0.5}"#), "{}", debug);
+
+        for _ in 0..30 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let unit = &state.export_cr_state(PlayerId::Player1).ally_units[0];
+        assert!(!unit.is_stunned && unit.is_raged);
+    }
+}
@@ -0,0 +1,22 @@
+//! Events emitted by the simulation.
+//!
+//! `GameState::events` holds everything that happened during the most
+//! recent [`crate::step`]; it is cleared at the start of each step.
+
+use crate::state::TowerType;
+use serde::{Deserialize, Serialize};
+use shared::PlayerId;
+
+/// Something noteworthy that happened during a tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// `target` regained `amount` HP from `source` (a healer or heal zone).
+    Healed { source: u32, target: u32, amount: f32 },
+    /// `entity` changed sides (see `GameState::transfer_ownership`).
+    OwnershipTransferred { entity: u32, from: PlayerId, to: PlayerId },
+    /// `player` destroyed the opponent's `tower`, taking `crowns` (see
+    /// `GameState::crowns`).
+    CrownTaken { player: PlayerId, tower: TowerType, crowns: u32 },
+    /// The scripted trigger at `index` in `GameState::triggers` fired.
+    TriggerFired { index: usize },
+}
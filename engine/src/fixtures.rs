@@ -0,0 +1,132 @@
+//! Ready-made match states for tests and downstream crates.
+//!
+//! Each fixture starts from `GameState::new(FIXTURE_SEED)` with the built-in
+//! test cards, so results don't drift as scenarios get copied around. Troops
+//! are spawned directly (no elixir cost, no deploy delay) at
+//! [`FIXTURE_LEVEL`].
+
+use crate::state::{EntityId, GameState, TowerType};
+use shared::{PlayerId, Position, Result};
+
+pub const FIXTURE_SEED: u64 = 0;
+pub const FIXTURE_LEVEL: u32 = 11;
+
+/// Spawns `card` for `owner` at `position` and returns the ID of the last
+/// entity it created.
+pub fn spawn(state: &mut GameState, owner: PlayerId, card: &str, position: Position) -> Result<EntityId> {
+    let card = state
+        .get_card_by_name(card)
+        .cloned()
+        .ok_or_else(|| shared::Error::Configuration(format!("Card '{}' not found", card)))?;
+    card.spawn(state, owner, position, FIXTURE_LEVEL)?;
+    Ok(*state
+        .entities
+        .keys()
+        .max_by_key(|id| id.as_u32())
+        .expect("spawn adds an entity"))
+}
+
+/// No towers and no units, both players on full elixir. Without King
+/// towers both players count as defeated once a step syncs tower HP, so
+/// check entities rather than `is_match_over` here.
+pub fn empty_arena() -> GameState {
+    let mut state = GameState::new(FIXTURE_SEED);
+    state.entities.clear();
+    for player in state.players.values_mut() {
+        player.elixir = player.max_elixir;
+    }
+    state
+}
+
+/// Two Knights facing each other across the `y = 3.5` bridge, towers intact.
+pub struct BridgeStandoff {
+    pub state: GameState,
+    /// Player 1's Knight, on its own side of the bridge.
+    pub ally: EntityId,
+    /// Player 2's Knight, on the far side.
+    pub enemy: EntityId,
+}
+
+pub fn bridge_standoff() -> BridgeStandoff {
+    let mut state = GameState::new(FIXTURE_SEED);
+    let ally = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(14.0, 3.5)).expect("Knight is a test card");
+    let enemy = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(18.0, 3.5)).expect("Knight is a test card");
+    BridgeStandoff { state, ally, enemy }
+}
+
+/// Player 1 pushing the `y = 3.5` lane: a Giant at the bridge with a
+/// Musketeer behind it, headed for Player 2's Right Princess tower (towers
+/// are point-reflected, so that lane is Player 2's right).
+pub struct TankPush {
+    pub state: GameState,
+    pub tank: EntityId,
+    pub support: EntityId,
+}
+
+pub fn tank_and_support_push() -> TankPush {
+    let mut state = GameState::new(FIXTURE_SEED);
+    let tank = spawn(&mut state, PlayerId::Player1, "Giant", Position::new(14.0, 3.5)).expect("Giant is a test card");
+    let support =
+        spawn(&mut state, PlayerId::Player1, "Musketeer", Position::new(11.0, 3.5)).expect("Musketeer is a test card");
+    TankPush { state, tank, support }
+}
+
+/// One tick before time runs out with crowns level at one each (both left
+/// Princess towers down). Player 1's remaining towers have more HP, so the
+/// next step ends the match in Player 1's favor on the tower HP tiebreak.
+pub fn overtime_tiebreak() -> GameState {
+    let mut state = GameState::new(FIXTURE_SEED);
+    let towers: Vec<(EntityId, PlayerId, TowerType)> = state
+        .entities
+        .iter()
+        .filter_map(|(id, e)| e.tower_type().map(|tt| (*id, e.owner, tt)))
+        .collect();
+    for (id, owner, tower_type) in towers {
+        match (owner, tower_type) {
+            (_, TowerType::LeftPrincess) => {
+                state.remove_entity(id);
+            }
+            (PlayerId::Player2, TowerType::RightPrincess) => {
+                let tower = state.entities.get_mut(&id).expect("id came from the map");
+                tower.hp = tower.max_hp * 0.5;
+            }
+            _ => {}
+        }
+    }
+    state.sync_tower_hp();
+    state.match_time = state.max_match_time - crate::DELTA_TIME;
+    state.tick = (state.match_time / crate::DELTA_TIME).round() as u64;
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_play_out_as_described() {
+        let mut standoff = bridge_standoff();
+        for _ in 0..120 {
+            crate::step(&mut standoff.state, &[]).unwrap();
+        }
+        let (ally, enemy) = (&standoff.state.entities[&standoff.ally], &standoff.state.entities[&standoff.enemy]);
+        assert_eq!(ally.target, Some(standoff.enemy.as_u32()));
+        assert_eq!(enemy.target, Some(standoff.ally.as_u32()));
+
+        let mut push = tank_and_support_push();
+        crate::step(&mut push.state, &[]).unwrap();
+        let target = push.state.entities[&push.tank].target.map(EntityId::from_u32).unwrap();
+        assert_eq!(push.state.entities[&target].tower_type(), Some(TowerType::RightPrincess));
+        assert!(push.state.entities[&push.support].position.x < push.state.entities[&push.tank].position.x);
+
+        let mut tiebreak = overtime_tiebreak();
+        assert!(!tiebreak.is_match_over());
+        crate::step(&mut tiebreak, &[]).unwrap();
+        let result = tiebreak.export_cr_state(PlayerId::Player1).result.unwrap();
+        assert_eq!((result.ally_crowns, result.enemy_crowns), (1, 1));
+        assert_eq!(result.winner, "ALLY");
+
+        let empty = empty_arena();
+        assert!(empty.entities.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Deploy formations for multi-unit cards.
+//!
+//! Offsets are computed in the deploying side's frame (forward = toward the
+//! enemy, lateral = across the lane) and are fully deterministic, so the
+//! same play always produces the same spread.
+
+use crate::fp;
+use serde::{Deserialize, Serialize};
+use shared::Position;
+
+/// Distance (tiles) between neighbouring units. Kept above two troop
+/// radii so freshly deployed units never start out overlapping.
+pub const FORMATION_SPACING: f32 = 1.0;
+
+/// How the units of a multi-unit card are arranged around the deploy point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Formation {
+    /// Side by side across the lane (Archers).
+    Line,
+    /// Evenly spaced on a circle (Goblin Gang, Minion Horde).
+    Ring,
+    /// Rows filling a rough square (Skeleton Army, Barbarians).
+    Cluster,
+}
+
+impl Formation {
+    /// Formation used when a card doesn't name one.
+    pub fn default_for(count: u32) -> Self {
+        if count <= 3 {
+            Formation::Line
+        } else {
+            Formation::Cluster
+        }
+    }
+
+    /// `(forward, lateral)` offsets for `count` units, centered on the
+    /// deploy point.
+    pub fn offsets(self, count: u32) -> Vec<(f32, f32)> {
+        if count <= 1 {
+            return vec![(0.0, 0.0); count as usize];
+        }
+        let n = count as f32;
+        match self {
+            Formation::Line => (0..count)
+                .map(|i| (0.0, (i as f32 - (n - 1.0) / 2.0) * FORMATION_SPACING))
+                .collect(),
+            Formation::Ring => {
+                // Radius at which neighbours sit exactly one spacing apart
+                let radius = FORMATION_SPACING / (2.0 * fp::sin(std::f32::consts::PI / n));
+                (0..count)
+                    .map(|i| {
+                        let angle = std::f32::consts::TAU * i as f32 / n;
+                        (radius * fp::cos(angle), radius * fp::sin(angle))
+                    })
+                    .collect()
+            }
+            Formation::Cluster => {
+                let cols = (n.sqrt().ceil() as u32).max(1);
+                let rows = count.div_ceil(cols);
+                (0..count)
+                    .map(|i| {
+                        let (row, col) = (i / cols, i % cols);
+                        // The last row may be short; center it on its own
+                        let in_row = (count - row * cols).min(cols) as f32;
+                        (
+                            ((rows - 1) as f32 / 2.0 - row as f32) * FORMATION_SPACING,
+                            (col as f32 - (in_row - 1.0) / 2.0) * FORMATION_SPACING,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// World positions for `count` units deployed at `anchor`, with
+    /// `forward` the unit vector toward the enemy side.
+    pub fn positions(self, count: u32, anchor: Position, forward: (f32, f32)) -> Vec<Position> {
+        let (fx, fy) = forward;
+        self.offsets(count)
+            .into_iter()
+            .map(|(a, b)| Position::new(anchor.x + fx * a - fy * b, anchor.y + fy * a + fx * b))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn min_gap(offsets: &[(f32, f32)]) -> f32 {
+        let mut gap = f32::MAX;
+        for (i, a) in offsets.iter().enumerate() {
+            for b in &offsets[i + 1..] {
+                gap = gap.min(((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt());
+            }
+        }
+        gap
+    }
+
+    #[test]
+    fn test_formations_are_centered_and_spaced() {
+        for formation in [Formation::Line, Formation::Ring, Formation::Cluster] {
+            for count in [2, 3, 5, 15] {
+                let offsets = formation.offsets(count);
+                assert_eq!(offsets.len(), count as usize);
+                assert!(min_gap(&offsets) >= FORMATION_SPACING - 1e-4, "{:?} x{}", formation, count);
+
+                let (sum_a, sum_b) = offsets.iter().fold((0.0, 0.0), |(x, y), o| (x + o.0, y + o.1));
+                if formation != Formation::Cluster {
+                    assert!(sum_a.abs() < 1e-3 && sum_b.abs() < 1e-3, "{:?} x{}", formation, count);
+                }
+            }
+        }
+        assert_eq!(Formation::Line.offsets(2), vec![(0.0, -0.5), (0.0, 0.5)]);
+    }
+
+    #[test]
+    fn test_positions_rotate_with_facing() {
+        let anchor = Position::new(10.0, 9.0);
+        let p1 = Formation::Line.positions(2, anchor, (1.0, 0.0));
+        let p2 = Formation::Line.positions(2, anchor, (-1.0, 0.0));
+        assert_eq!(p1, vec![Position::new(10.0, 8.5), Position::new(10.0, 9.5)]);
+        assert_eq!(p2, vec![Position::new(10.0, 9.5), Position::new(10.0, 8.5)]);
+    }
+
+    #[test]
+    fn test_multi_unit_cards_deploy_spread_out() {
+        use crate::state::GameState;
+        use shared::PlayerId;
+
+        let mut state = GameState::new(25);
+        state.entities.clear();
+        let archers = state.get_card_by_name("Archers").unwrap().clone();
+        archers.spawn(&mut state, PlayerId::Player2, Position::new(20.0, 9.0), 11).unwrap();
+
+        let mut ys: Vec<f32> = state.entities.values().map(|e| e.position.y).collect();
+        ys.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(ys, vec![8.5, 9.5]);
+        assert!(state.entities.values().all(|e| e.position.x == 20.0));
+    }
+}
@@ -0,0 +1,246 @@
+//! Floating-point policy for the simulation.
+//!
+//! Matches must replay bit-for-bit (see [`crate::state::GameState::state_hash`]),
+//! so every system follows these rules:
+//!
+//! 1. **Basic operations only.** `+ - * /` and `sqrt` are correctly rounded
+//!    by IEEE 754 on every target, and Rust never contracts `a * b + c`
+//!    into a fused multiply-add or reorders float math on its own. Code
+//!    must not opt in either: no `mul_add`, no `std::arch` intrinsics, no
+//!    fast-math style crates.
+//! 2. **Fixed evaluation order.** Entities are visited in id order (the
+//!    `BTreeMap`s), sums accumulate in that order, and nothing is reduced
+//!    in parallel inside a tick.
+//! 3. **Transcendentals go through this module.** `sin`, `cos` and `atan2`
+//!    come from the platform's libm, which is not required to round the
+//!    same way everywhere. By default these wrappers call `std` (identical
+//!    on the mainstream targets); with the `strict_fp` feature they use the
+//!    portable versions here, built from basic operations only, so every
+//!    IEEE 754 target gets the same bits. `powi`/`powf` aren't used; square
+//!    by multiplying.
+//!
+//! The audit test below rejects non-test code that breaks rule 1 or 3, and
+//! `reference/hash_vectors.json` lists scripted matches with the state
+//! hashes a `strict_fp` build must reproduce
+//! (`cargo test -p engine --features strict_fp`).
+
+#[cfg(not(feature = "strict_fp"))]
+pub use platform::{atan2, cos, sin};
+#[cfg(feature = "strict_fp")]
+pub use portable::{atan2, cos, sin};
+
+/// The platform's libm (the default).
+#[cfg_attr(feature = "strict_fp", allow(dead_code))]
+mod platform {
+    /// Sine of `x` (radians).
+    pub fn sin(x: f32) -> f32 {
+        x.sin()
+    }
+
+    /// Cosine of `x` (radians).
+    pub fn cos(x: f32) -> f32 {
+        x.cos()
+    }
+
+    /// Angle of the vector `(x, y)` from +x, in `[-PI, PI]`.
+    pub fn atan2(y: f32, x: f32) -> f32 {
+        y.atan2(x)
+    }
+}
+
+/// Transcendentals from basic operations only, evaluated in `f64` and
+/// rounded once to `f32`. Accurate to well under an `f32` ulp for the
+/// angles the simulation uses.
+#[cfg_attr(not(feature = "strict_fp"), allow(dead_code))]
+mod portable {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    /// `sin` and `cos` of `r` for `|r| <= PI / 4` (Taylor series).
+    fn sin_cos_reduced(r: f64) -> (f64, f64) {
+        let r2 = r * r;
+        let mut sin = 0.0;
+        let mut cos = 0.0;
+        let mut term_sin = r;
+        let mut term_cos = 1.0;
+        for n in 0..10 {
+            sin += term_sin;
+            cos += term_cos;
+            let k = (2 * n + 2) as f64;
+            term_cos = -term_cos * r2 / (k * (k - 1.0));
+            term_sin = -term_sin * r2 / ((k + 1.0) * k);
+        }
+        (sin, cos)
+    }
+
+    /// `(sin x, cos x)`, reducing `x` by quarter turns first.
+    fn sin_cos(x: f32) -> (f64, f64) {
+        let x = x as f64;
+        let quarter = (x / FRAC_PI_2).round();
+        let (s, c) = sin_cos_reduced(x - quarter * FRAC_PI_2);
+        match (quarter as i64).rem_euclid(4) {
+            0 => (s, c),
+            1 => (c, -s),
+            2 => (-s, -c),
+            _ => (-c, s),
+        }
+    }
+
+    /// Sine of `x` (radians).
+    pub fn sin(x: f32) -> f32 {
+        sin_cos(x).0 as f32
+    }
+
+    /// Cosine of `x` (radians).
+    pub fn cos(x: f32) -> f32 {
+        sin_cos(x).1 as f32
+    }
+
+    /// `atan` of `z` for `|z| <= 1`.
+    fn atan_unit(z: f64) -> f64 {
+        // Halve the angle twice (atan z = 2 atan(z / (1 + sqrt(1 + z^2))))
+        // so the series converges fast
+        let mut z = z;
+        for _ in 0..2 {
+            z /= 1.0 + (1.0 + z * z).sqrt();
+        }
+        let z2 = z * z;
+        let mut sum = 0.0;
+        let mut power = z;
+        for n in 0..12 {
+            let term = power / (2 * n + 1) as f64;
+            sum += if n % 2 == 0 { term } else { -term };
+            power *= z2;
+        }
+        4.0 * sum
+    }
+
+    /// Angle of the vector `(x, y)` from +x, in `[-PI, PI]` (0 for the
+    /// zero vector).
+    pub fn atan2(y: f32, x: f32) -> f32 {
+        let (y, x) = (y as f64, x as f64);
+        if x == 0.0 && y == 0.0 {
+            return 0.0;
+        }
+        let (ay, ax) = (y.abs(), x.abs());
+        let angle = if ay <= ax { atan_unit(ay / ax) } else { FRAC_PI_2 - atan_unit(ax / ay) };
+        let angle = if x < 0.0 { PI - angle } else { angle };
+        (if y < 0.0 { -angle } else { angle }) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::state::GameState;
+    use serde::Deserialize;
+    use shared::{PlayerId, Position};
+
+    #[test]
+    fn test_portable_math_matches_std() {
+        for i in -4000..=4000 {
+            let x = i as f32 * 0.0021;
+            assert!((portable::sin(x) - x.sin()).abs() <= 2.0 * f32::EPSILON, "sin {}", x);
+            assert!((portable::cos(x) - x.cos()).abs() <= 2.0 * f32::EPSILON, "cos {}", x);
+        }
+        for i in -60..=60 {
+            for j in -60..=60 {
+                let (y, x) = (i as f32 * 0.37, j as f32 * 0.41);
+                let expected = if x == 0.0 && y == 0.0 { 0.0 } else { y.atan2(x) };
+                assert!((portable::atan2(y, x) - expected).abs() <= 4.0 * f32::EPSILON, "atan2({}, {})", y, x);
+            }
+        }
+    }
+
+    /// Non-test source with its `#[cfg(test)]` module (always last) cut off.
+    fn non_test_sources() -> Vec<(std::path::PathBuf, String)> {
+        fn walk(dir: &std::path::Path, out: &mut Vec<(std::path::PathBuf, String)>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    walk(&path, out);
+                } else if path.extension().is_some_and(|e| e == "rs") {
+                    let source = std::fs::read_to_string(&path).unwrap();
+                    let code = source.split("#[cfg(test)]").next().unwrap_or_default().to_string();
+                    out.push((path, code));
+                }
+            }
+        }
+        let mut sources = Vec::new();
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        walk(&root.join("src"), &mut sources);
+        walk(&root.join("../shared/src"), &mut sources);
+        sources
+    }
+
+    #[test]
+    fn test_systems_follow_the_fp_policy() {
+        let forbidden = [
+            ".mul_add(", "std::arch", "core::arch", ".powi(", ".powf(", ".sin()", ".cos()", ".tan()",
+            ".sin_cos()", ".atan2(", ".exp()", ".ln()", ".hypot(",
+        ];
+        for (path, code) in non_test_sources() {
+            if path.ends_with("fp.rs") {
+                continue;
+            }
+            for pattern in forbidden {
+                assert!(!code.contains(pattern), "{} uses {} (see engine/src/fp.rs)", path.display(), pattern);
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Play {
+        player: PlayerId,
+        card: String,
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Deserialize)]
+    struct Vector {
+        name: String,
+        seed: u64,
+        ticks: u64,
+        plays: Vec<Play>,
+        state_hash: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Vectors {
+        vectors: Vec<Vector>,
+    }
+
+    fn run(vector: &Vector) -> u64 {
+        let mut state = GameState::new(vector.seed);
+        let plays: Vec<Action> = vector
+            .plays
+            .iter()
+            .map(|p| Action::PlayCard {
+                player: p.player,
+                card_name: p.card.clone(),
+                level: crate::fixtures::FIXTURE_LEVEL,
+                position: Position::new(p.x, p.y),
+            })
+            .collect();
+        crate::step(&mut state, &plays).unwrap();
+        for _ in 1..vector.ticks {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        state.state_hash()
+    }
+
+    #[test]
+    fn test_hash_vectors_replay() {
+        let vectors: Vectors = serde_json::from_str(include_str!("../reference/hash_vectors.json")).unwrap();
+        for vector in &vectors.vectors {
+            let hash = run(vector);
+            assert_eq!(hash, run(vector), "{} is not deterministic", vector.name);
+            // The expected hashes are for strict_fp builds; others use the
+            // platform's libm and may legitimately differ
+            if cfg!(feature = "strict_fp") {
+                assert_eq!(format!("{:016x}", hash), vector.state_hash, "{}", vector.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Importer for external match logs (imitation learning datasets).
+//!
+//! External logs are plain CSV with one card play per line:
+//!
+//! ```text
+//! tick,player,card,tile
+//! 120,1,Knight,70
+//! 300,2,Archers,41
+//! ```
+//!
+//! `player` is 1 or 2 and `tile` is a flat index into the 16x9 placement
+//! grid. Imported logs become [`Replay`]s and are re-simulated to check
+//! that every play was legal (known card, enough elixir, valid tile).
+
+use crate::action::Action;
+use crate::replay::Replay;
+use crate::state::GameState;
+use serde::{Deserialize, Serialize};
+use shared::{Error, PlayerId, Result};
+
+/// A single card play from an external log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalLogEntry {
+    pub tick: u64,
+    pub player: PlayerId,
+    pub card: String,
+    pub tile: usize,
+}
+
+/// Parses a CSV action log. Blank lines, `#` comments, and a
+/// `tick,player,card,tile` header are skipped.
+pub fn parse_action_log(text: &str) -> Result<Vec<ExternalLogEntry>> {
+    let mut entries = Vec::new();
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("tick,") {
+            continue;
+        }
+
+        let bad = |what: &str| Error::InvalidAction(format!("line {}: {}", line_no + 1, what));
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != 4 {
+            return Err(bad("expected 4 fields: tick,player,card,tile"));
+        }
+
+        let tick = fields[0].parse().map_err(|_| bad("invalid tick"))?;
+        let player = match fields[1] {
+            "1" => PlayerId::Player1,
+            "2" => PlayerId::Player2,
+            _ => return Err(bad("player must be 1 or 2")),
+        };
+        let tile = fields[3].parse().map_err(|_| bad("invalid tile"))?;
+
+        entries.push(ExternalLogEntry {
+            tick,
+            player,
+            card: fields[2].to_string(),
+            tile,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Converts log entries into a replay with the given seed and card level.
+/// Entries are ordered by tick (stable for plays on the same tick).
+pub fn import_action_log(entries: &[ExternalLogEntry], seed: u64, level: u32) -> Result<Replay> {
+    let state = GameState::new(seed);
+    let mut sorted: Vec<&ExternalLogEntry> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.tick);
+
+    let mut replay = Replay::new(seed);
+    for entry in sorted {
+        let position = state
+            .arena
+            .placement_tile_to_world(entry.tile, state.placement_grid)
+            .ok_or_else(|| {
+                Error::InvalidAction(format!("tick {}: invalid tile {}", entry.tick, entry.tile))
+            })?;
+        replay.record_action(
+            entry.tick,
+            Action::PlayCard {
+                player: entry.player,
+                card_name: entry.card.clone(),
+                level,
+                position,
+            },
+        );
+    }
+    Ok(replay)
+}
+
+/// Re-simulates a replay through its last action (see
+/// [`Replay::state_at`]), failing on the first tick whose actions the
+/// engine rejects. Returns the final state on success.
+pub fn verify_replay(replay: &Replay) -> Result<GameState> {
+    let end = replay.actions.last().map_or(0, |a| a.tick + 1);
+    replay.state_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_and_verify() {
+        let log = "tick,player,card,tile\n# opening\n60,1,Knight,70\n90,2,Archers,73\n";
+        let entries = parse_action_log(log).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].player, PlayerId::Player2);
+
+        let replay = import_action_log(&entries, 3, 11).unwrap();
+        assert_eq!(replay.actions.len(), 2);
+
+        let state = verify_replay(&replay).unwrap();
+        assert!(state.tick > 90);
+    }
+
+    #[test]
+    fn test_illegal_play_is_rejected() {
+        // Two Giants back-to-back cost more than the starting elixir
+        let log = "0,1,Giant,70\n1,1,Giant,70\n";
+        let replay = import_action_log(&parse_action_log(log).unwrap(), 3, 11).unwrap();
+
+        let err = verify_replay(&replay).unwrap_err().to_string();
+        assert!(err.contains("tick 1"), "{}", err);
+        assert!(err.contains("Not enough elixir"), "{}", err);
+    }
+
+    #[test]
+    fn test_replays_verify_under_their_own_mode() {
+        // Five elixir back two seconds after the first Giant only at triple rate
+        let log = "0,1,Giant,70\n120,1,Giant,70\n";
+        let mut replay = import_action_log(&parse_action_log(log).unwrap(), 3, 11).unwrap();
+        assert!(verify_replay(&replay).is_err());
+        replay.game_mode = "triple_elixir".to_string();
+        assert_eq!(verify_replay(&replay).unwrap().card_plays.len(), 2);
+    }
+
+    #[test]
+    fn test_bad_rows() {
+        assert!(parse_action_log("10,3,Knight,1").is_err());
+        assert!(parse_action_log("10,1,Knight").is_err());
+        assert!(import_action_log(&parse_action_log("10,1,Knight,999").unwrap(), 0, 11).is_err());
+    }
+}
@@ -8,21 +8,69 @@
 
 pub mod action;
 pub mod arena;
+pub mod balance;
+pub mod batch;
+pub mod bot;
+pub mod capabilities;
 pub mod card;
+pub mod card_db;
+pub mod card_stats;
+pub mod coverage;
 pub mod entities;
+pub mod events;
+pub mod fixtures;
+pub mod formation;
+pub mod fp;
+pub mod imitation;
+pub mod matchup;
+pub mod observation;
+pub mod placement;
+pub mod realtime;
+pub mod replay;
+pub mod reward;
+pub mod resync;
 pub mod rng;
+pub mod rpc;
+pub mod rollout;
+pub mod sampler;
+pub mod scenario;
+pub mod snapshot;
+pub mod soak;
+pub mod spatial;
 pub mod state;
 pub mod systems;
 pub mod tower;
+pub mod tournament;
+#[cfg(feature = "server")]
+pub mod ws;
 
-pub use action::Action;
-pub use arena::Arena;
-pub use card::{load_cards_from_json, Card, CardLevelStats, Rarity};
+pub use action::{Action, AgentAction, NO_OP_ACTION_ID};
+pub use arena::{Arena, Hazard};
+pub use batch::{BatchSim, BatchStep, SimBatch};
+pub use bot::{BotConfig, RuleBot};
+pub use capabilities::{capabilities, Capabilities};
+pub use card::{load_cards_from_json, Card, CardLevelStats, Rarity, SpeedTiers};
+pub use card_db::{CardDb, CardKey};
+pub use card_stats::{EffectiveCardStats, LevelStats};
+pub use events::GameEvent;
+pub use formation::Formation;
+pub use realtime::RealtimeRunner;
+pub use replay::{Annotation, Replay};
+pub use reward::{RewardConfig, RewardFn, RewardTally, RewardTracker};
+pub use resync::ResyncAssumptions;
 pub use rng::Rng;
-pub use state::GameState;
+pub use rollout::{rollout, Policy};
+pub use sampler::{SampledFrame, Snapshot, SnapshotKind, SnapshotSampler};
+pub use scenario::{Condition, Effect, Trigger};
+pub use snapshot::{SnapshotPublisher, SnapshotReader, StateSnapshot};
+pub use soak::{run_soak, spawn_soak, SoakConfig, SoakReport};
+pub use state::{Crown, DeckCard, EndReason, GameMode, GameState, MatchResult, TimeoutTermination};
+pub use tournament::{DeckEntry, MatchRecord, TournamentReport};
 pub use tower::{load_towers_from_json, Tower, TowerLevelStats};
 
-use shared::Result;
+use shared::{CRState, Error, PlayerId, Result};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Instant;
 
 /// Fixed timestep for simulation (60 FPS = ~16.67ms per tick).
 pub const DELTA_TIME: f32 = 1.0 / 60.0;
@@ -37,18 +85,109 @@ pub const DELTA_TIME: f32 = 1.0 / 60.0;
 /// # Determinism
 /// Given the same initial state, actions, and RNG seed, this function
 /// will always produce identical results.
+///
+/// # Step budget
+/// With a budget set (see [`GameState::set_step_budget`]), a step that runs
+/// over it stops early and ends the match with a [`TimeoutTermination`]
+/// instead of returning an error; the tick is left incomplete.
+///
+/// # Errors
+/// Invalid actions return their error, and none of the tick's actions are
+/// applied (see [`GameState::apply_actions`]). An internal panic is returned as
+/// [`Error::Internal`] instead of unwinding into the caller; the state may
+/// then be partially updated and should be discarded.
 pub fn step(state: &mut GameState, actions: &[Action]) -> Result<()> {
-    // Process actions
-    for action in actions {
-        state.apply_action(action)?;
+    let tick = state.tick;
+    catch_internal(|| format!("step at tick {}", tick), || step_inner(state, actions))
+}
+
+/// [`step`] for a tick whose actions come from separate agents, so one
+/// agent's rejected action doesn't cost the others theirs: if the tick's
+/// actions are rejected as a group, each is kept only if it still fits
+/// with the ones kept before it, and the tick runs with those. Returns the
+/// indices of the actions dropped.
+///
+/// # Errors
+/// Only an internal engine error (see [`step`]).
+pub fn step_keeping_valid(state: &mut GameState, actions: &[Action]) -> Result<Vec<usize>> {
+    let mut dropped = Vec::new();
+    let kept = if state.check_actions(actions).is_ok() {
+        actions.to_vec()
+    } else {
+        let mut kept = Vec::new();
+        for (i, action) in actions.iter().enumerate() {
+            kept.push(action.clone());
+            if state.check_actions(&kept).is_err() {
+                kept.pop();
+                dropped.push(i);
+            }
+        }
+        kept
+    };
+    step(state, &kept)?;
+    Ok(dropped)
+}
+
+/// Runs `f`, converting a panic inside it into [`Error::Internal`] prefixed
+/// with `context()`. Use this at API boundaries (servers, environment
+/// wrappers) so a single bad input can't take down a long-running process.
+pub fn catch_internal<T>(context: impl FnOnce() -> String, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(Error::Internal(format!("{}: {}", context(), message)))
+        }
+    }
+}
+
+/// A per-tick system's update function.
+type SystemUpdate = fn(&mut GameState, f32);
+
+fn step_inner(state: &mut GameState, actions: &[Action]) -> Result<()> {
+    // The clock is only read when there's a budget to hold the step to
+    let started = state.step_budget.map(|_| Instant::now());
+    state.events.clear();
+
+    // Remember where everything started this tick for render interpolation,
+    // and open a new tick of damage history (not kept headless)
+    let headless = state.headless;
+    for entity in state.entities.values_mut() {
+        entity.prev_position = entity.position;
+        if !headless {
+            entity.recent_damage.advance();
+        }
+    }
+
+    // Process actions (all or nothing)
+    state.apply_actions(actions)?;
+    if over_budget(state, started, "actions") {
+        return Ok(());
     }
 
     // Update systems
-    systems::elixir::update(state, DELTA_TIME);
-    systems::combat::update(state, DELTA_TIME); // Combat first to assign targets
-    systems::movement::update(state, DELTA_TIME); // Movement uses targets from combat
-    systems::projectile::update(state, DELTA_TIME); // Projectiles move toward targets
-    systems::lifecycle::update(state, DELTA_TIME);
+    let systems: [(&str, SystemUpdate); 10] = [
+        ("triggers", scenario::update), // Scripted events see the state as the tick starts
+        ("elixir", systems::elixir::update),
+        ("environment", systems::environment::update), // Hazards slow troops before they move
+        ("combat", systems::combat::update), // Combat first to assign targets
+        ("movement", systems::movement::update), // Movement uses targets from combat
+        ("projectile", systems::projectile::update), // Projectiles move toward targets
+        ("spell", systems::spell::update), // Spells land and deal area damage
+        ("zone", systems::zone::update), // Lingering zones deal damage over time
+        ("spawner", systems::spawner::update), // Buildings decay and spawn troops
+        ("lifecycle", systems::lifecycle::update),
+    ];
+    for (phase, update) in systems {
+        update(state, DELTA_TIME);
+        if over_budget(state, started, phase) {
+            return Ok(());
+        }
+    }
 
     // Increment tick counter and match time
     state.tick += 1;
@@ -57,6 +196,42 @@ pub fn step(state: &mut GameState, actions: &[Action]) -> Result<()> {
     Ok(())
 }
 
+/// Ends the match with a [`TimeoutTermination`] if the step that began at
+/// `started` (`None` without a budget) has used up the state's step budget.
+fn over_budget(state: &mut GameState, started: Option<Instant>, phase: &str) -> bool {
+    let (Some(budget), Some(started)) = (state.step_budget, started) else {
+        return false;
+    };
+    let elapsed = started.elapsed();
+    if elapsed < budget {
+        return false;
+    }
+    state.timeout = Some(TimeoutTermination {
+        tick: state.tick,
+        phase: phase.to_string(),
+        elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+        budget_ms: budget.as_secs_f64() * 1000.0,
+    });
+    true
+}
+
+/// Advances the simulation by one tick and exports a snapshot for `pov`.
+///
+/// Returns `None` without building the snapshot when the game is in
+/// headless throughput mode (see [`GameState::set_headless`]).
+pub fn step_and_export(
+    state: &mut GameState,
+    actions: &[Action],
+    pov: PlayerId,
+) -> Result<Option<CRState>> {
+    step(state, actions)?;
+
+    if state.headless {
+        return Ok(None);
+    }
+    Ok(Some(state.export_cr_state(pov)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,4 +251,706 @@ mod tests {
         assert_eq!(state1.tick, state2.tick);
         assert_eq!(state1.tick, 100);
     }
+
+    #[test]
+    fn test_step_budget_aborts_the_match() {
+        use std::time::Duration;
+
+        let mut state = GameState::new(5);
+        state.set_step_budget(Some(Duration::from_secs(60)));
+        step(&mut state, &[]).unwrap();
+        assert!(state.timeout.is_none());
+
+        // A zero budget runs out before the first system
+        state.set_step_budget(Some(Duration::ZERO));
+        step(&mut state, &[]).unwrap();
+        let timeout = state.timeout.clone().unwrap();
+        assert_eq!((timeout.tick, timeout.phase.as_str()), (1, "actions"));
+        assert_eq!(state.tick, 1);
+        assert!(state.is_match_over());
+
+        let result = state.export_cr_state(PlayerId::Player1).result.unwrap();
+        assert_eq!((result.winner.as_str(), result.end_reason.as_str()), ("DRAW", "timeout"));
+    }
+
+    #[test]
+    fn test_headless_skips_export() {
+        let mut state = GameState::new(3);
+        assert!(step_and_export(&mut state, &[], PlayerId::Player1).unwrap().is_some());
+
+        state.set_headless(true);
+        assert!(step_and_export(&mut state, &[], PlayerId::Player1).unwrap().is_none());
+        assert_eq!(state.tick, 2);
+    }
+
+    #[test]
+    fn test_headless_skips_events_and_damage_history() {
+        use shared::Position;
+
+        let mut state = GameState::new(3);
+        state.set_headless(true);
+        let princess = state
+            .entities
+            .iter()
+            .find(|(_, e)| e.owner == PlayerId::Player2 && e.tower_type() == Some(state::TowerType::LeftPrincess))
+            .map(|(id, _)| *id)
+            .unwrap();
+        let play = Action::PlayCard {
+            player: PlayerId::Player1,
+            card_name: "Knight".to_string(),
+            level: 11,
+            position: Position::new(10.0, 9.0),
+        };
+        state.damage(princess, state.entities[&princess].max_hp * 0.5);
+        assert!(state.entities[&princess].recent_damage.is_empty());
+        state.damage(princess, state.entities[&princess].max_hp);
+        step(&mut state, &[play]).unwrap();
+
+        // What the result and rewards are scored from is still kept
+        assert!(state.events.is_empty());
+        assert_eq!(state.crowns.len(), 1);
+        assert_eq!(state.card_plays.len(), 1);
+    }
+
+    #[test]
+    fn test_export_includes_previous_position() {
+        use shared::Position;
+
+        let mut state = GameState::new(4);
+        let spawn = Action::PlayCard {
+            player: PlayerId::Player1,
+            card_name: "Knight".to_string(),
+            level: 11,
+            position: Position::new(14.0, 9.0),
+        };
+        step(&mut state, &[spawn]).unwrap();
+        step(&mut state, &[]).unwrap();
+
+        let unit = &state.export_cr_state(PlayerId::Player1).ally_units[0];
+        let moved = ((unit.x - unit.prev_x).powi(2) + (unit.y - unit.prev_y).powi(2)).sqrt();
+        assert!(moved > 0.0);
+        assert!((moved - (unit.vx.powi(2) + unit.vy.powi(2)).sqrt() * DELTA_TIME).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_export_identifies_units_and_their_targets() {
+        use shared::Position;
+
+        let mut state = GameState::new(4);
+        state.set_deploy_zones(false);
+        let play = |player, card_name: &str, x| Action::PlayCard {
+            player,
+            card_name: card_name.to_string(),
+            level: 11,
+            position: Position::new(x, 9.0),
+        };
+        step(&mut state, &[play(PlayerId::Player1, "Knight", 13.0), play(PlayerId::Player2, "Baby Dragon", 19.0)]).unwrap();
+        for _ in 0..120 {
+            step(&mut state, &[]).unwrap();
+        }
+
+        let view = state.export_cr_state(PlayerId::Player1);
+        let knight = &view.ally_units[0];
+        assert_eq!((knight.card.as_str(), knight.level, knight.is_air), ("Knight", 11, false));
+        assert!(state.entities[&crate::state::EntityId::from_u32(knight.id)].is_alive());
+        assert!(knight.hp_frac < 1.0, "the dragon should have hit the knight");
+
+        let dragon = &view.enemy_units[0];
+        assert_eq!(dragon.card, "Baby Dragon");
+        assert!(dragon.is_air);
+        assert_eq!(dragon.target_id, Some(knight.id));
+    }
+
+    #[test]
+    fn test_export_includes_hand_and_cycle() {
+        use shared::Position;
+
+        let mut state = GameState::new(5);
+        let deck: Vec<String> = card::get_test_cards()
+            .iter()
+            .filter(|c| c.type_name == "troop")
+            .cycle()
+            .take(shared::dims::DECK_SIZE)
+            .map(|c| c.name.clone())
+            .collect();
+        state.set_player_deck(PlayerId::Player1, deck.clone()).unwrap();
+        state.set_player_deck(PlayerId::Player2, deck).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+
+        let before = state.export_cr_state(PlayerId::Player1);
+        assert_eq!(before.ally_cycle.hand.len(), shared::dims::HAND_SIZE);
+        assert!(before.ally_cycle.hand.iter().all(|c| c.elixir_cost > 0.0));
+        assert!(before.enemy_cycle.is_none());
+        let next = before.ally_cycle.next.clone().unwrap();
+
+        step(
+            &mut state,
+            &[Action::PlayCardFromHand {
+                player: PlayerId::Player1,
+                hand_index: 0,
+                position: Position::new(8.5, 9.5),
+            }],
+        )
+        .unwrap();
+        let after = state.export_cr_state(PlayerId::Player1);
+        assert_eq!(after.ally_cycle.hand[0], next);
+        assert_eq!(after.ally_cycle.cards_played, 1);
+        assert_eq!(after.ally_cycle.cycle_position, (before.ally_cycle.cycle_position + 1) % shared::dims::DECK_SIZE);
+
+        state.set_export_enemy_cycle(true);
+        let revealed = state.export_cr_state(PlayerId::Player2).enemy_cycle.unwrap();
+        assert_eq!(revealed, after.ally_cycle);
+    }
+
+    #[test]
+    fn test_recent_damage_fades_after_a_second() {
+        use entities::DAMAGE_HISTORY_TICKS;
+
+        let mut state = GameState::new(2);
+        let princess = state
+            .entities
+            .iter()
+            .find(|(_, e)| e.owner == PlayerId::Player2 && e.tower_type() == Some(state::TowerType::LeftPrincess))
+            .map(|(id, _)| *id)
+            .unwrap();
+        let max_hp = state.entities[&princess].max_hp;
+        state.damage(princess, max_hp * 0.25);
+        step(&mut state, &[]).unwrap();
+        state.damage(princess, max_hp * 0.25);
+
+        let towers = state.export_cr_state(PlayerId::Player1).enemy_towers;
+        assert!((towers[1].recent_damage_frac - 0.5).abs() < 1e-6);
+        assert_eq!(towers[0].recent_damage_frac.to_bits(), 0.0f32.to_bits());
+
+        for _ in 1..DAMAGE_HISTORY_TICKS {
+            step(&mut state, &[]).unwrap();
+        }
+        let towers = state.export_cr_state(PlayerId::Player1).enemy_towers;
+        assert!((towers[1].recent_damage_frac - 0.25).abs() < 1e-6);
+        step(&mut state, &[]).unwrap();
+        assert_eq!(state.export_cr_state(PlayerId::Player1).enemy_towers[1].recent_damage_frac, 0.0);
+        assert!(state.entities[&princess].recent_damage.is_empty());
+    }
+
+    #[test]
+    fn test_towers_spawned_as_entities() {
+        let state = GameState::new(1);
+        let towers: Vec<_> = state
+            .entities
+            .values()
+            .filter(|e| e.tower_type().is_some())
+            .collect();
+        assert_eq!(towers.len(), 6);
+    }
+
+    #[test]
+    fn test_troop_damages_tower() {
+        use shared::Position;
+
+        let mut state = GameState::new(7);
+        let king_pos = state.arena.tower_position(PlayerId::Player2, state::TowerType::King);
+
+        // Drop a Giant right next to the enemy King tower
+        state.set_deploy_zones(false);
+        step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Giant".to_string(),
+                level: 11,
+                position: Position::new(king_pos.x - 2.5, king_pos.y),
+            }],
+        )
+        .unwrap();
+
+        for _ in 0..300 {
+            step(&mut state, &[]).unwrap();
+        }
+
+        let king_hp = state.players[&PlayerId::Player2].tower_hp[&state::TowerType::King];
+        assert!(king_hp < 2400.0);
+    }
+
+    #[test]
+    fn test_crowns_are_logged_with_tick_and_attacker() {
+        use shared::Position;
+        use state::TowerType;
+
+        let mut state = GameState::new(7);
+        state.set_deploy_zones(false);
+        let princess = state.arena.tower_position(PlayerId::Player2, TowerType::LeftPrincess);
+        for entity in state.entities.values_mut() {
+            if entity.owner == PlayerId::Player2 && entity.tower_type() == Some(TowerType::LeftPrincess) {
+                entity.hp = 50.0;
+            }
+        }
+        step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Giant".to_string(),
+                level: 11,
+                position: Position::new(princess.x - 2.5, princess.y),
+            }],
+        )
+        .unwrap();
+        while state.crowns.is_empty() && state.tick < 300 {
+            step(&mut state, &[]).unwrap();
+        }
+        let crown = &state.crowns[0];
+        assert_eq!((crown.player, crown.tower, crown.crowns), (PlayerId::Player1, TowerType::LeftPrincess, 1));
+        assert_eq!(crown.tick, state.tick - 1);
+        assert_eq!(crown.card.as_deref(), Some("Giant"));
+        assert!(state.events.contains(&GameEvent::CrownTaken {
+            player: PlayerId::Player1,
+            tower: TowerType::LeftPrincess,
+            crowns: 1,
+        }));
+
+        // The King is worth the two crowns still outstanding
+        for entity in state.entities.values_mut() {
+            if entity.owner == PlayerId::Player2 && entity.tower_type() == Some(TowerType::King) {
+                entity.hp = 0.0;
+            }
+        }
+        step(&mut state, &[]).unwrap();
+        assert_eq!(state.crowns.len(), 2);
+        assert_eq!((state.crowns[1].tower, state.crowns[1].crowns), (TowerType::King, 2));
+
+        let view = state.export_cr_state(PlayerId::Player2);
+        assert!(view.crowns.iter().all(|c| c.owner == "ENEMY"));
+        assert_eq!(view.crowns[0].card.as_deref(), Some("Giant"));
+        assert_eq!(view.result.unwrap().crowns.len(), 2);
+    }
+
+    #[test]
+    fn test_troop_speed_falls_back_to_its_tier() {
+        use shared::Position;
+
+        let mut state = fixtures::empty_arena();
+        let mut card = state.get_card_by_name("Knight").cloned().unwrap();
+        card.movement_speed = Some("very_fast".to_string());
+        card.movement_speed_value = None;
+        let speed_of_new_troop = |state: &mut GameState| {
+            card.spawn(state, PlayerId::Player1, Position::new(8.0, 9.0), 11).unwrap();
+            let newest = state.entities.keys().max().copied().unwrap();
+            state.entities[&newest].movement_speed()
+        };
+
+        assert_eq!(speed_of_new_troop(&mut state), SpeedTiers::default().very_fast);
+        state.set_speed_tiers(SpeedTiers {
+            very_fast: 3.0,
+            ..SpeedTiers::default()
+        });
+        assert_eq!(speed_of_new_troop(&mut state), 3.0);
+    }
+
+    #[test]
+    fn test_terminal_frame_includes_result() {
+        let mut state = GameState::new(6);
+        assert!(state.export_cr_state(PlayerId::Player1).result.is_none());
+        assert_eq!(state.result(), None);
+
+        // Player1 took one princess tower before time ran out
+        state
+            .players
+            .get_mut(&PlayerId::Player2)
+            .unwrap()
+            .tower_hp
+            .insert(state::TowerType::LeftPrincess, 0.0);
+        state.match_time = state.max_match_time;
+
+        let outcome = state.result().unwrap();
+        assert_eq!(outcome.winner, Some(PlayerId::Player1));
+        assert_eq!(outcome.crowns[&PlayerId::Player1], 1);
+        assert_eq!(outcome.end_reason, state::EndReason::TimeUp);
+
+        let ally_view = state.export_cr_state(PlayerId::Player1);
+        let result = ally_view.result.as_ref().unwrap();
+        assert_eq!(result.winner, "ALLY");
+        assert_eq!((result.ally_crowns, result.enemy_crowns), (1, 0));
+        assert_eq!(result.end_reason, "time_up");
+        assert_eq!(result.enemy_tower_hp[1], 0.0);
+        assert!(ally_view.win && !ally_view.lose);
+
+        let enemy_view = state.export_cr_state(PlayerId::Player2);
+        assert_eq!(enemy_view.result.unwrap().winner, "ENEMY");
+        assert!(enemy_view.lose);
+    }
+
+    #[test]
+    fn test_game_modes_change_elixir_and_rules() {
+        use state::{GameMode, TowerType};
+
+        let mut triple = GameState::new_with_mode(7, GameMode::TripleElixir);
+        assert_eq!(triple.game_mode, "triple_elixir");
+        let start = triple.players[&PlayerId::Player1].elixir;
+        for _ in 0..30 {
+            step(&mut triple, &[]).unwrap();
+        }
+        assert!((triple.players[&PlayerId::Player1].elixir - start - 1.5).abs() < 1e-3);
+
+        // Sudden Death ends on the first crown
+        let mut sudden = GameState::new_with_mode(7, GameMode::SuddenDeath);
+        let tower = sudden
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player2 && e.tower_type() == Some(TowerType::LeftPrincess))
+            .unwrap();
+        tower.hp = 0.0;
+        step(&mut sudden, &[]).unwrap();
+        let result = sudden.result().unwrap();
+        assert_eq!(result.end_reason, state::EndReason::FirstCrown);
+        assert_eq!(result.winner, Some(PlayerId::Player1));
+
+        // Rage mode speeds every troop up
+        let speed = |mode: GameMode| {
+            let mut state = GameState::new_with_mode(7, mode);
+            let knight = fixtures::spawn(&mut state, PlayerId::Player1, "Knight", shared::Position::new(8.0, 9.0)).unwrap();
+            step(&mut state, &[]).unwrap();
+            state.entities[&knight].velocity.length()
+        };
+        let (standard, raged) = (speed(GameMode::Standard), speed(GameMode::Rage));
+        assert!((raged - standard * entities::RAGE_SPEED_MULTIPLIER).abs() < 1e-4);
+
+        // Replays bring the mode's rules back
+        let mut replay = Replay::new(7);
+        replay.record_metadata(&GameState::new_with_mode(7, GameMode::Rage));
+        assert!(replay.state_at(1).unwrap().event_modifiers.permanent_rage);
+        assert_eq!(GameMode::from_name("rage").map(GameMode::name), Some("rage"));
+    }
+
+    #[test]
+    fn test_2v2_teammates_share_towers_but_not_elixir() {
+        use shared::Position;
+        use state::TowerType;
+
+        let play = |player: PlayerId, x: f32, y: f32| Action::PlayCard {
+            player,
+            card_name: "Knight".to_string(),
+            level: 11,
+            position: Position::new(x, y),
+        };
+        let mut state = GameState::new_2v2(7);
+        assert!(state.is_2v2() && !GameState::new(7).is_2v2());
+        assert_eq!(state.entities.values().filter(|e| e.tower_type().is_some()).count(), 6);
+
+        step(&mut state, &[play(PlayerId::Player1, 10.0, 4.0), play(PlayerId::Player3, 10.0, 5.0)]).unwrap();
+        step(&mut state, &[play(PlayerId::Player4, 24.0, 9.0)]).unwrap();
+        for _ in 0..120 {
+            step(&mut state, &[]).unwrap();
+        }
+        let elixir = |state: &GameState, player: PlayerId| state.players[&player].elixir;
+        assert!(elixir(&state, PlayerId::Player3) < elixir(&state, PlayerId::Player2));
+        assert!(elixir(&state, PlayerId::Player4) < elixir(&state, PlayerId::Player2));
+
+        // Teammates never target each other
+        for entity in state.entities.values().filter(|e| e.tower_type().is_none()) {
+            let target = entity.target.and_then(|id| state.entities.get(&state::EntityId::from_u32(id)));
+            assert!(target.is_none_or(|t| !t.owner.is_ally_of(entity.owner)));
+        }
+
+        // Losing a tower costs the whole team
+        for entity in state.entities.values_mut() {
+            if entity.owner == PlayerId::Player2 && entity.tower_type() == Some(TowerType::LeftPrincess) {
+                entity.hp = 0.0;
+            }
+        }
+        step(&mut state, &[]).unwrap();
+        assert_eq!(state.players[&PlayerId::Player4].tower_hp, state.players[&PlayerId::Player2].tower_hp);
+        assert_eq!(state.crowns_against(PlayerId::Player4), 1);
+
+        let view = state.export_cr_state(PlayerId::Player3);
+        assert_eq!(view.ally_units.len(), 2);
+        assert_eq!(view.enemy_units.len(), 1);
+        assert_eq!(view.teammate_elixir, Some(elixir(&state, PlayerId::Player1)));
+        assert!(view.crowns.iter().all(|c| c.owner == "ALLY"));
+        assert!(view.enemy_towers[1].hp_frac == 0.0);
+        assert!(GameState::new(7).export_cr_state(PlayerId::Player1).teammate_cycle.is_none());
+    }
+
+    #[test]
+    fn test_multi_action_ticks_are_all_or_nothing() {
+        use shared::Position;
+
+        let play = |card: &str, y: f32| Action::PlayCard {
+            player: PlayerId::Player1,
+            card_name: card.to_string(),
+            level: 11,
+            position: Position::new(10.0, y),
+        };
+        let mut state = GameState::new(8);
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 6.0;
+        let before = state.entities.len();
+
+        // Knight (3) then Giant (5) overdraws on the second play
+        assert!(step(&mut state, &[play("Knight", 4.0), play("Giant", 14.0)]).is_err());
+        assert_eq!(state.entities.len(), before);
+        assert_eq!(state.players[&PlayerId::Player1].elixir, 6.0);
+        assert!(state.card_plays.is_empty());
+
+        // Two cheap cards fit, paid for in order
+        step(&mut state, &[play("Knight", 4.0), play("Knight", 14.0)]).unwrap();
+        assert_eq!(state.card_plays.len(), 2);
+        assert!(state.players[&PlayerId::Player1].elixir < 0.1);
+
+        state.set_max_actions_per_tick(Some(1));
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        assert!(step(&mut state, &[play("Knight", 4.0), play("Knight", 14.0)]).is_err());
+        assert_eq!(state.card_plays.len(), 2);
+
+        // Agents sharing a tick keep whichever plays fit
+        let dropped = step_keeping_valid(&mut state, &[play("Knight", 4.0), play("Knight", 14.0)]).unwrap();
+        assert_eq!(dropped, [1]);
+        assert_eq!(state.card_plays.len(), 3);
+
+        // A rejected group leaves hands uncycled and unknown levels are
+        // caught before anything spawns
+        state.set_max_actions_per_tick(None);
+        state.set_player_deck(PlayerId::Player1, vec!["Knight".to_string(); 8]).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        let hand = state.players[&PlayerId::Player1].hand.clone();
+        let from_hand = Action::PlayCardFromHand {
+            player: PlayerId::Player1,
+            hand_index: 0,
+            position: Position::new(10.0, 4.0),
+        };
+        let bad_level = Action::PlayCard {
+            player: PlayerId::Player1,
+            card_name: "Knight".to_string(),
+            level: 99,
+            position: Position::new(10.0, 14.0),
+        };
+        let before = state.entities.len();
+        assert!(state.check_actions(&[from_hand.clone(), bad_level.clone()]).is_err());
+        assert!(step(&mut state, &[from_hand, bad_level]).is_err());
+        assert_eq!(state.players[&PlayerId::Player1].hand, hand);
+        assert_eq!(state.entities.len(), before);
+    }
+
+    #[test]
+    fn test_plays_must_land_in_the_deploy_zone() {
+        use shared::Position;
+
+        let play = |player: PlayerId, card: &str, x: f32, y: f32| Action::PlayCard {
+            player,
+            card_name: card.to_string(),
+            level: 11,
+            position: Position::new(x, y),
+        };
+        let mut state = GameState::new(9);
+        for player in state.players.values_mut() {
+            player.elixir = 10.0;
+        }
+        let rejected = |state: &mut GameState, action: Action| {
+            matches!(step(state, &[action]), Err(Error::InvalidAction(_)))
+        };
+
+        // Enemy half, river and off-arena are out; spells go anywhere
+        assert!(rejected(&mut state, play(PlayerId::Player1, "Knight", 20.0, 4.0)));
+        assert!(rejected(&mut state, play(PlayerId::Player1, "Knight", 15.5, 9.0)));
+        assert!(rejected(&mut state, play(PlayerId::Player2, "Knight", 10.0, 9.0)));
+        assert!(rejected(&mut state, play(PlayerId::Player2, "Arrows", 40.0, 9.0)));
+        step(&mut state, &[play(PlayerId::Player2, "Knight", 20.0, 4.0)]).unwrap();
+        step(&mut state, &[play(PlayerId::Player1, "Arrows", 26.0, 4.0)]).unwrap();
+
+        // Taking Player2's left-lane princess opens a pocket in that lane only
+        let princess = state.arena.tower_position(PlayerId::Player2, state::TowerType::LeftPrincess);
+        let lane = state.arena.lane_of(&princess);
+        let tower = state
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player2 && e.tower_type() == Some(state::TowerType::LeftPrincess))
+            .unwrap();
+        tower.hp = 0.0;
+        step(&mut state, &[]).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        let pocket_y = if lane == 0 { 4.0 } else { 14.0 };
+        let other_y = if lane == 0 { 14.0 } else { 4.0 };
+        assert!(rejected(&mut state, play(PlayerId::Player1, "Knight", 19.0, other_y)));
+        assert!(rejected(&mut state, play(PlayerId::Player1, "Knight", 25.0, pocket_y)));
+        step(&mut state, &[play(PlayerId::Player1, "Knight", 19.0, pocket_y)]).unwrap();
+    }
+
+    #[test]
+    fn test_placement_grid_sets_action_space_and_masks() {
+        let mut state = GameState::new(10);
+        assert_eq!(state.export_cr_state(PlayerId::Player1).legal.tiles_flat.len(), 16 * 9);
+
+        state.set_placement_grid(8, 6).unwrap();
+        let legal = state.export_cr_state(PlayerId::Player1).legal;
+        assert_eq!((legal.grid_w, legal.grid_h, legal.tiles_flat.len()), (8, 6, 48));
+        assert!(state.set_placement_grid(0, 6).is_err());
+        assert_eq!(state.placement_grid.len(), 48);
+
+        // Tile indices past the configured grid are ignored
+        let deck: Vec<String> = ["Knight", "Archers", "Giant", "Musketeer"]
+            .iter()
+            .cycle()
+            .take(8)
+            .map(|c| c.to_string())
+            .collect();
+        state.set_player_deck(PlayerId::Player1, deck).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        state.set_headless(true);
+        let before = state.entities.len();
+        state::step_with_action(&mut state, PlayerId::Player1, 0, 48).unwrap();
+        assert_eq!(state.entities.len(), before);
+        // Row 2, column 1: a cell in Player1's half
+        state::step_with_action(&mut state, PlayerId::Player1, 0, 2 * 8 + 1).unwrap();
+        assert!(state.entities.len() > before || !state.pending_deploys.is_empty());
+    }
+
+    #[test]
+    fn test_exported_tiles_match_placement_checks() {
+        let mut state = GameState::new(12);
+        let knight = state.get_card_by_name("Knight").unwrap().clone();
+        let agrees = |state: &GameState, pov: PlayerId| {
+            let tiles = state.export_cr_state(pov).legal.tiles_flat;
+            tiles.iter().enumerate().all(|(tile, &legal)| {
+                let pos = state.arena.pov_tile_to_world(pov, tile, state.placement_grid).unwrap();
+                legal == state.check_placement(pov, &knight, pos).is_ok()
+            })
+        };
+        let count = |state: &GameState| {
+            state.export_cr_state(PlayerId::Player1).legal.tiles_flat.iter().filter(|&&t| t).count()
+        };
+        assert!(agrees(&state, PlayerId::Player1) && agrees(&state, PlayerId::Player2));
+        let own_half = count(&state);
+        assert!(own_half > 0 && own_half <= state.placement_grid.len() / 2);
+
+        // A fallen princess tower opens its pocket in the mask too
+        let tower = state
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player2 && e.tower_type() == Some(state::TowerType::LeftPrincess))
+            .unwrap();
+        tower.hp = 0.0;
+        step(&mut state, &[]).unwrap();
+        assert!(count(&state) > own_half);
+        assert!(agrees(&state, PlayerId::Player1) && agrees(&state, PlayerId::Player2));
+    }
+
+    #[test]
+    fn test_action_mask_marks_exactly_the_accepted_plays() {
+        let mut state = GameState::new(15);
+        state.set_headless(true);
+        state.set_placement_grid(8, 6).unwrap();
+        let deck = ["Knight", "Arrows", "Giant", "Archers", "Fireball", "Musketeer", "Knight", "Arrows"];
+        state.set_player_deck(PlayerId::Player2, deck.iter().map(|c| c.to_string()).collect()).unwrap();
+        // Knight, Arrows, Giant and Fireball in hand; only the first two affordable
+        let player = state.players.get_mut(&PlayerId::Player2).unwrap();
+        let index_of = |name: &str| player.deck.iter().position(|c| c.name == name).unwrap();
+        player.hand = ["Knight", "Arrows", "Giant", "Fireball"].map(index_of).to_vec();
+        player.elixir = 3.5;
+
+        let legal = state.export_cr_state(PlayerId::Player2).legal;
+        assert_eq!(legal.cards, [true, true, false, false, false, false, false, false]);
+        // Arrows reach past the deploy zone
+        let count = |tiles: &[bool]| tiles.iter().filter(|&&t| t).count();
+        assert_eq!(count(&legal.hand_tiles[1]), state.placement_grid.len());
+        assert!(count(&legal.hand_tiles[0]) < state.placement_grid.len());
+        let mask = legal.action_mask();
+        assert_eq!(mask.len(), AgentAction::space_size(state.placement_grid));
+        for (id, &marked) in mask.iter().enumerate().skip(1) {
+            let action = AgentAction::decode(id, state.placement_grid).unwrap();
+            let accepted = action
+                .to_action(&state, PlayerId::Player2)
+                .unwrap()
+                .is_some_and(|play| state.clone().apply_actions(&[play]).is_ok());
+            assert_eq!(marked, accepted, "action {}", id);
+        }
+    }
+
+    #[test]
+    fn test_agent_steps_run_engine_ticks() {
+        let mut state = GameState::new(14);
+        let deck: Vec<String> = ["Knight", "Archers", "Giant", "Musketeer"]
+            .iter()
+            .cycle()
+            .take(8)
+            .map(|c| c.to_string())
+            .collect();
+        state.set_player_deck(PlayerId::Player1, deck.clone()).unwrap();
+        state.set_player_deck(PlayerId::Player2, deck).unwrap();
+        state.set_headless(true);
+        state.set_frame_skip(30);
+        let elixir = state.players[&PlayerId::Player2].elixir;
+
+        // Player2 can't afford its play; Player1's still goes through
+        state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 0.0;
+        let hands = state.players.values().map(|p| (p.hand.clone(), p.next_card_index)).collect::<Vec<_>>();
+        let play = AgentAction::Play { slot: 0, tile: 2 * 16 + 3 };
+        state::step_with_agent_actions(&mut state, &[(PlayerId::Player1, play), (PlayerId::Player2, play)]).unwrap();
+        assert_eq!(state.tick, 30);
+        assert!((state.match_time - 0.5).abs() < 1e-4);
+        assert_eq!(state.card_plays.len(), 1);
+        assert_eq!(state.card_plays[0].player, PlayerId::Player1);
+        assert!(state.players[&PlayerId::Player2].elixir > 0.0);
+        // The played slot cycles in the next card; the dropped play leaves
+        // Player2's hand alone
+        let player1 = &state.players[&PlayerId::Player1];
+        assert_eq!(player1.hand[0], hands[0].1);
+        assert_eq!(player1.hand[1..], hands[0].0[1..]);
+        let player2 = &state.players[&PlayerId::Player2];
+        assert_eq!((player2.hand.clone(), player2.next_card_index), hands[1]);
+
+        // The troops it spawned move and the clock keeps running on no-ops
+        let troop = |state: &GameState| {
+            state
+                .entities
+                .values()
+                .find(|e| matches!(e.kind, entities::EntityKind::Troop(_)))
+                .map(|e| e.position)
+                .unwrap()
+        };
+        let spawned = troop(&state);
+        state::step_with_agent_action(&mut state, PlayerId::Player1, AgentAction::NoOp).unwrap();
+        assert_eq!(state.tick, 60);
+        assert_ne!(troop(&state), spawned);
+        assert!(state.players[&PlayerId::Player1].elixir < elixir);
+    }
+
+    #[test]
+    fn test_hand_plays_use_deck_levels() {
+        let mut state = GameState::new(6);
+        let mut cards = card::get_test_cards();
+        let knight = cards.iter_mut().find(|c| c.name == "Knight").unwrap();
+        let mut level_9 = knight.levels[0].clone();
+        level_9.level = 9;
+        level_9.hp = Some(1000.0);
+        knight.levels.push(level_9);
+        state.load_cards(cards).unwrap();
+
+        assert!(state.set_player_deck(PlayerId::Player1, vec![DeckCard::new("Knight", 12); 8]).is_err());
+        state.set_player_deck(PlayerId::Player1, vec![DeckCard::new("Knight", 9); 8]).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        assert_eq!(state.export_cr_state(PlayerId::Player1).ally_cycle.hand[0].level, 9);
+
+        // Hand plays take the deck's level; there's none to pass
+        let play = Action::PlayCardFromHand {
+            player: PlayerId::Player1,
+            hand_index: 0,
+            position: shared::Position::new(8.5, 9.5),
+        };
+        step(&mut state, &[play]).unwrap();
+        let knight = state.entities.values().find(|e| e.card() == Some("Knight")).unwrap();
+        assert_eq!(knight.max_hp, 1000.0);
+
+        // Decks stored as bare names still load, at the default level
+        let stored: Vec<DeckCard> = serde_json::from_str(r#"["Knight", {"name": "Giant", "level": 9}]"#).unwrap();
+        assert_eq!(stored, [DeckCard::new("Knight", state::DEFAULT_CARD_LEVEL), DeckCard::new("Giant", 9)]);
+    }
+
+    #[test]
+    fn test_panics_become_internal_errors() {
+        let err = catch_internal(|| "command 'STEP'".to_string(), || -> Result<()> { panic!("bad index") })
+            .unwrap_err();
+        assert!(matches!(&err, Error::Internal(msg) if msg == "command 'STEP': bad index"), "{}", err);
+
+        // Short decks are rejected up front instead of tripping an assert
+        let mut state = GameState::new(5);
+        let err = state.set_player_deck(PlayerId::Player1, vec!["Knight".to_string()]).unwrap_err();
+        assert!(matches!(err, Error::InvalidAction(_)));
+    }
 }
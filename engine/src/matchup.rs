@@ -0,0 +1,239 @@
+//! Unit interaction tests in a few lines: spawn named units, run some
+//! ticks, check who's left standing and with how much HP.
+//!
+//! [`Matchup`] is the builder; [`simulate_matchup!`](crate::simulate_matchup)
+//! writes the same thing declaratively:
+//!
+//! ```text
+//! let outcome = simulate_matchup! {
+//!     spawn knight = Player1 "Knight" at (10.0, 9.0);
+//!     spawn archers = Player2 "Archers" at (13.0, 9.0);
+//!     ticks 60 * 20;
+//!     survivors [knight];
+//!     hp knight in 1.0..;
+//! };
+//! assert_eq!(outcome.hp("archers"), 0.0);
+//! ```
+//!
+//! Matchups start from [`fixtures::empty_arena`] (no towers, so nothing
+//! else joins the fight) unless `towers;` comes first, and spawn like the
+//! fixtures do: at [`FIXTURE_LEVEL`], without deploy time.
+//!
+//! [`FIXTURE_LEVEL`]: crate::fixtures::FIXTURE_LEVEL
+
+use crate::entities::Entity;
+use crate::fixtures;
+use crate::state::{EntityId, GameState};
+use std::fmt::Debug;
+use std::ops::RangeBounds;
+
+/// Re-exported for [`simulate_matchup!`](crate::simulate_matchup).
+pub use shared::{PlayerId, Position};
+
+/// A fight to set up and run (see the module docs).
+pub struct Matchup {
+    state: GameState,
+    units: Vec<(String, Vec<EntityId>)>,
+}
+
+impl Default for Matchup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Matchup {
+    /// An empty arena: no towers, no units.
+    pub fn new() -> Self {
+        Self::on(fixtures::empty_arena())
+    }
+
+    /// A fresh match with both sides' towers up.
+    pub fn with_towers() -> Self {
+        Self::on(GameState::new(fixtures::FIXTURE_SEED))
+    }
+
+    /// Any starting state, for setups the other constructors don't cover.
+    pub fn on(state: GameState) -> Self {
+        Self {
+            state,
+            units: Vec::new(),
+        }
+    }
+
+    /// Spawns `card` for `owner` at `position`, naming everything it
+    /// creates `label` (all of a multi-unit card's troops share it).
+    ///
+    /// # Panics
+    /// If the card isn't loaded or can't be spawned there, or the label is
+    /// taken: a broken setup, not a result.
+    pub fn spawn(mut self, label: &str, owner: PlayerId, card: &str, position: Position) -> Self {
+        assert!(self.units.iter().all(|(l, _)| l != label), "label {} is used twice", label);
+        let first = self.state.entities.keys().map(|id| id.as_u32() + 1).max().unwrap_or(0);
+        fixtures::spawn(&mut self.state, owner, card, position)
+            .unwrap_or_else(|e| panic!("spawning {} for {}: {}", card, label, e));
+        let ids = self.state.entities.keys().copied().filter(|id| id.as_u32() >= first).collect();
+        self.units.push((label.to_string(), ids));
+        self
+    }
+
+    /// Adjusts the state before running (elixir, statuses, arena hazards).
+    pub fn setup(mut self, f: impl FnOnce(&mut GameState)) -> Self {
+        f(&mut self.state);
+        self
+    }
+
+    /// Steps the match `ticks` times with no card plays.
+    ///
+    /// # Panics
+    /// If a step fails.
+    pub fn run(mut self, ticks: u64) -> MatchupOutcome {
+        for _ in 0..ticks {
+            crate::step(&mut self.state, &[]).unwrap_or_else(|e| panic!("step at tick {}: {}", self.state.tick, e));
+        }
+        MatchupOutcome {
+            state: self.state,
+            units: self.units,
+        }
+    }
+}
+
+/// The state after a [`Matchup`] ran, with its named units.
+pub struct MatchupOutcome {
+    pub state: GameState,
+    units: Vec<(String, Vec<EntityId>)>,
+}
+
+impl MatchupOutcome {
+    /// The live units named `label` (removed and dead ones are left out).
+    ///
+    /// # Panics
+    /// If no unit was spawned as `label`.
+    pub fn units(&self, label: &str) -> Vec<&Entity> {
+        let (_, ids) = self
+            .units
+            .iter()
+            .find(|(l, _)| l == label)
+            .unwrap_or_else(|| panic!("no unit named {}", label));
+        ids.iter().filter_map(|id| self.state.entities.get(id)).filter(|e| e.is_alive()).collect()
+    }
+
+    /// HP (shields included) left across the units named `label`.
+    pub fn hp(&self, label: &str) -> f32 {
+        self.units(label).iter().map(|e| e.hp + e.shield).sum()
+    }
+
+    /// Labels with a unit still alive, in spawn order.
+    pub fn survivors(&self) -> Vec<&str> {
+        self.units
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .filter(|label| !self.units(label).is_empty())
+            .collect()
+    }
+
+    /// Asserts the labels alive are exactly `expected` (in any order).
+    pub fn assert_survivors(&self, expected: &[&str]) -> &Self {
+        let mut alive = self.survivors();
+        let mut expected = expected.to_vec();
+        alive.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(alive, expected, "survivors after {} ticks", self.state.tick);
+        self
+    }
+
+    /// Asserts the HP left across the units named `label` is in `range`.
+    pub fn assert_hp(&self, label: &str, range: impl RangeBounds<f32> + Debug) -> &Self {
+        let hp = self.hp(label);
+        assert!(range.contains(&hp), "{} has {} HP, expected {:?}", label, hp, range);
+        self
+    }
+}
+
+/// Declares a [`Matchup`], runs it and checks the outcome, returning the
+/// [`MatchupOutcome`] for further assertions:
+///
+/// ```text
+/// simulate_matchup! {
+///     towers;                                       // optional
+///     spawn <label> = <Player1|Player2> "<card>" at (<x>, <y>);   // one or more
+///     ticks <n>;
+///     survivors [<label>, ...];                     // optional, exact set
+///     hp <label> in <range>;                        // any number
+/// }
+/// ```
+#[macro_export]
+macro_rules! simulate_matchup {
+    (towers; $($rest:tt)*) => {
+        $crate::simulate_matchup!(@on $crate::matchup::Matchup::with_towers(); $($rest)*)
+    };
+    (
+        @on $matchup:expr;
+        $(spawn $label:ident = $owner:ident $card:literal at ($x:expr, $y:expr);)+
+        ticks $ticks:expr;
+        $(survivors [$($survivor:ident),* $(,)?];)?
+        $(hp $hp_label:ident in $range:expr;)*
+    ) => {{
+        let matchup = $matchup$(
+            .spawn(
+                stringify!($label),
+                $crate::matchup::PlayerId::$owner,
+                $card,
+                $crate::matchup::Position::new($x, $y),
+            )
+        )+;
+        let outcome = matchup.run($ticks);
+        $(outcome.assert_survivors(&[$(stringify!($survivor)),*]);)?
+        $(outcome.assert_hp(stringify!($hp_label), $range);)*
+        outcome
+    }};
+    ($($rest:tt)*) => {
+        $crate::simulate_matchup!(@on $crate::matchup::Matchup::new(); $($rest)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityKind;
+
+    #[test]
+    fn test_knight_beats_archers_one_on_one() {
+        let outcome = crate::simulate_matchup! {
+            spawn knight = Player1 "Knight" at (10.0, 9.0);
+            spawn archers = Player2 "Archers" at (13.0, 9.0);
+            ticks 60 * 20;
+            survivors [knight];
+            hp knight in 1.0..;
+        };
+        assert_eq!(outcome.units("archers").len(), 0);
+        assert!(outcome.hp("knight") < outcome.units("knight")[0].max_hp);
+    }
+
+    #[test]
+    fn test_builder_tracks_every_unit_of_a_card() {
+        let outcome = Matchup::new()
+            .spawn("archers", PlayerId::Player1, "Archers", Position::new(8.0, 9.0))
+            .spawn("dummy", PlayerId::Player2, "Knight", Position::new(20.0, 9.0))
+            .setup(|state| {
+                for entity in state.entities.values_mut() {
+                    if let EntityKind::Troop(data) = &mut entity.kind {
+                        data.movement_speed = 0.0;
+                    }
+                }
+            })
+            .run(30);
+        assert_eq!(outcome.units("archers").len(), 2);
+        let full = outcome.units("dummy")[0].max_hp;
+        outcome.assert_survivors(&["dummy", "archers"]).assert_hp("dummy", full..=full);
+
+        // With towers up, the Princess tower joins in
+        let defended = crate::simulate_matchup! {
+            towers;
+            spawn knight = Player2 "Knight" at (9.0, 3.5);
+            ticks 60 * 30;
+            survivors [];
+        };
+        assert!(defended.state.entities.values().any(|e| e.tower_type().is_some()));
+    }
+}
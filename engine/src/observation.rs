@@ -0,0 +1,146 @@
+//! Grid observation export: the arena rasterized into stacked per-tile
+//! feature planes, for agents that want a tensor rather than unit lists.
+
+use crate::entities::{CollisionShape, EntityKind, Transport};
+use crate::state::GameState;
+use shared::{dims, PlayerId};
+
+/// Summed HP fraction of the viewer's troops and buildings on each tile.
+pub const OBS_ALLY_HP: usize = 0;
+/// Summed HP fraction of the opponent's troops and buildings.
+pub const OBS_ENEMY_HP: usize = 1;
+/// The viewer's flying troops only; ground HP is [`OBS_ALLY_HP`] minus this.
+pub const OBS_ALLY_AIR: usize = 2;
+/// The opponent's flying troops only.
+pub const OBS_ENEMY_AIR: usize = 3;
+/// HP fraction of each of the viewer's towers, over every tile it covers.
+pub const OBS_ALLY_TOWERS: usize = 4;
+/// HP fraction of each of the opponent's towers.
+pub const OBS_ENEMY_TOWERS: usize = 5;
+/// The viewer's elixir over its max, on every tile.
+pub const OBS_ALLY_ELIXIR: usize = 6;
+/// The opponent's estimated elixir over its max, on every tile.
+pub const OBS_ENEMY_ELIXIR: usize = 7;
+
+/// Number of planes in the observation grid.
+pub const OBS_CHANNELS: usize = 8;
+
+/// `[channels, height, width]` of [`GameState::export_observation_grid`].
+pub const OBS_SHAPE: [usize; 3] = [OBS_CHANNELS, dims::ARENA_HEIGHT as usize, dims::ARENA_WIDTH as usize];
+
+/// Number of values in one observation grid (the product of [`OBS_SHAPE`]).
+pub const OBS_LEN: usize = OBS_SHAPE[0] * OBS_SHAPE[1] * OBS_SHAPE[2];
+
+impl GameState {
+    /// Rasterizes the arena into [`OBS_SHAPE`] planes, flattened channel
+    /// first, then row-major: tile `(x, y)` of plane `c` is at index
+    /// `(c * height + y) * width + x`.
+    ///
+    /// The grid is in `pov`'s frame, like agent placement tiles: Player2's
+    /// view is point-reflected so its own side is on the left. The
+    /// opponent's elixir is the estimate `pov` could have made, not the
+    /// true value.
+    pub fn export_observation_grid(&self, pov: PlayerId) -> Vec<f32> {
+        let width = self.arena.width as usize;
+        let height = self.arena.height as usize;
+        let plane = width * height;
+        let mut grid = vec![0.0; OBS_CHANNELS * plane];
+        let index = |channel: usize, x: u32, y: u32| channel * plane + y as usize * width + x as usize;
+
+        for entity in self.entities.values().filter(|e| e.is_alive()) {
+            let ally = entity.owner.is_ally_of(pov);
+            let hp = (entity.hp / entity.max_hp).clamp(0.0, 1.0);
+            let position = self.arena.pov_position(pov, &entity.position);
+            match &entity.kind {
+                EntityKind::Troop(_) | EntityKind::Building(_) => {
+                    let (x, y) = self.arena.world_to_tile(&position);
+                    grid[index(if ally { OBS_ALLY_HP } else { OBS_ENEMY_HP }, x, y)] += hp;
+                    if entity.transport() == Transport::Air {
+                        grid[index(if ally { OBS_ALLY_AIR } else { OBS_ENEMY_AIR }, x, y)] += hp;
+                    }
+                }
+                EntityKind::Tower(_) => {
+                    let channel = if ally { OBS_ALLY_TOWERS } else { OBS_ENEMY_TOWERS };
+                    let CollisionShape::Rectangle { half_width, half_height } = entity.collision_shape() else {
+                        continue;
+                    };
+                    for y in 0..self.arena.height {
+                        for x in 0..self.arena.width {
+                            let center = self.arena.tile_to_world(x, y);
+                            if (center.x - position.x).abs() <= half_width && (center.y - position.y).abs() <= half_height {
+                                grid[index(channel, x, y)] = hp;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let elixir = |player: PlayerId, estimated: bool| {
+            self.players.get(&player).map_or(0.0, |p| {
+                let elixir = if estimated { p.estimated_elixir } else { p.elixir };
+                elixir / p.max_elixir
+            })
+        };
+        let ally_elixir = elixir(pov, false);
+        let enemy_elixir = elixir(pov.opponent(), true);
+        grid[OBS_ALLY_ELIXIR * plane..(OBS_ALLY_ELIXIR + 1) * plane].fill(ally_elixir);
+        grid[OBS_ENEMY_ELIXIR * plane..(OBS_ENEMY_ELIXIR + 1) * plane].fill(enemy_elixir);
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use shared::Position;
+
+    #[test]
+    fn test_observation_grid_planes_follow_the_pov() {
+        let mut state = GameState::new(3);
+        state.set_deploy_zones(false);
+        let play = |player, card_name: &str, x, y| Action::PlayCard {
+            player,
+            card_name: card_name.to_string(),
+            level: 11,
+            position: Position::new(x, y),
+        };
+        crate::step(
+            &mut state,
+            &[play(PlayerId::Player1, "Knight", 10.5, 4.5), play(PlayerId::Player2, "Baby Dragon", 22.5, 12.5)],
+        )
+        .unwrap();
+
+        let grid = state.export_observation_grid(PlayerId::Player1);
+        assert_eq!(grid.len(), OBS_SHAPE.iter().product::<usize>());
+        let [_, height, width] = OBS_SHAPE;
+        let at = |grid: &[f32], channel: usize, x: usize, y: usize| grid[(channel * height + y) * width + x];
+
+        assert_eq!(at(&grid, OBS_ALLY_HP, 10, 4), 1.0);
+        assert_eq!(at(&grid, OBS_ALLY_AIR, 10, 4), 0.0);
+        assert_eq!(at(&grid, OBS_ENEMY_HP, 22, 12), 1.0);
+        assert_eq!(at(&grid, OBS_ENEMY_AIR, 22, 12), 1.0);
+        // A King covers 4x4 tiles, a Princess 3x3 (tile centers inside its
+        // footprint), on both sides
+        let covered = |channel: usize| (0..height * width).filter(|i| grid[channel * height * width + i] > 0.0).count();
+        assert_eq!(covered(OBS_ALLY_TOWERS), covered(OBS_ENEMY_TOWERS));
+        assert!(covered(OBS_ALLY_TOWERS) >= 16 + 2 * 9);
+        let elixir = state.players[&PlayerId::Player1].elixir / state.players[&PlayerId::Player1].max_elixir;
+        assert_eq!(at(&grid, OBS_ALLY_ELIXIR, 0, 0), elixir);
+        assert_eq!(at(&grid, OBS_ALLY_ELIXIR, width - 1, height - 1), elixir);
+
+        // Player2 sees the same board point-reflected, with the sides swapped
+        let mirrored = state.export_observation_grid(PlayerId::Player2);
+        assert_eq!(at(&mirrored, OBS_ENEMY_HP, width - 1 - 10, height - 1 - 4), 1.0);
+        assert_eq!(at(&mirrored, OBS_ALLY_AIR, width - 1 - 22, height - 1 - 12), 1.0);
+        for (channel, swapped) in [(OBS_ALLY_TOWERS, OBS_ENEMY_TOWERS), (OBS_ENEMY_TOWERS, OBS_ALLY_TOWERS)] {
+            for y in 0..height {
+                for x in 0..width {
+                    assert_eq!(at(&grid, channel, x, y), at(&mirrored, swapped, width - 1 - x, height - 1 - y));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Spawn-position adjustment: moves a new body off anything it would land
+//! inside, so the collision system never starts from an overlap it can't
+//! resolve (a troop dropped into a tower, a building on a building).
+//!
+//! Every spawn path goes through [`GameState::free_spawn_position`]: card
+//! deploys, landing travelling troops, spawner waves and scripted spawns,
+//! and death spawns once they exist. The search is a fixed spiral, so the
+//! same spawn into the same board always lands in the same spot.
+
+use crate::entities::{Entity, Transport};
+use crate::fp;
+use crate::state::GameState;
+use shared::{PlayerId, Position};
+
+/// Distance (tiles) between the spiral's rings.
+pub const SPAWN_SEARCH_STEP: f32 = 0.25;
+
+/// Rings searched before giving up and spawning at the requested spot (4
+/// tiles out).
+pub const SPAWN_SEARCH_RINGS: u32 = 16;
+
+/// Candidate points per ring, per ring index (ring `k` has `8 * k`), which
+/// keeps neighbouring candidates under a step apart.
+const POINTS_PER_RING: u32 = 8;
+
+impl GameState {
+    /// Where `entity` (not yet added) should spawn: its own position if no
+    /// body is in the way (callers have already checked the spot itself),
+    /// otherwise the nearest free point on a spiral
+    /// around it.
+    ///
+    /// Rings are searched outward [`SPAWN_SEARCH_STEP`] apart, each
+    /// counter-clockwise from the owner's forward direction (so both sides
+    /// resolve mirrored spawns the same way). A point is free when the body
+    /// fits inside the arena, a ground body stands on a walkable tile, and
+    /// it overlaps nothing on its layer that couldn't push it away: a troop
+    /// avoids every troop, building and tower it would collide with, a
+    /// building only other buildings and towers (troops under it get
+    /// shoved aside by the collision system). If no ring has room, the
+    /// requested position is kept.
+    pub fn free_spawn_position(&self, entity: &Entity) -> Position {
+        let origin = entity.position;
+        if !self.overlaps_a_body(entity, &origin) {
+            return origin;
+        }
+
+        let forward = match entity.owner {
+            PlayerId::Player1 | PlayerId::Player3 => 0.0,
+            PlayerId::Player2 | PlayerId::Player4 => std::f32::consts::PI,
+        };
+        for ring in 1..=SPAWN_SEARCH_RINGS {
+            let distance = ring as f32 * SPAWN_SEARCH_STEP;
+            let points = ring * POINTS_PER_RING;
+            for i in 0..points {
+                let angle = forward + i as f32 * std::f32::consts::TAU / points as f32;
+                let candidate = Position::new(
+                    origin.x + distance * fp::cos(angle),
+                    origin.y + distance * fp::sin(angle),
+                );
+                if self.spawn_point_is_free(entity, &candidate) {
+                    return candidate;
+                }
+            }
+        }
+        origin
+    }
+
+    /// Whether `entity` could be moved to `position` (see
+    /// [`GameState::free_spawn_position`]).
+    fn spawn_point_is_free(&self, entity: &Entity, position: &Position) -> bool {
+        let radius = entity.radius();
+        let width = self.arena.width as f32 * self.arena.tile_size;
+        let height = self.arena.height as f32 * self.arena.tile_size;
+        if position.x < radius || position.y < radius || position.x > width - radius || position.y > height - radius {
+            return false;
+        }
+        if entity.transport() == Transport::Ground && !entity.jumps_river() {
+            let (x, y) = self.arena.world_to_tile(position);
+            if !self.arena.get_tile(x, y).is_some_and(|tile| tile.is_walkable()) {
+                return false;
+            }
+        }
+        !self.overlaps_a_body(entity, position)
+    }
+
+    /// Whether `entity` at `position` would overlap a body it mustn't spawn
+    /// inside (see [`GameState::free_spawn_position`]).
+    fn overlaps_a_body(&self, entity: &Entity, position: &Position) -> bool {
+        let radius = entity.radius();
+        let transport = entity.transport();
+        self.entities.values().any(|other| {
+            other.is_alive()
+                && other.radius() > 0.0
+                && (other.can_move() || other.is_building())
+                && other.transport() == transport
+                && (entity.can_move() || other.is_building())
+                && position.distance_to(&other.position) < radius + other.radius()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::EntityKind;
+    use crate::state::{GameState, TowerType};
+    use shared::{PlayerId, Position};
+
+    fn bodies(state: &GameState) -> Vec<(Position, f32, bool)> {
+        state
+            .entities
+            .values()
+            .filter(|e| matches!(e.kind, EntityKind::Troop(_) | EntityKind::Building(_) | EntityKind::Tower(_)))
+            .map(|e| (e.position, e.radius(), e.can_move()))
+            .collect()
+    }
+
+    #[test]
+    fn test_spawns_never_start_inside_another_body() {
+        let mut state = GameState::new(12);
+        let knight = state.get_card_by_name("Knight").unwrap().clone();
+        let mut cannon = knight.clone();
+        cannon.name = "Cannon".to_string();
+        cannon.type_name = "building".to_string();
+        let princess = state.arena.tower_position(PlayerId::Player1, TowerType::LeftPrincess);
+
+        // Two Knights on one spot, one dropped straight onto a tower, and a
+        // Cannon on top of another Cannon
+        let spot = Position::new(8.5, 9.0);
+        for position in [spot, spot, princess] {
+            knight.spawn(&mut state, PlayerId::Player1, position, 11).unwrap();
+        }
+        let pad = Position::new(10.0, 13.0);
+        cannon.spawn(&mut state, PlayerId::Player1, pad, 11).unwrap();
+        cannon.spawn(&mut state, PlayerId::Player1, pad, 11).unwrap();
+
+        let bodies = bodies(&state);
+        for (i, a) in bodies.iter().enumerate() {
+            for b in &bodies[i + 1..] {
+                assert!(a.0.distance_to(&b.0) >= a.1 + b.1 - 1e-4, "{:?} overlaps {:?}", a, b);
+            }
+        }
+        // The first Knight kept its spot; the second took the nearest free one
+        assert!(bodies.iter().any(|b| b.0 == spot));
+        assert!(bodies.iter().any(|b| b.2 && b.0 != spot && b.0.distance_to(&spot) <= 0.8 + super::SPAWN_SEARCH_STEP));
+
+        // The same spawns on a fresh board land in exactly the same places
+        let mut again = GameState::new(12);
+        for position in [spot, spot, princess] {
+            knight.spawn(&mut again, PlayerId::Player1, position, 11).unwrap();
+        }
+        cannon.spawn(&mut again, PlayerId::Player1, pad, 11).unwrap();
+        cannon.spawn(&mut again, PlayerId::Player1, pad, 11).unwrap();
+        assert_eq!(again.state_hash(), state.state_hash());
+    }
+}
@@ -0,0 +1,234 @@
+//! Wall-clock pacing for live playback (visualizers, human-vs-bot play).
+//!
+//! A [`RealtimeRunner`] says when the next engine tick is due, at 60 Hz
+//! times a speed multiplier, and runs the ticks that are. Deadlines are
+//! measured from a fixed anchor rather than by sleeping a tick length at a
+//! time, so oversleeping or a slow tick never adds up to drift: the late
+//! ticks run on the next call. If the simulation falls so far behind that
+//! catching up would stall the frontend, the backlog is dropped instead
+//! (the match slows down rather than fast-forwarding).
+//!
+//! Pacing only decides *when* ticks run; every tick is still
+//! [`crate::DELTA_TIME`] of game time, so a match plays out the same at any
+//! speed.
+
+use crate::action::Action;
+use crate::state::GameState;
+use shared::Result;
+use std::time::{Duration, Instant};
+
+/// Most overdue ticks run in one go before the backlog is dropped (a
+/// tenth of a second at normal speed).
+pub const DEFAULT_MAX_CATCH_UP: u64 = 6;
+
+/// Slowest speed multiplier a runner accepts; slower ones (and NaN) are
+/// raised to it so tick deadlines stay representable.
+pub const MIN_SPEED: f32 = 0.01;
+
+/// Paces engine ticks against the wall clock (see the module docs).
+#[derive(Debug, Clone)]
+pub struct RealtimeRunner {
+    speed: f32,
+    max_catch_up: u64,
+    paused: bool,
+    /// When the tick count below started counting.
+    anchor: Instant,
+    /// Ticks run since `anchor`.
+    ticks: u64,
+}
+
+impl Default for RealtimeRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RealtimeRunner {
+    /// A runner at normal speed (60 ticks a second), with its first tick
+    /// due now.
+    pub fn new() -> Self {
+        Self::with_speed(1.0)
+    }
+
+    /// A runner at `speed` times normal (2.0 plays twice as fast).
+    /// Speeds below [`MIN_SPEED`] are raised to it.
+    pub fn with_speed(speed: f32) -> Self {
+        Self {
+            speed: speed.max(MIN_SPEED),
+            max_catch_up: DEFAULT_MAX_CATCH_UP,
+            paused: false,
+            anchor: Instant::now(),
+            ticks: 0,
+        }
+    }
+
+    /// The speed multiplier.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Changes the speed from now on; ticks already run keep their timing.
+    /// Speeds below [`MIN_SPEED`] are raised to it.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(MIN_SPEED);
+        self.restart(Instant::now());
+    }
+
+    /// Sets how many overdue ticks may run in one go (at least one).
+    pub fn set_max_catch_up(&mut self, ticks: u64) {
+        self.max_catch_up = ticks.max(1);
+    }
+
+    /// Whether the clock is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or resumes the clock. Resuming makes the next tick due
+    /// straight away rather than owing the time spent paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused && !paused {
+            self.restart(Instant::now());
+        }
+        self.paused = paused;
+    }
+
+    /// When the next tick is due (possibly already past).
+    pub fn next_tick_at(&self) -> Instant {
+        self.anchor + self.tick_offset(self.ticks)
+    }
+
+    /// Ticks due by now and not run yet (0 while paused).
+    pub fn ticks_due(&self) -> u64 {
+        self.ticks_due_at(Instant::now())
+    }
+
+    /// Runs `tick` once for every tick due by now, without waiting, and
+    /// returns how many ran. Stops at the first error.
+    pub fn advance(&mut self, tick: impl FnMut() -> Result<()>) -> Result<u64> {
+        self.advance_at(Instant::now(), tick)
+    }
+
+    /// Waits until the next tick is due, then steps `state` once with
+    /// `actions`: the blocking loop for frontends with nothing else to do
+    /// between ticks. While paused this only waits (one tick length) and
+    /// returns false.
+    pub fn step(&mut self, state: &mut GameState, actions: &[Action]) -> Result<bool> {
+        if self.paused {
+            std::thread::sleep(self.tick_offset(1));
+            return Ok(false);
+        }
+        let now = Instant::now();
+        let due = self.next_tick_at();
+        if due > now {
+            std::thread::sleep(due - now);
+        }
+        crate::step(state, actions)?;
+        self.ticks += 1;
+        self.drop_backlog(Instant::now());
+        Ok(true)
+    }
+
+    /// Wall time from the anchor to the start of tick `ticks`.
+    fn tick_offset(&self, ticks: u64) -> Duration {
+        Duration::from_secs_f64(ticks as f64 * f64::from(crate::DELTA_TIME) / f64::from(self.speed))
+    }
+
+    fn ticks_due_at(&self, now: Instant) -> u64 {
+        if self.paused || now < self.anchor {
+            return 0;
+        }
+        let elapsed = (now - self.anchor).as_secs_f64() * f64::from(self.speed);
+        // Tick n is due once n tick lengths have passed, so the first is
+        // due at the anchor itself
+        let owed = (elapsed / f64::from(crate::DELTA_TIME)) as u64 + 1;
+        owed.saturating_sub(self.ticks)
+    }
+
+    fn advance_at(&mut self, now: Instant, mut tick: impl FnMut() -> Result<()>) -> Result<u64> {
+        let due = self.ticks_due_at(now).min(self.max_catch_up);
+        for _ in 0..due {
+            tick()?;
+            self.ticks += 1;
+        }
+        self.drop_backlog(now);
+        Ok(due)
+    }
+
+    /// Forgets ticks still overdue after a catch-up, so the next one is due
+    /// a tick length from `now`.
+    fn drop_backlog(&mut self, now: Instant) {
+        if self.ticks_due_at(now) > self.max_catch_up {
+            self.restart(now + self.tick_offset(1));
+        }
+    }
+
+    /// Counts ticks afresh from `anchor`, the next one due then.
+    fn restart(&mut self, anchor: Instant) {
+        self.anchor = anchor;
+        self.ticks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticks_follow_the_wall_clock_without_drift() {
+        let mut runner = RealtimeRunner::with_speed(2.0);
+        let start = runner.anchor;
+        let tick = Duration::from_secs_f64(f64::from(crate::DELTA_TIME) / 2.0);
+        let ran = std::cell::Cell::new(0);
+        let mut count = || {
+            ran.set(ran.get() + 1);
+            Ok(())
+        };
+
+        // Called a little late every time, the total still matches the clock
+        let mut late_calls = 0;
+        for n in 0..100u32 {
+            late_calls += runner.advance_at(start + tick * n + tick / 3, &mut count).unwrap();
+        }
+        assert_eq!(late_calls, 100);
+        assert_eq!(runner.advance_at(start + tick * 99 + tick / 2, &mut count).unwrap(), 0);
+
+        // A long stall runs a capped catch-up and drops the rest
+        let stalled = start + tick * 1000;
+        assert_eq!(runner.advance_at(stalled, &mut count).unwrap(), DEFAULT_MAX_CATCH_UP);
+        assert_eq!(runner.ticks_due_at(stalled), 0);
+        assert_eq!(runner.ticks_due_at(stalled + tick), 1);
+        assert_eq!(ran.get(), 100 + DEFAULT_MAX_CATCH_UP);
+
+        runner.set_paused(true);
+        assert_eq!(runner.advance_at(stalled + tick * 50, &mut count).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_step_paces_real_ticks() {
+        let mut state = GameState::new(1);
+        let mut runner = RealtimeRunner::with_speed(20.0);
+        let started = Instant::now();
+        for _ in 0..30 {
+            assert!(runner.step(&mut state, &[]).unwrap());
+        }
+        // The 30th tick starts 29 tick lengths in: 29/60 s at 20x, about 24ms
+        assert!(started.elapsed() >= Duration::from_millis(24));
+        assert_eq!(state.tick, 30);
+    }
+
+    #[test]
+    fn test_tiny_and_negative_speeds_are_raised_to_the_minimum() {
+        for speed in [0.0, -1.0, f32::MIN_POSITIVE, 1e-20, f32::NAN] {
+            let mut runner = RealtimeRunner::with_speed(speed);
+            assert_eq!(runner.speed(), MIN_SPEED);
+            runner.set_speed(speed);
+            assert_eq!(runner.speed(), MIN_SPEED);
+            // The second tick is due 1/60 s / 0.01 after the first
+            let anchor = runner.anchor;
+            assert_eq!(runner.advance_at(anchor, || Ok(())).unwrap(), 1);
+            let wait = runner.next_tick_at() - anchor;
+            assert!((wait.as_secs_f64() - 1.0 / 0.6).abs() < 1e-3);
+        }
+    }
+}
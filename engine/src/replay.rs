@@ -0,0 +1,306 @@
+//! Replay files: recorded actions plus analyst annotations.
+//!
+//! A replay stores the seed, patch, game mode, starting decks, and every
+//! action with the tick it was applied on, which is enough to re-simulate
+//! the match exactly.
+//! Annotations ("misplay at 1:32") can be attached to ticks and queried so
+//! tools can jump straight to bookmarked moments.
+
+use crate::action::Action;
+use crate::state::{deck_hash, CardPlay, DeckCard, GameMode, GameState, DEFAULT_GAME_MODE, DEFAULT_PATCH_ID};
+use serde::{Deserialize, Serialize};
+use shared::{Error, PlayerId, Result};
+
+/// An action applied on a specific tick.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayAction {
+    pub tick: u64,
+    pub action: Action,
+}
+
+/// A note attached to a tick of a replay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub tick: u64,
+    pub author: String,
+    pub text: String,
+}
+
+/// A recorded match that can be re-simulated and annotated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+
+    /// Card set the match was played with.
+    #[serde(default = "default_patch_id")]
+    pub patch_id: String,
+
+    #[serde(default = "default_game_mode")]
+    pub game_mode: String,
+
+    /// Starting decks, applied in order before the first tick.
+    pub decks: Vec<(PlayerId, Vec<DeckCard>)>,
+
+    /// Hash of each recorded deck (see [`deck_hash`]), for matching an
+    /// exported episode to its replay without comparing card lists.
+    #[serde(default)]
+    pub deck_hashes: Vec<(PlayerId, u64)>,
+
+    /// Actions in the order they were applied.
+    pub actions: Vec<ReplayAction>,
+
+    /// Annotations, kept sorted by tick.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+impl Replay {
+    /// Creates an empty replay for a match with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            patch_id: DEFAULT_PATCH_ID.to_string(),
+            game_mode: DEFAULT_GAME_MODE.to_string(),
+            decks: Vec::new(),
+            deck_hashes: Vec::new(),
+            actions: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Copies the patch id and game mode from the match being recorded.
+    pub fn record_metadata(&mut self, state: &GameState) {
+        self.patch_id = state.patch_id.clone();
+        self.game_mode = state.game_mode.clone();
+    }
+
+    /// Records a player's starting deck (names or [`DeckCard`]s, as for
+    /// [`GameState::set_player_deck`]).
+    pub fn record_deck<C: Into<DeckCard>>(&mut self, player: PlayerId, deck: Vec<C>) {
+        let deck: Vec<DeckCard> = deck.into_iter().map(Into::into).collect();
+        let names: Vec<String> = deck.iter().map(|c| c.name.clone()).collect();
+        self.deck_hashes.push((player, deck_hash(&names)));
+        self.decks.push((player, deck));
+    }
+
+    /// Records an action applied on `tick`.
+    pub fn record_action(&mut self, tick: u64, action: Action) {
+        self.actions.push(ReplayAction { tick, action });
+    }
+
+    /// Attaches an annotation to `tick`.
+    pub fn annotate(&mut self, tick: u64, author: impl Into<String>, text: impl Into<String>) {
+        let annotation = Annotation {
+            tick,
+            author: author.into(),
+            text: text.into(),
+        };
+        // Insert after any existing annotations on the same tick
+        let index = self.annotations.partition_point(|a| a.tick <= tick);
+        self.annotations.insert(index, annotation);
+    }
+
+    /// Returns all annotations, sorted by tick.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Returns annotations with `start <= tick < end`.
+    pub fn annotations_between(&self, start: u64, end: u64) -> &[Annotation] {
+        let from = self.annotations.partition_point(|a| a.tick < start);
+        let to = self.annotations.partition_point(|a| a.tick < end);
+        &self.annotations[from..to.max(from)]
+    }
+
+    /// Returns the first annotation strictly after `tick` (for "next bookmark").
+    pub fn next_annotation(&self, tick: u64) -> Option<&Annotation> {
+        let index = self.annotations.partition_point(|a| a.tick <= tick);
+        self.annotations.get(index)
+    }
+
+    /// Returns annotations whose text contains `query` (case-insensitive).
+    pub fn search_annotations(&self, query: &str) -> Vec<&Annotation> {
+        let query = query.to_lowercase();
+        self.annotations
+            .iter()
+            .filter(|a| a.text.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// Removes all annotations on `tick`. Returns how many were removed.
+    pub fn remove_annotations_at(&mut self, tick: u64) -> usize {
+        let before = self.annotations.len();
+        self.annotations.retain(|a| a.tick != tick);
+        before - self.annotations.len()
+    }
+
+    /// Re-simulates the match up to (and including) `tick`, applying each
+    /// tick's recorded actions together as a step would. A rejected action
+    /// fails the whole replay, naming the tick it was recorded on.
+    ///
+    /// Use this to jump straight to an annotated moment during playback.
+    pub fn state_at(&self, tick: u64) -> Result<GameState> {
+        let mut state = GameState::new(self.seed);
+        state.set_patch_id(self.patch_id.clone());
+        // Modes the engine knows change the rules; others are just a name
+        if let Some(mode) = GameMode::from_name(&self.game_mode) {
+            state.apply_game_mode(mode);
+        }
+        state.set_game_mode(self.game_mode.clone());
+        for (player, deck) in &self.decks {
+            state.set_player_deck(*player, deck.clone())?;
+        }
+
+        let mut next = 0;
+        while state.tick < tick {
+            let start = next;
+            while next < self.actions.len() && self.actions[next].tick <= state.tick {
+                next += 1;
+            }
+            let actions: Vec<Action> = self.actions[start..next]
+                .iter()
+                .map(|a| a.action.clone())
+                .collect();
+            crate::step(&mut state, &actions).map_err(|e| match e {
+                Error::InvalidAction(message) => Error::InvalidAction(format!("tick {}: {}", state.tick, message)),
+                other => other,
+            })?;
+        }
+        Ok(state)
+    }
+
+    /// Re-simulates the whole match and returns every card play in order.
+    pub fn card_plays(&self) -> Result<Vec<CardPlay>> {
+        let end = self.actions.last().map_or(0, |a| a.tick + 1);
+        Ok(self.state_at(end)?.card_plays)
+    }
+
+    /// Loads a replay from a JSON file.
+    pub fn load(path: &str) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| shared::Error::InvalidAction(format!("Failed to read replay file: {}", e)))?;
+        let mut replay: Replay = serde_json::from_str(&data)?;
+        // Hand-edited files may list annotations out of order
+        replay.annotations.sort_by_key(|a| a.tick);
+        Ok(replay)
+    }
+
+    /// Saves the replay as JSON.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, data)
+            .map_err(|e| shared::Error::InvalidAction(format!("Failed to write replay file: {}", e)))
+    }
+}
+
+fn default_patch_id() -> String {
+    DEFAULT_PATCH_ID.to_string()
+}
+
+fn default_game_mode() -> String {
+    DEFAULT_GAME_MODE.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::Position;
+
+    #[test]
+    fn test_annotation_queries() {
+        let mut replay = Replay::new(1);
+        replay.annotate(5700, "coach", "Misplay at 1:32");
+        replay.annotate(600, "coach", "Good opening");
+        replay.annotate(600, "analyst", "Elixir lead");
+
+        let ticks: Vec<u64> = replay.annotations().iter().map(|a| a.tick).collect();
+        assert_eq!(ticks, vec![600, 600, 5700]);
+        assert_eq!(replay.annotations()[1].author, "analyst");
+
+        assert_eq!(replay.annotations_between(0, 601).len(), 2);
+        assert_eq!(replay.next_annotation(600).unwrap().tick, 5700);
+        assert!(replay.next_annotation(5700).is_none());
+        assert_eq!(replay.search_annotations("misplay")[0].tick, 5700);
+
+        assert_eq!(replay.remove_annotations_at(600), 2);
+        assert_eq!(replay.annotations().len(), 1);
+    }
+
+    #[test]
+    fn test_first_frame_carries_reconstruction_metadata() {
+        let deck: Vec<String> = crate::card::get_test_cards()
+            .iter()
+            .take(4)
+            .cycle()
+            .take(8)
+            .map(|c| c.name.clone())
+            .collect();
+        let mut replay = Replay::new(23);
+        replay.patch_id = "v2025_current".to_string();
+        replay.game_mode = "double_elixir".to_string();
+        replay.record_deck(PlayerId::Player2, deck.clone());
+
+        let state = replay.state_at(0).unwrap();
+        let metadata = state.export_cr_state(PlayerId::Player1).metadata.unwrap();
+        assert_eq!(metadata.seed, 23);
+        assert_eq!(metadata.patch_id, "v2025_current");
+        assert_eq!(metadata.game_mode, "double_elixir");
+        assert_eq!(metadata.ally_deck_hash, 0);
+        assert_eq!(metadata.enemy_deck_hash, replay.deck_hashes[0].1);
+        // Shuffling into the starting cycle doesn't change the hash
+        let mut reversed = deck;
+        reversed.reverse();
+        assert_eq!(deck_hash(&reversed), metadata.enemy_deck_hash);
+
+        assert!(replay.state_at(1).unwrap().export_cr_state(PlayerId::Player1).metadata.is_none());
+    }
+
+    #[test]
+    fn test_jump_to_annotated_tick() {
+        let mut replay = Replay::new(21);
+        replay.record_action(
+            10,
+            Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Knight".to_string(),
+                level: 11,
+                position: Position::new(10.0, 9.0),
+            },
+        );
+        replay.annotate(30, "coach", "Knight deployed");
+
+        let tick = replay.annotations()[0].tick;
+        let state = replay.state_at(tick).unwrap();
+        assert_eq!(state.tick, 30);
+        assert!(state.players[&PlayerId::Player1].elixir < 5.0);
+    }
+
+    #[test]
+    fn test_card_play_history() {
+        let mut replay = Replay::new(22);
+        for (tick, player, card, x) in [
+            (0, PlayerId::Player1, "Knight", 10.0),
+            (5, PlayerId::Player2, "Archers", 22.0),
+            (200, PlayerId::Player1, "Archers", 8.0),
+        ] {
+            replay.record_action(
+                tick,
+                Action::PlayCard {
+                    player,
+                    card_name: card.to_string(),
+                    level: 11,
+                    position: Position::new(x, 9.0),
+                },
+            );
+        }
+
+        let plays = replay.card_plays().unwrap();
+        assert_eq!(plays.len(), 3);
+        assert_eq!((plays[1].tick, plays[1].cost), (5, 3.0));
+        assert_eq!(plays[2].position, Position::new(8.0, 9.0));
+
+        let state = replay.state_at(201).unwrap();
+        assert_eq!(state.last_played(PlayerId::Player1, 5), vec!["Archers", "Knight"]);
+        assert_eq!(state.plays_by(PlayerId::Player2).count(), 1);
+    }
+}
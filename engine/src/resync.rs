@@ -0,0 +1,245 @@
+//! Best-effort reconstruction of a `GameState` from an observed `CRState`.
+//!
+//! A snapshot carries tower HP fractions, elixir, and each unit's position,
+//! card, level and HP fraction, but not the full entity state behind them
+//! (attack timers, paths, hidden status durations). Units whose card isn't
+//! loaded fall back to [`ResyncAssumptions`], so the rebuilt state is an
+//! approximation meant for short-horizon planning with the engine as a
+//! forward model, not an exact copy.
+
+use crate::card::Card;
+use crate::entities::StatusKind;
+use crate::state::{GameState, TowerType, TOWER_LEVEL};
+use shared::{CRState, Error, PlayerId, Position, Result, Unit, Velocity};
+
+/// How long an observed stun or rage is assumed to last (seconds).
+pub const ASSUMED_STATUS_DURATION: f32 = 0.5;
+
+/// What to assume for everything a snapshot doesn't say.
+#[derive(Debug, Clone)]
+pub struct ResyncAssumptions {
+    /// The player the snapshot was exported for ("ALLY").
+    pub pov: PlayerId,
+    /// Card used for an observed unit whose own card isn't loaded (or
+    /// can't be deployed at its level).
+    pub unit_card: String,
+    /// Level of the assumed units.
+    pub level: u32,
+    pub seed: u64,
+}
+
+impl Default for ResyncAssumptions {
+    fn default() -> Self {
+        Self {
+            pov: PlayerId::Player1,
+            unit_card: "Knight".to_string(),
+            level: TOWER_LEVEL,
+            seed: 0,
+        }
+    }
+}
+
+impl GameState {
+    /// Builds a state matching `snapshot` as closely as the snapshot allows:
+    /// match time, tower HP, elixir (the opponent's from the exported
+    /// estimate) and one entity per observed unit, of its own card and
+    /// level when `card_db` has them and `assumptions.unit_card` otherwise.
+    /// Spells in flight are not reconstructed.
+    pub fn from_cr_state(snapshot: &CRState, card_db: &[Card], assumptions: &ResyncAssumptions) -> Result<Self> {
+        let mut state = GameState::new(assumptions.seed);
+        state.load_cards(card_db.to_vec())?;
+
+        let unit_card = state
+            .get_card_by_name(&assumptions.unit_card)
+            .map(single_unit)
+            .ok_or_else(|| Error::Configuration(format!("Assumed unit card '{}' not found", assumptions.unit_card)))?;
+        unit_card.get_level_stats(assumptions.level)?;
+
+        state.match_time = snapshot.t_ms as f32 / 1000.0;
+        state.tick = (state.match_time / crate::DELTA_TIME).round() as u64;
+
+        let (ally, enemy) = (assumptions.pov, assumptions.pov.opponent());
+        for (player, towers) in [(ally, &snapshot.ally_towers), (enemy, &snapshot.enemy_towers)] {
+            // Exported in King, LeftPrincess, RightPrincess order
+            for (tower_type, tower) in [TowerType::King, TowerType::LeftPrincess, TowerType::RightPrincess]
+                .into_iter()
+                .zip(towers.iter())
+            {
+                let id = state
+                    .entities
+                    .iter()
+                    .find(|(_, e)| e.owner == player && e.tower_type() == Some(tower_type))
+                    .map(|(id, _)| *id);
+                if let Some(id) = id {
+                    let entity = state.entities.get_mut(&id).expect("tower id came from the map");
+                    entity.hp = entity.max_hp * tower.hp_frac.clamp(0.0, 1.0);
+                    if !entity.is_alive() {
+                        state.remove_entity(id);
+                    }
+                }
+            }
+        }
+        state.sync_tower_hp();
+
+        for (player, elixir) in [(ally, snapshot.ally_elixir), (enemy, snapshot.estimated_enemy_elixir)] {
+            if let Some(player_state) = state.players.get_mut(&player) {
+                player_state.elixir = elixir.clamp(0.0, player_state.max_elixir);
+                player_state.estimated_elixir = player_state.elixir;
+            }
+        }
+
+        for (player, units) in [(ally, &snapshot.ally_units), (enemy, &snapshot.enemy_units)] {
+            for unit in units {
+                let observed = state
+                    .get_card_by_name(&unit.card)
+                    .filter(|card| matches!(card.type_name.as_str(), "troop" | "tower troop" | "building"))
+                    .filter(|card| card.check_spawn(&state, unit.level).is_ok())
+                    .map(single_unit);
+                match observed {
+                    Some(card) => add_unit(&mut state, &card, player, unit, unit.level)?,
+                    None => add_unit(&mut state, &unit_card, player, unit, assumptions.level)?,
+                }
+            }
+        }
+
+        Ok(state)
+    }
+}
+
+/// `card` deploying a single entity, placed where it was seen: one unit of
+/// a multi-unit card, with no travel time or landing damage.
+fn single_unit(card: &Card) -> Card {
+    let mut card = card.clone();
+    card.count = Some(1);
+    card.travel_time = None;
+    for level in &mut card.levels {
+        level.spawn_damage = None;
+    }
+    card
+}
+
+/// Spawns one troop or building with the observed unit's kinematics, HP and
+/// status.
+fn add_unit(state: &mut GameState, card: &Card, owner: PlayerId, unit: &Unit, level: u32) -> Result<()> {
+    card.spawn(state, owner, Position::new(unit.x, unit.y), level)?;
+    let id = *state
+        .entities
+        .keys()
+        .max_by_key(|id| id.as_u32())
+        .expect("spawn adds an entity");
+    let entity = state.entities.get_mut(&id).expect("id came from the map");
+
+    entity.prev_position = Position::new(unit.prev_x, unit.prev_y);
+    entity.velocity = Velocity::new(unit.vx, unit.vy);
+    entity.facing = unit.facing;
+    // A unit in the snapshot is alive; no fraction at all means an older
+    // export that didn't carry it
+    if unit.hp_frac > 0.0 {
+        entity.hp = entity.max_hp * unit.hp_frac.min(1.0);
+    }
+    entity.shield = unit.shield.max(0.0);
+    entity.max_shield = entity.max_shield.max(entity.shield);
+    if unit.is_stunned {
+        entity.apply_status(StatusKind::Stun, ASSUMED_STATUS_DURATION);
+    }
+    if unit.is_raged {
+        entity.apply_status(StatusKind::Rage, ASSUMED_STATUS_DURATION);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Action;
+
+    fn observed_state() -> GameState {
+        let mut state = GameState::new(33);
+        for (player, x) in [(PlayerId::Player1, 12.0), (PlayerId::Player2, 22.0)] {
+            state.players.get_mut(&player).unwrap().elixir = 10.0;
+            crate::step(
+                &mut state,
+                &[Action::PlayCard {
+                    player,
+                    card_name: "Knight".to_string(),
+                    level: 11,
+                    position: Position::new(x, 4.0),
+                }],
+            )
+            .unwrap();
+        }
+        for _ in 0..30 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let tower = state
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player2 && e.tower_type() == Some(TowerType::RightPrincess))
+            .unwrap();
+        tower.hp = tower.max_hp * 0.25;
+        state.sync_tower_hp();
+        let knight = state
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player1 && e.tower_type().is_none())
+            .unwrap();
+        knight.hp = knight.max_hp * 0.4;
+        state
+    }
+
+    #[test]
+    fn test_resync_matches_snapshot() {
+        let original = observed_state();
+        for pov in [PlayerId::Player1, PlayerId::Player2] {
+            let snapshot = original.export_cr_state(pov);
+            // The units' own card and level win over the assumed ones
+            let assumptions = ResyncAssumptions {
+                pov,
+                unit_card: "Archers".to_string(),
+                ..Default::default()
+            };
+            let rebuilt = GameState::from_cr_state(&snapshot, &crate::card::get_test_cards(), &assumptions).unwrap();
+            let again = rebuilt.export_cr_state(pov);
+
+            assert_eq!(rebuilt.tick, original.tick);
+            assert_eq!(again.ally_elixir, snapshot.ally_elixir);
+            assert_eq!(again.estimated_enemy_elixir, snapshot.estimated_enemy_elixir);
+            for (a, b) in again.enemy_towers.iter().chain(&again.ally_towers).zip(
+                snapshot.enemy_towers.iter().chain(&snapshot.ally_towers),
+            ) {
+                assert!((a.hp_frac - b.hp_frac).abs() < 1e-6);
+            }
+            for (a, b) in [(&again.ally_units, &snapshot.ally_units), (&again.enemy_units, &snapshot.enemy_units)] {
+                assert_eq!(a.len(), 1);
+                assert_eq!((a[0].x, a[0].y, a[0].vx, a[0].vy), (b[0].x, b[0].y, b[0].vx, b[0].vy));
+                assert_eq!((&a[0].card, a[0].level), (&b[0].card, b[0].level));
+                assert!((a[0].hp_frac - b[0].hp_frac).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_resync_drops_destroyed_towers_and_checks_card() {
+        let mut snapshot = GameState::new(34).export_cr_state(PlayerId::Player1);
+        snapshot.enemy_towers[1].hp_frac = 0.0;
+        let cards = crate::card::get_test_cards();
+
+        let rebuilt = GameState::from_cr_state(&snapshot, &cards, &ResyncAssumptions::default()).unwrap();
+        assert_eq!(rebuilt.entities.values().filter(|e| e.tower_type().is_some()).count(), 5);
+        assert_eq!(rebuilt.crowns_against(PlayerId::Player2), 1);
+
+        // A unit of a card that isn't loaded becomes the assumed card
+        let mut observed = observed_state().export_cr_state(PlayerId::Player1);
+        observed.enemy_units[0].card = "Mystery".to_string();
+        let rebuilt = GameState::from_cr_state(&observed, &cards, &ResyncAssumptions::default()).unwrap();
+        let enemy_units = rebuilt.export_cr_state(PlayerId::Player1).enemy_units;
+        assert_eq!((enemy_units[0].card.as_str(), enemy_units[0].level), ("Knight", TOWER_LEVEL));
+
+        let assumptions = ResyncAssumptions {
+            unit_card: "Mystery".to_string(),
+            ..Default::default()
+        };
+        let err = GameState::from_cr_state(&snapshot, &cards, &assumptions).unwrap_err();
+        assert!(matches!(err, Error::Configuration(_)));
+    }
+}
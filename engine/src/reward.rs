@@ -0,0 +1,316 @@
+//! Per-step rewards for training agents.
+//!
+//! A reward compares two [`RewardTally`]s of the match from the agent's
+//! side, taken before and after a step: running totals (tower HP
+//! destroyed, crowns, elixir spent) that are cheap to take every step,
+//! unlike cloning the state. [`RewardFn`] is the extension point; the
+//! built-in terms below are combined with weights by [`Shaped`], and a
+//! [`RewardConfig`] names the weights for an experiment (it's what the
+//! server's `set_reward` method and the Python bindings accept).
+//!
+//! [`RewardTracker`] keeps the previous tally for one agent, so callers
+//! only hand it the state after each step.
+
+use crate::state::GameState;
+use serde::{Deserialize, Serialize};
+use shared::PlayerId;
+
+/// Running totals of a match from one side, for rewards to difference.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RewardTally {
+    /// Fraction of the opponent's total tower HP destroyed (0 to 1).
+    pub enemy_towers_lost: f32,
+    /// Fraction of our own total tower HP destroyed.
+    pub ally_towers_lost: f32,
+    /// Crowns taken from the opponent.
+    pub crowns: u32,
+    /// Crowns the opponent has taken.
+    pub crowns_lost: u32,
+    pub elixir: f32,
+    pub elixir_regen_rate: f32,
+    /// Elixir spent on plays so far.
+    pub elixir_spent: f32,
+    pub match_time: f32,
+    /// +1 once the match is won, -1 once it's lost, 0 otherwise (draws
+    /// and matches still running).
+    pub outcome: f32,
+}
+
+impl RewardTally {
+    /// Takes the tally of `state` from `pov`'s side.
+    pub fn of(state: &GameState, pov: PlayerId) -> Self {
+        let player = state.players.get(&pov);
+        Self {
+            enemy_towers_lost: state.tower_hp_lost_fraction(pov.opponent()),
+            ally_towers_lost: state.tower_hp_lost_fraction(pov),
+            crowns: state.crowns_against(pov.opponent()),
+            crowns_lost: state.crowns_against(pov),
+            elixir: player.map_or(0.0, |p| p.elixir),
+            elixir_regen_rate: player.map_or(0.0, |p| p.elixir_regen_rate),
+            elixir_spent: state.plays_by(pov).map(|play| play.cost).sum(),
+            match_time: state.match_time,
+            outcome: match state.winner() {
+                Some(winner) if winner.is_ally_of(pov) => 1.0,
+                Some(_) => -1.0,
+                None => 0.0,
+            },
+        }
+    }
+
+    /// Elixir regen lost to a full bar between `before` and this tally:
+    /// what the regen rate would have brought in, less what was actually
+    /// gained (spending included). An estimate if the rate changed
+    /// mid-step (double elixir); never negative.
+    pub fn elixir_leaked_since(&self, before: &RewardTally) -> f32 {
+        let possible = self.elixir_regen_rate * (self.match_time - before.match_time);
+        let gained = (self.elixir + self.elixir_spent) - (before.elixir + before.elixir_spent);
+        (possible - gained).max(0.0)
+    }
+}
+
+/// A reward for one step, from the tallies before and after it.
+///
+/// Implementations must be deterministic functions of the two tallies;
+/// they're shared across the environments of a batch, possibly on several
+/// threads.
+pub trait RewardFn: Send + Sync {
+    fn reward(&self, before: &RewardTally, after: &RewardTally) -> f32;
+}
+
+impl<F: Fn(&RewardTally, &RewardTally) -> f32 + Send + Sync> RewardFn for F {
+    fn reward(&self, before: &RewardTally, after: &RewardTally) -> f32 {
+        self(before, after)
+    }
+}
+
+/// Tower HP destroyed during the step, as a fraction of the opponent's
+/// total, minus `taken_weight` times the fraction of our own lost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TowerDamage {
+    pub taken_weight: f32,
+}
+
+impl RewardFn for TowerDamage {
+    fn reward(&self, before: &RewardTally, after: &RewardTally) -> f32 {
+        (after.enemy_towers_lost - before.enemy_towers_lost)
+            - self.taken_weight * (after.ally_towers_lost - before.ally_towers_lost)
+    }
+}
+
+/// Crowns taken during the step minus crowns lost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crowns;
+
+impl RewardFn for Crowns {
+    fn reward(&self, before: &RewardTally, after: &RewardTally) -> f32 {
+        let net = |t: &RewardTally| t.crowns as f32 - t.crowns_lost as f32;
+        net(after) - net(before)
+    }
+}
+
+/// +1 on the step that wins the match, -1 on the one that loses it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Win;
+
+impl RewardFn for Win {
+    fn reward(&self, before: &RewardTally, after: &RewardTally) -> f32 {
+        after.outcome - before.outcome
+    }
+}
+
+/// Minus the elixir leaked at a full bar during the step (see
+/// [`RewardTally::elixir_leaked_since`]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElixirLeak;
+
+impl RewardFn for ElixirLeak {
+    fn reward(&self, before: &RewardTally, after: &RewardTally) -> f32 {
+        -after.elixir_leaked_since(before)
+    }
+}
+
+/// A weighted sum of reward terms.
+#[derive(Default)]
+pub struct Shaped {
+    terms: Vec<(f32, Box<dyn RewardFn>)>,
+}
+
+impl Shaped {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `term`, scaled by `weight`.
+    pub fn with(mut self, weight: f32, term: impl RewardFn + 'static) -> Self {
+        self.terms.push((weight, Box::new(term)));
+        self
+    }
+}
+
+impl RewardFn for Shaped {
+    fn reward(&self, before: &RewardTally, after: &RewardTally) -> f32 {
+        self.terms.iter().map(|(weight, term)| weight * term.reward(before, after)).sum()
+    }
+}
+
+/// The weights of the built-in terms for one experiment. The defaults
+/// give the change in [`crate::rollout::score`] over the step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RewardConfig {
+    /// Weight of [`TowerDamage`].
+    pub tower_damage: f32,
+    /// [`TowerDamage::taken_weight`]: how much our own tower losses count
+    /// against the damage dealt.
+    pub damage_taken: f32,
+    /// Weight of [`Crowns`].
+    pub crowns: f32,
+    /// Weight of [`Win`].
+    pub win: f32,
+    /// Weight of [`ElixirLeak`] (per elixir leaked).
+    pub elixir_leak: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            tower_damage: 1.0,
+            damage_taken: 1.0,
+            crowns: 1.0,
+            win: 0.0,
+            elixir_leak: 0.0,
+        }
+    }
+}
+
+impl RewardConfig {
+    /// The reward these weights describe (terms weighted zero are left out).
+    pub fn build(&self) -> Shaped {
+        let mut shaped = Shaped::new();
+        if self.tower_damage != 0.0 {
+            shaped = shaped.with(
+                self.tower_damage,
+                TowerDamage {
+                    taken_weight: self.damage_taken,
+                },
+            );
+        }
+        if self.crowns != 0.0 {
+            shaped = shaped.with(self.crowns, Crowns);
+        }
+        if self.win != 0.0 {
+            shaped = shaped.with(self.win, Win);
+        }
+        if self.elixir_leak != 0.0 {
+            shaped = shaped.with(self.elixir_leak, ElixirLeak);
+        }
+        shaped
+    }
+}
+
+/// One agent's reward across the steps of a match.
+pub struct RewardTracker {
+    reward: Box<dyn RewardFn>,
+    pov: PlayerId,
+    last: RewardTally,
+}
+
+impl RewardTracker {
+    /// Tracks `reward` for `pov`, starting from `state`.
+    pub fn new(reward: impl RewardFn + 'static, pov: PlayerId, state: &GameState) -> Self {
+        Self {
+            reward: Box::new(reward),
+            pov,
+            last: RewardTally::of(state, pov),
+        }
+    }
+
+    /// Swaps in a different reward; the next step is scored with it.
+    pub fn set_reward(&mut self, reward: impl RewardFn + 'static) {
+        self.reward = Box::new(reward);
+    }
+
+    /// Starts over from `state` (a new match).
+    pub fn reset(&mut self, state: &GameState) {
+        self.last = RewardTally::of(state, self.pov);
+    }
+
+    /// The reward since the last call (or reset), now that `state` has
+    /// been stepped.
+    pub fn step(&mut self, state: &GameState) -> f32 {
+        let tally = RewardTally::of(state, self.pov);
+        let reward = self.reward.reward(&self.last, &tally);
+        self.last = tally;
+        reward
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TowerType;
+
+    fn damage_tower(state: &mut GameState, owner: PlayerId, tower: TowerType, damage: f32) {
+        let entity = state
+            .entities
+            .values_mut()
+            .find(|e| e.owner == owner && e.tower_type() == Some(tower))
+            .unwrap();
+        entity.hp -= damage;
+    }
+
+    #[test]
+    fn test_default_config_tracks_the_rollout_score() {
+        let mut state = GameState::new(8);
+        let mut tracker = RewardTracker::new(RewardConfig::default().build(), PlayerId::Player1, &state);
+        let start = crate::rollout::score(&state, PlayerId::Player1);
+
+        damage_tower(&mut state, PlayerId::Player2, TowerType::LeftPrincess, 10_000.0);
+        damage_tower(&mut state, PlayerId::Player1, TowerType::King, 500.0);
+        crate::step(&mut state, &[]).unwrap();
+        let reward = tracker.step(&state);
+        let expected = crate::rollout::score(&state, PlayerId::Player1) - start;
+        assert!((reward - expected).abs() < 1e-5);
+        assert!(reward > 1.0);
+        assert_eq!(tracker.step(&state), 0.0);
+    }
+
+    #[test]
+    fn test_win_and_elixir_leak_terms() {
+        let mut state = GameState::new(9);
+        let config = RewardConfig {
+            tower_damage: 0.0,
+            crowns: 0.0,
+            win: 10.0,
+            elixir_leak: 0.5,
+            ..RewardConfig::default()
+        };
+        let mut tracker = RewardTracker::new(config.build(), PlayerId::Player2, &state);
+
+        // A full bar leaks a whole step's regen
+        state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 10.0;
+        let before = RewardTally::of(&state, PlayerId::Player2);
+        tracker.reset(&state);
+        for _ in 0..60 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let leaked = RewardTally::of(&state, PlayerId::Player2).elixir_leaked_since(&before);
+        assert!((leaked - before.elixir_regen_rate).abs() < 1e-3);
+        assert!((tracker.step(&state) + 0.5 * leaked).abs() < 1e-5);
+
+        // Losing the King ends the match for Player2
+        damage_tower(&mut state, PlayerId::Player1, TowerType::King, 100_000.0);
+        state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 0.0;
+        tracker.reset(&state);
+        crate::step(&mut state, &[]).unwrap();
+        assert_eq!(state.winner(), Some(PlayerId::Player2));
+        assert!((tracker.step(&state) - 10.0).abs() < 1e-5);
+        // The lead's win is the whole team's
+        assert_eq!(RewardTally::of(&state, PlayerId::Player4).outcome, 1.0);
+        assert_eq!(RewardTally::of(&state, PlayerId::Player3).outcome, -1.0);
+
+        let config: RewardConfig = serde_json::from_str(r#"{"win": 1.0}"#).unwrap();
+        assert_eq!(config, RewardConfig { win: 1.0, ..RewardConfig::default() });
+        assert!(serde_json::from_str::<RewardConfig>(r#"{"wins": 1.0}"#).is_err());
+    }
+}
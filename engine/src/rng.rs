@@ -51,6 +51,14 @@ impl Rng {
         min + (self.rand_u32() % range) as i32
     }
 
+    /// Shuffles `items` in place (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.rand_int_range(0, i as i32 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
     /// Returns the seed used to initialize this RNG.
     pub fn seed(&self) -> u64 {
         self.seed
@@ -60,16 +68,6 @@ impl Rng {
     pub fn state(&self) -> u64 {
         self.state
     }
-
-    /// Restores the RNG after deserialization.
-    pub(crate) fn restore(&mut self) {
-        if self.rng.is_none() {
-            self.rng = Some(oorandom::Rand32::new(self.seed));
-            // Advance RNG to match the saved state
-            // This is a simplified approach; in production you'd want to
-            // serialize the full RNG state or track call count
-        }
-    }
 }
 
 #[cfg(test)]
@@ -92,7 +90,7 @@ mod tests {
 
         for _ in 0..1000 {
             let value = rng.rand_range(5.0, 10.0);
-            assert!(value >= 5.0 && value < 10.0);
+            assert!((5.0..10.0).contains(&value));
         }
     }
 
@@ -102,7 +100,7 @@ mod tests {
 
         for _ in 0..1000 {
             let value = rng.rand_int_range(1, 7); // Dice roll
-            assert!(value >= 1 && value < 7);
+            assert!((1..7).contains(&value));
         }
     }
 }
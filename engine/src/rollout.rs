@@ -0,0 +1,133 @@
+//! Forward-model rollouts for planning bots.
+//!
+//! [`rollout`] packages the usual planning loop: for each candidate set of
+//! actions, clone the state, play the candidate, let an assumed opponent
+//! policy respond for a fixed horizon, and score the outcome.
+
+use crate::action::Action;
+use crate::bot::RuleBot;
+use crate::state::GameState;
+use shared::PlayerId;
+
+/// Chooses a player's actions for one tick of a rollout.
+pub trait Policy {
+    fn act(&mut self, state: &GameState) -> Vec<Action>;
+}
+
+impl Policy for RuleBot {
+    fn act(&mut self, state: &GameState) -> Vec<Action> {
+        self.decide(state).into_iter().collect()
+    }
+}
+
+impl<F: FnMut(&GameState) -> Vec<Action>> Policy for F {
+    fn act(&mut self, state: &GameState) -> Vec<Action> {
+        self(state)
+    }
+}
+
+/// Plays each candidate action set for `player` on a copy of `state` and
+/// returns one score per candidate, in order (higher is better; see
+/// [`score`]).
+///
+/// Each candidate is applied on the first tick, alongside `opponent`'s
+/// actions, after which only `opponent` acts, for `horizon_ticks` ticks or
+/// until the match ends. Every candidate gets a fresh clone of `opponent`.
+/// Each tick's actions go through one step (see
+/// [`step_keeping_valid`](crate::step_keeping_valid)): opponent actions
+/// the engine rejects are dropped, and a candidate the engine rejects
+/// scores `f32::NEG_INFINITY`.
+pub fn rollout<P: Policy + Clone>(
+    state: &GameState,
+    player: PlayerId,
+    candidates: &[Vec<Action>],
+    opponent: &P,
+    horizon_ticks: u64,
+) -> Vec<f32> {
+    candidates
+        .iter()
+        .map(|candidate| {
+            let mut sim = state.clone();
+            sim.set_headless(true);
+            let mut opponent = opponent.clone();
+
+            let mut first = Some(candidate.as_slice());
+            for _ in 0..horizon_ticks {
+                if sim.is_match_over() {
+                    break;
+                }
+                // The candidate goes first, so it's never dropped for the opponent's sake
+                let mine = first.take().unwrap_or_default();
+                let actions = [mine, &opponent.act(&sim)].concat();
+                match crate::step_keeping_valid(&mut sim, &actions) {
+                    Ok(dropped) if dropped.iter().all(|&i| i >= mine.len()) => {}
+                    _ => return f32::NEG_INFINITY,
+                }
+            }
+            score(&sim, player)
+        })
+        .collect()
+}
+
+/// Tower HP balance from `player`'s side: the fraction of the opponent's
+/// total tower HP destroyed minus the fraction of `player`'s, plus one per
+/// crown of difference. Ranges over roughly [-4, 4].
+pub fn score(state: &GameState, player: PlayerId) -> f32 {
+    let opponent = player.opponent();
+    let crowns = state.crowns_against(opponent) as f32 - state.crowns_against(player) as f32;
+    state.tower_hp_lost_fraction(opponent) - state.tower_hp_lost_fraction(player) + crowns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::BotConfig;
+    use shared::Position;
+
+    fn play(player: PlayerId, card: &str, x: f32, y: f32) -> Action {
+        Action::PlayCard {
+            player,
+            card_name: card.to_string(),
+            level: 11,
+            position: Position::new(x, y),
+        }
+    }
+
+    #[test]
+    fn test_rollout_ranks_defending_over_idling() {
+        let mut state = GameState::new(35);
+        state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 10.0;
+        crate::step(&mut state, &[play(PlayerId::Player2, "Giant", 18.0, 3.5)]).unwrap();
+        let before = state.state_hash();
+
+        let idle = |_: &GameState| Vec::new();
+        let candidates = vec![
+            Vec::new(),
+            vec![play(PlayerId::Player1, "Knight", 9.0, 3.5)],
+            vec![play(PlayerId::Player1, "Unknown", 9.0, 3.5)],
+        ];
+        let scores = rollout(&state, PlayerId::Player1, &candidates, &idle, 60 * 20);
+
+        assert_eq!(scores.len(), 3);
+        assert!(scores[0] < 0.0, "{:?}", scores);
+        assert!(scores[1] > scores[0], "{:?}", scores);
+        assert_eq!(scores[2], f32::NEG_INFINITY);
+        // The real state is untouched
+        assert_eq!(state.state_hash(), before);
+
+        // An opponent's rejected plays are dropped without sinking the candidate
+        let misplays = |_: &GameState| vec![play(PlayerId::Player2, "Unknown", 24.0, 9.0)];
+        assert_eq!(rollout(&state, PlayerId::Player1, &candidates, &misplays, 60 * 20), scores);
+    }
+
+    #[test]
+    fn test_rollout_is_deterministic_with_a_bot_opponent() {
+        let state = GameState::new(36);
+        let bot = RuleBot::new(PlayerId::Player2, BotConfig::hard());
+        let candidates = vec![Vec::new(), vec![play(PlayerId::Player1, "Giant", 12.0, 3.5)]];
+
+        let first = rollout(&state, PlayerId::Player1, &candidates, &bot, 60 * 10);
+        let second = rollout(&state, PlayerId::Player1, &candidates, &bot, 60 * 10);
+        assert_eq!(first, second);
+    }
+}
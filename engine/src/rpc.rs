@@ -0,0 +1,758 @@
+//! JSON-RPC 2.0 protocol for driving a match from another process (the
+//! `crust_sim_server` binary speaks it over stdio, one message per line,
+//! and over WebSocket in live mode, see `ws` under the `server` feature).
+//!
+//! A message is one request object, or an array of them (a batch), answered
+//! by an array of responses in the same order. Requests without an `id` are
+//! notifications and get no response. Failures come back as error objects
+//! with the codes below, never as a dropped line.
+//!
+//! The schema is versioned by [`PROTOCOL_VERSION`], which changes whenever
+//! a method, parameter or result shape does:
+//!
+//! | method           | params                                  | result                      |
+//! |------------------|-----------------------------------------|-----------------------------|
+//! | `version`        |                                         | `{protocol, methods}`       |
+//! | `reset`          | `{seed?, mode?, two_v_two?}`            | state                       |
+//! | `state`          | `{pov?}`                                | state                       |
+//! | `result`         |                                         | [`MatchResult`](crate::state::MatchResult) or `null` |
+//! | `act`            | `{actions: [{player, action \| action_id}]}` | `{queued}`             |
+//! | `step`           | `{actions: [{player, action \| action_id}], pov?}` | state and `reward` |
+//! | `set_frame_skip` | `{ticks}`                               | `{frame_skip}`              |
+//! | `set_reward`     | [`RewardConfig`] weights (omitted: default) | the weights in use      |
+//! | `reload`         | `{cards, towers?}`                      | `{cards, towers}`           |
+//! | `exit`           |                                         | `null`, then the session ends |
+//!
+//! States are [`shared::CRState`] exports from `pov`'s side (`"Player1"` by
+//! default). A `step` plays every listed action from its player's own frame
+//! on one agent step (see [`step_with_agent_actions`]); `action` is an
+//! [`AgentAction`] (`"NoOp"` or `{"Play": {"slot", "tile"}}`) and
+//! `action_id` its flat id. `act` queues actions without advancing the
+//! match: they are played ahead of the next `step`'s own, or on the next
+//! tick a live server runs ([`Session::tick`]).
+//!
+//! `result` is the finished match from neither side (see
+//! [`GameState::result`]); it's `null` while the match is running. The
+//! same summary, from `pov`'s side, rides on a state's `result` field.
+//!
+//! A `reset` with a `mode` ([`GameMode`] name, e.g. `"triple_elixir"`)
+//! plays that match and every later one in the mode; likewise `two_v_two`
+//! switches to (or back from) 2v2 matches, where Player3 and Player4 join
+//! in (see [`GameState::new_2v2`]).
+//!
+//! A `step` result also carries `pov`'s `reward` for the step (see
+//! [`crate::reward`]), weighted as the last `set_reward` said; the weights
+//! outlive `reset`, so an experiment sets them once.
+//!
+//! Clients of a shared match (a live server's, see
+//! [`Session::handle_client_message`]) each hold one seat: they may only
+//! call [`CLIENT_METHODS`], see the match from their seat and act for it.
+
+use crate::action::AgentAction;
+use crate::card::{self, Card};
+use crate::reward::{RewardConfig, RewardFn, RewardTally};
+use crate::state::{step_agent_tick, step_with_agent_actions, GameMode, GameState, TOWER_LEVEL};
+use crate::tower::{self, Tower};
+use crate::catch_internal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use shared::{dims, Error, PlayerId, Result};
+
+/// Version of the method and parameter schema above.
+pub const PROTOCOL_VERSION: &str = "crust-sim/6";
+
+/// Methods a [`Session`] answers, in the order listed above.
+pub const METHODS: [&str; 10] = [
+    "version", "reset", "state", "result", "act", "step", "set_frame_skip", "set_reward", "reload", "exit",
+];
+
+/// Methods a client holding one seat of a shared match may call.
+pub const CLIENT_METHODS: [&str; 3] = ["version", "state", "act"];
+
+/// The message isn't valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// The message is JSON but not a request (or an empty batch).
+pub const INVALID_REQUEST: i64 = -32600;
+/// No such method.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Missing or malformed params, or an action the engine rejects.
+pub const INVALID_PARAMS: i64 = -32602;
+/// The engine failed internally (a caught panic, a serialization error).
+pub const INTERNAL_ERROR: i64 = -32603;
+/// Card or tower data that can't start a match (`reload`).
+pub const CONFIGURATION_ERROR: i64 = -32001;
+
+/// A JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(error: Error) -> Self {
+        let code = match error {
+            Error::InvalidAction(_) => INVALID_PARAMS,
+            Error::Configuration(_) => CONFIGURATION_ERROR,
+            Error::Serialization(_) | Error::Internal(_) => INTERNAL_ERROR,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+/// A JSON-RPC response: `result` on success, `error` otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl Response {
+    fn new(id: Value, outcome: std::result::Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".to_string(),
+            result,
+            error,
+            id,
+        }
+    }
+}
+
+/// Card and tower data that new matches are created from. `reload` swaps
+/// it out; a match already running keeps the data it started with.
+#[derive(Debug, Clone)]
+struct Catalog {
+    cards: Vec<Card>,
+    towers: Vec<Tower>,
+}
+
+impl Catalog {
+    fn builtin() -> Self {
+        Self {
+            cards: card::get_test_cards(),
+            towers: tower::get_default_towers(),
+        }
+    }
+
+    /// Loads a catalog from JSON files, checking it can start a match before
+    /// anything is replaced.
+    fn load(cards_path: &str, towers_path: Option<&str>) -> Result<Self> {
+        let catalog = Self {
+            cards: card::load_cards_from_json(cards_path)?,
+            towers: match towers_path {
+                Some(path) => tower::load_towers_from_json(path)?,
+                None => tower::get_default_towers(),
+            },
+        };
+        catalog.new_match(0, false)?;
+        Ok(catalog)
+    }
+
+    /// A fresh match with both decks cycled from the catalog's playable
+    /// cards (Player 2's in reverse order). In 2v2, each lead's teammate
+    /// gets the same deck as the lead.
+    fn new_match(&self, seed: u64, two_v_two: bool) -> Result<GameState> {
+        let mut game = if two_v_two { GameState::new_2v2(seed) } else { GameState::new(seed) };
+        game.load_cards(self.cards.clone())?;
+        game.spawn_towers(&self.towers, TOWER_LEVEL)?;
+
+        let playable: Vec<&Card> = self.cards.iter().filter(|c| c.type_name != "tower troop").collect();
+        if playable.is_empty() {
+            return Err(Error::Configuration("Card catalog has no playable cards".to_string()));
+        }
+        let player1_deck: Vec<String> = playable.iter().cycle().take(dims::DECK_SIZE).map(|c| c.name.clone()).collect();
+        let player2_deck: Vec<String> = playable.iter().rev().cycle().take(dims::DECK_SIZE).map(|c| c.name.clone()).collect();
+
+        let players: Vec<PlayerId> = game.players.keys().copied().collect();
+        for player in players {
+            let deck = if player.side() == PlayerId::Player1 { &player1_deck } else { &player2_deck };
+            game.set_player_deck(player, deck.clone())?;
+        }
+        Ok(game)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResetParams {
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    mode: Option<GameMode>,
+    #[serde(default)]
+    two_v_two: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateParams {
+    #[serde(default)]
+    pov: Option<PlayerId>,
+}
+
+/// One agent's action in a `step`: either the action or its flat id.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActionParams {
+    player: PlayerId,
+    #[serde(default)]
+    action: Option<AgentAction>,
+    #[serde(default)]
+    action_id: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActParams {
+    actions: Vec<ActionParams>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepParams {
+    #[serde(default)]
+    actions: Vec<ActionParams>,
+    #[serde(default)]
+    pov: Option<PlayerId>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrameSkipParams {
+    ticks: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReloadParams {
+    cards: String,
+    #[serde(default)]
+    towers: Option<String>,
+}
+
+/// One client's match and catalog, answering protocol messages.
+#[derive(Debug, Clone)]
+pub struct Session {
+    game: GameState,
+    catalog: Catalog,
+    /// Actions from `act`, played on the next step or tick.
+    queued: Vec<(PlayerId, AgentAction)>,
+    /// Weights of the reward `step` reports.
+    reward: RewardConfig,
+    /// Mode new matches are played in.
+    mode: GameMode,
+    /// Whether new matches are 2v2.
+    two_v_two: bool,
+    exiting: bool,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// A session on the built-in cards, with an empty match until `reset`.
+    pub fn new() -> Self {
+        Self {
+            game: GameState::new(0),
+            catalog: Catalog::builtin(),
+            queued: Vec::new(),
+            reward: RewardConfig::default(),
+            mode: GameMode::default(),
+            two_v_two: false,
+            exiting: false,
+        }
+    }
+
+    /// Starts a new match from the catalog, dropping any queued actions
+    /// (the `reset` method).
+    pub fn reset(&mut self, seed: u64) -> Result<()> {
+        self.game = self.new_match(seed)?;
+        self.queued.clear();
+        Ok(())
+    }
+
+    /// A fresh match from the catalog, as `reset` would start, leaving this
+    /// session's own match alone.
+    pub fn new_match(&self, seed: u64) -> Result<GameState> {
+        let mut game = self.catalog.new_match(seed, self.two_v_two)?;
+        game.apply_game_mode(self.mode);
+        Ok(game)
+    }
+
+    /// Plays later matches (from the next `reset`) in `mode`.
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+    }
+
+    /// Plays later matches (from the next `reset`) 2v2, or back in 1v1.
+    pub fn set_two_v_two(&mut self, two_v_two: bool) {
+        self.two_v_two = two_v_two;
+    }
+
+    /// Replaces the card and tower data later matches start from (the
+    /// `reload` method). The running match keeps what it started with.
+    pub fn reload(&mut self, cards_path: &str, towers_path: Option<&str>) -> Result<()> {
+        self.catalog = Catalog::load(cards_path, towers_path)?;
+        Ok(())
+    }
+
+    /// Runs one engine tick with the actions queued by `act`, for callers
+    /// that keep the match clock themselves (a live server). Errors only
+    /// if the engine fails internally.
+    pub fn tick(&mut self) -> Result<()> {
+        let queued = std::mem::take(&mut self.queued);
+        step_agent_tick(&mut self.game, &queued)
+    }
+
+    /// The match being played.
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    /// Mutable access to the match, for embedding callers and tests.
+    pub fn game_mut(&mut self) -> &mut GameState {
+        &mut self.game
+    }
+
+    /// Actions queued by `act` for the next step or tick.
+    pub fn queued(&self) -> &[(PlayerId, AgentAction)] {
+        &self.queued
+    }
+
+    /// Whether an `exit` request has been answered.
+    pub fn is_exiting(&self) -> bool {
+        self.exiting
+    }
+
+    /// Answers one message (a request or a batch). Returns the response
+    /// line to send back, or `None` if every request was a notification.
+    pub fn handle_message(&mut self, message: &str) -> Option<String> {
+        self.handle_message_from(message, None)
+    }
+
+    /// Answers a message from a client seated as `seat` in a shared match:
+    /// like [`handle_message`](Self::handle_message), but only
+    /// [`CLIENT_METHODS`] are allowed, `state` is from `seat`'s side, and
+    /// `act` only takes actions for `seat`.
+    pub fn handle_client_message(&mut self, message: &str, seat: PlayerId) -> Option<String> {
+        self.handle_message_from(message, Some(seat))
+    }
+
+    fn handle_message_from(&mut self, message: &str, seat: Option<PlayerId>) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(message) {
+            Err(e) => Some(json!(Response::new(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))))),
+            Ok(Value::Array(requests)) if requests.is_empty() => Some(json!(Response::new(
+                Value::Null,
+                Err(RpcError::new(INVALID_REQUEST, "Empty batch"))
+            ))),
+            Ok(Value::Array(requests)) => {
+                let responses: Vec<Response> = requests.into_iter().filter_map(|r| self.handle_request(r, seat)).collect();
+                (!responses.is_empty()).then(|| json!(responses))
+            }
+            Ok(request) => self.handle_request(request, seat).map(|r| json!(r)),
+        };
+        response.map(|r| r.to_string())
+    }
+
+    /// Answers one request; `None` for a notification.
+    fn handle_request(&mut self, request: Value, seat: Option<PlayerId>) -> Option<Response> {
+        let Value::Object(mut request) = request else {
+            return Some(Response::new(Value::Null, Err(RpcError::new(INVALID_REQUEST, "Request must be an object"))));
+        };
+        let id = request.remove("id");
+        let outcome = self.dispatch(&mut request, seat);
+        id.map(|id| Response::new(id, outcome))
+    }
+
+    /// Validates the envelope and runs the method, catching panics.
+    fn dispatch(&mut self, request: &mut Map<String, Value>, seat: Option<PlayerId>) -> std::result::Result<Value, RpcError> {
+        if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Err(RpcError::new(INVALID_REQUEST, "Expected \"jsonrpc\": \"2.0\""));
+        }
+        let Some(Value::String(method)) = request.remove("method") else {
+            return Err(RpcError::new(INVALID_REQUEST, "Missing method"));
+        };
+        let params = request.remove("params").unwrap_or_else(|| json!({}));
+        if !METHODS.contains(&method.as_str()) {
+            return Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method)));
+        }
+        if seat.is_some() && !CLIENT_METHODS.contains(&method.as_str()) {
+            return Err(RpcError::new(METHOD_NOT_FOUND, format!("Method {} isn't open to clients", method)));
+        }
+        catch_internal(|| format!("method '{}'", method), || self.call(&method, params, seat)).map_err(RpcError::from)
+    }
+
+    fn call(&mut self, method: &str, params: Value, seat: Option<PlayerId>) -> Result<Value> {
+        let state = |game: &GameState, pov: Option<PlayerId>| Ok(json!(game.export_cr_state(pov_in(game, pov)?)));
+        match method {
+            "version" => {
+                parse::<Map<String, Value>>(params)?;
+                Ok(json!({ "protocol": PROTOCOL_VERSION, "methods": METHODS }))
+            }
+            "reset" => {
+                let ResetParams { seed, mode, two_v_two } = parse(params)?;
+                if let Some(mode) = mode {
+                    self.set_mode(mode);
+                }
+                if let Some(two_v_two) = two_v_two {
+                    self.set_two_v_two(two_v_two);
+                }
+                self.reset(seed)?;
+                state(&self.game, None)
+            }
+            "state" => {
+                let StateParams { pov } = parse(params)?;
+                state(&self.game, seated(seat, pov)?.or(pov))
+            }
+            "result" => {
+                parse::<Map<String, Value>>(params)?;
+                Ok(json!(self.game.result()))
+            }
+            "act" => {
+                let ActParams { actions } = parse(params)?;
+                for action in &actions {
+                    seated(seat, Some(action.player))?;
+                }
+                let actions = self.agent_actions(actions)?;
+                self.queued.extend(actions);
+                Ok(json!({ "queued": self.queued.len() }))
+            }
+            "step" => {
+                let StepParams { actions, pov } = parse(params)?;
+                let actions = self.agent_actions(actions)?;
+                let pov = pov_in(&self.game, pov)?;
+                // Only taken once the step can't be refused, so a bad
+                // request leaves them queued
+                let mut queued = std::mem::take(&mut self.queued);
+                queued.extend(actions);
+                let before = RewardTally::of(&self.game, pov);
+                step_with_agent_actions(&mut self.game, &queued)?;
+                let mut frame = self.game.export_cr_state(pov);
+                frame.reward = Some(self.reward.build().reward(&before, &RewardTally::of(&self.game, pov)));
+                Ok(json!(frame))
+            }
+            "set_frame_skip" => {
+                let FrameSkipParams { ticks } = parse(params)?;
+                if ticks == 0 {
+                    return Err(Error::InvalidAction("Frame-skip must be at least one tick".to_string()));
+                }
+                self.game.set_frame_skip(ticks);
+                Ok(json!({ "frame_skip": self.game.frame_skip }))
+            }
+            "set_reward" => {
+                self.reward = parse(params)?;
+                Ok(json!(self.reward))
+            }
+            "reload" => {
+                let ReloadParams { cards, towers } = parse(params)?;
+                self.reload(&cards, towers.as_deref())?;
+                Ok(json!({ "cards": self.catalog.cards.len(), "towers": self.catalog.towers.len() }))
+            }
+            "exit" => {
+                self.exiting = true;
+                Ok(Value::Null)
+            }
+            other => Err(Error::Internal(format!("Method {} listed but not handled", other))),
+        }
+    }
+
+    /// Resolves `act`/`step` action params against the current match.
+    fn agent_actions(&self, actions: Vec<ActionParams>) -> Result<Vec<(PlayerId, AgentAction)>> {
+        actions
+            .into_iter()
+            .map(|a| {
+                let action = match (a.action, a.action_id) {
+                    (Some(action), None) => action,
+                    (None, Some(id)) => AgentAction::decode(id, self.game.placement_grid)?,
+                    _ => {
+                        return Err(Error::InvalidAction(
+                            "Each action needs exactly one of \"action\" and \"action_id\"".to_string(),
+                        ))
+                    }
+                };
+                Ok((a.player, action))
+            })
+            .collect()
+    }
+}
+
+/// Deserializes a method's params, reporting a mismatch as bad params.
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T> {
+    serde_json::from_value(params).map_err(|e| Error::InvalidAction(format!("Invalid params: {}", e)))
+}
+
+/// A seated client's side, erroring if it names another one (`None` for
+/// an unseated caller).
+fn seated(seat: Option<PlayerId>, player: Option<PlayerId>) -> Result<Option<PlayerId>> {
+    match (seat, player) {
+        (Some(seat), Some(player)) if player != seat => {
+            Err(Error::InvalidAction(format!("A client seated as {:?} can't act or look as {:?}", seat, player)))
+        }
+        _ => Ok(seat),
+    }
+}
+
+/// The side to export from: `pov` (Player1 by default), if it plays in
+/// `game` (Player3 and Player4 only do in 2v2).
+fn pov_in(game: &GameState, pov: Option<PlayerId>) -> Result<PlayerId> {
+    let pov = pov.unwrap_or(PlayerId::Player1);
+    if !game.players.contains_key(&pov) {
+        return Err(Error::InvalidAction(format!("{:?} isn't in this match", pov)));
+    }
+    Ok(pov)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(session: &mut Session, method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Response = serde_json::from_str(&session.handle_message(&request.to_string()).unwrap()).unwrap();
+        assert_eq!(response.id, json!(1));
+        match response.error {
+            Some(error) => Err(error),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        }
+    }
+
+    fn play(player: &str, slot: usize, tile: usize) -> Value {
+        json!({ "player": player, "action": { "Play": { "slot": slot, "tile": tile } } })
+    }
+
+    #[test]
+    fn test_batches_notifications_and_error_objects() {
+        let mut session = Session::new();
+        let version = call(&mut session, "version", json!({})).unwrap();
+        assert_eq!(version["protocol"], PROTOCOL_VERSION);
+
+        // A batch answers each request in order, skipping notifications
+        let batch = json!([
+            { "jsonrpc": "2.0", "id": "a", "method": "reset", "params": { "seed": 3 } },
+            { "jsonrpc": "2.0", "method": "set_frame_skip", "params": { "ticks": 4 } },
+            { "jsonrpc": "2.0", "id": "b", "method": "nope" },
+            { "jsonrpc": "2.0", "id": "c", "method": "step", "params": { "actions": [{ "player": "Player1", "action_id": 0 }] } },
+            { "jsonrpc": "1.0", "id": "d", "method": "state" },
+            7
+        ]);
+        let responses: Vec<Response> =
+            serde_json::from_str(&session.handle_message(&batch.to_string()).unwrap()).unwrap();
+        let ids: Vec<Value> = responses.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(ids, [json!("a"), json!("b"), json!("c"), json!("d"), Value::Null]);
+        assert!(responses[0].result.is_some());
+        let codes: Vec<Option<i64>> = responses.iter().map(|r| r.error.as_ref().map(|e| e.code)).collect();
+        assert_eq!(codes, [None, Some(METHOD_NOT_FOUND), None, Some(INVALID_REQUEST), Some(INVALID_REQUEST)]);
+        assert_eq!(session.game().tick, 4);
+
+        let parse_error: Response = serde_json::from_str(&session.handle_message("STEP 0 0").unwrap()).unwrap();
+        assert_eq!(parse_error.error.unwrap().code, PARSE_ERROR);
+        assert!(session.handle_message(r#"{"jsonrpc": "2.0", "method": "state"}"#).is_none());
+
+        // Bad params and rejected actions are invalid-params errors
+        assert_eq!(call(&mut session, "step", json!({ "actions": [{ "player": "Player1" }] })).unwrap_err().code, INVALID_PARAMS);
+        let out_of_range = json!({ "actions": [{ "player": "Player1", "action_id": 100000 }] });
+        assert_eq!(call(&mut session, "step", out_of_range).unwrap_err().code, INVALID_PARAMS);
+        assert_eq!(call(&mut session, "set_frame_skip", json!({ "ticks": 0 })).unwrap_err().code, INVALID_PARAMS);
+        assert_eq!(call(&mut session, "state", json!({ "pov": "Player3" })).unwrap_err().code, INVALID_PARAMS);
+
+        assert_eq!(call(&mut session, "exit", json!({})).unwrap(), Value::Null);
+        assert!(session.is_exiting());
+    }
+
+    #[test]
+    fn test_step_plays_several_actions_at_once() {
+        let mut session = Session::new();
+        call(&mut session, "reset", json!({ "seed": 2 })).unwrap();
+        for player in session.game_mut().players.values_mut() {
+            player.elixir = 10.0;
+        }
+
+        // The same tile index lands on mirrored spots for the two players
+        let actions = json!({ "actions": [play("Player1", 0, 67), play("Player2", 0, 67)], "pov": "Player2" });
+        let p2_view = call(&mut session, "step", actions).unwrap();
+        let game = session.game();
+        assert_eq!(game.tick, u64::from(crate::state::DEFAULT_FRAME_SKIP));
+        let plays = &game.card_plays;
+        assert_eq!((plays[0].player, plays[1].player), (PlayerId::Player1, PlayerId::Player2));
+        assert_eq!(game.arena.pov_position(PlayerId::Player2, &plays[1].position), plays[0].position);
+        assert_eq!(p2_view["ally_elixir"], json!(game.players[&PlayerId::Player2].elixir));
+
+        // Waiting passes time without playing anything
+        let step = json!({ "actions": [{ "player": "Player1", "action": "NoOp" }] });
+        let snapshot = call(&mut session, "step", step).unwrap();
+        assert_eq!(session.game().card_plays.len(), 2);
+        assert_eq!(snapshot["legal"]["no_op"], json!(true));
+
+        // Queued actions are played on the next step, ahead of its own
+        let queued = call(&mut session, "act", json!({ "actions": [play("Player1", 1, 67)] })).unwrap();
+        assert_eq!(queued["queued"], json!(1));
+        assert_eq!(session.game().card_plays.len(), 2);
+        assert!(call(&mut session, "step", json!({ "actions": [], "pov": "Player3" })).is_err());
+        assert_eq!(session.queued().len(), 1);
+        call(&mut session, "step", json!({ "actions": [] })).unwrap();
+        assert_eq!(session.game().card_plays.len(), 3);
+        assert!(session.queued().is_empty());
+    }
+
+    #[test]
+    fn test_clients_only_watch_and_act_for_their_seat() {
+        let mut session = Session::new();
+        session.reset(6).unwrap();
+        let mut client = |method: &str, params: Value| -> std::result::Result<Value, i64> {
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+            let response = session.handle_client_message(&request.to_string(), PlayerId::Player2).unwrap();
+            let response: Response = serde_json::from_str(&response).unwrap();
+            response.error.map_or(Ok(response.result.unwrap_or(Value::Null)), |e| Err(e.code))
+        };
+
+        for method in ["reset", "step", "reload", "set_reward", "exit"] {
+            assert_eq!(client(method, json!({})), Err(METHOD_NOT_FOUND), "{}", method);
+        }
+        assert_eq!(client("act", json!({ "actions": [play("Player1", 0, 67)] })), Err(INVALID_PARAMS));
+        assert_eq!(client("state", json!({ "pov": "Player1" })), Err(INVALID_PARAMS));
+        assert!(client("act", json!({ "actions": [play("Player2", 0, 67)] })).is_ok());
+        let view = client("state", json!({})).unwrap();
+        assert_eq!(view["ally_elixir"], json!(session.game().players[&PlayerId::Player2].elixir));
+        assert_eq!(session.queued(), &[(PlayerId::Player2, AgentAction::Play { slot: 0, tile: 67 })]);
+        assert!(!session.is_exiting());
+    }
+
+    #[test]
+    fn test_steps_report_the_configured_reward() {
+        let mut session = Session::new();
+        call(&mut session, "reset", json!({ "seed": 5 })).unwrap();
+        let frame = call(&mut session, "step", json!({})).unwrap();
+        assert_eq!(frame["reward"], json!(0.0));
+        assert!(call(&mut session, "state", json!({})).unwrap().get("reward").is_none());
+
+        // Only leaked elixir counts, and the weights outlive a reset
+        let weights = json!({ "tower_damage": 0.0, "crowns": 0.0, "elixir_leak": 2.0 });
+        let config = call(&mut session, "set_reward", weights).unwrap();
+        assert_eq!(config["elixir_leak"], json!(2.0));
+        call(&mut session, "reset", json!({ "seed": 5 })).unwrap();
+        session.game_mut().players.get_mut(&PlayerId::Player2).unwrap().elixir = 10.0;
+        let frame = call(&mut session, "step", json!({ "pov": "Player2" })).unwrap();
+        assert!(frame["reward"].as_f64().unwrap() < 0.0);
+        let frame = call(&mut session, "step", json!({})).unwrap();
+        assert_eq!(frame["reward"], json!(0.0));
+
+        assert_eq!(call(&mut session, "set_reward", json!({ "wins": 1.0 })).unwrap_err().code, INVALID_PARAMS);
+        assert_eq!(call(&mut session, "set_reward", json!({})).unwrap(), json!(RewardConfig::default()));
+    }
+
+    #[test]
+    fn test_result_is_null_until_the_match_ends() {
+        let mut session = Session::new();
+        call(&mut session, "reset", json!({ "seed": 6 })).unwrap();
+        assert_eq!(call(&mut session, "result", json!({})).unwrap(), Value::Null);
+
+        let king = session
+            .game_mut()
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player2 && e.tower_type() == Some(crate::state::TowerType::King))
+            .unwrap();
+        king.hp = 0.0;
+        call(&mut session, "step", json!({})).unwrap();
+        let result = call(&mut session, "result", json!({})).unwrap();
+        assert_eq!(result["winner"], json!("Player1"));
+        assert_eq!(result["end_reason"], json!("king_destroyed"));
+        assert_eq!(result["crowns"], json!({ "Player1": 3, "Player2": 0 }));
+        assert_eq!(result["tower_hp"]["Player2"][0], json!(0.0));
+
+        // States carry the same result from their own side
+        let state = call(&mut session, "state", json!({ "pov": "Player2" })).unwrap();
+        assert_eq!(state["result"]["winner"], json!("ENEMY"));
+        assert_eq!(state["result"]["enemy_crowns"], json!(3));
+    }
+
+    #[test]
+    fn test_reset_can_switch_game_mode() {
+        let mut session = Session::new();
+        call(&mut session, "reset", json!({ "seed": 1, "mode": "triple_elixir" })).unwrap();
+        assert_eq!(session.game().game_mode, "triple_elixir");
+        call(&mut session, "reset", json!({ "seed": 2 })).unwrap();
+        assert_eq!(session.game().players[&PlayerId::Player1].elixir_regen_rate, 3.0);
+        assert_eq!(call(&mut session, "reset", json!({ "mode": "chaos" })).unwrap_err().code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_reset_can_seat_four_players() {
+        let mut session = Session::new();
+        call(&mut session, "reset", json!({ "seed": 1, "two_v_two": true })).unwrap();
+        call(&mut session, "reset", json!({ "seed": 2 })).unwrap();
+        assert!(session.game().is_2v2());
+        let mut decks = [PlayerId::Player2, PlayerId::Player4].map(|p| session.game().players[&p].deck_names());
+        decks.iter_mut().for_each(|deck| deck.sort());
+        assert_eq!(decks[0], decks[1]);
+
+        let step = json!({ "actions": [play("Player3", 0, 67)], "pov": "Player3" });
+        let view = call(&mut session, "step", step).unwrap();
+        assert_eq!(session.game().card_plays[0].player, PlayerId::Player3);
+        assert!(view["teammate_elixir"].is_number());
+
+        call(&mut session, "reset", json!({ "two_v_two": false })).unwrap();
+        assert_eq!(call(&mut session, "state", json!({ "pov": "Player3" })).unwrap_err().code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_reload_applies_to_new_matches_only() {
+        let dir = std::env::temp_dir().join(format!("crust_sim_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cards.json");
+        let mut cards = card::get_test_cards();
+        for card in &mut cards {
+            card.elixir_cost = 1.0;
+        }
+        std::fs::write(&path, serde_json::to_string(&cards).unwrap()).unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut session = Session::new();
+        call(&mut session, "reset", json!({ "seed": 1 })).unwrap();
+        call(&mut session, "step", json!({ "actions": [play("Player1", 0, 0)] })).unwrap();
+        let tick = session.game().tick;
+
+        let loaded = call(&mut session, "reload", json!({ "cards": path })).unwrap();
+        assert_eq!(loaded["cards"], json!(cards.len()));
+        // The running match is untouched
+        assert_eq!(session.game().tick, tick);
+        assert_eq!(session.game().get_card_by_name("Knight").unwrap().elixir_cost, 3.0);
+
+        call(&mut session, "reset", json!({ "seed": 1 })).unwrap();
+        assert_eq!(session.game().get_card_by_name("Knight").unwrap().elixir_cost, 1.0);
+
+        // A bad file is rejected and the loaded catalog kept
+        let error = call(&mut session, "reload", json!({ "cards": "/nonexistent/cards.json" })).unwrap_err();
+        assert_ne!(error.code, INVALID_REQUEST);
+        assert_eq!(session.catalog.cards[0].elixir_cost, 1.0);
+
+        // So is one naming a card twice
+        cards.push(cards[0].clone());
+        std::fs::write(dir.join("cards.json"), serde_json::to_string(&cards).unwrap()).unwrap();
+        let error = call(&mut session, "reload", json!({ "cards": path })).unwrap_err();
+        assert_eq!(error.code, CONFIGURATION_ERROR);
+        assert!(error.message.contains("more than once"), "{}", error.message);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+//! Periodic snapshot sampling for visualization pipelines.
+//!
+//! A [`SnapshotSampler`] wraps [`crate::step`] and records a snapshot every
+//! N ticks, so callers can collect frames without writing their own loop.
+
+use crate::action::Action;
+use crate::state::GameState;
+use serde::{Deserialize, Serialize};
+use shared::{CRState, PlayerId, Result};
+use std::io::Write;
+
+/// What to capture for each sampled frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SnapshotKind {
+    /// The agent-facing `CRState` export for the sampler's POV.
+    CRState,
+    /// The full serialized `GameState` (entities, players, RNG).
+    Debug,
+}
+
+/// A captured snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Snapshot {
+    CRState(Box<CRState>),
+    Debug(serde_json::Value),
+}
+
+/// A snapshot tagged with the tick it was taken on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampledFrame {
+    pub tick: u64,
+    pub snapshot: Snapshot,
+}
+
+/// Captures a snapshot every `every_n_ticks` ticks.
+#[derive(Debug, Clone)]
+pub struct SnapshotSampler {
+    every_n_ticks: u64,
+    kind: SnapshotKind,
+    pov: PlayerId,
+    frames: Vec<SampledFrame>,
+}
+
+impl SnapshotSampler {
+    /// Creates a sampler capturing `CRState` exports for `pov`.
+    /// An interval of 0 is treated as 1 (every tick).
+    pub fn new(every_n_ticks: u64, pov: PlayerId) -> Self {
+        Self {
+            every_n_ticks: every_n_ticks.max(1),
+            kind: SnapshotKind::CRState,
+            pov,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Sets what kind of snapshot to capture.
+    pub fn with_kind(mut self, kind: SnapshotKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Records a frame if the state's current tick falls on the interval.
+    /// Nothing is captured while the game is in headless mode.
+    pub fn observe(&mut self, state: &GameState) -> Result<()> {
+        if state.headless || !state.tick.is_multiple_of(self.every_n_ticks) {
+            return Ok(());
+        }
+
+        let snapshot = match self.kind {
+            SnapshotKind::CRState => Snapshot::CRState(Box::new(state.export_cr_state(self.pov))),
+            SnapshotKind::Debug => Snapshot::Debug(serde_json::to_value(state)?),
+        };
+        self.frames.push(SampledFrame {
+            tick: state.tick,
+            snapshot,
+        });
+        Ok(())
+    }
+
+    /// Advances the simulation by one tick and samples the result.
+    pub fn step(&mut self, state: &mut GameState, actions: &[Action]) -> Result<()> {
+        crate::step(state, actions)?;
+        self.observe(state)
+    }
+
+    /// Returns the frames captured so far.
+    pub fn frames(&self) -> &[SampledFrame] {
+        &self.frames
+    }
+
+    /// Takes ownership of the captured frames, leaving the sampler empty.
+    pub fn take_frames(&mut self) -> Vec<SampledFrame> {
+        std::mem::take(&mut self.frames)
+    }
+
+    /// Writes captured frames as JSON Lines (one frame per line).
+    pub fn write_jsonl(&self, path: &str) -> Result<()> {
+        let file = std::fs::File::create(path).map_err(|e| {
+            shared::Error::Configuration(format!("Failed to create {}: {}", path, e))
+        })?;
+        let mut writer = std::io::BufWriter::new(file);
+        for frame in &self.frames {
+            let line = serde_json::to_string(frame)?;
+            writeln!(writer, "{}", line).map_err(|e| {
+                shared::Error::Configuration(format!("Failed to write {}: {}", path, e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_every_n_ticks() {
+        let mut state = GameState::new(9);
+        let mut sampler = SnapshotSampler::new(10, PlayerId::Player1);
+
+        for _ in 0..35 {
+            sampler.step(&mut state, &[]).unwrap();
+        }
+
+        let ticks: Vec<u64> = sampler.frames().iter().map(|f| f.tick).collect();
+        assert_eq!(ticks, vec![10, 20, 30]);
+        assert!(matches!(sampler.frames()[0].snapshot, Snapshot::CRState(_)));
+    }
+
+    #[test]
+    fn test_debug_snapshots() {
+        let mut state = GameState::new(9);
+        let mut sampler = SnapshotSampler::new(1, PlayerId::Player1).with_kind(SnapshotKind::Debug);
+        sampler.step(&mut state, &[]).unwrap();
+
+        match &sampler.frames()[0].snapshot {
+            Snapshot::Debug(value) => assert_eq!(value["tick"], 1),
+            other => panic!("unexpected snapshot {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Scripted scenario triggers: conditions checked every tick that fire
+//! effects ("when the enemy King drops below 50%, spawn a Giant", "at tick
+//! 500, give Player2 +3 elixir").
+//!
+//! Triggers live on the state (and so in its JSON), are checked in the order
+//! they were added at the start of each step's systems, and only read the
+//! state, so a scenario plays out the same way every run.
+
+use crate::entities::EntityKind;
+use crate::events::GameEvent;
+use crate::state::{GameState, TowerType, DEFAULT_CARD_LEVEL};
+use serde::{Deserialize, Serialize};
+use shared::{Error, PlayerId, Position, Result};
+
+/// A condition and the effects it fires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub when: Condition,
+    pub then: Vec<Effect>,
+    /// Fire on every tick the condition holds rather than just the first.
+    #[serde(default)]
+    pub repeat: bool,
+    /// Set once a non-repeating trigger has fired.
+    #[serde(default)]
+    pub fired: bool,
+}
+
+impl Trigger {
+    /// A trigger that fires `then` once, the first tick `when` holds.
+    pub fn once(when: Condition, then: Vec<Effect>) -> Self {
+        Self {
+            when,
+            then,
+            repeat: false,
+            fired: false,
+        }
+    }
+}
+
+/// What a trigger waits for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    /// The match has reached `tick`.
+    AtTick { tick: u64 },
+    /// `owner`'s `tower` is below `fraction` of its max HP (a destroyed
+    /// tower counts as 0).
+    TowerHpBelow {
+        owner: PlayerId,
+        tower: TowerType,
+        fraction: f32,
+    },
+    /// `player` has at least `amount` elixir.
+    ElixirAtLeast { player: PlayerId, amount: f32 },
+    /// `owner` has at most `count` living troops.
+    TroopsAtMost { owner: PlayerId, count: usize },
+    /// Every inner condition holds.
+    All { conditions: Vec<Condition> },
+    /// At least one inner condition holds.
+    Any { conditions: Vec<Condition> },
+}
+
+/// What a trigger does when it fires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Effect {
+    /// Spawns `card` for `owner` at `position` directly (no elixir, no
+    /// deploy delay), at `level` or [`DEFAULT_CARD_LEVEL`].
+    Spawn {
+        owner: PlayerId,
+        card: String,
+        position: Position,
+        #[serde(default)]
+        level: Option<u32>,
+    },
+    /// Adds `amount` elixir to `player`, capped at their max.
+    GrantElixir { player: PlayerId, amount: f32 },
+}
+
+impl Condition {
+    /// Whether the condition holds in `state`.
+    pub fn holds(&self, state: &GameState) -> bool {
+        match self {
+            Condition::AtTick { tick } => state.tick >= *tick,
+            Condition::TowerHpBelow { owner, tower, fraction } => {
+                let hp = state
+                    .entities
+                    .values()
+                    .find(|e| e.owner == owner.side() && e.tower_type() == Some(*tower) && e.is_alive())
+                    .map_or(0.0, |e| e.hp / e.max_hp);
+                hp < *fraction
+            }
+            Condition::ElixirAtLeast { player, amount } => {
+                state.players.get(player).is_some_and(|p| p.elixir >= *amount)
+            }
+            Condition::TroopsAtMost { owner, count } => troops(state, *owner) <= *count,
+            Condition::All { conditions } => conditions.iter().all(|c| c.holds(state)),
+            Condition::Any { conditions } => conditions.iter().any(|c| c.holds(state)),
+        }
+    }
+}
+
+/// Living troops of `owner`.
+fn troops(state: &GameState, owner: PlayerId) -> usize {
+    state
+        .entities
+        .values()
+        .filter(|e| e.owner == owner && e.is_alive() && matches!(e.kind, EntityKind::Troop(_)))
+        .count()
+}
+
+impl Effect {
+    fn apply(&self, state: &mut GameState) -> Result<()> {
+        match self {
+            Effect::Spawn {
+                owner,
+                card,
+                position,
+                level,
+            } => {
+                let card = state
+                    .get_card_by_name(card)
+                    .cloned()
+                    .ok_or_else(|| Error::Configuration(format!("Card '{}' not found", card)))?;
+                card.spawn(state, *owner, *position, level.unwrap_or(DEFAULT_CARD_LEVEL))
+            }
+            Effect::GrantElixir { player, amount } => {
+                if let Some(player) = state.players.get_mut(player) {
+                    player.elixir = (player.elixir + amount).min(player.max_elixir);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks the effect can be applied in `state` (the card exists at the
+    /// requested level).
+    fn validate(&self, state: &GameState) -> Result<()> {
+        match self {
+            Effect::Spawn { card, level, .. } => {
+                let card = state
+                    .get_card_by_name(card)
+                    .ok_or_else(|| Error::Configuration(format!("Card '{}' not found", card)))?;
+                card.get_level_stats(level.unwrap_or(DEFAULT_CARD_LEVEL)).map(|_| ())
+            }
+            Effect::GrantElixir { .. } => Ok(()),
+        }
+    }
+}
+
+impl GameState {
+    /// Adds a scripted trigger, after checking its effects against the
+    /// loaded cards.
+    pub fn add_trigger(&mut self, trigger: Trigger) -> Result<()> {
+        for effect in &trigger.then {
+            effect.validate(self)?;
+        }
+        self.triggers.push(trigger);
+        Ok(())
+    }
+}
+
+/// Fires every trigger whose condition holds, in the order they were added.
+/// Conditions are all checked before any effect is applied, so one trigger
+/// firing can't set off another in the same tick.
+pub fn update(state: &mut GameState, _dt: f32) {
+    let firing: Vec<usize> = state
+        .triggers
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| (t.repeat || !t.fired) && t.when.holds(state))
+        .map(|(i, _)| i)
+        .collect();
+
+    for index in firing {
+        state.triggers[index].fired = true;
+        let effects = state.triggers[index].then.clone();
+        for effect in &effects {
+            // Validated when the trigger was added; a spawn that still
+            // fails (cards reloaded since) just doesn't happen
+            let _ = effect.apply(state);
+        }
+        state.emit(GameEvent::TriggerFired { index });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triggers_fire_on_tick_and_tower_hp() {
+        let mut state = GameState::new(7);
+        let grant = Trigger::once(
+            Condition::AtTick { tick: 5 },
+            vec![Effect::GrantElixir {
+                player: PlayerId::Player2,
+                amount: 3.0,
+            }],
+        );
+        let spawn = Trigger::once(
+            Condition::TowerHpBelow {
+                owner: PlayerId::Player2,
+                tower: TowerType::King,
+                fraction: 0.5,
+            },
+            vec![Effect::Spawn {
+                owner: PlayerId::Player2,
+                card: "Giant".to_string(),
+                position: Position::new(24.0, 9.0),
+                level: None,
+            }],
+        );
+        state.add_trigger(grant).unwrap();
+        state.add_trigger(spawn).unwrap();
+        let unknown = Trigger::once(
+            Condition::AtTick { tick: 0 },
+            vec![Effect::Spawn {
+                owner: PlayerId::Player1,
+                card: "Nonexistent".to_string(),
+                position: Position::new(8.0, 9.0),
+                level: None,
+            }],
+        );
+        assert!(state.add_trigger(unknown).is_err());
+
+        state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 0.0;
+        for _ in 0..6 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let elixir = state.players[&PlayerId::Player2].elixir;
+        assert!((3.0..4.0).contains(&elixir), "elixir {}", elixir);
+        assert!(state.triggers[0].fired && !state.triggers[1].fired);
+        assert_eq!(troops(&state, PlayerId::Player2), 0);
+
+        // Knock the King below half: the Giant appears on the next step, once
+        let king = state
+            .entities
+            .values_mut()
+            .find(|e| e.owner == PlayerId::Player2 && e.tower_type() == Some(TowerType::King))
+            .unwrap();
+        king.hp = king.max_hp * 0.4;
+        crate::step(&mut state, &[]).unwrap();
+        assert!(state.events.contains(&GameEvent::TriggerFired { index: 1 }));
+        assert_eq!(troops(&state, PlayerId::Player2), 1);
+        crate::step(&mut state, &[]).unwrap();
+        assert!(state.events.is_empty());
+        assert_eq!(troops(&state, PlayerId::Player2), 1);
+    }
+
+    #[test]
+    fn test_triggers_round_trip_through_json() {
+        let json = r#"{
+            "when": {"kind": "all", "conditions": [
+                {"kind": "at_tick", "tick": 500},
+                {"kind": "troops_at_most", "owner": "Player1", "count": 2}
+            ]},
+            "then": [{"kind": "grant_elixir", "player": "Player2", "amount": 3.0}]
+        }"#;
+        let trigger: Trigger = serde_json::from_str(json).unwrap();
+        assert!(!trigger.repeat);
+        let back: Trigger = serde_json::from_str(&serde_json::to_string(&trigger).unwrap()).unwrap();
+        assert_eq!(back, trigger);
+    }
+}
@@ -0,0 +1,279 @@
+//! Long-run soak testing.
+//!
+//! Plays many bot-vs-bot matches with random decks and seeds and checks
+//! the state after every tick: panics, NaNs, broken invariants, and
+//! unbounded growth. Run it before rolling a new engine version out to
+//! training clusters; a clean [`SoakReport`] means nothing tripped.
+
+use crate::bot::BotMatch;
+use crate::card::Card;
+use crate::rng::Rng;
+use crate::state::GameState;
+use serde::{Deserialize, Serialize};
+use shared::{dims, PlayerId};
+use std::thread::JoinHandle;
+
+/// Failures kept per category; later ones are only counted.
+pub const MAX_RECORDED_FAILURES: usize = 100;
+
+/// How often (ticks) the serialized state size is sampled.
+const SIZE_SAMPLE_INTERVAL: u64 = 600;
+
+/// What to run.
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    /// Seeds `first_seed..first_seed + matches` are played, one match each.
+    pub first_seed: u64,
+    pub matches: u64,
+    /// Tick limit per match (matches that end sooner stop early).
+    pub max_ticks_per_match: u64,
+    /// Worker threads the matches are spread over.
+    pub threads: usize,
+    /// Card pool decks are drawn from. `None` uses the built-in cards.
+    pub cards: Option<Vec<Card>>,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            first_seed: 0,
+            matches: 100,
+            max_ticks_per_match: 180 * 60,
+            threads: 4,
+            cards: None,
+        }
+    }
+}
+
+/// One recorded problem.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoakFailure {
+    pub seed: u64,
+    pub tick: u64,
+    pub message: String,
+}
+
+/// Aggregated soak results.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SoakReport {
+    pub matches: u64,
+    pub ticks: u64,
+    /// Internal errors (caught panics). The match is abandoned after one.
+    pub panic_count: u64,
+    pub panics: Vec<SoakFailure>,
+    /// Non-finite HP, positions, velocities or elixir.
+    pub nan_count: u64,
+    pub nans: Vec<SoakFailure>,
+    /// Other broken invariants (HP over max, elixir out of range, entities
+    /// outside the arena).
+    pub violation_count: u64,
+    pub violations: Vec<SoakFailure>,
+    /// Most entities alive at once in any match.
+    pub peak_entities: usize,
+    /// Largest serialized state seen (bytes, sampled).
+    pub peak_state_bytes: usize,
+}
+
+impl SoakReport {
+    /// True if nothing went wrong.
+    pub fn is_clean(&self) -> bool {
+        self.panic_count == 0 && self.nan_count == 0 && self.violation_count == 0
+    }
+
+    /// One-line human-readable summary.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} matches, {} ticks: {} panics, {} NaNs, {} invariant violations; peak {} entities, {} state bytes",
+            self.matches,
+            self.ticks,
+            self.panic_count,
+            self.nan_count,
+            self.violation_count,
+            self.peak_entities,
+            self.peak_state_bytes
+        )
+    }
+
+    fn merge(&mut self, other: SoakReport) {
+        self.matches += other.matches;
+        self.ticks += other.ticks;
+        self.panic_count += other.panic_count;
+        self.nan_count += other.nan_count;
+        self.violation_count += other.violation_count;
+        for (mine, theirs) in [
+            (&mut self.panics, other.panics),
+            (&mut self.nans, other.nans),
+            (&mut self.violations, other.violations),
+        ] {
+            mine.extend(theirs);
+            mine.sort_by_key(|f| (f.seed, f.tick));
+            mine.truncate(MAX_RECORDED_FAILURES);
+        }
+        self.peak_entities = self.peak_entities.max(other.peak_entities);
+        self.peak_state_bytes = self.peak_state_bytes.max(other.peak_state_bytes);
+    }
+}
+
+/// Runs the soak on the calling thread (spread over `config.threads`
+/// workers). Results don't depend on the thread count.
+pub fn run_soak(config: &SoakConfig) -> SoakReport {
+    let cards = config.cards.clone().unwrap_or_else(crate::card::get_test_cards);
+    let seeds: Vec<u64> = (config.first_seed..config.first_seed + config.matches).collect();
+    let chunk = seeds.len().div_ceil(config.threads.max(1)).max(1);
+
+    let mut report = SoakReport::default();
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = seeds
+            .chunks(chunk)
+            .map(|seeds| {
+                let cards = &cards;
+                scope.spawn(move || {
+                    let mut report = SoakReport::default();
+                    for &seed in seeds {
+                        report.merge(soak_match(seed, cards, config.max_ticks_per_match));
+                    }
+                    report
+                })
+            })
+            .collect();
+        for worker in workers {
+            // Matches catch their own panics, so workers always finish
+            report.merge(worker.join().expect("soak worker panicked"));
+        }
+    });
+    report
+}
+
+/// Starts [`run_soak`] on a background thread.
+pub fn spawn_soak(config: SoakConfig) -> JoinHandle<SoakReport> {
+    std::thread::spawn(move || run_soak(&config))
+}
+
+/// Plays one bot-vs-bot match with random decks, checking every tick.
+fn soak_match(seed: u64, cards: &[Card], max_ticks: u64) -> SoakReport {
+    let mut report = SoakReport {
+        matches: 1,
+        ..Default::default()
+    };
+    let mut state = GameState::new(seed);
+    state.set_headless(true);
+    if let Err(e) = state.load_cards(cards.to_vec()) {
+        record(&mut report.violations, &mut report.violation_count, seed, 0, e.to_string());
+        return report;
+    }
+
+    // Decks come from their own stream so they don't perturb the match RNG
+    let mut deck_rng = Rng::new(seed ^ 0x5eed_dec4);
+    let mut names: Vec<String> = cards.iter().map(|c| c.name.clone()).collect();
+    if names.is_empty() {
+        return report;
+    }
+    for player in [PlayerId::Player1, PlayerId::Player2] {
+        deck_rng.shuffle(&mut names);
+        let deck = names.iter().cycle().take(dims::DECK_SIZE).cloned().collect();
+        if let Err(e) = state.set_player_deck(player, deck) {
+            record(&mut report.violations, &mut report.violation_count, seed, 0, e.to_string());
+            return report;
+        }
+    }
+
+    let mut game = BotMatch::new(state, seed);
+    while game.state.tick < max_ticks && !game.state.is_match_over() {
+        let tick = game.state.tick;
+        let result = crate::catch_internal(|| format!("soak tick {}", tick), || game.step());
+        if let Err(e) = result {
+            record(&mut report.panics, &mut report.panic_count, seed, tick, e.to_string());
+            break;
+        }
+        report.ticks += 1;
+
+        let state = &game.state;
+        check_state(state, seed, &mut report);
+        report.peak_entities = report.peak_entities.max(state.entities.len());
+        if state.tick.is_multiple_of(SIZE_SAMPLE_INTERVAL) {
+            let bytes = serde_json::to_vec(state).map_or(0, |b| b.len());
+            report.peak_state_bytes = report.peak_state_bytes.max(bytes);
+        }
+    }
+    report
+}
+
+/// Records per-tick invariant failures into `report`.
+fn check_state(state: &GameState, seed: u64, report: &mut SoakReport) {
+    let tick = state.tick;
+    let width = state.arena.width as f32 * state.arena.tile_size;
+    let height = state.arena.height as f32 * state.arena.tile_size;
+
+    for (id, e) in &state.entities {
+        let values = [e.hp, e.max_hp, e.shield, e.position.x, e.position.y, e.velocity.x, e.velocity.y];
+        if values.iter().any(|v| !v.is_finite()) {
+            let message = format!("entity {} has a non-finite value: {:?}", id.as_u32(), values);
+            record(&mut report.nans, &mut report.nan_count, seed, tick, message);
+            continue;
+        }
+        if e.hp > e.max_hp + 1e-3 {
+            let message = format!("entity {} has hp {} over max {}", id.as_u32(), e.hp, e.max_hp);
+            record(&mut report.violations, &mut report.violation_count, seed, tick, message);
+        }
+        let inside = (0.0..=width).contains(&e.position.x) && (0.0..=height).contains(&e.position.y);
+        if !inside {
+            let message = format!("entity {} left the arena at {:?}", id.as_u32(), e.position);
+            record(&mut report.violations, &mut report.violation_count, seed, tick, message);
+        }
+    }
+
+    for (player_id, player) in &state.players {
+        if !player.elixir.is_finite() {
+            let message = format!("{:?} elixir is {}", player_id, player.elixir);
+            record(&mut report.nans, &mut report.nan_count, seed, tick, message);
+        } else if player.elixir < -1e-3 || player.elixir > player.max_elixir + 1e-3 {
+            let message = format!("{:?} elixir {} outside 0..={}", player_id, player.elixir, player.max_elixir);
+            record(&mut report.violations, &mut report.violation_count, seed, tick, message);
+        }
+    }
+}
+
+fn record(list: &mut Vec<SoakFailure>, count: &mut u64, seed: u64, tick: u64, message: String) {
+    *count += 1;
+    if list.len() < MAX_RECORDED_FAILURES {
+        list.push(SoakFailure { seed, tick, message });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_soak_is_clean_and_thread_independent() {
+        let config = SoakConfig {
+            first_seed: 40,
+            matches: 4,
+            max_ticks_per_match: 60 * 30,
+            threads: 1,
+            cards: None,
+        };
+        let single = run_soak(&config);
+        assert!(single.is_clean(), "{}\n{:?}", single.summary(), single.violations);
+        assert_eq!(single.matches, 4);
+        assert_eq!(single.ticks, 4 * 60 * 30);
+        assert!(single.peak_entities > 6);
+
+        let threaded = spawn_soak(SoakConfig { threads: 3, ..config }).join().unwrap();
+        assert_eq!(threaded.summary(), single.summary());
+    }
+
+    #[test]
+    fn test_invariant_checks_catch_bad_state() {
+        let mut state = GameState::new(41);
+        let tower = state.entities.values_mut().next().unwrap();
+        tower.hp = f32::NAN;
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 12.0;
+
+        let mut report = SoakReport::default();
+        check_state(&state, 41, &mut report);
+        assert_eq!(report.nan_count, 1);
+        assert_eq!(report.violation_count, 1);
+        assert!(!report.is_clean());
+    }
+}
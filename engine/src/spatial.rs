@@ -0,0 +1,203 @@
+//! Uniform-grid spatial index over entity positions.
+//!
+//! Systems that need neighbor queries (targeting, collision) rebuild the
+//! grid from the current positions and then ask for the entities near a
+//! point instead of scanning every entity. Results are candidates: each
+//! [`GridEntry`] carries the owner, position and radius at rebuild time so
+//! callers can discard most of them before looking the entity up. Queries
+//! visit cells in a fixed order, so results are deterministic, but not in
+//! ID order: callers that pick a "best" entity should break ties by ID.
+
+use crate::arena::Arena;
+use crate::entities::Entity;
+use crate::state::EntityId;
+use shared::{PlayerId, Position};
+
+/// Cell edge length in tiles.
+pub const CELL_SIZE: f32 = 2.0;
+
+/// Up to this many entities a plain scan is cheaper than walking the grid.
+pub const LINEAR_SCAN_LIMIT: usize = 48;
+
+/// An indexed entity, as of the last rebuild.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridEntry {
+    pub id: EntityId,
+    pub owner: PlayerId,
+    pub position: Position,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpatialGrid {
+    cols: i32,
+    rows: i32,
+    /// Row-major cells, one layer per player so searches for enemies never
+    /// walk through allies.
+    cells: [Vec<Vec<GridEntry>>; 2],
+    /// Entities whose center is outside the arena; every query checks them.
+    outside: Vec<GridEntry>,
+    /// Largest body radius indexed, used to pad radius queries.
+    max_radius: f32,
+}
+
+impl SpatialGrid {
+    /// Re-indexes `entities` (living ones only) over `arena`.
+    pub fn rebuild<'a>(&mut self, arena: &Arena, entities: impl IntoIterator<Item = (&'a EntityId, &'a Entity)>) {
+        let width = arena.width as f32 * arena.tile_size;
+        let height = arena.height as f32 * arena.tile_size;
+        self.cols = ((width / CELL_SIZE).ceil() as i32).max(1);
+        self.rows = ((height / CELL_SIZE).ceil() as i32).max(1);
+        for layer in &mut self.cells {
+            layer.resize((self.cols * self.rows) as usize, Vec::new());
+            layer.iter_mut().for_each(Vec::clear);
+        }
+        self.outside.clear();
+        self.max_radius = 0.0;
+
+        for (id, entity) in entities {
+            if !entity.is_alive() {
+                continue;
+            }
+            self.max_radius = self.max_radius.max(entity.radius());
+            let entry = GridEntry {
+                id: *id,
+                owner: entity.owner,
+                position: entity.position,
+                radius: entity.radius(),
+            };
+            let p = &entity.position;
+            if (0.0..width).contains(&p.x) && (0.0..height).contains(&p.y) {
+                let (cx, cy) = self.cell_of(p);
+                self.cells[layer(entity.owner)][(cy * self.cols + cx) as usize].push(entry);
+            } else {
+                self.outside.push(entry);
+            }
+        }
+    }
+
+    /// Entities (of `owner`'s team, or everyone's for `None`) whose body may
+    /// overlap the circle of `radius` around `center`.
+    pub fn near(&self, center: &Position, radius: f32, owner: Option<PlayerId>) -> impl Iterator<Item = &GridEntry> {
+        let reach = radius + self.max_radius;
+        let (x0, y0) = self.cell_of(&Position::new(center.x - reach, center.y - reach));
+        let (x1, y1) = self.cell_of(&Position::new(center.x + reach, center.y + reach));
+        let cols = self.cols;
+        let cells = (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (y * cols + x) as usize));
+        let layers = self.layers(owner);
+        let inside = cells.flat_map(move |i| layers.iter().flat_map(move |layer| layer[i].iter()));
+        self.outside_of(owner).chain(inside)
+    }
+
+    /// Square rings of cells of growing size around `center`, nearest
+    /// first, holding the entities of `owner`'s team (everyone's for `None`). Each
+    /// ring comes with the distance every entity in later rings is known to
+    /// be beyond; entities outside the arena come with the first.
+    pub fn rings(
+        &self,
+        center: &Position,
+        owner: Option<PlayerId>,
+    ) -> impl Iterator<Item = (f32, impl Iterator<Item = &GridEntry>)> {
+        let (cx, cy) = self.cell_of(center);
+        let max_ring = if self.cols == 0 {
+            -1
+        } else {
+            cx.max(self.cols - 1 - cx).max(cy).max(self.rows - 1 - cy)
+        };
+        let layers = self.layers(owner);
+        (0..=max_ring).map(move |k| {
+            let outside = self.outside_of(owner).filter(move |_| k == 0);
+            let cells = self
+                .ring_cells(cx, cy, k)
+                .flat_map(move |i| layers.iter().flat_map(move |layer| layer[i].iter()));
+            // The center lies somewhere in its own cell, so anything past
+            // ring k is at least k cells away
+            (k as f32 * CELL_SIZE, outside.chain(cells))
+        })
+    }
+
+    /// Cell containing `p`, clamped to the grid.
+    fn cell_of(&self, p: &Position) -> (i32, i32) {
+        let x = ((p.x / CELL_SIZE).floor() as i32).clamp(0, self.cols - 1);
+        let y = ((p.y / CELL_SIZE).floor() as i32).clamp(0, self.rows - 1);
+        (x, y)
+    }
+
+    /// Indices of the in-grid cells at Chebyshev distance exactly `k` from
+    /// `(cx, cy)`.
+    fn ring_cells(&self, cx: i32, cy: i32, k: i32) -> impl Iterator<Item = usize> {
+        let (cols, rows) = (self.cols, self.rows);
+        let xs = (cx - k).max(0)..=(cx + k).min(cols - 1);
+        let inner_ys = (cy - k + 1).max(0)..=(cy + k - 1).min(rows - 1);
+        let edge_rows = [cy - k, cy + k].into_iter().take(if k == 0 { 1 } else { 2 });
+        let edge_cols = [cx - k, cx + k].into_iter().take(if k == 0 { 0 } else { 2 });
+        let top_bottom = edge_rows
+            .filter(move |y| (0..rows).contains(y))
+            .flat_map(move |y| xs.clone().map(move |x| (x, y)));
+        let sides = edge_cols
+            .filter(move |x| (0..cols).contains(x))
+            .flat_map(move |x| inner_ys.clone().map(move |y| (x, y)));
+        top_bottom.chain(sides).map(move |(x, y)| (y * cols + x) as usize)
+    }
+
+    fn layers(&self, owner: Option<PlayerId>) -> &[Vec<Vec<GridEntry>>] {
+        match owner {
+            Some(owner) => std::slice::from_ref(&self.cells[layer(owner)]),
+            None => &self.cells,
+        }
+    }
+
+    fn outside_of(&self, owner: Option<PlayerId>) -> impl Iterator<Item = &GridEntry> {
+        self.outside.iter().filter(move |e| owner.is_none_or(|owner| e.owner.is_ally_of(owner)))
+    }
+}
+
+/// One layer per team, so teammates share one.
+fn layer(owner: PlayerId) -> usize {
+    match owner {
+        PlayerId::Player1 | PlayerId::Player3 => 0,
+        PlayerId::Player2 | PlayerId::Player4 => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::GameState;
+    use shared::PlayerId;
+
+    #[test]
+    fn test_grid_queries_match_a_full_scan() {
+        let mut state = GameState::new(42);
+        let knight = state.get_card_by_name("Knight").unwrap().clone();
+        for i in 0..60 {
+            let position = Position::new(1.0 + (i * 7 % 30) as f32, 0.5 + (i * 5 % 17) as f32);
+            knight.spawn(&mut state, PlayerId::Player1, position, 11).unwrap();
+        }
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(&state.arena, &state.entities);
+
+        let center = Position::new(12.3, 7.7);
+        let radius = 3.0;
+        let expected: Vec<EntityId> = state
+            .entities
+            .iter()
+            .filter(|(_, e)| e.position.distance_to(&center) - e.radius() <= radius)
+            .map(|(id, _)| *id)
+            .collect();
+        let candidates: Vec<EntityId> = grid.near(&center, radius, None).map(|e| e.id).collect();
+        assert!(expected.iter().all(|id| candidates.contains(id)));
+        assert!(candidates.len() < state.entities.len());
+
+        // Rings cover every entity exactly once, with honest lower bounds
+        let mut seen = Vec::new();
+        for (beyond, ring) in grid.rings(&center, None) {
+            seen.extend(ring.map(|e| e.id));
+            for (id, e) in &state.entities {
+                assert!(seen.contains(id) || e.position.distance_to(&center) >= beyond);
+            }
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, state.entities.keys().copied().collect::<Vec<_>>());
+    }
+}
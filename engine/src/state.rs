@@ -1,11 +1,19 @@
 //! Game state management and serialization.
-use crate::action::Action;
-use crate::card::Card;
-use crate::entities::Entity;
+use crate::action::{Action, AgentAction};
+use crate::arena::{Arena, PlacementGrid, TileType};
+use crate::card::{Card, PendingDeploy, SpeedTiers};
+use crate::card_db::{CardDb, CardKey};
+use crate::entities::{DamageFilter, Entity, EntityKind, RetargetPolicies, StatusKind, TargetType, TowerData, Transport};
+use crate::events::GameEvent;
 use crate::rng::Rng;
+use crate::scenario::Trigger;
+use crate::spatial::SpatialGrid;
+use crate::systems::pathfinding::{FlowFields, PathfindingMode};
+use crate::tower::Tower;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use shared::{PlayerId, Result, CRState, Tower as CRTower, Unit as CRUnit, LegalMasks};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use shared::{dims, PlayerId, Result, CardCycle, CRState, Crown as CRCrown, HandCard, Tower as CRTower, Unit as CRUnit, LegalMasks, MatchMetadata, MatchResult as CRMatchResult, PendingSpell as CRSpell};
 
 /// The complete state of a game simulation.
 ///
@@ -21,14 +29,18 @@ pub struct GameState {
     /// Deterministic RNG for all randomness.
     pub rng: Rng,
 
+    /// Arena geometry (tiles and tower placements).
+    pub arena: Arena,
+
     /// All entities currently in the game (troops, towers, projectiles).
-    pub entities: HashMap<EntityId, Entity>,
+    /// Ordered by ID so systems visit entities in the same order every run.
+    pub entities: BTreeMap<EntityId, Entity>,
 
     /// Player-specific state (elixir, deck, etc.).
-    pub players: HashMap<PlayerId, PlayerState>,
+    pub players: BTreeMap<PlayerId, PlayerState>,
 
     /// Available cards (loaded at game start, indexed by card name).
-    cards: HashMap<String, Card>,
+    cards: BTreeMap<String, Card>,
 
     /// Next entity ID to assign.
     next_entity_id: u32,
@@ -38,16 +50,177 @@ pub struct GameState {
 
     /// Maximum match duration (in seconds).
     pub max_match_time: f32,
+
+    /// Headless throughput mode: suppresses per-tick logging, exports,
+    /// [`GameEvent`]s and damage history for maximum-speed playouts (MCTS
+    /// rollouts, value estimation). Crowns and card plays are still
+    /// recorded, since match results and rewards are scored from them.
+    #[serde(default)]
+    pub headless: bool,
+
+    /// Strict mode: reject decks (and direct card plays) that depend on
+    /// mechanics this build does not implement.
+    #[serde(default)]
+    pub strict: bool,
+
+    /// Target keep/drop rules per entity kind.
+    #[serde(default)]
+    pub retarget_policies: RetargetPolicies,
+
+    /// Melee attackers cannot engage targets on the other side of the
+    /// river (ranged attackers can always shoot across).
+    #[serde(default = "default_true")]
+    pub river_blocks_melee: bool,
+
+    /// Tiles per second for each `movement_speed` tier, for cards without
+    /// an explicit speed value.
+    #[serde(default)]
+    pub speed_tiers: SpeedTiers,
+
+    /// Card plays must land in the player's deploy zone (see
+    /// [`GameState::check_placement`]).
+    #[serde(default = "default_true")]
+    pub deploy_zones: bool,
+
+    /// Include the opponent's hand and cycle in exported state, for
+    /// full-information experiments.
+    #[serde(default)]
+    pub export_enemy_cycle: bool,
+
+    /// Grid agents pick placement tiles on (`step_with_action`, exported
+    /// `LegalMasks`).
+    #[serde(default)]
+    pub placement_grid: PlacementGrid,
+
+    /// Engine ticks each agent step runs (`step_with_action` and friends).
+    /// Left out of serialized state at its default, so it doesn't change
+    /// state hashes of matches that never touch it.
+    #[serde(default = "default_frame_skip", skip_serializing_if = "is_default_frame_skip")]
+    pub frame_skip: u32,
+
+    /// How ground units plan routes (per-unit A* or shared flow fields).
+    #[serde(default)]
+    pub pathfinding: PathfindingMode,
+
+    /// Rule tweaks for event modes (all off in a standard match).
+    #[serde(default, skip_serializing_if = "EventModifiers::is_standard")]
+    pub event_modifiers: EventModifiers,
+
+    /// Every card played this match, in play order.
+    #[serde(default)]
+    pub card_plays: Vec<CardPlay>,
+
+    /// Every crown taken this match, in the order the towers fell.
+    #[serde(default)]
+    pub crowns: Vec<Crown>,
+
+    /// Troop cards still travelling to their deploy point.
+    #[serde(default)]
+    pub pending_deploys: Vec<PendingDeploy>,
+
+    /// Default delay (seconds) between casting a damage spell and its launch,
+    /// for cards that don't set their own `cast_delay`.
+    #[serde(default)]
+    pub spell_cast_delay: f32,
+
+    /// Integer HP mode: HP only ever changes by whole points, as in the
+    /// live game. Fractional damage and healing carry over per entity.
+    #[serde(default)]
+    pub integer_hp: bool,
+
+    /// Card set this match was configured with (see `set_patch_id`).
+    #[serde(default = "default_patch_id")]
+    pub patch_id: String,
+
+    /// Game mode name, recorded in exports and replays.
+    #[serde(default = "default_game_mode")]
+    pub game_mode: String,
+
+    /// Most card plays one player may submit in a single tick (`None` for
+    /// no limit).
+    #[serde(default)]
+    pub max_actions_per_tick: Option<usize>,
+
+    /// Wall-clock budget for a single `step` (`None` for no limit). See
+    /// [`GameState::set_step_budget`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_budget: Option<Duration>,
+
+    /// Set when a step overran `step_budget`; the match is over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<TimeoutTermination>,
+
+    /// Scripted scenario triggers, checked every tick (see
+    /// [`GameState::add_trigger`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<Trigger>,
+
+    /// Events emitted during the most recent step.
+    #[serde(default)]
+    pub events: Vec<GameEvent>,
+
+    /// Neighbor index over entity positions, as of the last
+    /// [`GameState::rebuild_spatial_index`]. Derived data, never serialized.
+    #[serde(skip)]
+    pub spatial: SpatialGrid,
+
+    /// Flow fields kept between ticks in [`PathfindingMode::FlowField`].
+    /// Derived data, never serialized.
+    #[serde(skip)]
+    pub flow_fields: FlowFields,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_frame_skip() -> u32 {
+    DEFAULT_FRAME_SKIP
+}
+
+fn is_default_frame_skip(ticks: &u32) -> bool {
+    *ticks == DEFAULT_FRAME_SKIP
+}
+
+fn default_patch_id() -> String {
+    DEFAULT_PATCH_ID.to_string()
+}
+
+fn default_game_mode() -> String {
+    DEFAULT_GAME_MODE.to_string()
 }
 
-fn extract_entity_info(e: &Entity) -> Option<(PlayerId, (f32, f32), (f32, f32))> {
-    // Extract owner, position (x, y), and velocity (vx, vy) from Entity
+/// 64-bit FNV-1a hash, stable across runs and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Hash identifying a deck by its cards, independent of their order.
+/// An empty deck hashes to 0.
+pub fn deck_hash(deck: &[String]) -> u64 {
+    if deck.is_empty() {
+        return 0;
+    }
+    let mut cards: Vec<&str> = deck.iter().map(String::as_str).collect();
+    cards.sort_unstable();
+    fnv1a(cards.join("\n").as_bytes())
+}
+
+/// Owner, position (x, y), previous-tick position, and velocity (vx, vy)
+/// of an exported unit.
+type EntityInfo = (PlayerId, (f32, f32), (f32, f32), (f32, f32));
+
+fn extract_entity_info(e: &Entity) -> Option<EntityInfo> {
+    // Extract owner, position (x, y), previous position, and velocity (vx, vy) from Entity
     // Only include movable troop entities (not towers or projectiles)
     match &e.kind {
-        crate::entities::EntityKind::Troop(_) => {
+        EntityKind::Troop(_) => {
             Some((
                 e.owner,
                 (e.position.x, e.position.y),
+                (e.prev_position.x, e.prev_position.y),
                 (e.velocity.x, e.velocity.y),
             ))
         }
@@ -58,34 +231,404 @@ fn extract_entity_info(e: &Entity) -> Option<(PlayerId, (f32, f32), (f32, f32))>
 impl GameState {
     /// Creates a new game state with the given RNG seed.
     pub fn new(seed: u64) -> Self {
-        let mut players = HashMap::new();
+        let mut players = BTreeMap::new();
         players.insert(PlayerId::Player1, PlayerState::new(PlayerId::Player1));
         players.insert(PlayerId::Player2, PlayerState::new(PlayerId::Player2));
 
         // Load test cards
-        let mut cards = HashMap::new();
+        let mut cards = BTreeMap::new();
         for card in crate::card::get_test_cards() {
             cards.insert(card.name.clone(), card);
         }
 
-        Self {
+        let mut state = Self {
             tick: 0,
             rng: Rng::new(seed),
-            entities: HashMap::new(),
+            arena: Arena::new(),
+            entities: BTreeMap::new(),
             players,
             cards,
             next_entity_id: 1,
             match_time: 0.0,
-            max_match_time: 180.0, // 3 minutes (will be configurable)
+            max_match_time: STANDARD_MATCH_DURATION,
+            headless: false,
+            strict: false,
+            retarget_policies: RetargetPolicies::default(),
+            river_blocks_melee: true,
+            deploy_zones: true,
+            export_enemy_cycle: false,
+            placement_grid: PlacementGrid::default(),
+            frame_skip: DEFAULT_FRAME_SKIP,
+            speed_tiers: SpeedTiers::default(),
+            pathfinding: PathfindingMode::default(),
+            event_modifiers: EventModifiers::default(),
+            card_plays: Vec::new(),
+            crowns: Vec::new(),
+            pending_deploys: Vec::new(),
+            spell_cast_delay: 0.0,
+            integer_hp: false,
+            patch_id: default_patch_id(),
+            game_mode: default_game_mode(),
+            max_actions_per_tick: None,
+            step_budget: None,
+            timeout: None,
+            triggers: Vec::new(),
+            events: Vec::new(),
+            spatial: SpatialGrid::default(),
+            flow_fields: FlowFields::default(),
+        };
+
+        state
+            .spawn_towers(&crate::tower::get_default_towers(), TOWER_LEVEL)
+            .expect("default towers must define the default tower level");
+        state
+    }
+
+    /// Creates a game state for a match in `mode` (see
+    /// [`apply_game_mode`](Self::apply_game_mode)).
+    pub fn new_with_mode(seed: u64, mode: GameMode) -> Self {
+        let mut state = Self::new(seed);
+        state.apply_game_mode(mode);
+        state
+    }
+
+    /// Switches the match to `mode`'s rules: elixir rate for every player,
+    /// match length and event modifiers, and records its name. Meant for
+    /// match setup; a mode applied mid-match keeps the elixir and time
+    /// already on the clock.
+    pub fn apply_game_mode(&mut self, mode: GameMode) {
+        for player in self.players.values_mut() {
+            player.elixir_regen_rate = mode.elixir_regen_rate();
+        }
+        self.max_match_time = mode.match_duration();
+        self.event_modifiers = EventModifiers {
+            cycle_cheapest_card: self.event_modifiers.cycle_cheapest_card,
+            ..mode.event_modifiers()
+        };
+        self.set_game_mode(mode.name());
+    }
+
+    /// Creates a 2v2 match: Player3 joins Player1's team and Player4
+    /// Player2's. Teammates defend the same towers, so they share tower HP
+    /// and crowns, but each has their own elixir, deck and hand.
+    pub fn new_2v2(seed: u64) -> Self {
+        let mut state = Self::new(seed);
+        for id in [PlayerId::Player3, PlayerId::Player4] {
+            let lead = &state.players[&id.side()];
+            let mut player = PlayerState::new(id);
+            player.tower_hp = lead.tower_hp.clone();
+            player.tower_max_hp = lead.tower_max_hp.clone();
+            state.players.insert(id, player);
+        }
+        state
+    }
+
+    /// Whether this is a 2v2 match (see [`new_2v2`](Self::new_2v2)).
+    pub fn is_2v2(&self) -> bool {
+        self.players.keys().any(|id| *id != id.side())
+    }
+
+    /// Spawns King and Princess towers for both teams as real entities,
+    /// owned by each team's lead (Player1 and Player2).
+    ///
+    /// `towers` must contain a "King Tower" config and a "Tower Princess"
+    /// config. Any existing tower entities are replaced, and each player's
+    /// `tower_hp` table is reset to their team's new towers' HP.
+    pub fn spawn_towers(&mut self, towers: &[Tower], level: u32) -> Result<()> {
+        let find = |name: &str| {
+            towers.iter().find(|t| t.name == name).ok_or_else(|| {
+                shared::Error::Configuration(format!("Tower config '{}' not found", name))
+            })
+        };
+        let king = find("King Tower")?;
+        let princess = find("Tower Princess")?;
+
+        self.entities
+            .retain(|_, e| !matches!(e.kind, EntityKind::Tower(_)));
+
+        for player_id in [PlayerId::Player1, PlayerId::Player2] {
+            for tower_type in [TowerType::King, TowerType::LeftPrincess, TowerType::RightPrincess] {
+                let config = match tower_type {
+                    TowerType::King => king,
+                    TowerType::LeftPrincess | TowerType::RightPrincess => princess,
+                };
+                let stats = config.get_level_stats(level)?;
+                let data = TowerData {
+                    tower_type,
+                    base_hp: stats.hp.unwrap_or(0.0),
+                    damage: stats.damage.unwrap_or(0.0),
+                    range: config.range.unwrap_or(7.0),
+                    attack_speed: config.attack_speed.unwrap_or(1.0),
+                    first_hit_speed: config.first_hit_speed.unwrap_or(0.0),
+                    target_type: TargetType::from_targets(config.targets.as_deref()),
+                };
+
+                for (_, player) in self.players.iter_mut().filter(|(id, _)| id.is_ally_of(player_id)) {
+                    player.tower_hp.insert(tower_type, data.base_hp);
+                    player.tower_max_hp.insert(tower_type, data.base_hp);
+                }
+
+                let position = self.arena.tower_position(player_id, tower_type);
+                self.add_entity(Entity::new(player_id, position, EntityKind::Tower(data)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies tower entity HP into each player's `tower_hp` table (both
+    /// teammates' in 2v2). Towers that no longer exist as entities are
+    /// recorded as destroyed.
+    pub fn sync_tower_hp(&mut self) {
+        for player in self.players.values_mut() {
+            for hp in player.tower_hp.values_mut() {
+                *hp = 0.0;
+            }
+        }
+        for entity in self.entities.values() {
+            if let Some(tower_type) = entity.tower_type() {
+                for (_, player) in self.players.iter_mut().filter(|(id, _)| id.is_ally_of(entity.owner)) {
+                    player.tower_hp.insert(tower_type, entity.hp);
+                }
+            }
+        }
+    }
+
+    /// Deterministic 64-bit hash of the full serialized state (FNV-1a).
+    ///
+    /// Identical simulations produce identical hashes regardless of thread,
+    /// process, or platform, so this is the value to compare when checking
+    /// replays and batch runs for divergence.
+    pub fn state_hash(&self) -> u64 {
+        let bytes = serde_json::to_vec(self).expect("GameState is always serializable");
+        fnv1a(&bytes)
+    }
+
+    /// Records which card set the match uses (e.g. "v2025_current").
+    pub fn set_patch_id(&mut self, patch_id: impl Into<String>) {
+        self.patch_id = patch_id.into();
+    }
+
+    /// Records the game mode name.
+    pub fn set_game_mode(&mut self, game_mode: impl Into<String>) {
+        self.game_mode = game_mode.into();
+    }
+
+    /// Seed, patch, mode and deck hashes, from `pov`'s side.
+    pub fn match_metadata(&self, pov: PlayerId) -> MatchMetadata {
+        let deck = |player: PlayerId| self.players.get(&player).map_or(0, |p| deck_hash(&p.deck_names()));
+        MatchMetadata {
+            seed: self.rng.seed(),
+            patch_id: self.patch_id.clone(),
+            game_mode: self.game_mode.clone(),
+            ally_deck_hash: deck(pov),
+            enemy_deck_hash: deck(pov.opponent()),
+        }
+    }
+
+    /// Enables or disables headless throughput mode. Entering it drops the
+    /// events and damage history it won't keep up to date, so they start
+    /// afresh if it's turned off again.
+    pub fn set_headless(&mut self, headless: bool) {
+        if headless && !self.headless {
+            self.events.clear();
+            for entity in self.entities.values_mut() {
+                entity.recent_damage = Default::default();
+            }
+        }
+        self.headless = headless;
+    }
+
+    /// Records `event` for this step, unless running headless.
+    pub(crate) fn emit(&mut self, event: GameEvent) {
+        if !self.headless {
+            self.events.push(event);
+        }
+    }
+
+    /// Sets the default spell cast delay (seconds, clamped at 0).
+    pub fn set_spell_cast_delay(&mut self, delay: f32) {
+        self.spell_cast_delay = delay.max(0.0);
+    }
+
+    /// Enables or disables integer HP mode. Existing entities keep their
+    /// current HP; entities added afterwards start with whole-point HP.
+    pub fn set_integer_hp(&mut self, enabled: bool) {
+        self.integer_hp = enabled;
+    }
+
+    /// Enables or disables the river line-of-sight rule for melee attacks.
+    pub fn set_river_blocks_melee(&mut self, enabled: bool) {
+        self.river_blocks_melee = enabled;
+    }
+
+    /// Sets the tiles-per-second speed of each movement tier. Only affects
+    /// troops spawned afterwards from cards without a `movement_speed_value`.
+    pub fn set_speed_tiers(&mut self, tiers: SpeedTiers) {
+        self.speed_tiers = tiers;
+    }
+
+    /// Selects how ground units plan routes. Flow fields trade a little
+    /// path quality for much cheaper pathing in crowded matches.
+    pub fn set_pathfinding_mode(&mut self, mode: PathfindingMode) {
+        self.pathfinding = mode;
+    }
+
+    /// Limits how much wall-clock time one `step` may take (`None` for no
+    /// limit). A step that runs past it stops after the system it was in,
+    /// records a [`TimeoutTermination`] and ends the match, so a runaway
+    /// scripted effect can't stall a worker. Timing depends on the machine,
+    /// so a match that times out is not reproducible.
+    pub fn set_step_budget(&mut self, budget: Option<Duration>) {
+        self.step_budget = budget;
+    }
+
+    /// Re-indexes current entity positions for neighbor queries.
+    pub fn rebuild_spatial_index(&mut self) {
+        self.spatial.rebuild(&self.arena, &self.entities);
+    }
+
+    /// Returns true if `attacker` can hit `target` from where it stands:
+    /// the target is in range and, for melee attackers, not across the river.
+    pub fn can_engage(&self, attacker: &Entity, target: &Entity) -> bool {
+        if !attacker.in_attack_range(target) {
+            return false;
+        }
+        attacker.is_ranged()
+            || !self.river_blocks_melee
+            || !self.arena.line_crosses_river(&attacker.position, &target.position)
+    }
+
+    /// Enables or disables strict mode (see [`GameState::check_supported`]).
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// In strict mode, errors if any of `cards` relies on an unsupported
+    /// mechanic. Always succeeds when strict mode is off.
+    pub fn check_supported(&self, cards: &[String]) -> Result<()> {
+        if !self.strict {
+            return Ok(());
+        }
+        let unsupported = self.unsupported_features_in_deck(cards);
+        if unsupported.is_empty() {
+            return Ok(());
         }
+        let details: Vec<String> = unsupported
+            .iter()
+            .map(|(card, missing)| format!("{} ({})", card, missing.join(", ")))
+            .collect();
+        Err(shared::Error::Configuration(format!(
+            "Strict mode: unsupported mechanics in {}",
+            details.join("; ")
+        )))
     }
 
-    /// Loads cards from a JSON file.
-    pub fn load_cards(&mut self, cards: Vec<Card>) {
-        self.cards.clear();
+    /// Errors unless `player` may place `card` at `position`. Spells can land
+    /// anywhere in the arena and `deploy_anywhere` cards on any grass or
+    /// bridge tile; other troops and buildings need the player's deploy
+    /// zone (see [`Arena::in_deploy_zone`]), which grows into a lane once
+    /// the enemy princess tower there falls. With deploy zones off, only the
+    /// arena bounds are checked.
+    pub fn check_placement(&self, player: PlayerId, card: &Card, position: shared::Position) -> Result<()> {
+        if self.placement_allowed(player, Some(card), &position, &self.open_pockets(player)) {
+            return Ok(());
+        }
+        Err(shared::Error::InvalidAction(format!(
+            "{} can't be placed at ({:.1}, {:.1}) by {:?}",
+            card.name, position.x, position.y, player
+        )))
+    }
+
+    /// The rule behind [`check_placement`](Self::check_placement), with the
+    /// open pockets passed in so a whole grid can share them. `None` stands
+    /// for an ordinary troop or building.
+    fn placement_allowed(
+        &self,
+        player: PlayerId,
+        card: Option<&Card>,
+        position: &shared::Position,
+        open_pockets: &[usize],
+    ) -> bool {
+        if !self.arena.is_in_bounds(position) {
+            false
+        } else if !self.deploy_zones || card.is_some_and(|c| c.type_name == "spell") {
+            true
+        } else if card.is_some_and(|c| c.deploy_anywhere) {
+            let (x, y) = self.arena.world_to_tile(position);
+            matches!(self.arena.get_tile(x, y), Some(TileType::Grass | TileType::Bridge))
+        } else {
+            self.arena.in_deploy_zone(player, position, open_pockets)
+        }
+    }
+
+    /// One flag per placement tile, as `player` sees the grid: whether
+    /// `card` (or, for `None`, an ordinary troop) may be placed there.
+    fn placement_tiles(&self, player: PlayerId, card: Option<&Card>) -> Vec<bool> {
+        let pockets = self.open_pockets(player);
+        (0..self.placement_grid.len())
+            .map(|tile| {
+                self.arena
+                    .pov_tile_to_world(player, tile, self.placement_grid)
+                    .is_some_and(|pos| self.placement_allowed(player, card, &pos, &pockets))
+            })
+            .collect()
+    }
+
+    /// Lanes where `player`'s deploy zone reaches across the river: those
+    /// whose enemy princess tower has been destroyed.
+    fn open_pockets(&self, player: PlayerId) -> Vec<usize> {
+        let enemy = player.opponent();
+        let Some(enemy_state) = self.players.get(&enemy) else {
+            return Vec::new();
+        };
+        [TowerType::LeftPrincess, TowerType::RightPrincess]
+            .into_iter()
+            .filter(|tt| enemy_state.tower_hp.get(tt).copied().unwrap_or(0.0) <= 0.0)
+            .map(|tt| self.arena.lane_of(&self.arena.tower_position(enemy, tt)))
+            .collect()
+    }
+
+    /// Sets the placement grid resolution for tile-index actions and the
+    /// exported tile mask. Errors if it is empty or finer than the arena.
+    pub fn set_placement_grid(&mut self, width: usize, height: usize) -> Result<()> {
+        self.placement_grid = PlacementGrid::new(width, height, &self.arena)?;
+        Ok(())
+    }
+
+    /// Enables or disables deploy zone checks on card plays (the arena
+    /// bounds are always enforced).
+    pub fn set_deploy_zones(&mut self, enabled: bool) {
+        self.deploy_zones = enabled;
+    }
+
+    /// Reveals (or hides) the opponent's hand and cycle in exported state.
+    pub fn set_export_enemy_cycle(&mut self, enabled: bool) {
+        self.export_enemy_cycle = enabled;
+    }
+
+    /// Turns event-mode rule tweaks on or off.
+    pub fn set_event_modifiers(&mut self, modifiers: EventModifiers) {
+        self.event_modifiers = modifiers;
+    }
+
+    /// Sets how many engine ticks each agent step runs (at least one).
+    pub fn set_frame_skip(&mut self, ticks: u32) {
+        self.frame_skip = ticks.max(1);
+    }
+
+    /// Replaces the available cards. Cards are known by name, so two of
+    /// the same name are an error (and the loaded cards are kept); keep sets
+    /// with overlapping names in a [`CardDb`] instead.
+    pub fn load_cards(&mut self, cards: Vec<Card>) -> Result<()> {
+        let mut loaded = BTreeMap::new();
         for card in cards {
-            self.cards.insert(card.name.clone(), card);
+            let name = card.name.clone();
+            if loaded.insert(name.clone(), card).is_some() {
+                return Err(shared::Error::Configuration(format!("Card '{}' appears more than once", name)));
+            }
         }
+        self.cards = loaded;
+        Ok(())
     }
 
     /// Gets a card by name.
@@ -93,25 +636,103 @@ impl GameState {
         self.cards.get(name)
     }
 
-    /// Initializes a player's deck with the given card names.
+    /// Loaded cards in name order, as written (see [`GameState::cards`] for
+    /// what the engine makes of them).
+    pub fn loaded_cards(&self) -> impl Iterator<Item = &Card> {
+        self.cards.values()
+    }
+
+    /// Lists unsupported features for each card in `deck` (see
+    /// [`crate::capabilities`]). Cards that are fully supported or unknown
+    /// are omitted.
+    pub fn unsupported_features_in_deck(&self, deck: &[String]) -> Vec<(String, Vec<String>)> {
+        let caps = crate::capabilities::capabilities();
+        let mut report: Vec<(String, Vec<String>)> = Vec::new();
+        for name in deck {
+            if report.iter().any(|(n, _)| n == name) {
+                continue;
+            }
+            if let Some(card) = self.cards.get(name) {
+                let missing = caps.unsupported_features(card);
+                if !missing.is_empty() {
+                    report.push((name.clone(), missing));
+                }
+            }
+        }
+        report
+    }
+
+    /// Initializes a player's deck with the given cards: [`DeckCard`]s, or
+    /// bare names for cards at [`DEFAULT_CARD_LEVEL`]. Hand plays use each
+    /// card's deck level.
     /// The deck will be shuffled deterministically using the game's RNG.
-    pub fn set_player_deck(&mut self, player_id: PlayerId, deck: Vec<String>) -> Result<()> {
+    /// In strict mode, decks using unsupported mechanics are rejected.
+    pub fn set_player_deck<C: Into<DeckCard>>(&mut self, player_id: PlayerId, deck: Vec<C>) -> Result<()> {
+        let deck: Vec<DeckCard> = deck.into_iter().map(Into::into).collect();
+
+        // Validate that all cards exist at their levels
+        for entry in &deck {
+            let card = self.cards.get(&entry.name).ok_or_else(|| {
+                shared::Error::InvalidAction(format!("Card '{}' not found in available cards", entry.name))
+            })?;
+            card.get_level_stats(entry.level)?;
+        }
+
+        if deck.len() != dims::DECK_SIZE {
+            return Err(shared::Error::InvalidAction(format!(
+                "Deck must contain exactly {} cards, got {}",
+                dims::DECK_SIZE,
+                deck.len()
+            )));
+        }
+
+        let names: Vec<String> = deck.iter().map(|c| c.name.clone()).collect();
+        self.check_supported(&names)?;
+
         let player = self
             .players
             .get_mut(&player_id)
             .ok_or_else(|| shared::Error::InvalidAction("Player not found".to_string()))?;
+        player.set_deck(deck, &mut self.rng);
+        Ok(())
+    }
 
-        // Validate that all cards exist
-        for card_name in &deck {
-            if !self.cards.contains_key(card_name) {
-                return Err(shared::Error::InvalidAction(format!(
-                    "Card '{}' not found in available cards",
-                    card_name
-                )));
+    /// Loads the cards `decks` reference from `db` in place of the
+    /// available cards, then deals each player's deck (as
+    /// [`set_player_deck`](Self::set_player_deck)). References are resolved
+    /// by [`CardDb::resolve`]; the match
+    /// knows the cards by bare name. All or nothing.
+    ///
+    /// # Errors
+    /// If a reference is unknown or ambiguous, if two references pick
+    /// different cards of the same name, or if a deck is invalid.
+    pub fn set_decks_from_db(&mut self, db: &CardDb, decks: &[(PlayerId, Vec<DeckCard>)]) -> Result<()> {
+        let mut picked: BTreeMap<&str, (&CardKey, &Card)> = BTreeMap::new();
+        let mut dealt = Vec::with_capacity(decks.len());
+        for (player, deck) in decks {
+            let mut names = Vec::with_capacity(deck.len());
+            for entry in deck {
+                let (key, card) = db.resolve(&entry.name)?;
+                match picked.insert(key.name.as_str(), (key, card)) {
+                    Some((other, _)) if other != key => {
+                        return Err(shared::Error::InvalidAction(format!(
+                            "Decks pick both {} and {}; a match can only hold one card named '{}'",
+                            other, key, key.name
+                        )));
+                    }
+                    _ => names.push(DeckCard::new(key.name.clone(), entry.level)),
+                }
             }
+            dealt.push((*player, names));
         }
 
-        player.set_deck(deck, &mut self.rng);
+        // Stage on a copy so a bad deck leaves the state untouched
+        let mut staged = self.clone();
+        staged.load_cards(picked.into_values().map(|(_, card)| card.clone()).collect())?;
+        for (player, deck) in dealt {
+            staged.set_player_deck(player, deck)?;
+        }
+        *self = staged;
         Ok(())
     }
 
@@ -120,6 +741,43 @@ impl GameState {
         action.apply(self)
     }
 
+    /// Applies a tick's actions as one group, in order: each action sees
+    /// the elixir left by the ones before it. If any action fails, or a
+    /// player exceeds `max_actions_per_tick`, none of them take effect.
+    pub fn apply_actions(&mut self, actions: &[Action]) -> Result<()> {
+        self.check_action_cap(actions)?;
+        crate::action::apply_all(self, actions)
+    }
+
+    /// Errors if [`apply_actions`](Self::apply_actions) would reject
+    /// `actions`, without applying them.
+    pub fn check_actions(&self, actions: &[Action]) -> Result<()> {
+        self.check_action_cap(actions)?;
+        crate::action::check_all(self, actions)
+    }
+
+    /// Errors if a player submits more plays than `max_actions_per_tick`.
+    fn check_action_cap(&self, actions: &[Action]) -> Result<()> {
+        let Some(cap) = self.max_actions_per_tick else {
+            return Ok(());
+        };
+        for &player in self.players.keys() {
+            let plays = actions.iter().filter(|a| a.is_play() && a.player() == player).count();
+            if plays > cap {
+                return Err(shared::Error::InvalidAction(format!(
+                    "{:?} submitted {} plays this tick (limit {})",
+                    player, plays, cap
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the per-player cap on card plays in one tick (`None` to lift it).
+    pub fn set_max_actions_per_tick(&mut self, cap: Option<usize>) {
+        self.max_actions_per_tick = cap;
+    }
+
     /// Allocates a new entity ID.
     pub fn allocate_entity_id(&mut self) -> EntityId {
         let id = self.next_entity_id;
@@ -128,21 +786,350 @@ impl GameState {
     }
 
     /// Adds an entity to the game.
-    pub fn add_entity(&mut self, entity: Entity) -> EntityId {
+    pub fn add_entity(&mut self, mut entity: Entity) -> EntityId {
+        if self.integer_hp {
+            entity.max_hp = entity.max_hp.round();
+            entity.hp = entity.hp.round();
+            entity.max_shield = entity.max_shield.round();
+            entity.shield = entity.shield.round();
+        }
         let id = self.allocate_entity_id();
         self.entities.insert(id, entity);
         id
     }
 
-    /// Removes an entity from the game.
+    /// Removes an entity from the game. Entities targeting it forget it
+    /// straight away (see [`remove_dead_entities`](Self::remove_dead_entities)).
     pub fn remove_entity(&mut self, id: EntityId) -> Option<Entity> {
-        self.entities.remove(&id)
+        let entity = self.entities.remove(&id)?;
+        if entity.is_targetable() {
+            self.forget_targets(&[id]);
+        }
+        Some(entity)
+    }
+
+    /// Removes every dead entity (including spent spells and expired zones)
+    /// and returns their IDs in order.
+    ///
+    /// Entities that were targeting one drop the target and their first-hit
+    /// windup, so no `target` outlives the entity it names; combat picks a
+    /// new one on the next tick.
+    pub fn remove_dead_entities(&mut self) -> Vec<EntityId> {
+        let dead: Vec<EntityId> = self
+            .entities
+            .iter()
+            .filter(|(_, entity)| !entity.is_alive())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &dead {
+            self.entities.remove(id);
+        }
+        self.forget_targets(&dead);
+        dead
+    }
+
+    /// Clears every target naming one of `removed` (sorted IDs).
+    fn forget_targets(&mut self, removed: &[EntityId]) {
+        if removed.is_empty() {
+            return;
+        }
+        for entity in self.entities.values_mut() {
+            if entity.target.is_some_and(|target| removed.binary_search(&EntityId(target)).is_ok()) {
+                entity.target = None;
+                entity.first_hit_timer = None;
+            }
+        }
+    }
+
+    /// Hands entity `id` over to `new_owner` (mind-control style effects)
+    /// and records a [`GameEvent::OwnershipTransferred`].
+    ///
+    /// The entity drops its own target and first-hit windup, and entities
+    /// of `new_owner` that were targeting it forget it, so nobody keeps
+    /// attacking a new ally; homing projectiles already in flight toward it
+    /// fizzle. Towers can't change sides, since crowns and tower HP are
+    /// tracked per player. Transferring to the current owner does nothing.
+    pub fn transfer_ownership(&mut self, id: EntityId, new_owner: PlayerId) -> Result<()> {
+        let entity = self
+            .entities
+            .get_mut(&id)
+            .ok_or_else(|| shared::Error::InvalidAction(format!("Entity {} not found", id.as_u32())))?;
+        if entity.tower_type().is_some() {
+            return Err(shared::Error::InvalidAction(format!(
+                "Tower {} can't change owner",
+                id.as_u32()
+            )));
+        }
+        let previous_owner = entity.owner;
+        if previous_owner == new_owner {
+            return Ok(());
+        }
+        entity.owner = new_owner;
+        entity.target = None;
+        entity.first_hit_timer = None;
+        entity.path = None;
+
+        for other in self.entities.values_mut() {
+            if other.owner.is_ally_of(new_owner) && other.target == Some(id.as_u32()) {
+                other.target = None;
+                other.first_hit_timer = None;
+            }
+        }
+        self.emit(GameEvent::OwnershipTransferred {
+            entity: id.as_u32(),
+            from: previous_owner,
+            to: new_owner,
+        });
+        Ok(())
+    }
+
+    /// Returns living, targetable enemies of `owner` whose collision radius
+    /// overlaps a circle of `radius` around `center` (for area damage).
+    pub fn enemies_in_radius(&self, owner: PlayerId, center: &shared::Position, radius: f32) -> Vec<EntityId> {
+        self.targets_in_radius(owner, DamageFilter::Enemies, center, radius)
+    }
+
+    /// Like [`enemies_in_radius`](Self::enemies_in_radius), but for whichever
+    /// side(s) `affects` lets damage from `owner` land on.
+    pub fn targets_in_radius(
+        &self,
+        owner: PlayerId,
+        affects: DamageFilter,
+        center: &shared::Position,
+        radius: f32,
+    ) -> Vec<EntityId> {
+        self.entities
+            .iter()
+            .filter(|(_, e)| affects.hits(owner, e.owner) && e.is_alive() && e.is_targetable())
+            .filter(|(_, e)| center.distance_to(&e.position) - e.radius() <= radius)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Returns living troops of `owner`'s team whose collision radius overlaps a
+    /// circle of `radius` around `center` (for area healing).
+    pub fn allied_troops_in_radius(&self, owner: PlayerId, center: &shared::Position, radius: f32) -> Vec<EntityId> {
+        self.entities
+            .iter()
+            .filter(|(_, e)| e.owner.is_ally_of(owner) && e.is_alive() && matches!(e.kind, EntityKind::Troop(_)))
+            .filter(|(_, e)| center.distance_to(&e.position) - e.radius() <= radius)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Applies `amount` damage to `target` (shield first). In integer HP
+    /// mode only whole points land; the fraction carries to the next change.
+    pub fn damage(&mut self, target: EntityId, amount: f32) {
+        let (integer_hp, headless) = (self.integer_hp, self.headless);
+        if let Some(entity) = self.entities.get_mut(&target) {
+            let before = entity.hp + entity.shield;
+            if integer_hp {
+                let whole = -entity.carry_hp_change(-amount);
+                if whole > 0.0 {
+                    entity.take_damage(whole);
+                }
+            } else {
+                entity.take_damage(amount);
+            }
+            if !headless {
+                entity.recent_damage.record(before - (entity.hp + entity.shield));
+            }
+        }
+    }
+
+    /// Like [`damage`](Self::damage), crediting the hit to `source` (see
+    /// [`Entity::last_hit_by`]).
+    pub fn damage_from(&mut self, source: EntityId, target: EntityId, amount: f32) {
+        if let Some(entity) = self.entities.get_mut(&target) {
+            entity.last_hit_by = Some(source.as_u32());
+        }
+        self.damage(target, amount);
+    }
+
+    /// Logs a [`Crown`] (and a [`GameEvent::CrownTaken`]) for every tower
+    /// that fell this tick. Princess towers count first, and a King is worth
+    /// whatever its side hadn't already given up, so no one takes more than
+    /// three crowns.
+    pub(crate) fn record_crowns(&mut self) {
+        let mut fallen: Vec<(PlayerId, TowerType, Option<u32>)> = self
+            .entities
+            .values()
+            .filter(|e| !e.is_alive())
+            .filter_map(|e| e.tower_type().map(|tower| (e.owner, tower, e.last_hit_by)))
+            .collect();
+        fallen.sort_by_key(|&(_, tower, _)| tower == TowerType::King);
+
+        for (defender, tower, source) in fallen {
+            let player = defender.opponent();
+            let crowns = match tower {
+                TowerType::King => {
+                    let taken: u32 = self.crowns.iter().filter(|c| c.player == player).map(|c| c.crowns).sum();
+                    3u32.saturating_sub(taken)
+                }
+                TowerType::LeftPrincess | TowerType::RightPrincess => 1,
+            };
+            // The hitter may be gone already (an archer killed while its
+            // arrow was in flight), leaving the card unknown
+            let card = source
+                .and_then(|id| self.entities.get(&EntityId(id)))
+                .and_then(|e| e.card())
+                .map(str::to_string);
+            self.crowns.push(Crown {
+                tick: self.tick,
+                player,
+                tower,
+                crowns,
+                source,
+                card,
+            });
+            self.emit(GameEvent::CrownTaken { player, tower, crowns });
+        }
+    }
+
+    /// Appends a successful card play to the match history.
+    pub(crate) fn record_play(&mut self, player: PlayerId, card: &Card, position: shared::Position) {
+        if let Some(player_state) = self.players.get_mut(&player) {
+            player_state.observe_play(card.elixir_cost);
+        }
+        self.card_plays.push(CardPlay {
+            tick: self.tick,
+            player,
+            card: card.name.clone(),
+            position,
+            cost: card.elixir_cost,
+        });
+    }
+
+    /// Cards `player` has played, oldest first.
+    pub fn plays_by(&self, player: PlayerId) -> impl Iterator<Item = &CardPlay> {
+        self.card_plays.iter().filter(move |p| p.player == player)
+    }
+
+    /// Names of the last `n` cards `player` played, most recent first.
+    pub fn last_played(&self, player: PlayerId, n: usize) -> Vec<&str> {
+        self.card_plays
+            .iter()
+            .rev()
+            .filter(|p| p.player == player)
+            .take(n)
+            .map(|p| p.card.as_str())
+            .collect()
+    }
+
+    /// Heals `target` by up to `amount` and records a [`GameEvent::Healed`]
+    /// if any HP was restored. Returns the amount healed.
+    pub fn heal(&mut self, source: EntityId, target: EntityId, amount: f32) -> f32 {
+        let integer_hp = self.integer_hp;
+        let healed = match self.entities.get_mut(&target) {
+            Some(entity) if integer_hp => {
+                let whole = entity.carry_hp_change(amount);
+                let healed = entity.heal(whole);
+                // Healing past full HP is lost rather than banked
+                if entity.hp >= entity.max_hp {
+                    entity.hp_carry = entity.hp_carry.min(0.0);
+                }
+                healed
+            }
+            Some(entity) => entity.heal(amount),
+            None => return 0.0,
+        };
+        if healed > 0.0 {
+            self.emit(GameEvent::Healed {
+                source: source.as_u32(),
+                target: target.as_u32(),
+                amount: healed,
+            });
+        }
+        healed
     }
 
     /// Checks if the match has ended.
     pub fn is_match_over(&self) -> bool {
         self.match_time >= self.max_match_time
             || self.players.values().any(|p| p.is_defeated())
+            || self.timeout.is_some()
+            || (self.event_modifiers.first_crown_wins && !self.crowns.is_empty())
+    }
+
+    /// The winner of a finished match: more crowns, then more tower HP
+    /// left. `None` while it's running, for a draw, or if it was aborted.
+    pub fn winner(&self) -> Option<PlayerId> {
+        if !self.is_match_over() || self.timeout.is_some() {
+            return None;
+        }
+        let standing = |player: PlayerId| {
+            let hp: f32 = self.players.get(&player).map_or(0.0, |p| p.tower_hp.values().sum());
+            (self.crowns_against(player.opponent()), hp)
+        };
+        let (crowns1, hp1) = standing(PlayerId::Player1);
+        let (crowns2, hp2) = standing(PlayerId::Player2);
+        match crowns1.cmp(&crowns2) {
+            std::cmp::Ordering::Greater => Some(PlayerId::Player1),
+            std::cmp::Ordering::Less => Some(PlayerId::Player2),
+            std::cmp::Ordering::Equal if hp1 > hp2 => Some(PlayerId::Player1),
+            std::cmp::Ordering::Equal if hp2 > hp1 => Some(PlayerId::Player2),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    /// How the match went, once [`is_match_over`](Self::is_match_over);
+    /// `None` while it's running.
+    pub fn result(&self) -> Option<MatchResult> {
+        if !self.is_match_over() {
+            return None;
+        }
+        let end_reason = if self.timeout.is_some() {
+            EndReason::Timeout
+        } else if self.players.values().any(|p| p.is_defeated()) {
+            EndReason::KingDestroyed
+        } else if self.match_time < self.max_match_time {
+            EndReason::FirstCrown
+        } else {
+            EndReason::TimeUp
+        };
+        let final_hp = |player: &PlayerState| {
+            [TowerType::King, TowerType::LeftPrincess, TowerType::RightPrincess]
+                .iter()
+                .map(|tt| player.tower_hp.get(tt).copied().unwrap_or(0.0).max(0.0))
+                .collect()
+        };
+        Some(MatchResult {
+            winner: self.winner(),
+            crowns: self.players.keys().map(|&id| (id, self.crowns_against(id.opponent()))).collect(),
+            tower_hp: self.players.iter().map(|(&id, player)| (id, final_hp(player))).collect(),
+            end_reason,
+            duration_s: self.match_time,
+        })
+    }
+
+    /// Crowns taken from `defender`'s towers: one per destroyed Princess
+    /// tower, or three once the King tower falls.
+    pub fn crowns_against(&self, defender: PlayerId) -> u32 {
+        let player = match self.players.get(&defender) {
+            Some(p) => p,
+            None => return 0,
+        };
+        if player.is_defeated() {
+            return 3;
+        }
+        [TowerType::LeftPrincess, TowerType::RightPrincess]
+            .iter()
+            .filter(|tt| player.tower_hp.get(tt).copied().unwrap_or(0.0) <= 0.0)
+            .count() as u32
+    }
+
+    /// Fraction of `player`'s total tower HP destroyed, 0 to 1 (0 if they
+    /// have no towers).
+    pub fn tower_hp_lost_fraction(&self, player: PlayerId) -> f32 {
+        self.players.get(&player).map_or(0.0, |p| {
+            let max: f32 = p.tower_max_hp.values().sum();
+            let hp: f32 = p.tower_hp.values().map(|hp| hp.max(0.0)).sum();
+            if max > 0.0 {
+                1.0 - hp / max
+            } else {
+                0.0
+            }
+        })
     }
 
     /// Advances match time by delta.
@@ -151,8 +1138,35 @@ impl GameState {
     }
 }
 
+pub use shared::dims::{PLACEMENT_GRID_H, PLACEMENT_GRID_W};
+
+/// Card level used for the default King and Princess towers.
+pub const TOWER_LEVEL: u32 = 11;
+
+/// Level of deck cards given without one (see [`GameState::set_player_deck`]).
+pub const DEFAULT_CARD_LEVEL: u32 = 11;
+
+/// Patch id for the built-in test cards.
+pub const DEFAULT_PATCH_ID: &str = "builtin";
+
+/// Game mode of a standard 1v1 match.
+pub const DEFAULT_GAME_MODE: &str = "standard";
+
+/// Elixir per second in a standard match.
+pub const STANDARD_ELIXIR_REGEN_RATE: f32 = 1.0;
+
+/// Length of a standard match (seconds).
+pub const STANDARD_MATCH_DURATION: f32 = 180.0;
+
+/// Engine ticks per agent step by default: one second of match time, so
+/// each action is a decision a second.
+pub const DEFAULT_FRAME_SKIP: u32 = 60;
+
+/// Elixir each player starts the match with.
+pub const STARTING_ELIXIR: f32 = 5.0;
+
 /// Unique identifier for an entity.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct EntityId(u32);
 
 impl EntityId {
@@ -165,6 +1179,222 @@ impl EntityId {
     }
 }
 
+/// Why a match was aborted for overrunning its step budget.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeoutTermination {
+    /// Tick that was being simulated (it never completed).
+    pub tick: u64,
+    /// Stage of the step that was running when the budget ran out
+    /// ("actions", or a system name such as "combat").
+    pub phase: String,
+    pub elapsed_ms: f64,
+    pub budget_ms: f64,
+}
+
+/// Rule tweaks from event modes, all off in a standard match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventModifiers {
+    /// Players may cycle the cheapest card in their hand without deploying
+    /// it (see [`Action::CycleCheapestCard`](crate::action::Action::CycleCheapestCard)).
+    #[serde(default)]
+    pub cycle_cheapest_card: bool,
+    /// The first crown taken ends the match (Sudden Death).
+    #[serde(default)]
+    pub first_crown_wins: bool,
+    /// Every troop is permanently raged (Rage mode; see
+    /// [`RAGE_SPEED_MULTIPLIER`](crate::entities::RAGE_SPEED_MULTIPLIER)).
+    #[serde(default)]
+    pub permanent_rage: bool,
+}
+
+impl EventModifiers {
+    /// True when no modifier is on.
+    pub fn is_standard(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// CR's match modes: how fast elixir comes in, how long a match runs, and
+/// which [`EventModifiers`] are on (see [`GameState::new_with_mode`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    /// A regular ladder match.
+    #[default]
+    Standard,
+    /// Double elixir, and the first crown wins.
+    SuddenDeath,
+    /// Triple elixir from the start.
+    TripleElixir,
+    /// Every troop raged for the whole match.
+    Rage,
+}
+
+impl GameMode {
+    /// Every mode, by name.
+    pub const ALL: [(&'static str, GameMode); 4] = [
+        (DEFAULT_GAME_MODE, GameMode::Standard),
+        ("sudden_death", GameMode::SuddenDeath),
+        ("triple_elixir", GameMode::TripleElixir),
+        ("rage", GameMode::Rage),
+    ];
+
+    /// The mode called `name` (as in [`ALL`](Self::ALL)).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|(n, _)| *n == name).map(|&(_, mode)| mode)
+    }
+
+    /// The name recorded as [`GameState::game_mode`].
+    pub fn name(self) -> &'static str {
+        Self::ALL.iter().find(|(_, mode)| *mode == self).map_or(DEFAULT_GAME_MODE, |(name, _)| name)
+    }
+
+    /// Elixir per second.
+    pub fn elixir_regen_rate(self) -> f32 {
+        match self {
+            GameMode::Standard | GameMode::Rage => STANDARD_ELIXIR_REGEN_RATE,
+            GameMode::SuddenDeath => 2.0 * STANDARD_ELIXIR_REGEN_RATE,
+            GameMode::TripleElixir => 3.0 * STANDARD_ELIXIR_REGEN_RATE,
+        }
+    }
+
+    /// Match length (seconds).
+    pub fn match_duration(self) -> f32 {
+        STANDARD_MATCH_DURATION
+    }
+
+    pub fn event_modifiers(self) -> EventModifiers {
+        EventModifiers {
+            first_crown_wins: self == GameMode::SuddenDeath,
+            permanent_rage: self == GameMode::Rage,
+            ..EventModifiers::default()
+        }
+    }
+}
+
+/// A card played during the match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardPlay {
+    pub tick: u64,
+    pub player: PlayerId,
+    pub card: String,
+    pub position: shared::Position,
+    /// Elixir spent on the play.
+    pub cost: f32,
+}
+
+/// A crown taken during the match: `player` destroyed the opponent's
+/// `tower`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Crown {
+    pub tick: u64,
+    pub player: PlayerId,
+    pub tower: TowerType,
+    /// Crowns the tower was worth (a King makes up the rest of three).
+    pub crowns: u32,
+    /// Entity that landed the last hit, if the tower was hit at all.
+    pub source: Option<u32>,
+    /// Card that put `source` on the field, if known.
+    pub card: Option<String>,
+}
+
+/// How a match ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// A King tower fell.
+    KingDestroyed,
+    /// The clock ran out.
+    TimeUp,
+    /// A Princess tower fell with [`EventModifiers::first_crown_wins`] on.
+    FirstCrown,
+    /// A step overran its budget and the match was aborted (see
+    /// [`TimeoutTermination`]).
+    Timeout,
+}
+
+impl EndReason {
+    /// The name used in JSON (`"king_destroyed"`, `"time_up"`,
+    /// `"first_crown"`, `"timeout"`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EndReason::KingDestroyed => "king_destroyed",
+            EndReason::TimeUp => "time_up",
+            EndReason::FirstCrown => "first_crown",
+            EndReason::Timeout => "timeout",
+        }
+    }
+}
+
+/// Summary of a finished match, from neither side (see
+/// [`GameState::result`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    /// See [`GameState::winner`]; `None` for a draw or an aborted match.
+    pub winner: Option<PlayerId>,
+    /// Crowns each player took.
+    pub crowns: BTreeMap<PlayerId, u32>,
+    /// Each player's final tower HP, in King, LeftPrincess, RightPrincess
+    /// order (0 for fallen towers).
+    pub tower_hp: BTreeMap<PlayerId, Vec<f32>>,
+    pub end_reason: EndReason,
+    /// Match time played (seconds).
+    pub duration_s: f32,
+}
+
+/// One card in a player's deck, at the level it's played at. Deserializes
+/// from a bare name too (at [`DEFAULT_CARD_LEVEL`]), as decks were stored
+/// before they had levels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredDeckCard")]
+pub struct DeckCard {
+    pub name: String,
+    pub level: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredDeckCard {
+    Name(String),
+    Card { name: String, level: u32 },
+}
+
+impl From<StoredDeckCard> for DeckCard {
+    fn from(stored: StoredDeckCard) -> Self {
+        match stored {
+            StoredDeckCard::Name(name) => name.into(),
+            StoredDeckCard::Card { name, level } => Self::new(name, level),
+        }
+    }
+}
+
+impl DeckCard {
+    pub fn new(name: impl Into<String>, level: u32) -> Self {
+        Self {
+            name: name.into(),
+            level,
+        }
+    }
+}
+
+impl From<String> for DeckCard {
+    fn from(name: String) -> Self {
+        Self::new(name, DEFAULT_CARD_LEVEL)
+    }
+}
+
+impl From<&str> for DeckCard {
+    fn from(name: &str) -> Self {
+        Self::new(name, DEFAULT_CARD_LEVEL)
+    }
+}
+
+impl From<(String, u32)> for DeckCard {
+    fn from((name, level): (String, u32)) -> Self {
+        Self::new(name, level)
+    }
+}
+
 /// Player-specific state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerState {
@@ -172,79 +1402,132 @@ pub struct PlayerState {
     pub elixir: f32,
     pub max_elixir: f32,
     pub elixir_regen_rate: f32,
-    pub tower_hp: HashMap<TowerType, f32>,
+    pub tower_hp: BTreeMap<TowerType, f32>,
 
-    /// The player's 8-card deck (card names).
-    pub deck: Vec<String>,
+    /// Starting HP of each tower (for hp fractions and damage totals).
+    pub tower_max_hp: BTreeMap<TowerType, f32>,
+
+    /// The player's 8-card deck, with each card's level.
+    pub deck: Vec<DeckCard>,
 
     /// Current hand (4 card indices into the cycle).
     pub hand: Vec<usize>,
 
-    /// Current position in the deck cycle (0-7, wraps around).
+    /// Deck index of the next card to draw (the front of `cycle`).
     pub next_card_index: usize,
+
+    /// Deck indices waiting to be drawn, next first. A played card joins
+    /// the back, so it can't come round again until every other card has.
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub cycle: VecDeque<usize>,
+
+    /// Hand slot the player has picked up but not played yet. Selecting
+    /// never cycles the card; any play from hand drops the selection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected: Option<usize>,
+
+    /// This player's elixir as the opponent can infer it: starting elixir
+    /// plus regen over time, minus the cost of every play they've seen.
+    #[serde(default = "default_starting_elixir")]
+    pub estimated_elixir: f32,
+}
+
+fn default_starting_elixir() -> f32 {
+    STARTING_ELIXIR
 }
 
 impl PlayerState {
     /// Creates a new player state with an empty deck.
     /// Use `set_deck()` to initialize the deck and hand.
     pub fn new(id: PlayerId) -> Self {
-        let mut tower_hp = HashMap::new();
-        tower_hp.insert(TowerType::King, 2400.0);
-        tower_hp.insert(TowerType::LeftPrincess, 1400.0);
-        tower_hp.insert(TowerType::RightPrincess, 1400.0);
+        let mut tower_hp = BTreeMap::new();
+        tower_hp.insert(TowerType::King, dims::KING_TOWER_HP);
+        tower_hp.insert(TowerType::LeftPrincess, dims::PRINCESS_TOWER_HP);
+        tower_hp.insert(TowerType::RightPrincess, dims::PRINCESS_TOWER_HP);
 
         Self {
             id,
-            elixir: 5.0,
+            elixir: STARTING_ELIXIR,
             max_elixir: 10.0,
-            elixir_regen_rate: 1.0, // 1 elixir per second (will be configurable)
+            elixir_regen_rate: STANDARD_ELIXIR_REGEN_RATE,
+            tower_max_hp: tower_hp.clone(),
             tower_hp,
             deck: Vec::new(),
             hand: Vec::new(),
             next_card_index: 0,
+            cycle: VecDeque::new(),
+            selected: None,
+            estimated_elixir: STARTING_ELIXIR,
         }
     }
 
     /// Sets the player's deck and initializes the hand with the first 4 cards.
     /// The deck should contain exactly 8 card names.
-    pub fn set_deck(&mut self, deck: Vec<String>, rng: &mut crate::rng::Rng) {
-        assert_eq!(deck.len(), 8, "Deck must contain exactly 8 cards");
+    pub fn set_deck(&mut self, deck: Vec<DeckCard>, rng: &mut crate::rng::Rng) {
+        assert_eq!(deck.len(), dims::DECK_SIZE, "Deck must contain exactly {} cards", dims::DECK_SIZE);
         self.deck = deck;
 
         // Shuffle the initial deck order using RNG for determinism
-        for i in (1..self.deck.len()).rev() {
-            let j = rng.rand_int_range(0, i as i32 + 1) as usize;
-            self.deck.swap(i, j);
-        }
+        rng.shuffle(&mut self.deck);
 
-        // Initialize hand with first 4 cards (indices 0-3)
-        self.hand = vec![0, 1, 2, 3];
-        self.next_card_index = 4; // Next card to draw is at index 4
+        // Initialize hand with the first HAND_SIZE cards; the rest wait in order
+        self.hand = (0..dims::HAND_SIZE).collect();
+        self.cycle = (dims::HAND_SIZE..self.deck.len()).collect();
+        self.next_card_index = dims::HAND_SIZE; // Next card to draw follows the hand
+        self.selected = None;
     }
 
     /// Gets the card name at the given hand index (0-3).
     pub fn get_hand_card(&self, hand_index: usize) -> Option<&String> {
+        self.hand_card(hand_index).map(|card| &card.name)
+    }
+
+    /// Gets the deck entry (name and level) at the given hand index.
+    pub fn hand_card(&self, hand_index: usize) -> Option<&DeckCard> {
         self.hand.get(hand_index).and_then(|&deck_index| self.deck.get(deck_index))
     }
 
+    /// The deck's card names, in cycle order.
+    pub fn deck_names(&self) -> Vec<String> {
+        self.deck.iter().map(|card| card.name.clone()).collect()
+    }
+
     /// Plays a card from the hand and cycles in the next card.
-    /// Returns the card name that was played.
-    pub fn play_card_from_hand(&mut self, hand_index: usize) -> Option<String> {
+    /// Returns the deck entry that was played.
+    pub fn play_card_from_hand(&mut self, hand_index: usize) -> Option<DeckCard> {
         if hand_index >= self.hand.len() {
             return None;
         }
 
-        // Get the card name before removing it
+        // Get the card before removing it
         let deck_index = self.hand[hand_index];
-        let card_name = self.deck.get(deck_index)?.clone();
+        let card = self.deck.get(deck_index)?.clone();
 
-        // Replace this hand slot with the next card in the cycle
-        self.hand[hand_index] = self.next_card_index;
+        // Replace this hand slot with the next card in the cycle, and send
+        // the played one to the back
+        if let Some(next) = self.cycle.pop_front() {
+            self.hand[hand_index] = next;
+            self.cycle.push_back(deck_index);
+        }
+        self.selected = None;
+        self.next_card_index = self.cycle.front().copied().unwrap_or(deck_index);
+
+        Some(card)
+    }
 
-        // Advance the cycle (wraps around to 0 after 7)
-        self.next_card_index = (self.next_card_index + 1) % 8;
+    /// Picks up the card in `hand_index` without playing it, replacing any
+    /// earlier selection. Returns false if the slot is empty.
+    pub fn select_card(&mut self, hand_index: usize) -> bool {
+        if hand_index >= self.hand.len() {
+            return false;
+        }
+        self.selected = Some(hand_index);
+        true
+    }
 
-        Some(card_name)
+    /// Puts the selected card back in its slot, uncycled.
+    pub fn cancel_selection(&mut self) {
+        self.selected = None;
     }
 
     /// Checks if this player has been defeated (King tower destroyed).
@@ -257,6 +1540,18 @@ impl PlayerState {
         self.elixir = (self.elixir + amount).min(self.max_elixir);
     }
 
+    /// Advances the public elixir estimate by `dt` seconds of regen.
+    pub fn regen_estimate(&mut self, dt: f32) {
+        self.estimated_elixir = (self.estimated_elixir + self.elixir_regen_rate * dt).min(self.max_elixir);
+    }
+
+    /// Updates the public elixir estimate for an observed play. Seeing a
+    /// play proves the player had at least its cost, so an estimate that
+    /// has drifted low is corrected before the cost comes off.
+    pub fn observe_play(&mut self, cost: f32) {
+        self.estimated_elixir = self.estimated_elixir.max(cost) - cost;
+    }
+
     /// Attempts to spend elixir. Returns true if successful.
     pub fn spend_elixir(&mut self, cost: f32) -> bool {
         if self.elixir >= cost {
@@ -269,7 +1564,7 @@ impl PlayerState {
 }
 
 /// Tower types in the arena.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum TowerType {
     King,
     LeftPrincess,
@@ -280,80 +1575,71 @@ impl GameState {
     /// Export a snapshot of the game for RL / external control.
     /// `pov` = which player is considered "ALLY" (usually Player1).
     pub fn export_cr_state(&self, pov: PlayerId) -> CRState {
-        // Decide who is ally vs enemy from the POV
-        let (ally_id, enemy_id) = match pov {
-            PlayerId::Player1 => (PlayerId::Player1, PlayerId::Player2),
-            PlayerId::Player2 => (PlayerId::Player2, PlayerId::Player1),
-        };
+        // Decide who is ally vs enemy from the POV (in 2v2 the enemy is
+        // the opposing team's lead, who owns its towers)
+        let (ally_id, enemy_id) = (pov, pov.opponent());
 
         let ally_player = self.players.get(&ally_id).expect("ally player missing");
         let enemy_player = self.players.get(&enemy_id).expect("enemy player missing");
 
         // === Tower snapshots ===
 
-        const KING_MAX_HP: f32 = 2400.0;
-        const PRINCESS_MAX_HP: f32 = 1400.0;
-
-        // TEMP: positions are rough placeholders; tweak later.
-        fn tower_pos_for(player: PlayerId, tt: TowerType) -> (f32, f32) {
-            match (player, tt) {
-                // Player1 bottom, Player2 top (arbitrary grid coords)
-                (PlayerId::Player1, TowerType::King)          => (16.0,  2.0),
-                (PlayerId::Player1, TowerType::LeftPrincess)  => (8.0,   4.0),
-                (PlayerId::Player1, TowerType::RightPrincess) => (24.0,  4.0),
-                (PlayerId::Player2, TowerType::King)          => (16.0, 30.0),
-                (PlayerId::Player2, TowerType::LeftPrincess)  => (8.0,  28.0),
-                (PlayerId::Player2, TowerType::RightPrincess) => (24.0, 28.0),
+        let tower_snapshots = |player_id: PlayerId, player: &PlayerState, owner: &str| {
+            let mut towers = Vec::new();
+            for tt in [TowerType::King, TowerType::LeftPrincess, TowerType::RightPrincess] {
+                let hp = player.tower_hp.get(&tt).copied().unwrap_or(0.0);
+                let max_hp = player.tower_max_hp.get(&tt).copied().unwrap_or(1.0);
+                let pos = self.arena.tower_position(player_id, tt);
+                let recent_damage = self
+                    .entities
+                    .values()
+                    .find(|e| e.owner == player_id.side() && e.tower_type() == Some(tt))
+                    .map_or(0.0, |e| e.recent_damage.total());
+                towers.push(CRTower {
+                    owner: owner.to_string(),
+                    x: pos.x,
+                    y: pos.y,
+                    hp_frac: (hp / max_hp).clamp(0.0, 1.0),
+                    recent_damage_frac: recent_damage / max_hp,
+                });
             }
-        }
-
-        let mut ally_towers = Vec::new();
-        let mut enemy_towers = Vec::new();
-
-        for (&tt, &hp) in &ally_player.tower_hp {
-            let max_hp = match tt {
-                TowerType::King => KING_MAX_HP,
-                TowerType::LeftPrincess | TowerType::RightPrincess => PRINCESS_MAX_HP,
-            };
-            let (x, y) = tower_pos_for(ally_id, tt);
-            ally_towers.push(CRTower {
-                owner: "ALLY".to_string(),
-                x,
-                y,
-                hp_frac: (hp / max_hp).clamp(0.0, 1.0),
-            });
-        }
+            towers
+        };
 
-        for (&tt, &hp) in &enemy_player.tower_hp {
-            let max_hp = match tt {
-                TowerType::King => KING_MAX_HP,
-                TowerType::LeftPrincess | TowerType::RightPrincess => PRINCESS_MAX_HP,
-            };
-            let (x, y) = tower_pos_for(enemy_id, tt);
-            enemy_towers.push(CRTower {
-                owner: "ENEMY".to_string(),
-                x,
-                y,
-                hp_frac: (hp / max_hp).clamp(0.0, 1.0),
-            });
-        }
+        let ally_towers = tower_snapshots(ally_id, ally_player, "ALLY");
+        let enemy_towers = tower_snapshots(enemy_id, enemy_player, "ENEMY");
 
-        // === Units (currently empty; we’ll fill later) ===
+        // === Units ===
 
         let mut ally_units: Vec<CRUnit> = Vec::new();
         let mut enemy_units: Vec<CRUnit> = Vec::new();
 
-        for entity in self.entities.values() {
-            if let Some((owner_id, (x, y), (vx, vy))) = extract_entity_info(entity) {
-                let owner_str = if owner_id == ally_id { "ALLY" } else { "ENEMY" }.to_string();
+        for (&id, entity) in &self.entities {
+            if let Some((owner_id, (x, y), (prev_x, prev_y), (vx, vy))) = extract_entity_info(entity) {
+                let EntityKind::Troop(troop) = &entity.kind else { continue };
+                let owner_str = if owner_id.is_ally_of(ally_id) { "ALLY" } else { "ENEMY" }.to_string();
                 let unit = CRUnit {
                     owner: owner_str,
                     x,
                     y,
                     vx,
                     vy,
+                    prev_x,
+                    prev_y,
+                    facing: entity.facing,
+                    shield: entity.shield,
+                    is_stunned: entity.has_status(StatusKind::Stun) || entity.has_status(StatusKind::Freeze),
+                    is_raged: entity.has_status(StatusKind::Rage),
+                    is_jumping: entity.has_status(StatusKind::Jumping),
+                    id: id.as_u32(),
+                    card: troop.card.clone(),
+                    hp_frac: (entity.hp / entity.max_hp).clamp(0.0, 1.0),
+                    level: troop.level,
+                    is_air: troop.transport == Transport::Air,
+                    target_id: entity.target,
+                    recent_damage_frac: entity.recent_damage.total() / entity.max_hp,
                 };
-                if owner_id == ally_id {
+                if owner_id.is_ally_of(ally_id) {
                     ally_units.push(unit);
                 } else {
                     enemy_units.push(unit);
@@ -361,33 +1647,121 @@ impl GameState {
             }
         }
 
-        // === Legal masks (placeholder; everything allowed for now) ===
-
+        // === Spells in flight (both sides, so agents can dodge) ===
+
+        let pending_spells = self
+            .entities
+            .values()
+            .filter(|e| e.is_alive())
+            .filter_map(|e| match &e.kind {
+                EntityKind::Spell(data) => Some(CRSpell {
+                    owner: if e.owner.is_ally_of(ally_id) { "ALLY" } else { "ENEMY" }.to_string(),
+                    x: e.position.x,
+                    y: e.position.y,
+                    radius: data.radius,
+                    time_to_impact: data.time_to_impact.max(0.0),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        // === Legal masks (the same placement and elixir checks a play
+        // from the hand goes through) ===
+
+        let hand_cards: Vec<Option<&Card>> = (0..dims::HAND_SLOTS)
+            .map(|slot| {
+                let entry = ally_player.hand.get(slot).and_then(|&i| ally_player.deck.get(i))?;
+                self.get_card_by_name(&entry.name)
+            })
+            .collect();
         let legal = LegalMasks {
-            cards: vec![true; 8],        // 8 hand slots
-            tiles_flat: vec![true; 16 * 9], // 16x9 placement grid
+            cards: hand_cards
+                .iter()
+                .map(|card| card.is_some_and(|c| ally_player.elixir >= c.elixir_cost))
+                .collect(),
+            hand_tiles: hand_cards
+                .iter()
+                .map(|card| card.map_or_else(Vec::new, |c| self.placement_tiles(pov, Some(c))))
+                .collect(),
+            tiles_flat: self.placement_tiles(pov, None),
+            grid_w: self.placement_grid.width,
+            grid_h: self.placement_grid.height,
+            no_op: true,
         };
 
         // === Damage-based helpers ===
 
         let ally_total_hp: f32  = ally_player.tower_hp.values().sum();
         let enemy_total_hp: f32 = enemy_player.tower_hp.values().sum();
-        let ally_max_total  = KING_MAX_HP + 2.0 * PRINCESS_MAX_HP;
-        let enemy_max_total = ally_max_total;
+        let ally_max_total: f32  = ally_player.tower_max_hp.values().sum();
+        let enemy_max_total: f32 = enemy_player.tower_max_hp.values().sum();
 
         let ally_tower_hp_drop  = (ally_max_total  - ally_total_hp).max(0.0);
         let enemy_tower_hp_drop = (enemy_max_total - enemy_total_hp).max(0.0);
 
-        // === Win / lose flags ===
+        // === Hands and deck cycles ===
 
-        let win  = enemy_player.is_defeated()
-            || (self.is_match_over() && ally_total_hp > enemy_total_hp);
-        let lose = ally_player.is_defeated()
-            || (self.is_match_over() && enemy_total_hp > ally_total_hp);
+        let card_cycle = |player_id: PlayerId, player: &PlayerState| {
+            let hand_card = |deck_index: usize| {
+                player.deck.get(deck_index).map(|entry| HandCard {
+                    card: entry.name.clone(),
+                    elixir_cost: self.cards.get(&entry.name).map_or(0.0, |c| c.elixir_cost),
+                    level: entry.level,
+                })
+            };
+            CardCycle {
+                hand: player.hand.iter().filter_map(|&i| hand_card(i)).collect(),
+                next: hand_card(player.next_card_index),
+                cycle_position: player.next_card_index,
+                cards_played: self.plays_by(player_id).count() as u32,
+                selected: player.selected,
+            }
+        };
+        let ally_cycle = card_cycle(ally_id, ally_player);
+        let enemy_cycle = self.export_enemy_cycle.then(|| card_cycle(enemy_id, enemy_player));
+        let teammate = self.players.get(&pov.teammate());
+
+        // === Crowns taken so far ===
+
+        let crowns: Vec<CRCrown> = self
+            .crowns
+            .iter()
+            .map(|c| CRCrown {
+                owner: if c.player.is_ally_of(ally_id) { "ALLY" } else { "ENEMY" }.to_string(),
+                tick: c.tick,
+                tower: format!("{:?}", c.tower),
+                crowns: c.crowns,
+                source_id: c.source,
+                card: c.card.clone(),
+            })
+            .collect();
+
+        // === Match result (terminal frames only) ===
+
+        let result = self.result().map(|result| {
+            let side = |player: PlayerId| if player.is_ally_of(ally_id) { "ALLY" } else { "ENEMY" };
+            let tower_hp = |player: PlayerId| result.tower_hp.get(&player).cloned().unwrap_or_default();
+            CRMatchResult {
+                winner: result.winner.map_or("DRAW", side).to_string(),
+                ally_crowns: result.crowns.get(&ally_id).copied().unwrap_or(0),
+                enemy_crowns: result.crowns.get(&enemy_id).copied().unwrap_or(0),
+                end_reason: result.end_reason.as_str().to_string(),
+                ally_tower_hp: tower_hp(ally_id),
+                enemy_tower_hp: tower_hp(enemy_id),
+                duration_s: result.duration_s,
+                crowns: crowns.clone(),
+            }
+        });
+
+        // === Win / lose flags (agree with the result block) ===
+
+        let win = result.as_ref().is_some_and(|r| r.winner == "ALLY");
+        let lose = result.as_ref().is_some_and(|r| r.winner == "ENEMY");
 
         CRState {
             t_ms: (self.match_time * 1000.0) as u64,
             ally_elixir: ally_player.elixir,
+            estimated_enemy_elixir: enemy_player.estimated_elixir,
             time_left: (self.max_match_time - self.match_time).max(0.0),
             overtime: self.match_time > self.max_match_time,
 
@@ -395,6 +1769,12 @@ impl GameState {
             enemy_towers,
             ally_units,
             enemy_units,
+            pending_spells,
+
+            ally_cycle,
+            enemy_cycle,
+            teammate_elixir: teammate.map(|p| p.elixir),
+            teammate_cycle: teammate.map(|p| card_cycle(pov.teammate(), p)),
 
             legal,
 
@@ -403,112 +1783,108 @@ impl GameState {
 
             enemy_tower_hp_drop,
             ally_tower_hp_drop,
+            crowns,
+
+            result,
+
+            // Only the match as it starts, before any tick has run, so an
+            // episode carries it exactly once
+            metadata: (self.tick == 0).then(|| self.match_metadata(pov)),
+            reward: None,
         }
     }
 }
 
+/// Writes a debug line to stderr unless the game is running headless.
+macro_rules! log_unless_headless {
+    ($verbose:expr, $($arg:tt)*) => {
+        if $verbose {
+            eprintln!($($arg)*);
+        }
+    };
+}
 
+/// Agent step for `pov`: plays hand slot `card_idx` at placement tile
+/// `tile_idx` (in `pov`'s frame), then runs the step's ticks.
 pub fn step_with_action(
     game: &mut GameState,
     pov: PlayerId,
     card_idx: usize,
     tile_idx: usize,
-) {
-    eprintln!(
-        "step_with_action: pov={:?}, card_idx={}, tile_idx={}, match_time={}",
-        pov, card_idx, tile_idx, game.match_time
-    );
-
-    // 1) Choose which player is "us"
-    let player_id = pov;
-
-    // 2) Get mutable reference to that player's state
-    let player_state = match game.players.get_mut(&player_id) {
-        Some(p) => p,
-        None => {
-            eprintln!("step_with_action: player {:?} not found", player_id);
-            return;
-        }
-    };
+) -> Result<()> {
+    step_with_agent_action(game, pov, AgentAction::Play { slot: card_idx, tile: tile_idx })
+}
 
-    // Track elixir and entity count before action
-    let elixir_before = player_state.elixir;
-    let entity_count_before = game.entities.len();
-
-    // 3) Validate card_idx (0–3 for the 4-card hand)
-    if card_idx >= player_state.hand.len() {
-        eprintln!("step_with_action: invalid card_idx {}", card_idx);
-        return;
-    }
-
-    // Which card in the deck does this hand slot point to?
-    let deck_index = player_state.hand[card_idx];
-    let maybe_card_name = player_state.deck.get(deck_index).cloned();
-    let card_name = match maybe_card_name {
-        Some(name) => name,
-        None => {
-            eprintln!(
-                "step_with_action: no card at deck index {} for player {:?}",
-                deck_index, player_id
-            );
-            return;
-        }
-    };
+/// [`step_with_action`] for an action from the flat agent action space
+/// (see [`AgentAction`]). A no-op only runs the ticks.
+pub fn step_with_agent_action(game: &mut GameState, pov: PlayerId, action: AgentAction) -> Result<()> {
+    step_with_agent_actions(game, &[(pov, action)])
+}
 
-    // 4) Convert tile_idx into an (x, y) placement in a 16x9 grid
-    let grid_w = 16;
-    let grid_h = 9;
-    if tile_idx >= grid_w * grid_h {
-        eprintln!("step_with_action: invalid tile_idx {}", tile_idx);
-        return;
-    }
-    let gx = (tile_idx % grid_w) as f32;
-    let gy = (tile_idx / grid_w) as f32;
-
-    // TODO: if you want world coords, convert (gx, gy) via your Arena
-    let x = gx;
-    let y = gy;
-
-    // 5) Build an Action that your engine understands
-    // Action::PlayCard expects: player, card_name, level, position
-    // Use level 11 as default (matches test cards)
-    let position = shared::Position::new(x, y);
-    let action = Action::PlayCard {
-        player: player_id,
-        card_name: card_name.clone(),
-        level: 11,
-        position,
-    };
+/// [`step_with_agent_action`] for several agents sharing one step (self-play):
+/// each action is played from its player's own frame, in order, on the
+/// first tick, and the match then runs [`GameState::frame_skip`] engine
+/// ticks in all (fewer if it ends first).
+///
+/// A play that can't be made (bad slot or tile, not enough elixir) is
+/// logged and dropped, without costing the other agents theirs.
+///
+/// # Errors
+/// Only an internal engine error (see [`crate::step`]); the state should
+/// then be discarded.
+pub fn step_with_agent_actions(game: &mut GameState, actions: &[(PlayerId, AgentAction)]) -> Result<()> {
+    let verbose = !game.headless;
+
+    for tick in 0..game.frame_skip.max(1) {
+        if game.is_match_over() {
+            break;
+        }
+        if tick == 0 {
+            step_agent_tick(game, actions)?;
+        } else {
+            crate::step(game, &[])?;
+        }
+    }
 
-    eprintln!(
-        "step_with_action: applying PlayCard(player={:?}, card={}, level=11, position=({}, {}))",
-        player_id, card_name, x, y
-    );
-
-    // 6) Apply the action
-    if let Err(e) = game.apply_action(&action) {
-        eprintln!("step_with_action: apply_action error: {:?}", e);
-        // Still advance time even if action fails
-    } else {
-        // Log elixir and entity changes after successful action
-        let elixir_after = game.players.get(&player_id).map(|p| p.elixir).unwrap_or(0.0);
-        let entity_count_after = game.entities.len();
-        eprintln!(
-            "step_with_action: card '{}' played. elixir: {} -> {}, entities: {} -> {}",
-            card_name, elixir_before, elixir_after, entity_count_before, entity_count_after
+    for &(pov, _) in actions {
+        log_unless_headless!(
+            verbose,
+            "step_with_action: finished, tick={}, match_time={}, {:?} elixir={}",
+            game.tick,
+            game.match_time,
+            pov,
+            game.players
+                .get(&pov)
+                .map(|p| p.elixir)
+                .unwrap_or(-1.0)
         );
     }
+    Ok(())
+}
 
-    // 7) Advance the simulation by Δt
-    let delta_t = 1.0;
-    game.advance_time(delta_t);
+/// One engine tick with agents' actions, each played from its player's
+/// own frame. Plays that can't be made are logged and dropped, as in
+/// [`step_with_agent_actions`], which runs this on the first tick of a step.
+///
+/// # Errors
+/// Only an internal engine error (see [`crate::step`]).
+pub fn step_agent_tick(game: &mut GameState, actions: &[(PlayerId, AgentAction)]) -> Result<()> {
+    let verbose = !game.headless;
+
+    // Decoded like every other consumer of the action space, so plays come
+    // from the hand and cycle it
+    let mut plays = Vec::new();
+    for &(pov, action) in actions {
+        log_unless_headless!(verbose, "step_with_action: pov={:?}, action={:?}, match_time={}", pov, action, game.match_time);
+        match action.to_action(game, pov) {
+            Ok(Some(play)) => plays.push(play),
+            Ok(None) => {}
+            Err(e) => log_unless_headless!(verbose, "step_with_action: {:?} dropped: {}", action, e),
+        }
+    }
 
-    eprintln!(
-        "step_with_action: finished, new match_time={}, ally elixir={}",
-        game.match_time,
-        game.players
-            .get(&player_id)
-            .map(|p| p.elixir)
-            .unwrap_or(-1.0)
-    );
-}
\ No newline at end of file
+    for i in crate::step_keeping_valid(game, &plays)? {
+        log_unless_headless!(verbose, "step_with_action: play rejected: {:?}", plays[i]);
+    }
+    Ok(())
+}
@@ -1,19 +1,35 @@
 //! Combat system (targeting, attacking, damage).
 
 use crate::entities::TargetType;
+use crate::spatial::LINEAR_SCAN_LIMIT;
 use crate::state::{EntityId, GameState};
+use crate::systems::knockback;
 use shared::PlayerId;
 
+/// Slack for float drift when counting timers down in `dt` steps.
+const TIMER_EPSILON: f32 = 1e-4;
+
+/// How much closer (tiles) a new target must be before an approaching unit
+/// switches to it.
+pub const TARGET_SWITCH_MARGIN: f32 = 0.5;
+
 /// Updates combat logic (targeting, attacks).
 pub fn update(state: &mut GameState, dt: f32) {
-    // Update attack cooldowns
+    // Update attack cooldowns. A cooldown may dip below zero on the tick it
+    // runs out; if the entity attacks that tick the overshoot is carried into
+    // the next cooldown, otherwise it is dropped.
     for entity in state.entities.values_mut() {
         if entity.attack_cooldown > 0.0 {
-            entity.attack_cooldown = (entity.attack_cooldown - dt).max(0.0);
+            entity.attack_cooldown -= dt;
+        } else {
+            entity.attack_cooldown = 0.0;
         }
     }
 
     // First pass: Assign targets to all entities that can attack
+    if state.entities.len() > LINEAR_SCAN_LIMIT {
+        state.rebuild_spatial_index();
+    }
     let mut target_assignments = Vec::new();
 
     for (attacker_id, attacker) in &state.entities {
@@ -22,18 +38,35 @@ pub fn update(state: &mut GameState, dt: f32) {
             continue;
         }
 
-        // Find or verify target
-        let target_id = if let Some(current_target) = attacker.target {
-            // Check if current target is still valid
-            if is_valid_target(state, *attacker_id, EntityId::from_u32(current_target)) {
-                Some(EntityId::from_u32(current_target))
-            } else {
-                // Find new target
-                find_target(state, *attacker_id, attacker.owner, attacker.target_type())
+        let policy = match state.retarget_policies.for_entity(attacker) {
+            Some(policy) => policy,
+            None => continue,
+        };
+
+        // Keep a valid current target, otherwise re-scan
+        let current = attacker
+            .target
+            .map(EntityId::from_u32)
+            .filter(|&id| is_valid_target(state, *attacker_id, id));
+
+        let target_id = match current {
+            Some(current_id) => {
+                let current_target = &state.entities[&current_id];
+                if policy.switch_while_approaching && !state.can_engage(attacker, current_target) {
+                    // Not engaged yet: take a clearly closer target if there
+                    // is one, so near-ties don't flip back and forth
+                    let current_distance = attacker.position.distance_to(&current_target.position);
+                    find_target(state, *attacker_id, attacker.owner, attacker.target_type())
+                        .filter(|id| {
+                            let distance = attacker.position.distance_to(&state.entities[id].position);
+                            distance + TARGET_SWITCH_MARGIN < current_distance
+                        })
+                        .or(Some(current_id))
+                } else {
+                    Some(current_id)
+                }
             }
-        } else {
-            // Find new target
-            find_target(state, *attacker_id, attacker.owner, attacker.target_type())
+            None => find_target(state, *attacker_id, attacker.owner, attacker.target_type()),
         };
 
         if let Some(target_id) = target_id {
@@ -41,35 +74,57 @@ pub fn update(state: &mut GameState, dt: f32) {
         }
     }
 
-    // Apply target assignments
+    // Apply target assignments (a new target restarts the first-hit windup)
     for (attacker_id, target_id) in &target_assignments {
         if let Some(attacker) = state.entities.get_mut(attacker_id) {
+            if attacker.target != Some(target_id.as_u32()) {
+                attacker.first_hit_timer = Some(attacker.first_hit_speed());
+            }
             attacker.target = Some(target_id.as_u32());
         }
     }
 
     // Second pass: Execute attacks for entities that are ready and in range
     let mut attacks = Vec::new();
+    let mut windups = Vec::new();
 
     for (attacker_id, target_id) in target_assignments {
         let attacker = &state.entities[&attacker_id];
+        let target = &state.entities[&target_id];
 
-        // Skip if on cooldown
-        if attacker.attack_cooldown > 0.0 {
+        // Check if target is in range (and not cut off by the river)
+        if !state.can_engage(attacker, target) {
             continue;
         }
 
-        let target = &state.entities[&target_id];
-        let distance = attacker.position.distance_to(&target.position);
+        match attacker.first_hit_timer {
+            // Still loading the first hit on this target
+            Some(remaining) if remaining - dt > TIMER_EPSILON => {
+                windups.push((attacker_id, remaining - dt));
+                continue;
+            }
+            // Windup complete: the first hit ignores the regular cooldown
+            Some(_) => {}
+            // Skip if on cooldown
+            None if attacker.attack_cooldown > TIMER_EPSILON => continue,
+            None => {}
+        }
+
+        attacks.push((attacker_id, target_id, attacker.damage(), attacker.attack_speed()));
+    }
 
-        // Check if target is in range
-        if distance <= attacker.attack_range() {
-            attacks.push((attacker_id, target_id, attacker.damage(), attacker.attack_speed()));
+    for (attacker_id, remaining) in windups {
+        if let Some(attacker) = state.entities.get_mut(&attacker_id) {
+            attacker.first_hit_timer = Some(remaining);
         }
     }
 
-    // Apply attacks
-    for (attacker_id, target_id, damage, attack_speed) in attacks {
+    // Apply attacks. Every attack that fired this tick was decided above from
+    // the same pre-attack state, so resolution is simultaneous: all hits land
+    // first (two units that kill each other both die), then on-hit effects
+    // run in entity ID order. The outcome never depends on attack order.
+    let mut pushes = Vec::new();
+    for &(attacker_id, target_id, damage, _) in &attacks {
         let attacker = &state.entities[&attacker_id];
 
         // Check if this is a ranged attack
@@ -78,14 +133,32 @@ pub fn update(state: &mut GameState, dt: f32) {
             spawn_projectile(state, attacker_id, target_id, damage);
         } else {
             // Melee: Apply damage instantly
-            if let Some(target) = state.entities.get_mut(&target_id) {
-                target.take_damage(damage);
+            if let Some(distance) = attacker.knockback() {
+                let direction = attacker.position.direction_to(&state.entities[&target_id].position);
+                pushes.push((target_id, direction, distance));
+            }
+            state.damage_from(attacker_id, target_id, damage);
+        }
+    }
+
+    for (target_id, direction, distance) in pushes {
+        knockback::push(state, target_id, direction, distance);
+    }
+
+    for &(attacker_id, _, _, attack_speed) in &attacks {
+        // On-hit healing for the attacker and nearby allied troops (a healer
+        // killed this tick still heals its allies, but not itself)
+        if let Some(heal) = state.entities[&attacker_id].heal_on_hit() {
+            let attacker = &state.entities[&attacker_id];
+            for ally in state.allied_troops_in_radius(attacker.owner, &attacker.position, heal.radius) {
+                state.heal(attacker_id, ally, heal.amount);
             }
         }
 
-        // Set cooldown
+        // Set cooldown, keeping any overshoot so attack timing doesn't drift
         if let Some(attacker) = state.entities.get_mut(&attacker_id) {
-            attacker.attack_cooldown = attack_speed;
+            attacker.attack_cooldown = attack_speed + attacker.attack_cooldown.min(0.0);
+            attacker.first_hit_timer = None;
         }
     }
 }
@@ -95,15 +168,18 @@ fn spawn_projectile(state: &mut GameState, attacker_id: EntityId, target_id: Ent
     use crate::entities::{Entity, EntityKind, ProjectileData};
 
     let attacker = &state.entities[&attacker_id];
+    let splash_radius = attacker.splash_radius();
 
     let projectile = Entity::new(
         attacker.owner,
         attacker.position, // Start at attacker's position
-        EntityKind::Projectile(ProjectileData {
-            damage,
-            speed: 15.0, // Projectiles move at 15 tiles/second (fast)
-            target_id: Some(target_id.as_u32()),
-        }),
+        // Projectiles move at 15 tiles/second (fast)
+        EntityKind::Projectile(
+            ProjectileData::homing(damage, 15.0, target_id.as_u32())
+                .with_splash(splash_radius)
+                .with_knockback(attacker.knockback())
+                .with_source(attacker_id.as_u32()),
+        ),
     );
 
     state.add_entity(projectile);
@@ -111,6 +187,7 @@ fn spawn_projectile(state: &mut GameState, attacker_id: EntityId, target_id: Ent
 
 /// Finds the best target for an attacker.
 /// Returns the nearest valid enemy, regardless of range (for movement purposes).
+/// Stationary attackers only consider enemies within their attack range.
 fn find_target(
     state: &GameState,
     attacker_id: EntityId,
@@ -119,45 +196,67 @@ fn find_target(
 ) -> Option<EntityId> {
     let attacker = &state.entities[&attacker_id];
 
-    let mut best_target: Option<(EntityId, f32)> = None;
+    // Siege buildings rank any building ahead of any troop
+    let rank = |entity: &crate::entities::Entity| !(attacker.prefers_buildings() && entity.is_building());
+    let mut best_target: Option<(EntityId, (bool, f32))> = None;
 
-    for (id, entity) in &state.entities {
-        // Skip self
-        if *id == attacker_id {
-            continue;
-        }
+    let consider = |candidates: &mut dyn Iterator<Item = EntityId>, best_target: &mut Option<(EntityId, (bool, f32))>| {
+        for id in candidates {
+            let id = &id;
+            let Some(entity) = state.entities.get(id) else {
+                continue;
+            };
 
-        // Skip allies
-        if entity.owner == attacker_owner {
-            continue;
-        }
+            // Skip allies, teammates' included (and self)
+            if entity.owner.is_ally_of(attacker_owner) {
+                continue;
+            }
 
-        // Skip dead entities
-        if !entity.is_alive() {
-            continue;
-        }
+            // Skip dead entities and things that can't be attacked (projectiles, spells)
+            if !entity.is_alive() || !entity.is_targetable() {
+                continue;
+            }
 
-        // Check target type compatibility
-        if let Some(target_type) = target_type {
-            if !is_valid_target_type(entity, target_type) {
+            // Stationary attackers (towers) only consider targets already in range
+            if !attacker.can_move() && !state.can_engage(attacker, entity) {
                 continue;
             }
-        }
 
-        let distance = attacker.position.distance_to(&entity.position);
+            // Check target type compatibility
+            if let Some(target_type) = target_type {
+                if !is_valid_target_type(entity, target_type) {
+                    continue;
+                }
+            }
 
-        // Prioritize targets by distance (closest first)
-        match best_target {
-            None => {
-                best_target = Some((*id, distance));
+            let distance = (rank(entity), attacker.position.distance_squared(&entity.position));
+
+            // Prioritize targets by distance (closest first), then by ID
+            let closer = match *best_target {
+                None => true,
+                Some((best_id, best_distance)) => (distance, *id) < (best_distance, best_id),
+            };
+            if closer {
+                *best_target = Some((*id, distance));
             }
-            Some((_, best_distance)) => {
-                // Prefer closer targets
-                if distance < best_distance {
-                    best_target = Some((*id, distance));
-                }
+        }
+    };
+
+    let enemies = Some(attacker_owner.opponent());
+    if state.entities.len() <= LINEAR_SCAN_LIMIT {
+        consider(&mut state.entities.keys().copied(), &mut best_target);
+    } else if attacker.can_move() {
+        // Search outward until nothing unvisited could beat the best so far
+        let settled = |rank: bool| !rank || !attacker.prefers_buildings();
+        for (beyond, ring) in state.spatial.rings(&attacker.position, enemies) {
+            consider(&mut ring.map(|e| e.id), &mut best_target);
+            if matches!(best_target, Some((_, (r, d))) if settled(r) && d <= beyond * beyond) {
+                break;
             }
         }
+    } else {
+        let candidates = state.spatial.near(&attacker.position, attacker.attack_range(), enemies);
+        consider(&mut candidates.map(|e| e.id), &mut best_target);
     }
 
     // Return the nearest target (even if out of range)
@@ -165,7 +264,8 @@ fn find_target(
     best_target.map(|(id, _)| id)
 }
 
-/// Checks if a target is still valid (alive and enemy).
+/// Checks if a target is still valid: alive, enemy, targetable, and allowed
+/// by the attacker's target type and retarget policy.
 fn is_valid_target(state: &GameState, attacker_id: EntityId, target_id: EntityId) -> bool {
     let attacker = match state.entities.get(&attacker_id) {
         Some(a) => a,
@@ -177,27 +277,418 @@ fn is_valid_target(state: &GameState, attacker_id: EntityId, target_id: EntityId
         None => return false,
     };
 
+    // Sides can change mid-match (see `GameState::transfer_ownership`)
+    if !target.is_targetable() || target.owner.is_ally_of(attacker.owner) {
+        return false;
+    }
+
+    // Stationary attackers can never reach an out-of-range target
+    let drop_out_of_range = !attacker.can_move()
+        || state
+            .retarget_policies
+            .for_entity(attacker)
+            .is_some_and(|p| p.drop_out_of_range);
+    if drop_out_of_range && !state.can_engage(attacker, target) {
+        return false;
+    }
+
+    // Target must still match what the attacker is allowed to hit
+    if let Some(target_type) = attacker.target_type() {
+        if !is_valid_target_type(target, target_type) {
+            return false;
+        }
+    }
+
     // Target must be alive and enemy
-    target.is_alive() && target.owner != attacker.owner
+    target.is_alive() && !target.owner.is_ally_of(attacker.owner)
 }
 
 /// Checks if an entity matches the target type.
-fn is_valid_target_type(entity: &crate::entities::Entity, target_type: TargetType) -> bool {
-    use crate::entities::EntityKind;
+pub(crate) fn is_valid_target_type(entity: &crate::entities::Entity, target_type: TargetType) -> bool {
+    use crate::entities::Transport;
 
     match target_type {
-        TargetType::Ground => {
-            // TODO: Add air/ground transport tracking to entities
-            true // For now, treat all troops as ground
+        TargetType::Ground => entity.transport() == Transport::Ground,
+        TargetType::Air => entity.transport() == Transport::Air,
+        TargetType::Both => true,
+        // Building-targeting troops walk past enemy troops entirely
+        TargetType::Buildings => entity.is_building(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityKind;
+    use crate::fixtures::spawn;
+    use shared::Position;
+
+    #[test]
+    fn test_melee_cannot_engage_across_river() {
+        let duel = |y: f32, river_blocks_melee: bool| {
+            let mut state = GameState::new(26);
+            state.set_river_blocks_melee(river_blocks_melee);
+            let a = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(14.5, y)).unwrap();
+            let b = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(17.5, y)).unwrap();
+            // Long-reach melee so the river is the only thing in the way
+            for id in [a, b] {
+                if let EntityKind::Troop(data) = &mut state.entities.get_mut(&id).unwrap().kind {
+                    data.range = 3.0;
+                }
+            }
+            for _ in 0..120 {
+                update(&mut state, 1.0 / 60.0);
+            }
+            state.entities[&b].hp < state.entities[&b].max_hp
+        };
+
+        assert!(!duel(9.0, true));
+        assert!(duel(9.0, false));
+        // Along a bridge nothing is in the way
+        assert!(duel(3.5, true));
+    }
+
+    #[test]
+    fn test_ranged_can_shoot_across_river() {
+        let mut state = GameState::new(27);
+        let archers = spawn(&mut state, PlayerId::Player1, "Archers", Position::new(13.0, 9.0)).unwrap();
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(18.0, 9.0)).unwrap();
+        assert!(state.can_engage(&state.entities[&archers], &state.entities[&target]));
+    }
+
+    #[test]
+    fn test_healer_heals_self_and_allies_on_hit() {
+        let mut state = GameState::new(32);
+        let healer = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(12.0, 9.0)).unwrap();
+        let ally = spawn(&mut state, PlayerId::Player1, "Archers", Position::new(10.0, 12.0)).unwrap();
+        let far_ally = spawn(&mut state, PlayerId::Player1, "Giant", Position::new(4.0, 16.0)).unwrap();
+        let enemy = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(13.0, 9.0)).unwrap();
+        if let EntityKind::Troop(data) = &mut state.entities.get_mut(&enemy).unwrap().kind {
+            data.damage = 0.0;
         }
-        TargetType::Air => {
-            // TODO: Add air/ground transport tracking to entities
-            false // For now, no air units
+        if let EntityKind::Troop(data) = &mut state.entities.get_mut(&healer).unwrap().kind {
+            data.heal_on_hit = Some(crate::entities::HealOnHit { amount: 40.0, radius: 4.0 });
         }
-        TargetType::Both => true,
-        TargetType::Buildings => {
-            // Towers are buildings
-            matches!(entity.kind, EntityKind::Tower(_))
+        for id in [healer, ally, far_ally] {
+            let entity = state.entities.get_mut(&id).unwrap();
+            entity.hp = entity.max_hp - 100.0;
+        }
+
+        while state.events.is_empty() {
+            update(&mut state, 1.0 / 60.0);
+        }
+
+        let missing = |id: EntityId| state.entities[&id].max_hp - state.entities[&id].hp;
+        assert_eq!(missing(healer), 60.0);
+        assert_eq!(missing(ally), 60.0);
+        assert_eq!(missing(far_ally), 100.0);
+        assert_eq!(state.events.len(), 2);
+    }
+
+    #[test]
+    fn test_ground_only_troops_ignore_air() {
+        let mut state = GameState::new(11);
+        let dragon = spawn(&mut state, PlayerId::Player2, "Baby Dragon", Position::new(17.0, 9.0)).unwrap();
+        let knight = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(15.0, 9.0)).unwrap();
+        let musketeer = spawn(&mut state, PlayerId::Player1, "Musketeer", Position::new(15.0, 11.0)).unwrap();
+
+        state.rebuild_spatial_index();
+        let knight_target = find_target(&state, knight, PlayerId::Player1, state.entities[&knight].target_type());
+        assert_ne!(knight_target, Some(dragon));
+
+        let musketeer_target =
+            find_target(&state, musketeer, PlayerId::Player1, state.entities[&musketeer].target_type());
+        assert_eq!(musketeer_target, Some(dragon));
+    }
+
+    #[test]
+    fn test_ground_only_towers_ignore_air() {
+        let mut state = GameState::new(14);
+        let mut towers = crate::tower::get_default_towers();
+        for tower in &mut towers {
+            tower.targets = Some(vec!["ground".to_string()]);
+        }
+        state.spawn_towers(&towers, 11).unwrap();
+
+        let tower_pos = state
+            .arena
+            .tower_position(PlayerId::Player1, crate::state::TowerType::LeftPrincess);
+        let dragon = spawn(&mut state, PlayerId::Player2, "Baby Dragon", Position::new(tower_pos.x + 3.0, tower_pos.y)).unwrap();
+        update(&mut state, 0.0);
+        assert!(state
+            .entities
+            .values()
+            .filter(|e| e.tower_type().is_some())
+            .all(|e| e.target != Some(dragon.as_u32())));
+
+        let knight = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(tower_pos.x + 4.0, tower_pos.y)).unwrap();
+        update(&mut state, 0.0);
+        let tower = state.entities.values().find(|e| e.position == tower_pos).unwrap();
+        assert_eq!(tower.target, Some(knight.as_u32()));
+    }
+
+    #[test]
+    fn test_building_targeters_ignore_troops() {
+        let mut state = GameState::new(12);
+        let knight = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 9.0)).unwrap();
+        let giant = spawn(&mut state, PlayerId::Player1, "Giant", Position::new(15.0, 9.0)).unwrap();
+
+        // A troop target left over from before is dropped
+        state.entities.get_mut(&giant).unwrap().target = Some(knight.as_u32());
+        assert!(!is_valid_target(&state, giant, knight));
+
+        update(&mut state, 0.0);
+        let target = state.entities[&giant].target.map(EntityId::from_u32).unwrap();
+        assert!(state.entities[&target].is_building());
+    }
+
+    #[test]
+    fn test_tower_keeps_lock_until_target_leaves_range() {
+        let mut state = GameState::new(13);
+        let tower_pos = state
+            .arena
+            .tower_position(PlayerId::Player1, crate::state::TowerType::LeftPrincess);
+        let tower = state
+            .entities
+            .iter()
+            .find(|(_, e)| e.owner == PlayerId::Player1 && e.position == tower_pos)
+            .map(|(id, _)| *id)
+            .unwrap();
+
+        let far = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(tower_pos.x + 6.0, tower_pos.y)).unwrap();
+        update(&mut state, 0.0);
+        assert_eq!(state.entities[&tower].target, Some(far.as_u32()));
+
+        // A closer enemy does not break the lock
+        let near = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(tower_pos.x + 3.0, tower_pos.y)).unwrap();
+        update(&mut state, 0.0);
+        assert_eq!(state.entities[&tower].target, Some(far.as_u32()));
+
+        // Leaving range does
+        state.entities.get_mut(&far).unwrap().position.x += 10.0;
+        update(&mut state, 0.0);
+        assert_eq!(state.entities[&tower].target, Some(near.as_u32()));
+    }
+
+    #[test]
+    fn test_troop_switches_to_closer_target_while_approaching() {
+        let mut state = GameState::new(14);
+        let knight = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(12.0, 9.0)).unwrap();
+        let far = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(20.0, 9.0)).unwrap();
+        update(&mut state, 0.0);
+        assert_eq!(state.entities[&knight].target, Some(far.as_u32()));
+
+        let near = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(15.0, 9.0)).unwrap();
+        update(&mut state, 0.0);
+        assert_eq!(state.entities[&knight].target, Some(near.as_u32()));
+
+        // With switching disabled the original target is kept
+        state.retarget_policies.troop.switch_while_approaching = false;
+        state.entities.get_mut(&knight).unwrap().target = Some(far.as_u32());
+        update(&mut state, 0.0);
+        assert_eq!(state.entities[&knight].target, Some(far.as_u32()));
+    }
+
+    #[test]
+    fn test_first_hit_windup() {
+        let mut state = GameState::new(15);
+        let attacker = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(12.0, 9.0)).unwrap();
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(13.0, 9.0)).unwrap();
+        if let EntityKind::Troop(data) = &mut state.entities.get_mut(&attacker).unwrap().kind {
+            data.first_hit_speed = 0.5;
+        }
+
+        let dt = 0.1;
+        let mut first_hit_tick = None;
+        for tick in 0..10 {
+            update(&mut state, dt);
+            if state.entities[&target].hp < state.entities[&target].max_hp {
+                first_hit_tick = Some(tick);
+                break;
+            }
+        }
+
+        // Windup counts down 0.5s in 0.1s steps: hit lands on the fifth update
+        assert_eq!(first_hit_tick, Some(4));
+        assert!(state.entities[&attacker].first_hit_timer.is_none());
+        assert!(state.entities[&attacker].attack_cooldown > 0.0);
+    }
+
+    #[test]
+    fn test_sustained_dps_matches_card_stats() {
+        // 0.34s is not a whole number of ticks, so per-hit rounding would drift
+        for attack_speed in [1.2, 0.34] {
+            let mut state = GameState::new(16);
+            let attacker = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(12.0, 9.0)).unwrap();
+            let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(13.0, 9.0)).unwrap();
+            if let EntityKind::Troop(data) = &mut state.entities.get_mut(&attacker).unwrap().kind {
+                data.attack_speed = attack_speed;
+            }
+            {
+                let target = state.entities.get_mut(&target).unwrap();
+                target.max_hp = 1.0e6;
+                target.hp = 1.0e6;
+                if let EntityKind::Troop(data) = &mut target.kind {
+                    data.damage = 0.0;
+                }
+            }
+            let damage = state.entities[&attacker].damage();
+
+            let seconds = 60.0;
+            for _ in 0..(seconds * 60.0) as u32 {
+                update(&mut state, crate::DELTA_TIME);
+            }
+            let dealt = state.entities[&target].max_hp - state.entities[&target].hp;
+            let hits = (dealt / damage).round();
+            let expected = seconds / attack_speed;
+            assert!((hits - expected).abs() <= 1.0, "{}s: {} hits, expected {}", attack_speed, hits, expected);
+        }
+    }
+
+    #[test]
+    fn test_mutual_lethal_hits_resolve_simultaneously() {
+        // Spawn order decides entity IDs, and so processing order
+        for healer_first in [true, false] {
+            let mut state = GameState::new(17);
+            let (healer, enemy) = if healer_first {
+                let healer = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(12.0, 9.0)).unwrap();
+                (healer, spawn(&mut state, PlayerId::Player2, "Knight", Position::new(13.0, 9.0)).unwrap())
+            } else {
+                let enemy = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(13.0, 9.0)).unwrap();
+                (spawn(&mut state, PlayerId::Player1, "Knight", Position::new(12.0, 9.0)).unwrap(), enemy)
+            };
+            if let EntityKind::Troop(data) = &mut state.entities.get_mut(&healer).unwrap().kind {
+                data.heal_on_hit = Some(crate::entities::HealOnHit { amount: 500.0, radius: 4.0 });
+            }
+            // Each is one hit from death
+            for id in [healer, enemy] {
+                state.entities.get_mut(&id).unwrap().hp = 1.0;
+            }
+
+            while state.entities[&healer].hp > 0.0 && state.entities[&enemy].hp > 0.0 {
+                update(&mut state, 1.0 / 60.0);
+            }
+            // Both hits land before the heal, so neither survives
+            assert_eq!(state.entities[&healer].hp, 0.0, "healer_first = {}", healer_first);
+            assert_eq!(state.entities[&enemy].hp, 0.0, "healer_first = {}", healer_first);
+        }
+    }
+
+    #[test]
+    fn test_siege_building_range_and_priority() {
+        let siege_state = |prefers_buildings: bool| {
+            let mut state = GameState::new(27);
+            let mut mortar = state.get_card_by_name("Archers").unwrap().clone();
+            mortar.name = "Mortar".to_string();
+            mortar.type_name = "building".to_string();
+            mortar.count = None;
+            mortar.range = Some(11.5);
+            mortar.min_range = Some(3.5);
+            mortar.prefers_buildings = prefers_buildings;
+            let mut cards = crate::card::get_test_cards();
+            cards.push(mortar);
+            state.load_cards(cards).unwrap();
+            state
+        };
+
+        // Enemies inside the blind spot are ignored; across the river is fine
+        let mut state = siege_state(false);
+        let mortar = spawn(&mut state, PlayerId::Player1, "Mortar", Position::new(10.0, 9.0)).unwrap();
+        let close = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(12.0, 9.0)).unwrap();
+        state.rebuild_spatial_index();
+        assert!(find_target(&state, mortar, PlayerId::Player1, Some(TargetType::Both)).is_none());
+        let far = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(18.5, 9.0)).unwrap();
+        state.rebuild_spatial_index();
+        assert_eq!(find_target(&state, mortar, PlayerId::Player1, Some(TargetType::Both)), Some(far));
+        assert!(!state.entities[&mortar].in_attack_range(&state.entities[&close]));
+
+        // A siege building skips a closer troop for the princess tower
+        for prefers_buildings in [false, true] {
+            let mut state = siege_state(prefers_buildings);
+            let mortar = spawn(&mut state, PlayerId::Player1, "Mortar", Position::new(14.0, 3.5)).unwrap();
+            let knight = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(19.0, 3.5)).unwrap();
+            let expected = if prefers_buildings {
+                let (id, _) = state
+                    .entities
+                    .iter()
+                    .find(|(_, e)| e.owner == PlayerId::Player2 && e.tower_type().is_some() && e.position.y < 9.0)
+                    .unwrap();
+                *id
+            } else {
+                knight
+            };
+            state.rebuild_spatial_index();
+            assert_eq!(
+                find_target(&state, mortar, PlayerId::Player1, Some(TargetType::Both)),
+                Some(expected),
+                "prefers_buildings = {}",
+                prefers_buildings
+            );
+        }
+    }
+
+    #[test]
+    fn test_converted_units_stop_being_attacked() {
+        let mut state = crate::fixtures::empty_arena();
+        let archers = crate::fixtures::spawn(&mut state, PlayerId::Player1, "Archers", Position::new(8.0, 9.0)).unwrap();
+        let knight = crate::fixtures::spawn(&mut state, PlayerId::Player2, "Knight", Position::new(12.0, 9.0)).unwrap();
+        if let EntityKind::Troop(data) = &mut state.entities.get_mut(&knight).unwrap().kind {
+            data.movement_speed = 0.0;
+        }
+        let is_projectile = |e: &crate::entities::Entity| matches!(e.kind, EntityKind::Projectile(_));
+        while !state.entities.values().any(is_projectile) {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert_eq!(state.entities[&archers].target, Some(knight.as_u32()));
+
+        // Switch sides with an arrow in flight
+        state.transfer_ownership(knight, PlayerId::Player1).unwrap();
+        assert_eq!(state.entities[&archers].target, None);
+        assert!(matches!(
+            state.events.last(),
+            Some(crate::GameEvent::OwnershipTransferred { from: PlayerId::Player2, .. })
+        ));
+        let hp = state.entities[&knight].hp;
+        for _ in 0..60 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert_eq!(state.entities[&knight].hp, hp);
+        assert!(!state.entities.values().any(is_projectile));
+        assert!(state.entities.values().all(|e| e.target.is_none()));
+
+        // Towers stay put
+        let mut state = GameState::new(44);
+        let tower = *state.entities.keys().next().unwrap();
+        assert!(state.transfer_ownership(tower, PlayerId::Player2).is_err());
+    }
+
+    #[test]
+    fn test_grid_targeting_matches_a_full_scan() {
+        let mut state = GameState::new(43);
+        let knight = state.get_card_by_name("Knight").unwrap().clone();
+        for i in 0..LINEAR_SCAN_LIMIT {
+            let owner = if i % 3 == 0 { PlayerId::Player2 } else { PlayerId::Player1 };
+            let position = Position::new(1.0 + (i * 11 % 30) as f32, 0.5 + (i * 7 % 17) as f32);
+            knight.spawn(&mut state, owner, position, 11).unwrap();
+        }
+        assert!(state.entities.len() > LINEAR_SCAN_LIMIT);
+        state.rebuild_spatial_index();
+
+        for (id, attacker) in &state.entities {
+            if !attacker.can_move() {
+                continue;
+            }
+            let expected = state
+                .entities
+                .iter()
+                .filter(|(_, e)| e.owner != attacker.owner && e.is_targetable())
+                .min_by(|(a_id, a), (b_id, b)| {
+                    let da = attacker.position.distance_squared(&a.position);
+                    let db = attacker.position.distance_squared(&b.position);
+                    da.total_cmp(&db).then(a_id.cmp(b_id))
+                })
+                .map(|(id, _)| *id);
+            assert_eq!(find_target(&state, *id, attacker.owner, attacker.target_type()), expected);
         }
     }
 }
@@ -2,11 +2,12 @@
 
 use crate::state::GameState;
 
-/// Updates elixir for all players.
+/// Updates elixir (and the opponent-visible estimate) for all players.
 pub fn update(state: &mut GameState, dt: f32) {
     for player in state.players.values_mut() {
         let regen = player.elixir_regen_rate * dt;
         player.add_elixir(regen);
+        player.regen_estimate(dt);
     }
 }
 
@@ -42,4 +43,30 @@ mod tests {
         let final_elixir = state.players[&PlayerId::Player1].elixir;
         assert_eq!(final_elixir, 10.0); // Capped at max
     }
+
+    #[test]
+    fn test_enemy_elixir_estimate_tracks_observed_plays() {
+        let mut state = GameState::new(42);
+        // Hidden from the opponent: the estimate still assumes a normal start
+        state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 10.0;
+        let play = |card: &str| crate::Action::PlayCard {
+            player: PlayerId::Player2,
+            card_name: card.to_string(),
+            level: 11,
+            position: shared::Position::new(24.0, 9.0),
+        };
+
+        crate::step(&mut state, &[play("Giant"), play("Knight")]).unwrap();
+        let seen = state.export_cr_state(PlayerId::Player1);
+        // Giant empties the assumed 5, and the Knight proves there was more
+        // (one tick of regen follows the plays)
+        assert!((seen.estimated_enemy_elixir - 1.0 / 60.0).abs() < 1e-6);
+        assert!((state.players[&PlayerId::Player2].elixir - 2.0).abs() < 0.02);
+
+        for _ in 0..60 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let seen = state.export_cr_state(PlayerId::Player1);
+        assert!((seen.estimated_enemy_elixir - 61.0 / 60.0).abs() < 0.01);
+    }
 }
@@ -0,0 +1,133 @@
+//! Environment system (event arena hazards that damage or slow the troops
+//! standing on them, and mode-wide effects such as Rage mode).
+
+use crate::entities::{StatusKind, Transport};
+use crate::state::{EntityId, GameState};
+
+/// How long (seconds) a hazard's slow outlasts a troop's last tick on it.
+pub const HAZARD_SLOW_LINGER: f32 = 0.2;
+
+/// How long (seconds) each tick's permanent rage lasts; it's renewed every
+/// tick, so it only runs out for troops that died.
+pub const PERMANENT_RAGE_REFRESH: f32 = 0.2;
+
+/// Applies every hazard in the arena layout to the live troops on it:
+/// damage on the hazard's pulses and a slow while they stand there. Ground
+/// hazards spare air troops and river jumpers in mid-air. In Rage mode
+/// every troop is raged too.
+pub fn update(state: &mut GameState, _dt: f32) {
+    if state.event_modifiers.permanent_rage {
+        for entity in state.entities.values_mut().filter(|e| e.can_move() && e.is_alive()) {
+            entity.apply_status(StatusKind::Rage, PERMANENT_RAGE_REFRESH);
+        }
+    }
+    if state.arena.hazards.is_empty() {
+        return;
+    }
+    // Pulses are timed by the tick this step ends on
+    let tick = state.tick + 1;
+    let mut hits: Vec<(EntityId, f32)> = Vec::new();
+    let mut slowed: Vec<EntityId> = Vec::new();
+
+    for (id, entity) in &state.entities {
+        if !entity.can_move() || !entity.is_alive() {
+            continue;
+        }
+        let airborne = entity.transport() == Transport::Air || entity.has_status(StatusKind::Jumping);
+        for hazard in state.arena.hazards_at(&entity.position) {
+            if airborne && !hazard.hits_air {
+                continue;
+            }
+            if hazard.pulses_at(tick) {
+                hits.push((*id, hazard.damage));
+            }
+            if hazard.slows && slowed.last() != Some(id) {
+                slowed.push(*id);
+            }
+        }
+    }
+
+    for (id, damage) in hits {
+        state.damage(id, damage);
+    }
+    for id in slowed {
+        if let Some(entity) = state.entities.get_mut(&id) {
+            entity.apply_status(StatusKind::Slow, HAZARD_SLOW_LINGER);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Hazard;
+    use crate::entities::EntityKind;
+    use crate::fixtures::{empty_arena, spawn};
+    use shared::{PlayerId, Position};
+
+    #[test]
+    fn test_hazards_pulse_damage_on_troops_standing_in_them() {
+        let mut state = empty_arena();
+        let lava = Hazard {
+            damage: 50.0,
+            interval: 0.5,
+            ..Hazard::new(8, 6, 3, 3)
+        };
+        state.arena.add_hazard(lava).unwrap();
+        let inside = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(9.5, 7.5)).unwrap();
+        let outside = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(9.5, 11.5)).unwrap();
+        let flyer = spawn(&mut state, PlayerId::Player1, "Baby Dragon", Position::new(9.0, 7.0)).unwrap();
+        // Hold everyone in place so only the hazard matters
+        for entity in state.entities.values_mut() {
+            if let EntityKind::Troop(data) = &mut entity.kind {
+                data.movement_speed = 0.0;
+            }
+        }
+        let hp = |state: &GameState, id: EntityId| state.entities[&id].hp + state.entities[&id].shield;
+        let start = [inside, outside, flyer].map(|id| hp(&state, id));
+
+        // Two pulses in the first second (at 0.5s and 1.0s)
+        for _ in 0..29 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert_eq!(hp(&state, inside), start[0]);
+        for _ in 0..31 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert_eq!(start[0] - hp(&state, inside), 100.0);
+        assert_eq!(hp(&state, outside), start[1]);
+        assert_eq!(hp(&state, flyer), start[2]);
+    }
+
+    #[test]
+    fn test_slowing_hazards_slow_troops_until_they_leave() {
+        let mut state = GameState::new(41);
+        state.load_cards(crate::card::get_test_cards()).unwrap();
+        let mud = Hazard {
+            slows: true,
+            ..Hazard::new(4, 0, 2, 18)
+        };
+        state.arena.add_hazard(mud).unwrap();
+        let knight = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(5.0, 9.0)).unwrap();
+        let full_speed = match &state.entities[&knight].kind {
+            EntityKind::Troop(data) => data.movement_speed,
+            _ => unreachable!(),
+        };
+
+        crate::step(&mut state, &[]).unwrap();
+        let entity = &state.entities[&knight];
+        assert!(entity.has_status(StatusKind::Slow));
+        assert!((entity.velocity.length() - full_speed * crate::entities::SLOW_SPEED_MULTIPLIER).abs() < 1e-4);
+
+        // Once it has walked off, the slow wears off
+        for _ in 0..60 * 5 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let entity = &state.entities[&knight];
+        assert!(entity.position.x > 6.5, "{:?}", entity.position);
+        assert!(!entity.has_status(StatusKind::Slow));
+
+        assert!(state.arena.add_hazard(Hazard::new(30, 0, 4, 1)).is_err());
+        assert!(state.arena.add_hazard(Hazard { damage: 1.0, interval: 0.0, ..Hazard::new(0, 0, 1, 1) }).is_err());
+    }
+}
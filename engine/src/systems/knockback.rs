@@ -0,0 +1,169 @@
+//! Knockback (pushback) applied by hits flagged with the "knockback" effect.
+//!
+//! Knockback is not a per-tick system: spells, projectiles, and melee hits
+//! call [`push`] when they land.
+
+use crate::entities::{CollisionShape, EntityKind};
+use crate::state::{EntityId, GameState};
+use shared::Position;
+
+/// Largest single movement step (tiles) while resolving a push, so troops
+/// stop at buildings instead of tunnelling through them.
+const PUSH_STEP: f32 = 0.1;
+
+/// Displaces a troop by up to `distance` tiles along `direction` (a unit
+/// vector), stopping at the arena edge or at buildings, and restarts its
+/// attack windup. Towers and other non-troops are unaffected.
+pub fn push(state: &mut GameState, target_id: EntityId, direction: (f32, f32), distance: f32) {
+    let target = match state.entities.get(&target_id) {
+        Some(t) if matches!(t.kind, EntityKind::Troop(_)) && t.is_alive() => t,
+        _ => return,
+    };
+    if direction == (0.0, 0.0) || distance <= 0.0 {
+        return;
+    }
+
+    let radius = target.radius();
+    let min = Position::new(radius, radius);
+    let max = Position::new(
+        state.arena.width as f32 * state.arena.tile_size - radius,
+        state.arena.height as f32 * state.arena.tile_size - radius,
+    );
+
+    let mut position = target.position;
+    let mut remaining = distance;
+    while remaining > 0.0 {
+        let step = remaining.min(PUSH_STEP);
+        let next = Position::new(position.x + direction.0 * step, position.y + direction.1 * step)
+            .clamp_to_rect(&min, &max);
+        if next == position || blocked(state, target_id, &next, radius) {
+            break;
+        }
+        position = next;
+        remaining -= step;
+    }
+
+    if let Some(target) = state.entities.get_mut(&target_id) {
+        target.position = position;
+        target.first_hit_timer = Some(target.first_hit_speed());
+    }
+}
+
+/// Pushes a troop directly away from `origin` (spell or explosion center).
+pub fn push_from(state: &mut GameState, target_id: EntityId, origin: &Position, distance: f32) {
+    let direction = match state.entities.get(&target_id) {
+        Some(target) => origin.direction_to(&target.position),
+        None => return,
+    };
+    push(state, target_id, direction, distance);
+}
+
+/// Returns true if a troop of `radius` at `position` would overlap a building.
+fn blocked(state: &GameState, moving_id: EntityId, position: &Position, radius: f32) -> bool {
+    state.entities.iter().any(|(id, other)| {
+        if *id == moving_id || !other.is_building() {
+            return false;
+        }
+        match other.collision_shape() {
+            CollisionShape::Rectangle { half_width, half_height } => {
+                position.circle_collides_rect(radius, &other.position, half_width, half_height)
+            }
+            CollisionShape::Circle { radius: other_radius } => {
+                let reach = radius + other_radius;
+                position.distance_squared(&other.position) < reach * reach
+            }
+            CollisionShape::None => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::fixtures::spawn;
+    use shared::PlayerId;
+
+    #[test]
+    fn test_knockback_spell_pushes_troops_away() {
+        let mut state = GameState::new(24);
+        let mut cards = crate::card::get_test_cards();
+        let fireball = cards.iter_mut().find(|c| c.name == "Fireball").unwrap();
+        fireball.effects = Some(vec!["damage".to_string(), "knockback".to_string()]);
+        fireball.knockback_distance = Some(1.5);
+        state.load_cards(cards).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+
+        let victim = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(20.0, 9.0)).unwrap();
+        // Hold the victim in place so only the knockback moves it
+        if let EntityKind::Troop(data) = &mut state.entities.get_mut(&victim).unwrap().kind {
+            data.movement_speed = 0.0;
+        }
+        let tower_positions: Vec<Position> = state
+            .entities
+            .values()
+            .filter(|e| e.is_building())
+            .map(|e| e.position)
+            .collect();
+
+        crate::step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Fireball".to_string(),
+                level: 11,
+                position: Position::new(19.0, 9.0),
+            }],
+        )
+        .unwrap();
+        while state.entities[&victim].hp == state.entities[&victim].max_hp {
+            crate::step(&mut state, &[]).unwrap();
+        }
+
+        let after = state.entities[&victim].position;
+        assert!((after.x - 21.5).abs() < 1e-4 && (after.y - 9.0).abs() < 1e-4, "{:?}", after);
+        let after_towers: Vec<Position> = state
+            .entities
+            .values()
+            .filter(|e| e.is_building())
+            .map(|e| e.position)
+            .collect();
+        assert_eq!(tower_positions.len(), after_towers.len());
+        assert!(tower_positions.iter().all(|p| after_towers.contains(p)));
+    }
+
+    #[test]
+    fn test_push_respects_arena_edge_and_resets_windup() {
+        let mut state = GameState::new(25);
+        let troop = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(10.0, 17.0)).unwrap();
+        state.entities.get_mut(&troop).unwrap().first_hit_timer = None;
+
+        push(&mut state, troop, (0.0, 1.0), 3.0);
+
+        let entity = &state.entities[&troop];
+        assert_eq!(entity.position.y, 18.0 - entity.radius());
+        assert_eq!(entity.first_hit_timer, Some(entity.first_hit_speed()));
+    }
+
+    #[test]
+    fn test_spawn_damage_hits_and_pushes_on_landing() {
+        let mut state = GameState::new(28);
+        let mut mega = state.get_card_by_name("Knight").unwrap().clone();
+        mega.name = "Mega Knight".to_string();
+        mega.levels.iter_mut().for_each(|l| l.spawn_damage = Some(150.0));
+        mega.spawn_damage_radius = Some(2.0);
+        mega.spawn_knockback = Some(1.0);
+
+        let near = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(21.5, 9.0)).unwrap();
+        let far = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(24.0, 9.0)).unwrap();
+        let ally = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(19.0, 9.0)).unwrap();
+        mega.spawn(&mut state, PlayerId::Player1, Position::new(20.0, 9.0), 11).unwrap();
+
+        let hit = &state.entities[&near];
+        assert_eq!(hit.max_hp - hit.hp, 150.0);
+        assert!((hit.position.x - 22.5).abs() < 1e-4, "{:?}", hit.position);
+        for id in [far, ally] {
+            assert_eq!(state.entities[&id].hp, state.entities[&id].max_hp);
+        }
+    }
+}
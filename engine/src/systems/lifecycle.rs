@@ -3,9 +3,51 @@
 use crate::state::GameState;
 
 /// Removes dead entities and manages spawning.
-pub fn update(state: &mut GameState, _dt: f32) {
-    // Remove dead entities
-    state.entities.retain(|_, entity| entity.is_alive());
+pub fn update(state: &mut GameState, dt: f32) {
+    // Mirror tower entity HP into player state before destroyed towers are removed
+    state.sync_tower_hp();
+    state.record_crowns();
+
+    // Expire status effects
+    for entity in state.entities.values_mut() {
+        entity.tick_statuses(dt);
+    }
+
+    // Remove dead entities, clearing targets that named them
+    state.remove_dead_entities();
 
     // TODO: Handle spawn timers and death effects
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{PlayerId, Position};
+
+    #[test]
+    fn test_removing_the_dead_clears_targets_on_them() {
+        let mut state = crate::fixtures::empty_arena();
+        let knight = crate::fixtures::spawn(&mut state, PlayerId::Player1, "Knight", Position::new(9.0, 9.0)).unwrap();
+        let archers = crate::fixtures::spawn(&mut state, PlayerId::Player2, "Archers", Position::new(12.0, 9.0)).unwrap();
+        let giant = crate::fixtures::spawn(&mut state, PlayerId::Player2, "Giant", Position::new(9.0, 12.0)).unwrap();
+        for id in [archers, giant] {
+            let entity = state.entities.get_mut(&id).unwrap();
+            entity.target = Some(knight.as_u32());
+            entity.first_hit_timer = Some(0.5);
+        }
+        state.entities.get_mut(&knight).unwrap().target = Some(giant.as_u32());
+
+        state.entities.get_mut(&knight).unwrap().hp = 0.0;
+        update(&mut state, crate::DELTA_TIME);
+        assert!(!state.entities.contains_key(&knight));
+        for id in [archers, giant] {
+            assert_eq!(state.entities[&id].target, None);
+            assert_eq!(state.entities[&id].first_hit_timer, None);
+        }
+
+        // Direct removals clean up too
+        state.entities.get_mut(&archers).unwrap().target = Some(giant.as_u32());
+        state.remove_entity(giant);
+        assert_eq!(state.entities[&archers].target, None);
+    }
+}
@@ -2,6 +2,12 @@
 
 pub mod combat;
 pub mod elixir;
+pub mod environment;
+pub mod knockback;
 pub mod lifecycle;
 pub mod movement;
+pub mod pathfinding;
 pub mod projectile;
+pub mod spawner;
+pub mod spell;
+pub mod zone;
@@ -1,12 +1,25 @@
 //! Movement system for entities.
 
+use crate::entities::{Entity, StatusKind, Transport};
+use crate::fp;
+use crate::spatial::LINEAR_SCAN_LIMIT;
 use crate::state::{EntityId, GameState};
+use crate::systems::pathfinding::{self, Path, PathfindingMode};
 use shared::{Position, Velocity};
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Unbounded};
 
 /// Updates entity movement - sets velocity toward targets and applies movement.
 pub fn update(state: &mut GameState, dt: f32) {
     // First pass: Update velocities based on targets
     let mut velocity_updates: Vec<(EntityId, Velocity)> = Vec::new();
+    let mut face_targets: Vec<(EntityId, Position)> = Vec::new();
+    let mut path_updates: Vec<(EntityId, Option<Path>)> = Vec::new();
+    // Borrowed out of the state for the pass over its entities
+    let mut flow_fields = std::mem::take(&mut state.flow_fields);
+    if state.pathfinding == PathfindingMode::FlowField {
+        flow_fields.sync(&state.arena);
+    }
 
     for (id, entity) in &state.entities {
         // Only move troops (not towers)
@@ -14,46 +27,78 @@ pub fn update(state: &mut GameState, dt: f32) {
             continue;
         }
 
-        // Check if entity has a target
-        if let Some(target_id) = entity.target {
-            let target_entity_id = EntityId::from_u32(target_id);
-
-            // Get target position (if target still exists)
-            if let Some(target) = state.entities.get(&target_entity_id) {
-                let distance = entity.position.distance_to(&target.position);
-                let attack_range = entity.attack_range();
-
-                // If target is out of range, move toward it
-                if distance > attack_range {
-                    let (dir_x, dir_y) = entity.position.direction_to(&target.position);
-                    let move_speed = entity.movement_speed();
-
-                    velocity_updates.push((
-                        *id,
-                        Velocity::new(dir_x * move_speed, dir_y * move_speed),
-                    ));
-                } else {
-                    // Target in range - stop moving
+        let target = entity.target.and_then(|target_id| state.entities.get(&EntityId::from_u32(target_id)));
+        let (aim, pathing) = match target {
+            Some(target) => {
+                // Move while out of range. A unit already chasing keeps
+                // closing until it is well inside range, so a target walking
+                // away doesn't make it stop and start every tick.
+                let chasing = entity.velocity.length_squared() > 0.0;
+                // Nor does a unit stop in mid-jump over the river
+                let airborne = entity.jumps_river() && state.arena.is_river(&entity.position);
+                let settled =
+                    state.can_engage(entity, target) && !(chasing && closing_in(entity, target)) && !airborne;
+                if settled {
+                    // Target in range - stop moving and turn to face it
                     velocity_updates.push((*id, Velocity::zero()));
+                    face_targets.push((*id, target.position));
+                    continue;
                 }
-            } else {
-                // Target doesn't exist anymore - stop
-                velocity_updates.push((*id, Velocity::zero()));
+                // Ground units cross the river by bridge, except ranged
+                // units aggroed on a troop, which hold at the bank
+                let holds_at_bank = entity.is_ranged() && !target.is_building();
+                (lead_point(state, entity, target), !holds_at_bank)
             }
-        } else {
-            // No target - stop moving
-            velocity_updates.push((*id, Velocity::zero()));
-        }
+            // Nothing to attack: march down the lane
+            None => match march_point(state, entity) {
+                Some(point) => (point, true),
+                None => {
+                    velocity_updates.push((*id, Velocity::zero()));
+                    continue;
+                }
+            },
+        };
+
+        let goal = match state.pathfinding {
+            _ if entity.transport() != Transport::Ground || !pathing => aim,
+            PathfindingMode::AStar => {
+                let (waypoint, path) = pathfinding::next_waypoint(&state.arena, entity, &aim);
+                path_updates.push((*id, path));
+                waypoint
+            }
+            PathfindingMode::FlowField => {
+                path_updates.push((*id, None));
+                pathfinding::next_waypoint_flow(&state.arena, &mut flow_fields, entity, &aim)
+            }
+        };
+        let (dir_x, dir_y) = entity.position.direction_to(&goal);
+        let move_speed = entity.movement_speed();
+        velocity_updates.push((*id, Velocity::new(dir_x * move_speed, dir_y * move_speed)));
     }
 
-    // Apply velocity updates
+    state.flow_fields = flow_fields;
+
+    // Apply velocity updates (moving entities face where they are heading)
     for (id, velocity) in velocity_updates {
         if let Some(entity) = state.entities.get_mut(&id) {
             entity.velocity = velocity;
+            if velocity.x != 0.0 || velocity.y != 0.0 {
+                entity.facing = fp::atan2(velocity.y, velocity.x);
+            }
+        }
+    }
+    for (id, point) in face_targets {
+        if let Some(entity) = state.entities.get_mut(&id) {
+            entity.face_toward(&point);
+        }
+    }
+    for (id, path) in path_updates {
+        if let Some(entity) = state.entities.get_mut(&id) {
+            entity.path = path;
         }
     }
 
-    // Second pass: Apply velocities to positions with collision detection
+    // Second pass: Apply velocities, sliding along the river bank
     let mut position_updates: Vec<(EntityId, Position)> = Vec::new();
 
     for (id, entity) in &state.entities {
@@ -62,18 +107,14 @@ pub fn update(state: &mut GameState, dt: f32) {
             continue;
         }
 
-        // Calculate new position
-        let new_x = entity.position.x + entity.velocity.x * dt;
-        let new_y = entity.position.y + entity.velocity.y * dt;
-        let new_position = Position::new(new_x, new_y);
-
-        // Check for collisions with other entities
-        let would_collide = check_collision(state, *id, &new_position);
-
-        if !would_collide {
-            position_updates.push((*id, new_position));
+        let target = entity.position.advance(&entity.velocity, dt);
+        let position = entity.position;
+        // Ground units never step into the river (but can walk out of it);
+        // blocked moves keep whichever axis stays dry
+        let candidates = [target, Position::new(target.x, position.y), Position::new(position.x, target.y)];
+        if let Some(next) = candidates.into_iter().find(|p| !enters_water(state, entity, p)) {
+            position_updates.push((*id, clamp_to_arena(state, entity, &next)));
         }
-        // If collision detected, don't move (stay in current position)
     }
 
     // Apply position updates
@@ -82,35 +123,470 @@ pub fn update(state: &mut GameState, dt: f32) {
             entity.position = position;
         }
     }
+
+    // Third pass: Push overlapping bodies apart
+    for _ in 0..SEPARATION_ITERATIONS {
+        separate(state);
+    }
+
+    // Units over the river are mid-jump
+    let river = &state.arena;
+    for entity in state.entities.values_mut() {
+        if entity.jumps_river() && river.is_river(&entity.position) {
+            entity.apply_status(StatusKind::Jumping, JUMP_LANDING_TIME);
+        }
+    }
 }
 
-/// Checks if moving an entity to a new position would cause a collision.
-fn check_collision(state: &GameState, moving_entity_id: EntityId, new_position: &Position) -> bool {
-    let moving_entity = &state.entities[&moving_entity_id];
-    let moving_radius = moving_entity.radius();
+/// Relaxation passes per tick when pushing overlapping bodies apart.
+const SEPARATION_ITERATIONS: usize = 2;
 
-    // Check against all other entities
-    for (other_id, other_entity) in &state.entities {
-        // Skip self
-        if *other_id == moving_entity_id {
-            continue;
+/// How far inside its attack range (tiles) a chasing unit gets before it
+/// stops.
+pub const CHASE_HYSTERESIS: f32 = 0.25;
+
+/// Longest lead (seconds) a chaser aims ahead of a moving target.
+pub const MAX_LEAD_TIME: f32 = 1.0;
+
+/// How close (tiles, along the lane) a marching troop gets to a waypoint
+/// before moving on to the next.
+pub const MARCH_ARRIVAL_DISTANCE: f32 = 0.25;
+
+/// How close a marching troop walks to the enemy King tower's center
+/// before stopping (its hitbox is about 2 tiles across each way).
+pub const KING_ARRIVAL_DISTANCE: f32 = 2.5;
+
+/// How long (seconds) the jump status outlasts a river jumper's last tick
+/// over the water, so the landing shows up in snapshots.
+pub const JUMP_LANDING_TIME: f32 = 0.2;
+
+/// True while `entity` is in range of `target` but not yet
+/// [`CHASE_HYSTERESIS`] inside it.
+fn closing_in(entity: &Entity, target: &Entity) -> bool {
+    let distance = entity.position.distance_to(&target.position) - target.radius();
+    let stop_at = (entity.attack_range() - CHASE_HYSTERESIS).max(entity.min_attack_range());
+    distance > stop_at
+}
+
+/// Where to head to catch `target`: its position extrapolated by its
+/// current velocity over roughly the time it takes to get there.
+fn lead_point(state: &GameState, entity: &Entity, target: &Entity) -> Position {
+    let speed = entity.movement_speed();
+    if speed <= 0.0 || target.velocity.length_squared() == 0.0 {
+        return target.position;
+    }
+    let lead = (entity.position.distance_to(&target.position) / speed).min(MAX_LEAD_TIME);
+    clamp_to_arena(state, target, &target.position.advance(&target.velocity, lead))
+}
+
+/// Where a troop with nothing to attack heads next along its lane's
+/// [`march_waypoints`](crate::arena::Arena::march_waypoints): the first one
+/// it hasn't yet passed on its way toward the enemy side. `None` once it
+/// has reached the enemy King tower.
+fn march_point(state: &GameState, entity: &Entity) -> Option<Position> {
+    let lane = entity.lane().unwrap_or_else(|| state.arena.lane_of(&entity.position));
+    let [near, far, king] = state.arena.march_waypoints(entity.owner, lane);
+    let forward = (far.x - near.x).signum();
+    let ahead = |point: &Position| (point.x - entity.position.x) * forward > MARCH_ARRIVAL_DISTANCE;
+    if ahead(&near) {
+        Some(near)
+    } else if ahead(&far) {
+        Some(far)
+    } else if entity.position.distance_to(&king) > KING_ARRIVAL_DISTANCE {
+        Some(king)
+    } else {
+        None
+    }
+}
+
+/// Returns true if moving `entity` to `position` would walk a ground unit
+/// into the river. Units that jump the river never do.
+fn enters_water(state: &GameState, entity: &Entity, position: &Position) -> bool {
+    entity.transport() == Transport::Ground
+        && !entity.jumps_river()
+        && state.arena.is_river(position)
+        && !state.arena.is_river(&entity.position)
+}
+
+/// Keeps the whole body inside the arena.
+fn clamp_to_arena(state: &GameState, entity: &Entity, position: &Position) -> Position {
+    let radius = entity.radius();
+    let width = state.arena.width as f32 * state.arena.tile_size;
+    let height = state.arena.height as f32 * state.arena.tile_size;
+    position.clamp_to_rect(&Position::new(radius, radius), &Position::new(width - radius, height - radius))
+}
+
+/// Pushes overlapping bodies apart along their contact normal. Two troops
+/// split the overlap in inverse proportion to their mass, so heavy troops
+/// shove light ones aside; a troop overlapping a tower or building takes
+/// all of it, so it slides around the obstacle instead of stopping dead.
+/// Air and ground are separate layers: flyers pass over ground troops and
+/// buildings, and only bump into other flyers.
+fn separate(state: &mut GameState) {
+    let linear = state.entities.len() <= LINEAR_SCAN_LIMIT;
+    if !linear {
+        state.rebuild_spatial_index();
+    }
+    let is_body = |e: &Entity| e.is_alive() && e.radius() > 0.0 && (e.can_move() || e.is_building());
+
+    let mut pushes: BTreeMap<EntityId, (f32, f32)> = BTreeMap::new();
+    for (a_id, a) in state.entities.iter().filter(|(_, e)| is_body(e)) {
+        let (mut later, mut nearby);
+        let candidates: &mut dyn Iterator<Item = (&EntityId, &Entity)> = if linear {
+            later = state.entities.range((Excluded(*a_id), Unbounded));
+            &mut later
+        } else {
+            // Each pair once, from the lower ID's side
+            nearby = state
+                .spatial
+                .near(&a.position, a.radius(), None)
+                .filter(|e| e.id > *a_id && a.position.distance_to(&e.position) < a.radius() + e.radius)
+                .map(|e| (&e.id, &state.entities[&e.id]));
+            &mut nearby
+        };
+        for (&b_id, b) in candidates {
+            if !is_body(b) || a.transport() != b.transport() || !(a.can_move() || b.can_move()) {
+                continue;
+            }
+            let overlap = a.radius() + b.radius() - a.position.distance_to(&b.position);
+            if overlap <= 0.0 {
+                continue;
+            }
+            // Bodies exactly on top of each other split along x, by ID order
+            let normal = match a.position.direction_to(&b.position) {
+                (0.0, 0.0) => (1.0, 0.0),
+                direction => direction,
+            };
+            let (a_share, b_share) = match (a.can_move(), b.can_move()) {
+                // The lighter troop gives way more; equal masses split evenly
+                (true, true) => match a.mass() + b.mass() {
+                    total if total > 0.0 => (b.mass() / total, a.mass() / total),
+                    _ => (0.5, 0.5),
+                },
+                (true, false) => (1.0, 0.0),
+                _ => (0.0, 1.0),
+            };
+            for (id, sign, share) in [(*a_id, -1.0, a_share), (b_id, 1.0, b_share)] {
+                if share > 0.0 {
+                    let push = pushes.entry(id).or_insert((0.0, 0.0));
+                    push.0 += sign * normal.0 * overlap * share;
+                    push.1 += sign * normal.1 * overlap * share;
+                }
+            }
         }
+    }
 
-        // Skip entities with no collision radius
-        let other_radius = other_entity.radius();
-        if other_radius == 0.0 {
-            continue;
+    let moves: Vec<(EntityId, Position)> = pushes
+        .into_iter()
+        .filter_map(|(id, (dx, dy))| {
+            let entity = &state.entities[&id];
+            let pushed = Position::new(entity.position.x + dx, entity.position.y + dy);
+            (!enters_water(state, entity, &pushed)).then(|| (id, clamp_to_arena(state, entity, &pushed)))
+        })
+        .collect();
+    for (id, position) in moves {
+        if let Some(entity) = state.entities.get_mut(&id) {
+            entity.position = position;
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::state::TowerType;
+    use shared::PlayerId;
+
+    #[test]
+    fn test_facing_follows_movement_and_target() {
+        let mut state = fixtures::empty_arena();
+        let ally = spawn(&mut state, PlayerId::Player1, "Knight", 10.0, 9.0);
+        let enemy = spawn(&mut state, PlayerId::Player2, "Knight", 10.0, 14.0);
+        assert_eq!(state.entities[&enemy].facing, std::f32::consts::PI);
 
-        // Calculate distance between centers
-        let distance = new_position.distance_to(&other_entity.position);
-        let min_distance = moving_radius + other_radius;
+        // Walking toward an enemy straight "up" the y axis
+        state.entities.get_mut(&ally).unwrap().target = Some(enemy.as_u32());
+        update(&mut state, 1.0 / 60.0);
+        let (fx, fy) = state.entities[&ally].facing_vector();
+        assert!(fx.abs() < 1e-4 && (fy - 1.0).abs() < 1e-4);
 
-        // Collision if circles overlap
-        if distance < min_distance {
-            return true;
+        // Once in range it stops and keeps facing the target
+        state.entities.get_mut(&enemy).unwrap().position = Position::new(9.0, 9.0);
+        update(&mut state, 1.0 / 60.0);
+        let (fx, _) = state.entities[&ally].facing_vector();
+        assert!((fx + 1.0).abs() < 1e-3);
+
+        let exported = state.export_cr_state(PlayerId::Player1);
+        assert!((exported.ally_units[0].facing.abs() - std::f32::consts::PI).abs() < 0.05);
+    }
+
+    fn spawn(state: &mut GameState, owner: PlayerId, card: &str, x: f32, y: f32) -> EntityId {
+        fixtures::spawn(state, owner, card, Position::new(x, y)).unwrap()
+    }
+
+    /// Keeps a troop from moving on its own (idle troops march otherwise).
+    fn park(state: &mut GameState, id: EntityId) {
+        if let crate::entities::EntityKind::Troop(data) = &mut state.entities.get_mut(&id).unwrap().kind {
+            data.movement_speed = 0.0;
+        }
+    }
+
+    fn walk(state: &mut GameState, id: EntityId, target: EntityId, ticks: u32) -> Vec<Position> {
+        let mut path = Vec::new();
+        for _ in 0..ticks {
+            state.entities.get_mut(&id).unwrap().target = Some(target.as_u32());
+            update(state, 1.0 / 60.0);
+            path.push(state.entities[&id].position);
+        }
+        path
+    }
+
+    #[test]
+    fn test_melee_reroutes_via_bridge() {
+        let mut state = GameState::new(28);
+        let knight = spawn(&mut state, PlayerId::Player1, "Knight", 12.0, 9.0);
+        let king = state
+            .entities
+            .iter()
+            .find(|(_, e)| e.owner == PlayerId::Player2 && e.tower_type() == Some(TowerType::King))
+            .map(|(id, _)| *id)
+            .unwrap();
+
+        let path = walk(&mut state, knight, king, 60 * 20);
+        assert!(path.iter().all(|p| !state.arena.is_river(p)));
+        assert!(path.last().unwrap().x > 20.0, "{:?}", path.last());
+    }
+
+    #[test]
+    fn test_troops_cross_on_the_bridge() {
+        let mut state = GameState::new(30);
+        let knight = spawn(&mut state, PlayerId::Player1, "Knight", 12.0, 7.0);
+        let king = state
+            .entities
+            .iter()
+            .find(|(_, e)| e.owner == PlayerId::Player2 && e.tower_type() == Some(TowerType::King))
+            .map(|(id, _)| *id)
+            .unwrap();
+
+        let radius = state.entities[&knight].radius();
+        let path = walk(&mut state, knight, king, 60 * 20);
+        let on_bridge: Vec<&Position> = path.iter().filter(|p| p.x > 15.0 && p.x < 17.0).collect();
+        assert!(!on_bridge.is_empty());
+        // The nearer (left) bridge spans y 2..5; the whole body stays on it
+        for p in on_bridge {
+            assert!(p.y - radius >= 2.0 - 1e-4 && p.y + radius <= 5.0 + 1e-4, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn test_flyers_go_straight_over_river_and_troops() {
+        let mut state = GameState::new(31);
+        let dragon = spawn(&mut state, PlayerId::Player1, "Baby Dragon", 12.0, 9.0);
+        let king = state
+            .entities
+            .iter()
+            .find(|(_, e)| e.owner == PlayerId::Player2 && e.tower_type() == Some(TowerType::King))
+            .map(|(id, _)| *id)
+            .unwrap();
+        // A ground troop parked right in the flight line
+        let blocker = spawn(&mut state, PlayerId::Player1, "Knight", 13.0, 9.0);
+        if let crate::entities::EntityKind::Troop(data) = &mut state.entities.get_mut(&blocker).unwrap().kind {
+            data.movement_speed = 0.0;
+        }
+
+        let path = walk(&mut state, dragon, king, 60 * 6);
+        assert!(path.iter().all(|p| (p.y - 9.0).abs() < 1e-3), "{:?}", path.last());
+        assert!(path.iter().any(|p| state.arena.is_river(p)));
+        assert!(path.last().unwrap().x > 17.0);
+    }
+
+    #[test]
+    fn test_river_jumpers_go_straight_across() {
+        let mut state = GameState::new(31);
+        let hog = spawn(&mut state, PlayerId::Player1, "Knight", 12.0, 9.0);
+        if let crate::entities::EntityKind::Troop(data) = &mut state.entities.get_mut(&hog).unwrap().kind {
+            data.jumps_river = true;
+        }
+        let king = state
+            .entities
+            .iter()
+            .find(|(_, e)| e.owner == PlayerId::Player2 && e.tower_type() == Some(TowerType::King))
+            .map(|(id, _)| *id)
+            .unwrap();
+
+        let mut jumped = false;
+        for _ in 0..60 * 6 {
+            let position = walk(&mut state, hog, king, 1)[0];
+            assert!((position.y - 9.0).abs() < 1e-3, "{:?}", position);
+            if state.arena.is_river(&position) {
+                jumped = true;
+                assert!(state.entities[&hog].has_status(StatusKind::Jumping));
+            }
+        }
+        assert!(jumped);
+        assert!(state.entities[&hog].position.x > 17.0);
+    }
+
+    #[test]
+    fn test_idle_troops_march_down_their_lane() {
+        let mut state = fixtures::empty_arena();
+        let knight = spawn(&mut state, PlayerId::Player1, "Knight", 8.0, 12.0);
+        let dragon = spawn(&mut state, PlayerId::Player2, "Baby Dragon", 25.0, 5.0);
+        let (mut crossed_at, mut flew_over) = (None, None);
+        for _ in 0..60 * 40 {
+            update(&mut state, 1.0 / 60.0);
+            let (k, d) = (state.entities[&knight].position, state.entities[&dragon].position);
+            if crossed_at.is_none() && k.x > 16.0 {
+                crossed_at = Some(k.y);
+            }
+            if flew_over.is_none() && d.x < 16.0 {
+                flew_over = Some(d.y);
+            }
         }
+        // Over the bridge of the lane each was deployed in
+        let crossed_at = crossed_at.expect("knight never crossed");
+        assert!((13.0..16.0).contains(&crossed_at), "{}", crossed_at);
+        let flew_over = flew_over.expect("dragon never crossed");
+        assert!((2.0..5.0).contains(&flew_over), "{}", flew_over);
+
+        // Then on to the enemy King tower, and stop there
+        let king = state.arena.tower_position(PlayerId::Player2, TowerType::King);
+        let knight = &state.entities[&knight];
+        assert!(knight.position.distance_to(&king) <= KING_ARRIVAL_DISTANCE + 1e-3, "{:?}", knight.position);
+        assert_eq!(knight.velocity, Velocity::zero());
+    }
+
+    #[test]
+    fn test_movement_stays_inside_the_arena() {
+        let mut state = GameState::new(32);
+        let dragon = spawn(&mut state, PlayerId::Player1, "Baby Dragon", 10.0, 17.5);
+        let enemy = spawn(&mut state, PlayerId::Player2, "Knight", 24.0, 17.5);
+        // Hugging the edge, more than half the body hangs outside
+        state.entities.get_mut(&dragon).unwrap().position.y = 17.95;
+        let radius = state.entities[&dragon].radius();
+
+        walk(&mut state, dragon, enemy, 1);
+        let position = state.entities[&dragon].position;
+        assert!(position.x > 10.0 && position.y <= 18.0 - radius, "{:?}", position);
     }
 
-    false
+    #[test]
+    fn test_overlapping_troops_are_pushed_apart() {
+        let mut state = GameState::new(33);
+        let a = spawn(&mut state, PlayerId::Player1, "Knight", 10.0, 9.0);
+        let b = spawn(&mut state, PlayerId::Player1, "Knight", 12.0, 9.0);
+        // Spawns never overlap, so push the two together by hand
+        state.entities.get_mut(&b).unwrap().position = Position::new(10.2, 9.0);
+        park(&mut state, a);
+        park(&mut state, b);
+        let before = state.entities[&a].position;
+
+        update(&mut state, 1.0 / 60.0);
+        let (pa, pb) = (state.entities[&a].position, state.entities[&b].position);
+        let min = state.entities[&a].radius() + state.entities[&b].radius();
+        assert!(pa.distance_to(&pb) > 0.2, "{:?} {:?}", pa, pb);
+        for _ in 0..10 {
+            update(&mut state, 1.0 / 60.0);
+        }
+        let (pa, pb) = (state.entities[&a].position, state.entities[&b].position);
+        assert!(pa.distance_to(&pb) >= min - 1e-3, "{:?} {:?}", pa, pb);
+        // Pushed along the contact normal, evenly
+        assert!(pa.x < before.x && (pa.y - 9.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_heavy_troops_shove_light_ones() {
+        let mut state = GameState::new(35);
+        let mut golem = state.get_card_by_name("Knight").unwrap().clone();
+        golem.mass = Some(4.0 * crate::card::DEFAULT_MASS);
+        golem.spawn(&mut state, PlayerId::Player1, Position::new(10.0, 9.0), 11).unwrap();
+        let heavy = *state.entities.keys().max_by_key(|id| id.as_u32()).unwrap();
+        let light = spawn(&mut state, PlayerId::Player1, "Knight", 12.0, 9.0);
+        state.entities.get_mut(&light).unwrap().position = Position::new(10.2, 9.0);
+        park(&mut state, heavy);
+        park(&mut state, light);
+
+        update(&mut state, 1.0 / 60.0);
+        let (ph, pl) = (state.entities[&heavy].position, state.entities[&light].position);
+        let min = state.entities[&heavy].radius() + state.entities[&light].radius();
+        assert!(ph.distance_to(&pl) >= min - 1e-3, "{:?} {:?}", ph, pl);
+        // The light troop takes four fifths of the overlap
+        let (heavy_moved, light_moved) = (10.0 - ph.x, pl.x - 10.2);
+        assert!((light_moved - 4.0 * heavy_moved).abs() < 1e-4, "{} {}", heavy_moved, light_moved);
+    }
+
+    #[test]
+    fn test_chasing_a_fleeing_target_does_not_stutter() {
+        let mut state = fixtures::empty_arena();
+        let chaser = spawn(&mut state, PlayerId::Player1, "Knight", 4.0, 9.0);
+        let runner = spawn(&mut state, PlayerId::Player2, "Knight", 5.3, 9.0);
+        let goal = spawn(&mut state, PlayerId::Player1, "Knight", 14.0, 9.0);
+        for (id, factor) in [(goal, 0.0), (runner, 0.5)] {
+            if let crate::entities::EntityKind::Troop(data) = &mut state.entities.get_mut(&id).unwrap().kind {
+                data.movement_speed *= factor;
+            }
+        }
+
+        let mut restarts = 0;
+        let mut was_moving = false;
+        for _ in 0..120 {
+            state.entities.get_mut(&chaser).unwrap().target = Some(runner.as_u32());
+            state.entities.get_mut(&runner).unwrap().target = Some(goal.as_u32());
+            update(&mut state, 1.0 / 60.0);
+            let moving = state.entities[&chaser].velocity.length_squared() > 0.0;
+            if moving && !was_moving {
+                restarts += 1;
+            }
+            was_moving = moving;
+        }
+        // A slower runner is caught, then slips out of range now and then;
+        // without the band the chaser would stop and start every few ticks
+        assert!(restarts <= 4, "chaser restarted {} times", restarts);
+        let (c, r) = (&state.entities[&chaser], &state.entities[&runner]);
+        let gap = c.position.distance_to(&r.position) - r.radius();
+        assert!(gap < c.attack_range() + 0.1, "{:?} {:?}", c.position, r.position);
+    }
+
+    #[test]
+    fn test_chasers_lead_moving_targets() {
+        let mut state = fixtures::empty_arena();
+        let chaser = spawn(&mut state, PlayerId::Player1, "Knight", 6.0, 9.0);
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", 10.0, 9.0);
+        state.entities.get_mut(&chaser).unwrap().target = Some(target.as_u32());
+        state.entities.get_mut(&target).unwrap().velocity = Velocity::new(0.0, 1.0);
+
+        update(&mut state, 1.0 / 60.0);
+        // Heads for where the target will be, not straight at it
+        assert!(state.entities[&chaser].velocity.y > 0.0);
+    }
+
+    #[test]
+    fn test_troops_slide_past_a_blocker() {
+        let mut state = GameState::new(34);
+        let walker = spawn(&mut state, PlayerId::Player1, "Knight", 9.0, 9.0);
+        let blocker = spawn(&mut state, PlayerId::Player1, "Knight", 10.5, 9.05);
+        let enemy = spawn(&mut state, PlayerId::Player2, "Knight", 13.5, 9.0);
+        for id in [blocker, enemy] {
+            if let crate::entities::EntityKind::Troop(data) = &mut state.entities.get_mut(&id).unwrap().kind {
+                data.movement_speed = 0.0;
+            }
+        }
+
+        let path = walk(&mut state, walker, enemy, 60 * 6);
+        assert!(path.last().unwrap().x > 11.0, "{:?}", path.last());
+    }
+
+    #[test]
+    fn test_ranged_holds_at_bank_against_troops() {
+        let mut state = GameState::new(29);
+        let musketeer = spawn(&mut state, PlayerId::Player1, "Musketeer", 12.0, 9.0);
+        let enemy = spawn(&mut state, PlayerId::Player2, "Knight", 22.0, 9.0);
+        park(&mut state, enemy);
+
+        let path = walk(&mut state, musketeer, enemy, 60 * 8);
+        let last = path.last().unwrap();
+        assert!(path.iter().all(|p| !state.arena.is_river(p)));
+        assert!(last.x > 14.0 && last.x < 15.0 && (last.y - 9.0).abs() < 1e-3, "{:?}", last);
+    }
 }
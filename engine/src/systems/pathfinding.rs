@@ -0,0 +1,432 @@
+//! Pathfinding for ground units over the arena tile grid.
+//!
+//! Not a per-tick system: the movement system asks [`next_waypoint`] where
+//! a ground unit should head. Paths are planned with A* over walkable tiles
+//! (8-connected, no corner cutting), cached on the entity, and re-planned
+//! once the goal has moved more than [`REPLAN_DISTANCE`].
+//!
+//! With [`PathfindingMode::FlowField`], units ask [`next_waypoint_flow`]
+//! instead: one distance field is built per goal tile and shared by every
+//! unit heading there, so a crowd pushing the same tower costs one search
+//! rather than one per unit. Fields are kept until the arena's tiles change.
+
+use crate::arena::{Arena, TileType};
+use crate::entities::Entity;
+use serde::{Deserialize, Serialize};
+use shared::Position;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+/// Goal movement (tiles) after which a cached path is re-planned.
+pub const REPLAN_DISTANCE: f32 = 1.0;
+
+/// Step costs, scaled so diagonal moves stay integral (10 * sqrt 2 ≈ 14).
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Tiles a flow-field unit looks ahead along the field for a straight
+/// shortcut.
+pub const FLOW_LOOKAHEAD: usize = 8;
+
+/// How ground units find their way around the river and walls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathfindingMode {
+    /// Per-unit A* with a cached path (see [`next_waypoint`]).
+    #[default]
+    AStar,
+    /// Shared per-goal flow fields (see [`next_waypoint_flow`]); cheaper
+    /// with hundreds of units converging on the same few targets.
+    FlowField,
+}
+
+/// Most flow fields kept at once; the cache starts over past this.
+pub const MAX_FLOW_FIELDS: usize = 64;
+
+/// Flow fields built so far, keyed by goal tile. They stay valid until the
+/// arena's tiles change (see [`FlowFields::sync`]). Clones start empty.
+#[derive(Debug, Default)]
+pub struct FlowFields {
+    fields: BTreeMap<(u32, u32), Field>,
+    /// Tiles the fields were built over.
+    tiles: Vec<Vec<TileType>>,
+}
+
+impl Clone for FlowFields {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// For every tile (row-major), the next tile downhill toward the goal, or
+/// `NONE` at the goal itself and wherever the goal can't be reached.
+#[derive(Debug, Clone)]
+struct Field {
+    next: Vec<usize>,
+}
+
+impl Field {
+    const NONE: usize = usize::MAX;
+}
+
+impl FlowFields {
+    /// Number of distinct goals a field has been built for.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Drops every field if `arena`'s tiles differ from the ones they were
+    /// built over (or too many have piled up).
+    pub fn sync(&mut self, arena: &Arena) {
+        if self.tiles != arena.tiles || self.fields.len() > MAX_FLOW_FIELDS {
+            self.fields.clear();
+            self.tiles.clone_from(&arena.tiles);
+        }
+    }
+
+    fn field(&mut self, arena: &Arena, goal: (u32, u32)) -> &Field {
+        self.fields.entry(goal).or_insert_with(|| build_field(arena, goal))
+    }
+}
+
+/// A planned route to `goal`. `waypoints` are still ahead of the unit,
+/// nearest first, and end at the goal (or the closest reachable point).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Path {
+    pub goal: Position,
+    pub waypoints: Vec<Position>,
+}
+
+/// Returns the point `entity` should walk toward to reach `goal`, and its
+/// path cache afterwards (`None` when the direct line is clear).
+///
+/// Shortcuts are only taken when the unit's whole body stays over walkable
+/// tiles, so troops head for the nearest bridge and cross along it rather
+/// than clipping the river bank. Units that jump the river count it as
+/// walkable for shortcuts, so they go straight over it unless something
+/// else is in the way; the planned path itself still uses a bridge.
+pub fn next_waypoint(arena: &Arena, entity: &Entity, goal: &Position) -> (Position, Option<Path>) {
+    let from = &entity.position;
+    let is_clear = |a: &Position, b: &Position| is_clear(arena, entity, a, b);
+    if is_clear(from, goal) {
+        return (*goal, None);
+    }
+
+    let mut path = match &entity.path {
+        Some(path) if path.goal.distance_to(goal) <= REPLAN_DISTANCE && !path.waypoints.is_empty() => path.clone(),
+        _ => match find_path(arena, from, goal) {
+            Some(path) => path,
+            // Nowhere to go: head straight and let movement stop at the water
+            None => return (*goal, None),
+        },
+    };
+
+    // Skip every waypoint the one after it can be reached directly from
+    // here, which also recovers after being pushed off the path
+    while path.waypoints.len() > 1 && is_clear(from, &path.waypoints[1]) {
+        path.waypoints.remove(0);
+    }
+    (path.waypoints[0], Some(path))
+}
+
+/// Flow-field counterpart of [`next_waypoint`]: the point `entity` should
+/// walk toward to reach `goal`, using (and if need be building) the
+/// goal's field in `fields` (call [`FlowFields::sync`] first each tick).
+/// Nothing is cached on the entity.
+///
+/// The unit follows the field downhill for up to [`FLOW_LOOKAHEAD`] tiles
+/// and heads for the farthest of them it can reach in a straight line. A
+/// unit that can't reach the goal at all heads straight for it.
+pub fn next_waypoint_flow(arena: &Arena, fields: &mut FlowFields, entity: &Entity, goal: &Position) -> Position {
+    let from = &entity.position;
+    if is_clear(arena, entity, from, goal) {
+        return *goal;
+    }
+    let w = arena.width;
+    let goal_tile = arena.world_to_tile(goal);
+    let field = fields.field(arena, goal_tile);
+    let (x, y) = arena.world_to_tile(from);
+    let mut current = (y * w + x) as usize;
+
+    let mut ahead = Vec::with_capacity(FLOW_LOOKAHEAD);
+    while ahead.len() < FLOW_LOOKAHEAD && field.next[current] != Field::NONE {
+        current = field.next[current];
+        if current == (goal_tile.1 * w + goal_tile.0) as usize {
+            ahead.push(*goal);
+            break;
+        }
+        ahead.push(arena.tile_to_world(current as u32 % w, current as u32 / w));
+    }
+    // Unreachable (or already on the goal tile): head straight for it
+    let Some(&first) = ahead.first() else {
+        return *goal;
+    };
+
+    // The farthest point in sight: usually the end of the lookahead,
+    // otherwise walk forward from the first step while the next is clear
+    let last = ahead[ahead.len() - 1];
+    if ahead.len() > 1 && is_clear(arena, entity, from, &last) {
+        return last;
+    }
+    ahead
+        .iter()
+        .skip(1)
+        .take_while(|point| is_clear(arena, entity, from, point))
+        .last()
+        .copied()
+        .unwrap_or(first)
+}
+
+/// Builds the flow field toward `goal`: Dijkstra outward from the goal
+/// (step costs are symmetric, so costs match A*), then each tile points at
+/// its cheapest neighbor, the first one winning ties.
+fn build_field(arena: &Arena, goal: (u32, u32)) -> Field {
+    let (w, h) = (arena.width as i32, arena.height as i32);
+    let open: Vec<bool> = (0..w * h).map(|i| walkable(arena, i % w, i / w)).collect();
+    let walkable = |x: i32, y: i32| x >= 0 && y >= 0 && x < w && y < h && open[(y * w + x) as usize];
+    let mut cost = vec![u32::MAX; (arena.width * arena.height) as usize];
+    let start = (goal.1 as i32 * w + goal.0 as i32) as usize;
+    cost[start] = 0;
+    let mut open = BinaryHeap::from([Reverse((0, start))]);
+    while let Some(Reverse((here, current))) = open.pop() {
+        if here > cost[current] {
+            continue;
+        }
+        let (x, y) = ((current as i32) % w, (current as i32) / w);
+        for (nx, ny, step) in neighbors(walkable, x, y) {
+            let next = (ny * w + nx) as usize;
+            if here + step < cost[next] {
+                cost[next] = here + step;
+                open.push(Reverse((here + step, next)));
+            }
+        }
+    }
+
+    let next = (0..cost.len())
+        .map(|i| {
+            let (x, y) = ((i as i32) % w, (i as i32) / w);
+            neighbors(walkable, x, y)
+                .map(|(nx, ny, _)| (cost[(ny * w + nx) as usize], (ny * w + nx) as usize))
+                .filter(|&(c, _)| c < cost[i])
+                .min_by_key(|&(c, _)| c)
+                .map_or(Field::NONE, |(_, n)| n)
+        })
+        .collect();
+    Field { next }
+}
+
+/// Plans a walkable route from `from` to `goal`. If the goal's tile can't
+/// be reached, the route ends at the reachable tile closest to it.
+/// Returns `None` if `from` is not on a walkable tile.
+pub fn find_path(arena: &Arena, from: &Position, goal: &Position) -> Option<Path> {
+    let (w, h) = (arena.width as i32, arena.height as i32);
+    let start = arena.world_to_tile(from);
+    let end = arena.world_to_tile(goal);
+    let (start, end) = ((start.0 as i32, start.1 as i32), (end.0 as i32, end.1 as i32));
+    if !walkable(arena, start.0, start.1) {
+        return None;
+    }
+
+    let index = |x: i32, y: i32| (y * w + x) as usize;
+    let heuristic = |x: i32, y: i32| {
+        let (dx, dy) = ((x - end.0).unsigned_abs(), (y - end.1).unsigned_abs());
+        STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+    };
+
+    let mut cost = vec![u32::MAX; (w * h) as usize];
+    let mut came_from = vec![usize::MAX; (w * h) as usize];
+    // Ordered by (f, h, index) so ties resolve the same way every run
+    let mut open = BinaryHeap::new();
+    cost[index(start.0, start.1)] = 0;
+    open.push(Reverse((heuristic(start.0, start.1), heuristic(start.0, start.1), index(start.0, start.1))));
+    let mut closest = (heuristic(start.0, start.1), index(start.0, start.1));
+
+    while let Some(Reverse((_, h_here, current))) = open.pop() {
+        let (x, y) = ((current as i32) % w, (current as i32) / w);
+        if (x, y) == end {
+            closest = (0, current);
+            break;
+        }
+        if h_here < closest.0 {
+            closest = (h_here, current);
+        }
+
+        for (nx, ny, step) in neighbors(|x, y| walkable(arena, x, y), x, y) {
+            let next_cost = cost[current] + step;
+            let next = index(nx, ny);
+            if next_cost < cost[next] {
+                cost[next] = next_cost;
+                came_from[next] = current;
+                let h_next = heuristic(nx, ny);
+                open.push(Reverse((next_cost + h_next, h_next, next)));
+            }
+        }
+    }
+
+    // Walk back from the end tile, dropping the start tile itself
+    let mut tiles = Vec::new();
+    let mut current = closest.1;
+    while current != index(start.0, start.1) {
+        tiles.push(current);
+        current = came_from[current];
+    }
+    tiles.reverse();
+
+    let mut waypoints: Vec<Position> = tiles
+        .into_iter()
+        .map(|i| arena.tile_to_world(i as u32 % arena.width, i as u32 / arena.width))
+        .collect();
+    if closest.1 == index(end.0, end.1) {
+        // Finish on the goal itself rather than its tile center
+        waypoints.pop();
+        waypoints.push(*goal);
+    }
+    if waypoints.is_empty() {
+        waypoints.push(arena.tile_to_world(start.0 as u32, start.1 as u32));
+    }
+
+    Some(Path {
+        goal: *goal,
+        waypoints,
+    })
+}
+
+/// Tiles one step from `(x, y)` that pass `walkable`, with the cost of the
+/// step.
+/// Diagonals may not clip the corner of a blocked tile.
+fn neighbors(walkable: impl Fn(i32, i32) -> bool + Copy, x: i32, y: i32) -> impl Iterator<Item = (i32, i32, u32)> {
+    (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+        .filter(|&d| d != (0, 0))
+        .filter_map(move |(dx, dy)| {
+            let (nx, ny) = (x + dx, y + dy);
+            if !walkable(nx, ny) {
+                return None;
+            }
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal && !(walkable(x + dx, y) && walkable(x, y + dy)) {
+                return None;
+            }
+            Some((nx, ny, if diagonal { DIAGONAL_COST } else { STRAIGHT_COST }))
+        })
+}
+
+/// True if `entity`'s whole body can go straight from `a` to `b` (over the
+/// river too, for units that jump it).
+fn is_clear(arena: &Arena, entity: &Entity, a: &Position, b: &Position) -> bool {
+    if entity.jumps_river() {
+        arena.segment_is_clear_jumping(a, b, entity.radius())
+    } else {
+        arena.segment_is_clear(a, b, entity.radius())
+    }
+}
+
+fn walkable(arena: &Arena, x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0 && arena.get_tile(x as u32, y as u32).is_some_and(|t| t.is_walkable())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::TileType;
+    use crate::fixtures;
+    use crate::state::{GameState, TowerType};
+    use shared::PlayerId;
+
+    #[test]
+    fn test_path_crosses_at_a_bridge() {
+        let arena = Arena::new();
+        let path = find_path(&arena, &Position::new(10.5, 9.5), &Position::new(22.5, 9.5)).unwrap();
+        assert_eq!(*path.waypoints.last().unwrap(), Position::new(22.5, 9.5));
+        assert!(path.waypoints.iter().all(|p| !arena.is_river(p)));
+        assert!(path.waypoints.iter().any(|p| p.x > 15.0 && p.x < 17.0));
+        // Every leg stays dry
+        let mut from = Position::new(10.5, 9.5);
+        for waypoint in &path.waypoints {
+            assert!(arena.segment_is_walkable(&from, waypoint), "{:?} -> {:?}", from, waypoint);
+            from = *waypoint;
+        }
+    }
+
+    #[test]
+    fn test_path_routes_around_walls() {
+        let mut arena = Arena::new();
+        // A wall across the whole left half, with a gap at the top row
+        for y in 1..18 {
+            arena.tiles[y][8] = TileType::Wall;
+        }
+        let path = find_path(&arena, &Position::new(5.5, 9.5), &Position::new(11.5, 9.5)).unwrap();
+        assert!(path.waypoints.iter().any(|p| p.y < 1.0));
+        assert_eq!(*path.waypoints.last().unwrap(), Position::new(11.5, 9.5));
+
+        // Fully walled off: end as close as possible
+        arena.tiles[0][8] = TileType::Wall;
+        let path = find_path(&arena, &Position::new(5.5, 9.5), &Position::new(11.5, 9.5)).unwrap();
+        assert_eq!(*path.waypoints.last().unwrap(), Position::new(7.5, 9.5));
+    }
+
+    #[test]
+    fn test_knight_walks_around_a_wall() {
+        'modes: for mode in [PathfindingMode::AStar, PathfindingMode::FlowField] {
+            let mut state = fixtures::empty_arena();
+            state.set_pathfinding_mode(mode);
+            for y in 5..14 {
+                state.arena.tiles[y][5] = TileType::Wall;
+            }
+            let id = fixtures::spawn(&mut state, PlayerId::Player2, "Knight", Position::new(8.5, 9.0)).unwrap();
+            // Park a dummy enemy behind the wall
+            let dummy = fixtures::spawn(&mut state, PlayerId::Player1, "Knight", Position::new(2.5, 9.0)).unwrap();
+            if let crate::entities::EntityKind::Troop(data) = &mut state.entities.get_mut(&dummy).unwrap().kind {
+                data.movement_speed = 0.0;
+            }
+
+            let mut blocked = 0;
+            for _ in 0..600 {
+                crate::step(&mut state, &[]).unwrap();
+                let position = state.entities[&id].position;
+                assert!(state.arena.segment_is_walkable(&position, &position));
+                if state.entities[&id].velocity.length() == 0.0 {
+                    blocked += 1;
+                }
+                if position.x < 5.0 {
+                    continue 'modes;
+                }
+            }
+            panic!("{:?}: knight never got around the wall (stopped for {} ticks)", mode, blocked);
+        }
+    }
+
+    #[test]
+    fn test_crowds_share_one_flow_field() {
+        let mut state = GameState::new(45);
+        let knight = state.get_card_by_name("Knight").unwrap().clone();
+        for i in 0..40 {
+            let position = Position::new(2.0 + (i % 10) as f32, 6.0 + (i / 10) as f32);
+            knight.spawn(&mut state, PlayerId::Player1, position, 11).unwrap();
+        }
+        let goal = state.arena.tower_position(PlayerId::Player2, TowerType::King);
+
+        let mut fields = FlowFields::default();
+        fields.sync(&state.arena);
+        for entity in state.entities.values().filter(|e| e.can_move()) {
+            let waypoint = next_waypoint_flow(&state.arena, &mut fields, entity, &goal);
+            // Each heads for dry ground in a straight line
+            assert!(state.arena.segment_is_walkable(&entity.position, &waypoint), "{:?}", entity.position);
+        }
+        assert_eq!(fields.len(), 1);
+
+        // And the crowd gets across by bridge
+        state.set_pathfinding_mode(PathfindingMode::FlowField);
+        for _ in 0..60 * 25 {
+            crate::step(&mut state, &[]).unwrap();
+            assert!(state.entities.values().all(|e| !state.arena.is_river(&e.position)));
+        }
+        let troops: Vec<&Entity> = state.entities.values().filter(|e| e.can_move()).collect();
+        assert!(!troops.is_empty());
+        assert!(troops.iter().all(|e| e.position.x > 17.0));
+    }
+}
@@ -1,15 +1,49 @@
 //! Projectile system (movement and collision).
 
-use crate::entities::{CollisionShape, EntityKind};
+use crate::entities::{CollisionShape, DamageFilter, EntityKind, ProjectileBehavior};
 use crate::state::{EntityId, GameState};
-use shared::Position;
+use crate::systems::knockback;
+use shared::{PlayerId, Position};
+
+/// Damage resolved when a projectile lands.
+enum Impact {
+    /// Damage a single entity.
+    /// `push` is an optional knockback (direction, distance).
+    Direct {
+        target_id: EntityId,
+        damage: f32,
+        push: Option<((f32, f32), f32)>,
+    },
+    /// Damage every unit `affects` allows within `radius` of `center`.
+    Splash {
+        owner: PlayerId,
+        affects: DamageFilter,
+        center: Position,
+        radius: f32,
+        damage: f32,
+        tower_multiplier: f32,
+        knockback: Option<f32>,
+    },
+    /// Damage `first`, then jump to up to `jumps` more units `affects`
+    /// allows.
+    Chain {
+        owner: PlayerId,
+        affects: DamageFilter,
+        first: EntityId,
+        damage: f32,
+        jumps: u32,
+        jump_radius: f32,
+        falloff: f32,
+    },
+}
 
 /// Updates projectile movement and handles collisions with targets.
 pub fn update(state: &mut GameState, dt: f32) {
     // Collect projectile updates
-    let mut position_updates = Vec::new();
-    let mut hits = Vec::new();  // (projectile_id, target_id, damage)
+    let mut position_updates = Vec::new(); // (projectile_id, position, traveled)
+    let mut impacts = Vec::new(); // (credited entity, impact)
     let mut remove_projectiles = Vec::new();
+    let mut pierced = Vec::new(); // (projectile_id, newly hit entities)
 
     for (proj_id, projectile) in &state.entities {
         // Only process projectiles
@@ -17,21 +51,110 @@ pub fn update(state: &mut GameState, dt: f32) {
             EntityKind::Projectile(data) => data,
             _ => continue,
         };
+        // Hits count as the shooter's, or the projectile's own if nothing fired it
+        let credit = proj_data.source.map_or(*proj_id, EntityId::from_u32);
+        let step = proj_data.speed * dt;
+        let traveled = proj_data.traveled + step;
+        let out_of_range = proj_data.max_distance.is_some_and(|max| traveled >= max);
 
-        // Check if target still exists
-        let target_id = match proj_data.target_id {
-            Some(id) => EntityId::from_u32(id),
-            None => {
-                // No target - remove projectile
-                remove_projectiles.push(*proj_id);
-                continue;
+        // Detonates at `point`, hitting whatever is there (or splashing)
+        let land_at = |point: Position| -> Option<Impact> {
+            if let Some(radius) = proj_data.splash_radius {
+                return Some(Impact::Splash {
+                    owner: projectile.owner,
+                    affects: proj_data.affects,
+                    center: point,
+                    radius,
+                    damage: proj_data.damage,
+                    tower_multiplier: proj_data.tower_damage_multiplier,
+                    knockback: proj_data.knockback,
+                });
             }
+            state
+                .targets_in_radius(projectile.owner, proj_data.affects, &point, projectile.radius())
+                .into_iter()
+                .min_by(|a, b| {
+                    let da = point.distance_squared(&state.entities[a].position);
+                    let db = point.distance_squared(&state.entities[b].position);
+                    da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|target_id| Impact::Direct {
+                    target_id,
+                    damage: proj_data.damage,
+                    push: proj_data.knockback.map(|distance| {
+                        (projectile.position.direction_to(&state.entities[&target_id].position), distance)
+                    }),
+                })
         };
 
+        // Piercing: fly straight, damaging everything along the way once
+        if let ProjectileBehavior::Piercing { direction, width, hit } = &proj_data.behavior {
+            let new_position = Position::new(
+                projectile.position.x + direction.0 * step,
+                projectile.position.y + direction.1 * step,
+            );
+            let newly_hit: Vec<EntityId> = state
+                .targets_in_radius(projectile.owner, proj_data.affects, &new_position, width / 2.0)
+                .into_iter()
+                .filter(|id| !hit.contains(&id.as_u32()))
+                .collect();
+            for &target_id in &newly_hit {
+                impacts.push((
+                    credit,
+                    Impact::Direct {
+                        target_id,
+                        damage: proj_data.damage,
+                        push: proj_data.knockback.map(|distance| (*direction, distance)),
+                    },
+                ));
+            }
+            if !newly_hit.is_empty() {
+                pierced.push((*proj_id, newly_hit));
+            }
+
+            if out_of_range {
+                remove_projectiles.push(*proj_id);
+            } else {
+                position_updates.push((*proj_id, new_position, traveled));
+            }
+            continue;
+        }
+
+        // Non-homing: fly to a fixed point and detonate there
+        if proj_data.target_id.is_none() {
+            let aim = match proj_data.target_position {
+                Some(aim) => aim,
+                None => {
+                    // No target - remove projectile
+                    remove_projectiles.push(*proj_id);
+                    continue;
+                }
+            };
+
+            let (dir_x, dir_y) = projectile.position.direction_to(&aim);
+            let new_position = Position::new(
+                projectile.position.x + dir_x * step,
+                projectile.position.y + dir_y * step,
+            );
+
+            if projectile.position.distance_to(&aim) <= step {
+                impacts.extend(land_at(aim).map(|impact| (credit, impact)));
+                remove_projectiles.push(*proj_id);
+            } else if out_of_range {
+                impacts.extend(land_at(new_position).map(|impact| (credit, impact)));
+                remove_projectiles.push(*proj_id);
+            } else {
+                position_updates.push((*proj_id, new_position, traveled));
+            }
+            continue;
+        }
+
+        // Homing: check if target still exists and is still fair game
+        let target_id = EntityId::from_u32(proj_data.target_id.unwrap_or_default());
         let target = match state.entities.get(&target_id) {
-            Some(t) if t.is_alive() => t,
+            Some(t) if t.is_alive() && proj_data.affects.hits(projectile.owner, t.owner) => t,
             _ => {
-                // Target dead or missing - remove projectile
+                // Target dead, missing or changed sides - remove projectile
                 remove_projectiles.push(*proj_id);
                 continue;
             }
@@ -39,16 +162,16 @@ pub fn update(state: &mut GameState, dt: f32) {
 
         // Move toward target
         let (dir_x, dir_y) = projectile.position.direction_to(&target.position);
-        let new_x = projectile.position.x + dir_x * proj_data.speed * dt;
-        let new_y = projectile.position.y + dir_y * proj_data.speed * dt;
+        let new_x = projectile.position.x + dir_x * step;
+        let new_y = projectile.position.y + dir_y * step;
         let new_position = Position::new(new_x, new_y);
 
         // Check if projectile hit target (supports both circle and rectangle collision)
         let hit = match target.collision_shape() {
             CollisionShape::Circle { radius } => {
                 // Circle-to-circle collision
-                let distance = new_position.distance_to(&target.position);
-                distance <= (projectile.radius() + radius)
+                let reach = projectile.radius() + radius;
+                new_position.distance_squared(&target.position) <= reach * reach
             }
             CollisionShape::Rectangle { half_width, half_height } => {
                 // Circle-to-rectangle collision (for towers)
@@ -58,26 +181,122 @@ pub fn update(state: &mut GameState, dt: f32) {
         };
 
         if hit {
-            // Hit! Apply damage and remove projectile
-            hits.push((*proj_id, target_id, proj_data.damage));
+            // Hit! Apply damage (to the target, or everything in the blast) and remove projectile
+            let impact = match (&proj_data.behavior, proj_data.splash_radius) {
+                (
+                    ProjectileBehavior::Chaining {
+                        max_jumps,
+                        jump_radius,
+                        falloff,
+                    },
+                    _,
+                ) => Some(Impact::Chain {
+                    owner: projectile.owner,
+                    affects: proj_data.affects,
+                    first: target_id,
+                    damage: proj_data.damage,
+                    jumps: *max_jumps,
+                    jump_radius: *jump_radius,
+                    falloff: *falloff,
+                }),
+                (_, Some(_)) => land_at(target.position),
+                (_, None) => Some(Impact::Direct {
+                    target_id,
+                    damage: proj_data.damage,
+                    push: proj_data.knockback.map(|distance| ((dir_x, dir_y), distance)),
+                }),
+            };
+            impacts.extend(impact.map(|impact| (credit, impact)));
+            remove_projectiles.push(*proj_id);
+        } else if out_of_range {
+            // Ran out of range before reaching the target
             remove_projectiles.push(*proj_id);
         } else {
             // No hit yet - update position
-            position_updates.push((*proj_id, new_position));
+            position_updates.push((*proj_id, new_position, traveled));
         }
     }
 
     // Apply position updates
-    for (id, position) in position_updates {
+    for (id, position, traveled) in position_updates {
         if let Some(entity) = state.entities.get_mut(&id) {
             entity.position = position;
+            if let EntityKind::Projectile(data) = &mut entity.kind {
+                data.traveled = traveled;
+            }
         }
     }
 
-    // Apply hits
-    for (_, target_id, damage) in hits {
-        if let Some(target) = state.entities.get_mut(&target_id) {
-            target.take_damage(damage);
+    for (id, newly_hit) in pierced {
+        if let Some(EntityKind::Projectile(data)) = state.entities.get_mut(&id).map(|e| &mut e.kind) {
+            if let ProjectileBehavior::Piercing { hit, .. } = &mut data.behavior {
+                hit.extend(newly_hit.iter().map(|id| id.as_u32()));
+            }
+        }
+    }
+
+    // Apply hits (splash only hits the sides the projectile's filter allows)
+    for (credit, impact) in impacts {
+        match impact {
+            Impact::Direct { target_id, damage, push } => {
+                state.damage_from(credit, target_id, damage);
+                if let Some((direction, distance)) = push {
+                    knockback::push(state, target_id, direction, distance);
+                }
+            }
+            Impact::Splash {
+                owner,
+                affects,
+                center,
+                radius,
+                damage,
+                tower_multiplier,
+                knockback,
+            } => {
+                for id in state.targets_in_radius(owner, affects, &center, radius) {
+                    let scale = if state.entities[&id].tower_type().is_some() { tower_multiplier } else { 1.0 };
+                    state.damage_from(credit, id, damage * scale);
+                    if let Some(distance) = knockback {
+                        knockback::push_from(state, id, &center, distance);
+                    }
+                }
+            }
+            Impact::Chain {
+                owner,
+                affects,
+                first,
+                damage,
+                jumps,
+                jump_radius,
+                falloff,
+            } => {
+                let mut hit = vec![first];
+                let mut damage = damage;
+                let mut next = Some(first);
+                while let Some((target_id, from)) =
+                    next.and_then(|id| state.entities.get(&id).map(|t| (id, t.position)))
+                {
+                    state.damage_from(credit, target_id, damage);
+                    if hit.len() > jumps as usize {
+                        break;
+                    }
+
+                    // Jump to the nearest unit not yet hit
+                    next = state
+                        .targets_in_radius(owner, affects, &from, jump_radius)
+                        .into_iter()
+                        .filter(|id| !hit.contains(id))
+                        .min_by(|a, b| {
+                            let da = from.distance_squared(&state.entities[a].position);
+                            let db = from.distance_squared(&state.entities[b].position);
+                            da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+                        });
+                    if let Some(id) = next {
+                        hit.push(id);
+                        damage *= falloff;
+                    }
+                }
+            }
         }
     }
 
@@ -86,3 +305,150 @@ pub fn update(state: &mut GameState, dt: f32) {
         state.remove_entity(id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, ProjectileData};
+    use crate::fixtures::spawn;
+
+    fn fire(state: &mut GameState, from: Position, data: ProjectileData) -> EntityId {
+        state.add_entity(Entity::new(PlayerId::Player1, from, EntityKind::Projectile(data)))
+    }
+
+    #[test]
+    fn test_splash_hits_enemies_near_impact_only() {
+        let mut state = GameState::new(16);
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 9.0)).unwrap();
+        let near = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 10.0)).unwrap();
+        let far = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 13.0)).unwrap();
+        let ally = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(16.0, 8.0)).unwrap();
+
+        let data = ProjectileData::homing(100.0, 15.0, target.as_u32()).with_splash(Some(1.5));
+        fire(&mut state, Position::new(15.5, 9.0), data);
+        update(&mut state, 1.0 / 60.0);
+
+        let lost = |id: EntityId| state.entities[&id].max_hp - state.entities[&id].hp;
+        assert_eq!(lost(target), 100.0);
+        assert_eq!(lost(near), 100.0);
+        assert_eq!(lost(far), 0.0);
+        assert_eq!(lost(ally), 0.0);
+    }
+
+    #[test]
+    fn test_friendly_fire_splash_hits_both_sides() {
+        let mut state = GameState::new(16);
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 9.0)).unwrap();
+        let ally = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(16.0, 8.0)).unwrap();
+
+        let data = ProjectileData::homing(100.0, 15.0, target.as_u32())
+            .with_splash(Some(1.5))
+            .with_affects(DamageFilter::All);
+        fire(&mut state, Position::new(15.5, 9.0), data);
+        update(&mut state, 1.0 / 60.0);
+
+        let lost = |id: EntityId| state.entities[&id].max_hp - state.entities[&id].hp;
+        assert_eq!(lost(target), 100.0);
+        assert_eq!(lost(ally), 100.0);
+
+        // Ally-only damage spares enemies caught in the blast
+        let mut state = GameState::new(16);
+        let ally = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(16.0, 9.0)).unwrap();
+        let enemy = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 10.0)).unwrap();
+        let data = ProjectileData::homing(100.0, 15.0, ally.as_u32())
+            .with_splash(Some(1.5))
+            .with_affects(DamageFilter::Allies);
+        fire(&mut state, Position::new(15.5, 9.0), data);
+        update(&mut state, 1.0 / 60.0);
+        let lost = |id: EntityId| state.entities[&id].max_hp - state.entities[&id].hp;
+        assert_eq!(lost(ally), 100.0);
+        assert_eq!(lost(enemy), 0.0);
+    }
+
+    #[test]
+    fn test_aimed_projectile_can_miss() {
+        let mut state = GameState::new(17);
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 9.0)).unwrap();
+        let aim = state.entities[&target].position;
+        let shell = fire(&mut state, Position::new(12.0, 9.0), ProjectileData::aimed(100.0, 15.0, aim));
+
+        // The target walks away before the shell lands
+        state.entities.get_mut(&target).unwrap().position.y += 3.0;
+        for _ in 0..30 {
+            update(&mut state, 1.0 / 60.0);
+        }
+
+        assert!(!state.entities.contains_key(&shell));
+        assert_eq!(state.entities[&target].hp, state.entities[&target].max_hp);
+    }
+
+    #[test]
+    fn test_aimed_projectile_hits_what_is_there() {
+        let mut state = GameState::new(18);
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 9.0)).unwrap();
+        fire(&mut state, Position::new(12.0, 9.0), ProjectileData::aimed(100.0, 15.0, Position::new(16.0, 9.0)));
+        for _ in 0..30 {
+            update(&mut state, 1.0 / 60.0);
+        }
+        assert_eq!(state.entities[&target].max_hp - state.entities[&target].hp, 100.0);
+    }
+
+    #[test]
+    fn test_piercing_hits_each_enemy_once() {
+        let mut state = GameState::new(20);
+        let first = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(14.0, 9.0)).unwrap();
+        let second = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(17.0, 9.3)).unwrap();
+        let beside = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(15.0, 12.0)).unwrap();
+        let ally = spawn(&mut state, PlayerId::Player1, "Knight", Position::new(13.0, 9.0)).unwrap();
+
+        let from = Position::new(11.0, 9.0);
+        let data = ProjectileData::piercing(100.0, 10.0, from, Position::new(20.0, 9.0), 1.0, 10.0);
+        let log = fire(&mut state, from, data);
+        for _ in 0..90 {
+            update(&mut state, 1.0 / 60.0);
+        }
+
+        let lost = |id: EntityId| state.entities[&id].max_hp - state.entities[&id].hp;
+        assert!(!state.entities.contains_key(&log));
+        assert_eq!(lost(first), 100.0);
+        assert_eq!(lost(second), 100.0);
+        assert_eq!(lost(beside), 0.0);
+        assert_eq!(lost(ally), 0.0);
+    }
+
+    #[test]
+    fn test_chain_jumps_with_falloff() {
+        let mut state = GameState::new(21);
+        let target = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 9.0)).unwrap();
+        let near = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 11.0)).unwrap();
+        let nearer = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(17.5, 9.0)).unwrap();
+        let out_of_reach = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(16.0, 16.0)).unwrap();
+
+        let data = ProjectileData::homing(100.0, 15.0, target.as_u32()).with_chain(2, 2.5, 0.5);
+        fire(&mut state, Position::new(15.5, 9.0), data);
+        update(&mut state, 1.0 / 60.0);
+
+        // target -> nearer (closest to target) -> near (closest to nearer)
+        let lost = |id: EntityId| state.entities[&id].max_hp - state.entities[&id].hp;
+        assert_eq!(lost(target), 100.0);
+        assert_eq!(lost(nearer), 50.0);
+        assert_eq!(lost(near), 25.0);
+        assert_eq!(lost(out_of_reach), 0.0);
+    }
+
+    #[test]
+    fn test_max_distance_detonates_splash() {
+        let mut state = GameState::new(19);
+        let victim = spawn(&mut state, PlayerId::Player2, "Knight", Position::new(14.0, 9.0)).unwrap();
+        let data = ProjectileData::aimed(100.0, 15.0, Position::new(30.0, 9.0))
+            .with_splash(Some(1.0))
+            .with_max_distance(2.0);
+        let shell = fire(&mut state, Position::new(12.0, 9.0), data);
+
+        for _ in 0..30 {
+            update(&mut state, 1.0 / 60.0);
+        }
+        assert!(!state.entities.contains_key(&shell));
+        assert_eq!(state.entities[&victim].max_hp - state.entities[&victim].hp, 100.0);
+    }
+}
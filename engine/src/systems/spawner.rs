@@ -0,0 +1,212 @@
+//! Spawner system (delayed deploys, building lifetimes and periodic troop
+//! production).
+
+use crate::entities::EntityKind;
+use crate::formation::Formation;
+use crate::state::GameState;
+use shared::{PlayerId, Position};
+
+/// Slack for float drift when counting timers down in `dt` steps.
+const TIMER_EPSILON: f32 = 1e-4;
+
+/// Gap (tiles) between a building's edge and the units it spawns.
+const SPAWN_GAP: f32 = 0.6;
+
+/// A wave of troops due this tick.
+struct Wave {
+    owner: PlayerId,
+    card: String,
+    level: u32,
+    positions: Vec<Position>,
+}
+
+/// Lands travelling troops that have arrived, decays building HP over its
+/// lifetime, and spawns a wave from each spawner building whose timer has
+/// run out.
+pub fn update(state: &mut GameState, dt: f32) {
+    for deploy in &mut state.pending_deploys {
+        deploy.remaining -= dt;
+    }
+    let (arrived, travelling) = std::mem::take(&mut state.pending_deploys)
+        .into_iter()
+        .partition::<Vec<_>, _>(|d| d.remaining <= TIMER_EPSILON);
+    state.pending_deploys = travelling;
+    for deploy in arrived {
+        if let Some(card) = state.get_card_by_name(&deploy.card).cloned() {
+            let _ = card.land(state, &deploy);
+        }
+    }
+
+    let width = state.arena.width as f32 * state.arena.tile_size;
+    let height = state.arena.height as f32 * state.arena.tile_size;
+    let mut waves = Vec::new();
+    let mut decay = Vec::new();
+
+    for (id, building) in state.entities.iter_mut() {
+        if !building.is_alive() {
+            continue;
+        }
+        let radius = building.radius();
+        let (forward_x, forward_y) = building.facing_vector();
+        let data = match &mut building.kind {
+            EntityKind::Building(data) => data,
+            _ => continue,
+        };
+
+        if let Some(spawner) = &mut data.spawner {
+            spawner.timer -= dt;
+            if spawner.timer <= TIMER_EPSILON {
+                spawner.timer += spawner.interval;
+
+                // Units line up side by side just in front of the building
+                let front = Position::new(
+                    building.position.x + forward_x * (radius + SPAWN_GAP),
+                    building.position.y + forward_y * (radius + SPAWN_GAP),
+                );
+                let positions = Formation::Line
+                    .positions(spawner.count, front, (forward_x, forward_y))
+                    .into_iter()
+                    .map(|p| p.clamp_to_rect(&Position::new(0.5, 0.5), &Position::new(width - 0.5, height - 0.5)))
+                    .collect();
+                waves.push(Wave {
+                    owner: building.owner,
+                    card: spawner.card.clone(),
+                    level: spawner.level,
+                    positions,
+                });
+            }
+        }
+
+        // Buildings lose their HP evenly over their lifetime
+        if data.lifetime > 0.0 {
+            decay.push((*id, building.max_hp * dt / data.lifetime));
+        } else {
+            building.hp = 0.0;
+        }
+    }
+
+    for (id, amount) in decay {
+        state.damage(id, amount);
+    }
+
+    for wave in waves {
+        // The reference was checked at deploy time; skip it if the card has
+        // since been unloaded
+        let card = match state.get_card_by_name(&wave.card) {
+            Some(card) => card.clone(),
+            None => continue,
+        };
+        for position in wave.positions {
+            let _ = card.spawn(state, wave.owner, position, wave.level);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Entity;
+    use crate::state::EntityId;
+
+    fn knight_hut_state() -> (GameState, EntityId) {
+        let mut state = GameState::new(31);
+        state.entities.clear();
+        let mut hut = state.get_card_by_name("Knight").unwrap().clone();
+        hut.name = "Knight Hut".to_string();
+        hut.type_name = "building".to_string();
+        hut.duration = Some(5.0);
+        hut.spawn_card = Some("Knight".to_string());
+        hut.spawn_interval = Some(2.0);
+        hut.spawn_count = Some(2);
+        hut.spawn(&mut state, PlayerId::Player1, Position::new(6.0, 9.0), 11).unwrap();
+        let id = *state.entities.keys().next().unwrap();
+        (state, id)
+    }
+
+    fn troops(state: &GameState) -> Vec<&Entity> {
+        state
+            .entities
+            .values()
+            .filter(|e| matches!(e.kind, EntityKind::Troop(_)))
+            .collect()
+    }
+
+    #[test]
+    fn test_spawner_produces_waves_in_front() {
+        let (mut state, _) = knight_hut_state();
+
+        for _ in 0..119 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert!(troops(&state).is_empty());
+
+        crate::step(&mut state, &[]).unwrap();
+        let wave = troops(&state);
+        assert_eq!(wave.len(), 2);
+        // Side by side in front of the hut, toward the enemy
+        for knight in &wave {
+            assert_eq!(knight.owner, PlayerId::Player1);
+            assert!((knight.position.x - 7.6).abs() < 1e-4);
+        }
+        let mut ys: Vec<f32> = wave.iter().map(|k| k.position.y).collect();
+        ys.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(ys, vec![8.5, 9.5]);
+
+        for _ in 0..120 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert_eq!(troops(&state).len(), 4);
+    }
+
+    #[test]
+    fn test_building_decays_over_lifetime() {
+        let (mut state, hut) = knight_hut_state();
+
+        for _ in 0..150 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let building = &state.entities[&hut];
+        assert!((building.hp / building.max_hp - 0.5).abs() < 1e-3);
+
+        for _ in 0..151 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert!(!state.entities.contains_key(&hut));
+    }
+
+    #[test]
+    fn test_travelling_troop_appears_after_delay() {
+        let mut state = GameState::new(32);
+        let mut miner = state.get_card_by_name("Knight").unwrap().clone();
+        miner.name = "Miner".to_string();
+        miner.deploy_anywhere = true;
+        miner.travel_time = Some(1.0);
+        let mut cards = crate::card::get_test_cards();
+        cards.push(miner);
+        state.load_cards(cards).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+
+        let target = Position::new(26.0, 7.0);
+        crate::step(
+            &mut state,
+            &[crate::Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Miner".to_string(),
+                level: 11,
+                position: target,
+            }],
+        )
+        .unwrap();
+        for _ in 0..58 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        assert!(troops(&state).is_empty());
+        assert_eq!(state.pending_deploys.len(), 1);
+
+        crate::step(&mut state, &[]).unwrap();
+        let landed = troops(&state);
+        assert_eq!(landed.len(), 1);
+        assert_eq!(landed[0].position, target);
+        assert!(state.pending_deploys.is_empty());
+    }
+}
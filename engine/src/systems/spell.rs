@@ -0,0 +1,168 @@
+//! Spell system (travel time and area damage on impact).
+
+use crate::entities::EntityKind;
+use crate::state::{EntityId, GameState};
+use crate::systems::knockback;
+
+/// Counts down spell travel time and applies area damage on impact.
+/// Spent spells are marked dead so the lifecycle system removes them.
+pub fn update(state: &mut GameState, dt: f32) {
+    let mut impacts = Vec::new();
+
+    for (spell_id, spell) in state.entities.iter_mut() {
+        let data = match &mut spell.kind {
+            EntityKind::Spell(data) => data,
+            _ => continue,
+        };
+
+        data.time_to_impact -= dt;
+        if data.time_to_impact <= 0.0 && spell.is_alive() {
+            impacts.push(*spell_id);
+        }
+    }
+
+    for spell_id in impacts {
+        apply_impact(state, spell_id);
+    }
+}
+
+/// Damages every troop and tower overlapping the spell's radius that its
+/// damage filter allows (enemies by default).
+fn apply_impact(state: &mut GameState, spell_id: EntityId) {
+    let spell = &state.entities[&spell_id];
+    let data = match &spell.kind {
+        EntityKind::Spell(data) => data.clone(),
+        _ => return,
+    };
+    let owner = spell.owner;
+    let center = spell.position;
+
+    let hits: Vec<_> = state
+        .targets_in_radius(owner, data.affects, &center, data.radius)
+        .into_iter()
+        .map(|id| {
+            let damage = if state.entities[&id].tower_type().is_some() {
+                data.tower_damage
+            } else {
+                data.damage
+            };
+            (id, damage)
+        })
+        .collect();
+
+    for (id, damage) in hits {
+        state.damage_from(spell_id, id, damage);
+        if let Some(distance) = data.knockback {
+            knockback::push_from(state, id, &center, distance);
+        }
+    }
+
+    // Instant spells are spent after impact
+    if let Some(spell) = state.entities.get_mut(&spell_id) {
+        spell.hp = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::TowerType;
+    use crate::{step, Action, GameState};
+    use shared::{PlayerId, Position};
+
+    #[test]
+    fn test_fireball_damages_troops_and_tower() {
+        let mut state = GameState::new(5);
+        let tower_pos = state.arena.tower_position(PlayerId::Player2, TowerType::LeftPrincess);
+        let target = Position::new(tower_pos.x - 2.0, tower_pos.y);
+
+        state.players.get_mut(&PlayerId::Player2).unwrap().elixir = 10.0;
+        step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player2,
+                card_name: "Knight".to_string(),
+                level: 11,
+                position: target,
+            }],
+        )
+        .unwrap();
+        step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Fireball".to_string(),
+                level: 11,
+                position: target,
+            }],
+        )
+        .unwrap();
+
+        // Let the fireball travel and land
+        for _ in 0..240 {
+            step(&mut state, &[]).unwrap();
+        }
+
+        let knight = state
+            .entities
+            .values()
+            .find(|e| e.owner == PlayerId::Player2 && e.tower_type().is_none())
+            .unwrap();
+        assert!(knight.hp <= knight.max_hp - 572.0);
+
+        // Tower takes reduced damage
+        let tower_hp = state.players[&PlayerId::Player2].tower_hp[&TowerType::LeftPrincess];
+        let tower_damage = 1400.0 - tower_hp;
+        assert!(tower_damage > 0.0 && tower_damage < 572.0);
+
+        // Spell entity is cleaned up
+        assert!(!state
+            .entities
+            .values()
+            .any(|e| matches!(e.kind, crate::entities::EntityKind::Spell(_))));
+    }
+
+    #[test]
+    fn test_cast_delay_postpones_impact_and_is_exported() {
+        let mut state = GameState::new(6);
+        state.set_spell_cast_delay(0.5);
+        let target = Position::new(24.0, 9.0);
+        let origin = state.arena.tower_position(PlayerId::Player1, TowerType::King);
+        let speed = state
+            .get_card_by_name("Fireball")
+            .unwrap()
+            .projectile_speed
+            .unwrap_or(crate::card::DEFAULT_SPELL_SPEED);
+        let expected = 0.5 + origin.distance_to(&target) / speed;
+
+        step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Fireball".to_string(),
+                level: 11,
+                position: target,
+            }],
+        )
+        .unwrap();
+
+        // The defender sees the incoming spell and when it lands
+        let spells = state.export_cr_state(PlayerId::Player2).pending_spells;
+        assert_eq!(spells.len(), 1);
+        assert_eq!(spells[0].owner, "ENEMY");
+        assert!((spells[0].time_to_impact - (expected - crate::DELTA_TIME)).abs() < 1e-3);
+
+        let is_pending = |state: &GameState| {
+            state
+                .entities
+                .values()
+                .any(|e| matches!(e.kind, crate::entities::EntityKind::Spell(_)))
+        };
+        let mut ticks: u64 = 1;
+        while is_pending(&state) {
+            step(&mut state, &[]).unwrap();
+            ticks += 1;
+        }
+        let expected_ticks = (expected / crate::DELTA_TIME).round() as u64;
+        assert!(ticks.abs_diff(expected_ticks) <= 1, "{} vs {}", ticks, expected_ticks);
+    }
+}
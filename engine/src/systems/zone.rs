@@ -0,0 +1,201 @@
+//! Zone system (lingering damage-over-time and healing areas such as Poison).
+
+use crate::entities::EntityKind;
+use crate::state::{EntityId, GameState};
+use crate::systems::combat::is_valid_target_type;
+
+/// Damages enemies (and heals allied troops) inside each zone and counts
+/// down its duration.
+/// Expired zones are marked dead so the lifecycle system removes them.
+pub fn update(state: &mut GameState, dt: f32) {
+    let mut hits: Vec<(EntityId, EntityId, f32)> = Vec::new();
+    let mut heals: Vec<(EntityId, EntityId, f32)> = Vec::new();
+    let mut expired = Vec::new();
+
+    for (zone_id, zone) in &state.entities {
+        let data = match &zone.kind {
+            EntityKind::Zone(data) => data,
+            _ => continue,
+        };
+        if !zone.is_alive() {
+            continue;
+        }
+
+        // Only the part of this tick the zone was still active for counts
+        let active = dt.min(data.remaining.max(0.0));
+        for id in state.targets_in_radius(zone.owner, data.affects, &zone.position, data.radius) {
+            let target = &state.entities[&id];
+            if !is_valid_target_type(target, data.target_type) {
+                continue;
+            }
+            let dps = if target.tower_type().is_some() { data.tower_dps } else { data.dps };
+            hits.push((*zone_id, id, dps * active));
+        }
+
+        if data.heal_per_second > 0.0 {
+            for id in state.allied_troops_in_radius(zone.owner, &zone.position, data.radius) {
+                heals.push((*zone_id, id, data.heal_per_second * active));
+            }
+        }
+
+        if data.remaining - dt <= 0.0 {
+            expired.push(*zone_id);
+        }
+    }
+
+    for (zone_id, id, damage) in hits {
+        state.damage_from(zone_id, id, damage);
+    }
+    for (zone_id, id, amount) in heals {
+        state.heal(zone_id, id, amount);
+    }
+
+    for zone in state.entities.values_mut() {
+        if let EntityKind::Zone(data) = &mut zone.kind {
+            data.remaining -= dt;
+        }
+    }
+    for id in expired {
+        if let Some(zone) = state.entities.get_mut(&id) {
+            zone.hp = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use shared::{PlayerId, Position};
+
+    fn poison_state() -> GameState {
+        let mut state = GameState::new(30);
+        let mut cards = crate::card::get_test_cards();
+        let mut poison = cards.iter().find(|c| c.name == "Fireball").unwrap().clone();
+        poison.name = "Poison".to_string();
+        poison.duration = Some(8.0);
+        poison.radius = Some(3.5);
+        for level in &mut poison.levels {
+            level.dps = Some(100.0);
+        }
+        cards.push(poison);
+        state.load_cards(cards).unwrap();
+        state.players.get_mut(&PlayerId::Player1).unwrap().elixir = 10.0;
+        state
+    }
+
+    #[test]
+    fn test_poison_deals_damage_over_duration() {
+        let mut state = poison_state();
+        let card = state.get_card_by_name("Knight").unwrap().clone();
+        card.spawn(&mut state, PlayerId::Player2, Position::new(20.0, 9.0), 11).unwrap();
+        let victim = state.entities.keys().copied().max_by_key(|id| id.as_u32()).unwrap();
+        if let EntityKind::Troop(data) = &mut state.entities.get_mut(&victim).unwrap().kind {
+            data.movement_speed = 0.0;
+            data.base_hp = 10_000.0;
+        }
+        let victim_entity = state.entities.get_mut(&victim).unwrap();
+        victim_entity.max_hp = 10_000.0;
+        victim_entity.hp = 10_000.0;
+
+        crate::step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Poison".to_string(),
+                level: 11,
+                position: Position::new(20.0, 9.0),
+            }],
+        )
+        .unwrap();
+
+        for _ in 0..60 * 4 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let lost = state.entities[&victim].max_hp - state.entities[&victim].hp;
+        assert!((lost - 100.0 * (4.0 + 1.0 / 60.0)).abs() < 1.0, "{}", lost);
+
+        for _ in 0..60 * 6 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let lost = state.entities[&victim].max_hp - state.entities[&victim].hp;
+        assert!((lost - 800.0).abs() < 1.0, "{}", lost);
+        assert!(!state.entities.values().any(|e| matches!(e.kind, EntityKind::Zone(_))));
+    }
+
+    #[test]
+    fn test_integer_hp_mode_carries_fractions() {
+        let mut state = poison_state();
+        state.set_integer_hp(true);
+        let card = state.get_card_by_name("Knight").unwrap().clone();
+        card.spawn(&mut state, PlayerId::Player2, Position::new(20.0, 9.0), 11).unwrap();
+        let victim = state.entities.keys().copied().max_by_key(|id| id.as_u32()).unwrap();
+        if let EntityKind::Troop(data) = &mut state.entities.get_mut(&victim).unwrap().kind {
+            data.movement_speed = 0.0;
+        }
+
+        // Whole points land; the remainder waits for the next hit
+        for expected in [10.0, 10.0, 11.0] {
+            let before = state.entities[&victim].hp;
+            state.damage(victim, 10.4);
+            assert_eq!(before - state.entities[&victim].hp, expected);
+        }
+
+        // Per-tick poison damage (100/60 per tick) still adds up
+        let before = state.entities[&victim].hp;
+        crate::step(
+            &mut state,
+            &[Action::PlayCard {
+                player: PlayerId::Player1,
+                card_name: "Poison".to_string(),
+                level: 11,
+                position: Position::new(20.0, 9.0),
+            }],
+        )
+        .unwrap();
+        for _ in 0..60 * 2 - 1 {
+            crate::step(&mut state, &[]).unwrap();
+        }
+        let victim = &state.entities[&victim];
+        assert_eq!(victim.hp.fract(), 0.0);
+        assert!((before - victim.hp - 200.0).abs() <= 1.0, "{}", before - victim.hp);
+    }
+
+    #[test]
+    fn test_heal_zone_restores_hp_up_to_max() {
+        let mut state = GameState::new(31);
+        let mut cards = crate::card::get_test_cards();
+        let mut heal = cards.iter().find(|c| c.name == "Fireball").unwrap().clone();
+        heal.name = "Heal".to_string();
+        heal.duration = Some(2.0);
+        heal.radius = Some(3.0);
+        for level in &mut heal.levels {
+            level.healing = Some(100.0);
+        }
+        cards.push(heal.clone());
+        state.load_cards(cards).unwrap();
+
+        let knight = state.get_card_by_name("Knight").unwrap().clone();
+        knight.spawn(&mut state, PlayerId::Player1, Position::new(10.0, 9.0), 11).unwrap();
+        let ally = state.entities.keys().copied().max_by_key(|id| id.as_u32()).unwrap();
+        {
+            let entity = state.entities.get_mut(&ally).unwrap();
+            entity.hp = entity.max_hp - 150.0;
+            entity.shield = 0.0;
+            if let EntityKind::Troop(data) = &mut entity.kind {
+                data.movement_speed = 0.0;
+            }
+        }
+        heal.spawn(&mut state, PlayerId::Player1, Position::new(10.0, 9.0), 11).unwrap();
+
+        update(&mut state, 1.0);
+        assert_eq!(state.entities[&ally].max_hp - state.entities[&ally].hp, 50.0);
+        assert!(matches!(
+            state.events[..],
+            [crate::events::GameEvent::Healed { amount, .. }] if amount == 100.0
+        ));
+
+        update(&mut state, 1.0);
+        assert_eq!(state.entities[&ally].hp, state.entities[&ally].max_hp);
+    }
+}
@@ -0,0 +1,256 @@
+//! Tournament bookkeeping and aggregate balance reporting.
+//!
+//! Matches are recorded as [`MatchRecord`]s and folded into a
+//! [`TournamentReport`] with per-card pick/win rates, a deck-vs-deck
+//! matchup matrix, and average crowns. Reports serialize to JSON for
+//! dashboards.
+
+use crate::state::{CardPlay, GameState, TimeoutTermination};
+use serde::{Deserialize, Serialize};
+use shared::{PlayerId, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A named deck taking part in a tournament.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeckEntry {
+    pub name: String,
+    pub cards: Vec<String>,
+}
+
+/// The outcome of a single tournament match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchRecord {
+    pub player1: DeckEntry,
+    pub player2: DeckEntry,
+
+    /// Winning player, or `None` for a draw.
+    pub winner: Option<PlayerId>,
+
+    /// Crowns earned by (Player1, Player2).
+    pub crowns: (u32, u32),
+
+    /// Every card played in the match, in order.
+    #[serde(default)]
+    pub plays: Vec<CardPlay>,
+
+    /// Set if the match was aborted for overrunning its step budget.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<TimeoutTermination>,
+}
+
+impl MatchRecord {
+    /// Builds a record from a finished game.
+    ///
+    /// A player earns one crown per destroyed Princess tower, or three for
+    /// the King tower. The winner is [`GameState::winner`]'s: more crowns,
+    /// then more tower HP left. A draw, a match aborted by a timeout and
+    /// one stopped before it ended have none.
+    pub fn from_state(state: &GameState, player1: DeckEntry, player2: DeckEntry) -> Self {
+        let crowns = (
+            state.crowns_against(PlayerId::Player2),
+            state.crowns_against(PlayerId::Player1),
+        );
+        let winner = state.winner();
+
+        Self {
+            player1,
+            player2,
+            winner,
+            crowns,
+            plays: state.card_plays.clone(),
+            timeout: state.timeout.clone(),
+        }
+    }
+}
+
+/// Pick and win counts for a single card.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CardStats {
+    /// Number of deck appearances (a card counts once per deck per match).
+    pub picks: u32,
+    pub wins: u32,
+    pub pick_rate: f32,
+    pub win_rate: f32,
+}
+
+/// Results of one deck against another.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MatchupStats {
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub win_rate: f32,
+}
+
+/// Aggregate report across all recorded matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TournamentReport {
+    /// Completed matches; the statistics below cover only these.
+    pub matches: u32,
+
+    /// Matches aborted by a step timeout, left out of every statistic.
+    #[serde(default)]
+    pub timeouts: u32,
+
+    /// Per-card statistics, keyed by card name.
+    pub cards: BTreeMap<String, CardStats>,
+
+    /// Matchup matrix: `matchups[deck][opponent]` from `deck`'s perspective.
+    pub matchups: BTreeMap<String, BTreeMap<String, MatchupStats>>,
+
+    /// Average crowns earned per player per match.
+    pub average_crowns: f32,
+}
+
+impl TournamentReport {
+    /// Builds a report from a set of match records.
+    pub fn from_records(records: &[MatchRecord]) -> Self {
+        let (timed_out, completed): (Vec<&MatchRecord>, Vec<&MatchRecord>) =
+            records.iter().partition(|r| r.timeout.is_some());
+        let mut report = TournamentReport {
+            matches: completed.len() as u32,
+            timeouts: timed_out.len() as u32,
+            ..Default::default()
+        };
+        let mut total_crowns = 0u32;
+
+        for record in completed {
+            total_crowns += record.crowns.0 + record.crowns.1;
+
+            let sides = [
+                (PlayerId::Player1, &record.player1, &record.player2),
+                (PlayerId::Player2, &record.player2, &record.player1),
+            ];
+            for (side, deck, opponent) in sides {
+                let won = record.winner == Some(side);
+
+                let unique: BTreeSet<&String> = deck.cards.iter().collect();
+                for card in unique {
+                    let stats = report.cards.entry(card.clone()).or_default();
+                    stats.picks += 1;
+                    if won {
+                        stats.wins += 1;
+                    }
+                }
+
+                let matchup = report
+                    .matchups
+                    .entry(deck.name.clone())
+                    .or_default()
+                    .entry(opponent.name.clone())
+                    .or_default();
+                matchup.games += 1;
+                if won {
+                    matchup.wins += 1;
+                } else if record.winner.is_none() {
+                    matchup.draws += 1;
+                }
+            }
+        }
+
+        let deck_slots = (report.matches * 2) as f32;
+        for stats in report.cards.values_mut() {
+            stats.pick_rate = stats.picks as f32 / deck_slots;
+            stats.win_rate = stats.wins as f32 / stats.picks as f32;
+        }
+        for row in report.matchups.values_mut() {
+            for matchup in row.values_mut() {
+                matchup.win_rate = matchup.wins as f32 / matchup.games as f32;
+            }
+        }
+        if report.matches > 0 {
+            report.average_crowns = total_crowns as f32 / deck_slots;
+        }
+
+        report
+    }
+
+    /// Serializes the report as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deck(name: &str, cards: &[&str]) -> DeckEntry {
+        DeckEntry {
+            name: name.to_string(),
+            cards: cards.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_report_aggregates_cards_and_matchups() {
+        let beatdown = deck("beatdown", &["Giant", "Archers", "Archers"]);
+        let cycle = deck("cycle", &["Knight", "Archers"]);
+
+        let records = vec![
+            MatchRecord {
+                player1: beatdown.clone(),
+                player2: cycle.clone(),
+                winner: Some(PlayerId::Player1),
+                crowns: (3, 1),
+                plays: Vec::new(),
+                timeout: None,
+            },
+            MatchRecord {
+                player1: cycle,
+                player2: beatdown,
+                winner: None,
+                crowns: (1, 1),
+                plays: Vec::new(),
+                timeout: None,
+            },
+            // Aborted: ignored by the statistics
+            MatchRecord {
+                player1: deck("beatdown", &["Giant"]),
+                player2: deck("cycle", &["Knight"]),
+                winner: None,
+                crowns: (0, 0),
+                plays: Vec::new(),
+                timeout: Some(TimeoutTermination {
+                    tick: 10,
+                    phase: "combat".to_string(),
+                    elapsed_ms: 30.0,
+                    budget_ms: 20.0,
+                }),
+            },
+        ];
+
+        let report = TournamentReport::from_records(&records);
+        assert_eq!((report.matches, report.timeouts), (2, 1));
+
+        // Archers is in every deck, counted once per deck
+        assert_eq!(report.cards["Archers"].picks, 4);
+        assert_eq!(report.cards["Archers"].pick_rate, 1.0);
+        assert_eq!(report.cards["Giant"].wins, 1);
+        assert_eq!(report.cards["Giant"].win_rate, 0.5);
+
+        let vs_cycle = &report.matchups["beatdown"]["cycle"];
+        assert_eq!((vs_cycle.games, vs_cycle.wins, vs_cycle.draws), (2, 1, 1));
+        assert_eq!(report.matchups["cycle"]["beatdown"].wins, 0);
+
+        assert_eq!(report.average_crowns, 1.5);
+        assert!(report.to_json().unwrap().contains("\"matchups\""));
+    }
+
+    #[test]
+    fn test_records_break_crown_ties_like_the_match_result() {
+        let mut state = GameState::new(4);
+        for entity in state.entities.values_mut() {
+            if entity.owner == PlayerId::Player2 && entity.tower_type().is_some() {
+                entity.hp -= 100.0;
+            }
+        }
+        state.sync_tower_hp();
+        state.match_time = state.max_match_time;
+
+        let record = MatchRecord::from_state(&state, deck("a", &["Knight"]), deck("b", &["Knight"]));
+        assert_eq!(record.crowns, (0, 0));
+        assert_eq!(record.winner, Some(PlayerId::Player1));
+        assert_eq!(record.winner, state.result().unwrap().winner);
+    }
+}
@@ -1,7 +1,7 @@
 //! Tower configurations and stats.
 
 use serde::{Deserialize, Serialize};
-use shared::Result;
+use shared::{dims, Result};
 
 /// A tower troop configuration (e.g., Tower Princess, Cannoneer, etc.).
 /// These don't have elixir costs and replace the default tower.
@@ -38,6 +38,18 @@ pub struct TowerLevelStats {
     pub dps: Option<f32>,
 }
 
+impl Tower {
+    /// Get stats for a specific tower level.
+    pub fn get_level_stats(&self, level: u32) -> Result<&TowerLevelStats> {
+        self.levels
+            .iter()
+            .find(|stats| stats.level == level)
+            .ok_or_else(|| {
+                shared::Error::InvalidAction(format!("Level {} not found for {}", level, self.name))
+            })
+    }
+}
+
 /// Tower rarity (reusing from cards).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -58,3 +70,42 @@ pub fn load_towers_from_json(path: &str) -> Result<Vec<Tower>> {
 
     Ok(towers)
 }
+
+/// Get the default King and Princess tower configurations.
+///
+/// Stats follow the v2020_06 mechanics baseline until a towers file is
+/// added to the patch configs.
+pub fn get_default_towers() -> Vec<Tower> {
+    vec![
+        Tower {
+            name: "King Tower".to_string(),
+            url: None,
+            rarity: Rarity::Common,
+            attack_speed: Some(1.0),
+            first_hit_speed: None,
+            range: Some(7.0),
+            targets: Some(vec!["air".to_string(), "ground".to_string()]),
+            levels: vec![TowerLevelStats {
+                level: 11,
+                hp: Some(dims::KING_TOWER_HP),
+                damage: Some(90.0),
+                dps: None,
+            }],
+        },
+        Tower {
+            name: "Tower Princess".to_string(),
+            url: None,
+            rarity: Rarity::Common,
+            attack_speed: Some(0.8),
+            first_hit_speed: None,
+            range: Some(7.0),
+            targets: Some(vec!["air".to_string(), "ground".to_string()]),
+            levels: vec![TowerLevelStats {
+                level: 11,
+                hp: Some(dims::PRINCESS_TOWER_HP),
+                damage: Some(90.0),
+                dps: None,
+            }],
+        },
+    ]
+}
@@ -0,0 +1,389 @@
+//! Live mode: a match served over WebSocket, for browser visualizers and
+//! remote bots (`crust_sim_server --websocket <port>`).
+//!
+//! Unlike the stdio server, the [`LiveServer`] keeps the match clock: it
+//! runs ticks in real time (see [`crate::realtime`]) and pushes a
+//! `state` notification (`{"jsonrpc": "2.0", "method": "state", "params":
+//! <CRState>}`) to every client each `stream_every` ticks. Clients send
+//! JSON-RPC messages as over stdio (see [`crate::rpc`]), answered on their
+//! own connection, but only the [`CLIENT_METHODS`]: they watch and `act`,
+//! and can't reset, reload or stop the shared match.
+//!
+//! Each client holds one seat, picked when connecting with a `pov` query
+//! parameter (`ws://host:port/?pov=Player2`; Player1 by default; Player3
+//! and Player4 too in a 2v2 match), and may only see the match from it and
+//! act for it. The server is
+//! single-threaded: sockets are non-blocking and polled between ticks.
+//!
+//! The match only ticks while at least one client is connected; with
+//! nobody watching the server sleeps, polling for new connections a few
+//! times a second. With an idle timeout ([`LiveServer::set_idle_timeout`])
+//! [`LiveServer::run`] returns once nobody has been connected for that
+//! long, which shuts the server (and its one match) down; there is no
+//! per-session eviction, as a server only ever holds the one session.
+//!
+//! [`CLIENT_METHODS`]: crate::rpc::CLIENT_METHODS
+
+use crate::realtime::RealtimeRunner;
+use crate::rpc::Session;
+use crate::state::GameState;
+use serde_json::json;
+use shared::{Error, PlayerId, Result};
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response, ServerHandshake};
+use tungstenite::handshake::{HandshakeError, MidHandshake};
+use tungstenite::{Message, WebSocket};
+
+/// Longest the server sleeps between polls for client messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Longest the server sleeps between polls while no client is connected.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a new connection may take to finish its handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One connected client.
+struct Client {
+    socket: WebSocket<TcpStream>,
+    pov: PlayerId,
+}
+
+/// A connection still part-way through its handshake, carried on across
+/// polls until it completes or runs out of time.
+struct PendingClient {
+    handshake: MidHandshake<ServerHandshake<TcpStream, SeatCheck>>,
+    seat: Arc<OnceLock<PlayerId>>,
+    deadline: Instant,
+}
+
+/// Handshake callback reading the client's side from the request: accepts
+/// it (recording the side in `seat`) only if it's a player in the match.
+struct SeatCheck {
+    players: Vec<PlayerId>,
+    seat: Arc<OnceLock<PlayerId>>,
+}
+
+impl Callback for SeatCheck {
+    fn on_request(self, request: &Request, response: Response) -> std::result::Result<Response, ErrorResponse> {
+        match request.uri().query().map(pov_from_query).unwrap_or(Some(PlayerId::Player1)) {
+            Some(side) if self.players.contains(&side) => {
+                let _ = self.seat.set(side);
+                Ok(response)
+            }
+            _ => {
+                let mut rejection = ErrorResponse::new(Some("pov must be a player in this match".to_string()));
+                *rejection.status_mut() = tungstenite::http::StatusCode::BAD_REQUEST;
+                Err(rejection)
+            }
+        }
+    }
+}
+
+/// A match served to WebSocket clients (see the module docs).
+pub struct LiveServer {
+    listener: TcpListener,
+    session: Session,
+    clients: Vec<Client>,
+    handshakes: Vec<PendingClient>,
+    stream_every: u64,
+    idle_timeout: Option<Duration>,
+}
+
+impl LiveServer {
+    /// Listens on `addr` and starts a match from `seed`, streaming state
+    /// every `stream_every` ticks (at least one).
+    pub fn bind(addr: impl ToSocketAddrs, seed: u64, stream_every: u64) -> Result<Self> {
+        let listener = TcpListener::bind(addr).map_err(|e| Error::Configuration(format!("Cannot listen: {}", e)))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| Error::Internal(format!("Cannot poll the listener: {}", e)))?;
+        let mut session = Session::new();
+        session.reset(seed)?;
+        Ok(Self {
+            listener,
+            session,
+            clients: Vec::new(),
+            handshakes: Vec::new(),
+            stream_every: stream_every.max(1),
+            idle_timeout: None,
+        })
+    }
+
+    /// The address clients connect to (useful after binding port 0).
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// The session clients are talking to.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Mutable access to the session, for setting up the match (a 2v2
+    /// one, say) before clients connect.
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Stops [`run`](Self::run) once no client has been connected (or
+    /// been connecting) for `timeout`; never, with `None`, the default.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Number of connected clients.
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Serves until the session exits or has sat idle past the timeout: ticks
+    /// in real time while the match is running and someone is connected,
+    /// and keeps answering clients once it's over.
+    pub fn run(&mut self) -> Result<()> {
+        let mut clock = RealtimeRunner::new();
+        let mut last_active = Instant::now();
+        while !self.session.is_exiting() {
+            self.poll();
+            let idle = self.clients.is_empty();
+            if !idle || !self.handshakes.is_empty() {
+                last_active = Instant::now();
+            } else if self.idle_timeout.is_some_and(|timeout| last_active.elapsed() >= timeout) {
+                break;
+            }
+            clock.set_paused(idle || self.session.game().is_match_over());
+            if clock.advance(|| self.tick())? == 0 {
+                let wait = match (clock.is_paused(), idle) {
+                    (true, true) => IDLE_POLL_INTERVAL,
+                    (true, false) => POLL_INTERVAL,
+                    (false, _) => clock.next_tick_at().saturating_duration_since(Instant::now()).min(POLL_INTERVAL),
+                };
+                std::thread::sleep(wait);
+            }
+        }
+        Ok(())
+    }
+
+    /// Accepts new connections, carries on their handshakes and answers
+    /// every message waiting on the open ones. Never blocks on a slow
+    /// client: a handshake not done within [`HANDSHAKE_TIMEOUT`] is dropped,
+    /// as are clients that error or close.
+    pub fn poll(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Some(pending) = start_handshake(stream, self.session.game()) {
+                self.handshakes.push(pending);
+            }
+        }
+        let now = Instant::now();
+        for pending in std::mem::take(&mut self.handshakes) {
+            match pending.handshake.handshake() {
+                Ok(socket) => {
+                    if let Some(&pov) = pending.seat.get() {
+                        self.clients.push(Client { socket, pov });
+                    }
+                }
+                Err(HandshakeError::Interrupted(handshake)) if now < pending.deadline => {
+                    self.handshakes.push(PendingClient { handshake, ..pending });
+                }
+                // Failed, rejected or out of time
+                Err(_) => {}
+            }
+        }
+
+        let session = &mut self.session;
+        self.clients.retain_mut(|client| {
+            loop {
+                match client.socket.read() {
+                    Ok(Message::Text(text)) => {
+                        if let Some(response) = session.handle_client_message(&text, client.pov) {
+                            if !send(&mut client.socket, response) {
+                                return false;
+                            }
+                        }
+                    }
+                    Ok(Message::Close(_)) => return false,
+                    // Pings are answered by the socket itself
+                    Ok(_) => {}
+                    Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(_) => return false,
+                }
+            }
+            // Push out anything an earlier send left buffered
+            match client.socket.flush() {
+                Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => true,
+                result => result.is_ok(),
+            }
+        });
+    }
+
+    /// Runs one engine tick with the actions clients queued, then streams
+    /// state if this tick is due.
+    pub fn tick(&mut self) -> Result<()> {
+        self.session.tick()?;
+        if self.session.game().tick.is_multiple_of(self.stream_every) {
+            self.broadcast();
+        }
+        Ok(())
+    }
+
+    /// Sends every client a `state` notification from its side.
+    pub fn broadcast(&mut self) {
+        let game = self.session.game();
+        self.clients.retain_mut(|client| {
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "state",
+                "params": game.export_cr_state(client.pov),
+            });
+            send(&mut client.socket, notification.to_string())
+        });
+    }
+}
+
+/// Starts the handshake on a new connection, switched to non-blocking so
+/// [`LiveServer::poll`] can carry it on. `None` if the socket can't be
+/// polled.
+fn start_handshake(stream: TcpStream, game: &GameState) -> Option<PendingClient> {
+    stream.set_nonblocking(true).ok()?;
+    let seat = Arc::new(OnceLock::new());
+    let check = SeatCheck {
+        players: game.players.keys().copied().collect(),
+        seat: Arc::clone(&seat),
+    };
+    Some(PendingClient {
+        handshake: ServerHandshake::start(stream, check, None),
+        seat,
+        deadline: Instant::now() + HANDSHAKE_TIMEOUT,
+    })
+}
+
+/// The side named by a `pov=` query parameter (Player1 if there's none),
+/// or `None` if it names no player.
+fn pov_from_query(query: &str) -> Option<PlayerId> {
+    match query.split('&').find_map(|pair| pair.strip_prefix("pov=")) {
+        None => Some(PlayerId::Player1),
+        Some("Player1") => Some(PlayerId::Player1),
+        Some("Player2") => Some(PlayerId::Player2),
+        Some("Player3") => Some(PlayerId::Player3),
+        Some("Player4") => Some(PlayerId::Player4),
+        Some(_) => None,
+    }
+}
+
+/// Queues `text` on the socket. False if the client is gone; a send that
+/// would block stays buffered until the next flush.
+fn send(socket: &mut WebSocket<TcpStream>, text: String) -> bool {
+    match socket.send(Message::Text(text)) {
+        Ok(()) => true,
+        Err(tungstenite::Error::Io(e)) => e.kind() == io::ErrorKind::WouldBlock,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_clients_get_answers_and_streamed_state() {
+        let mut server = LiveServer::bind("127.0.0.1:0", 4, 10).unwrap();
+        let url = format!("ws://{}/?pov=Player2", server.local_addr().unwrap());
+        let before = serde_json::to_value(server.session().game().export_cr_state(PlayerId::Player2).ally_cycle).unwrap();
+
+        let client = std::thread::spawn(move || {
+            let (mut socket, _) = tungstenite::connect(url).unwrap();
+            let act = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "act",
+                "params": { "actions": [{ "player": "Player2", "action": { "Play": { "slot": 0, "tile": 67 } } }] },
+            });
+            socket.send(Message::Text(act.to_string())).unwrap();
+            let mut messages = Vec::new();
+            while messages.len() < 3 {
+                if let Message::Text(text) = socket.read().unwrap() {
+                    messages.push(serde_json::from_str::<Value>(&text).unwrap());
+                }
+            }
+            messages
+        });
+
+        // Answer the client's act before running any ticks
+        while server.session().queued().is_empty() {
+            server.poll();
+        }
+        for _ in 0..20 {
+            server.tick().unwrap();
+        }
+        let messages = client.join().unwrap();
+
+        assert_eq!(messages[0]["id"], json!(1));
+        assert_eq!(messages[0]["result"]["queued"], json!(1));
+        // Streamed every tenth tick, from the client's side
+        let times: Vec<&Value> = messages[1..].iter().map(|m| &m["params"]["t_ms"]).collect();
+        assert!(times[0].as_u64() < times[1].as_u64());
+        for message in &messages[1..] {
+            assert_eq!(message["method"], "state");
+            let cycle = &message["params"]["ally_cycle"];
+            assert_eq!(cycle["cards_played"], json!(1));
+            // The played slot drew the next card, and the cycle moved on
+            assert_eq!(cycle["hand"][0], before["next"]);
+            assert_eq!(cycle["hand"].as_array().unwrap()[1..], before["hand"].as_array().unwrap()[1..]);
+            assert_ne!(cycle["next"], before["next"]);
+            assert_ne!(cycle["cycle_position"], before["cycle_position"]);
+        }
+        let game = server.session().game();
+        assert_eq!(game.tick, 20);
+        assert_eq!(game.card_plays.len(), 1);
+        assert_eq!((game.card_plays[0].player, game.card_plays[0].tick), (PlayerId::Player2, 0));
+    }
+
+    #[test]
+    fn test_slow_handshake_does_not_stall_polling() {
+        let mut server = LiveServer::bind("127.0.0.1:0", 4, 10).unwrap();
+        let addr = server.local_addr().unwrap();
+
+        // Connected, but never sends its upgrade request
+        let _silent = TcpStream::connect(addr).unwrap();
+        while server.handshakes.is_empty() {
+            server.poll();
+        }
+        let started = Instant::now();
+        server.poll();
+        assert!(started.elapsed() < HANDSHAKE_TIMEOUT / 4);
+        assert_eq!(server.client_count(), 0);
+
+        // A client arriving meanwhile still gets through
+        let url = format!("ws://{}/?pov=Player2", addr);
+        // (handing its socket back, so it stays open until joined)
+        let client = std::thread::spawn(move || tungstenite::connect(url).ok().map(|(socket, _)| socket));
+        while server.client_count() == 0 {
+            server.poll();
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        assert!(client.join().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_idle_server_sleeps_then_stops() {
+        let mut server = LiveServer::bind("127.0.0.1:0", 4, 10).unwrap();
+        server.set_idle_timeout(Some(Duration::from_millis(100)));
+        let started = Instant::now();
+        server.run().unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(server.session().game().tick, 0);
+    }
+
+    #[test]
+    fn test_pov_query() {
+        assert_eq!(pov_from_query("pov=Player2"), Some(PlayerId::Player2));
+        assert_eq!(pov_from_query("x=1&pov=Player1"), Some(PlayerId::Player1));
+        assert_eq!(pov_from_query("x=1"), Some(PlayerId::Player1));
+        assert_eq!(pov_from_query("pov=Player4"), Some(PlayerId::Player4));
+        assert_eq!(pov_from_query("pov=Player5"), None);
+    }
+}
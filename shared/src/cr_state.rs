@@ -6,6 +6,10 @@ pub struct Tower {
     pub x: f32,
     pub y: f32,
     pub hp_frac: f32,    // 0.0–1.0
+    /// Damage taken over the last second as a fraction of max HP; above
+    /// zero while under attack.
+    #[serde(default)]
+    pub recent_damage_frac: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -15,18 +19,183 @@ pub struct Unit {
     pub y: f32,
     pub vx: f32,
     pub vy: f32,
+    /// Position at the previous tick, for renderers interpolating between frames.
+    #[serde(default)]
+    pub prev_x: f32,
+    #[serde(default)]
+    pub prev_y: f32,
+    /// Facing direction in radians (0 = +x, counter-clockwise).
+    #[serde(default)]
+    pub facing: f32,
+    /// Remaining shield points, separate from HP (0 if the unit has none).
+    #[serde(default)]
+    pub shield: f32,
+    /// Stunned or frozen: can neither move nor attack.
+    #[serde(default)]
+    pub is_stunned: bool,
+    #[serde(default)]
+    pub is_raged: bool,
+    /// Mid-jump over the river (Hog Rider, Ram Rider).
+    #[serde(default)]
+    pub is_jumping: bool,
+    /// Entity id, stable for the unit's lifetime.
+    #[serde(default)]
+    pub id: u32,
+    /// Name of the card that deployed the unit (cards are keyed by name).
+    #[serde(default)]
+    pub card: String,
+    /// Current HP as a fraction of max HP, 0.0–1.0.
+    #[serde(default)]
+    pub hp_frac: f32,
+    /// Card level the unit was deployed at.
+    #[serde(default)]
+    pub level: u32,
+    #[serde(default)]
+    pub is_air: bool,
+    /// Entity id of the unit's current target, if it has one.
+    #[serde(default)]
+    pub target_id: Option<u32>,
+    /// Damage taken over the last second as a fraction of max HP.
+    #[serde(default)]
+    pub recent_damage_frac: f32,
+}
+
+/// A card in hand or coming up next.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct HandCard {
+    pub card: String,    // card name, which is also its id
+    pub elixir_cost: f32,
+    /// Level the card plays at, from the deck.
+    #[serde(default)]
+    pub level: u32,
+}
+
+/// A player's hand and how far through their deck cycle they are.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CardCycle {
+    pub hand: Vec<HandCard>,   // by hand slot, len = dims::HAND_SIZE
+    /// The card that replaces the next one played.
+    #[serde(default)]
+    pub next: Option<HandCard>,
+    /// Position of `next` in the deck, 0..dims::DECK_SIZE.
+    pub cycle_position: usize,
+    /// Cards played so far this match.
+    pub cards_played: u32,
+    /// Hand slot the player is holding (selected but not yet played).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected: Option<usize>,
+}
+
+/// A damage spell that has been cast but not landed yet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingSpell {
+    pub owner: String,   // "ALLY" or "ENEMY"
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub time_to_impact: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LegalMasks {
-    pub cards: Vec<bool>,       // len = 8
-    pub tiles_flat: Vec<bool>,  // len = place_W * place_H
+    /// Hand slots holding a card the POV can afford. len = dims::HAND_SLOTS.
+    pub cards: Vec<bool>,
+    /// Tiles an ordinary troop may be placed on, from the POV's side: its
+    /// deploy zone plus any pocket opened by a fallen enemy princess tower.
+    /// len = grid_w * grid_h, row-major.
+    pub tiles_flat: Vec<bool>,
+    /// Per hand slot, the tiles that slot's card may be placed on (spells
+    /// reach past the deploy zone); empty for an empty slot. Slots without
+    /// an entry fall back to `tiles_flat`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hand_tiles: Vec<Vec<bool>>,
+    /// Placement grid the match uses (dims::PLACEMENT_GRID_W x _H unless configured).
+    #[serde(default = "default_grid_w")]
+    pub grid_w: usize,
+    #[serde(default = "default_grid_h")]
+    pub grid_h: usize,
+    /// Passing the step (the `WAIT` command, flat action id 0) is always
+    /// legal.
+    #[serde(default = "default_no_op")]
+    pub no_op: bool,
+}
+
+impl LegalMasks {
+    /// One flag per flat agent action id: the no-op first, then every hand
+    /// slot's tiles in turn (`1 + slot * tiles + tile`), each legal when
+    /// its card is playable and the card may be placed on the tile.
+    pub fn action_mask(&self) -> Vec<bool> {
+        let tiles = self.tiles_flat.len();
+        let mut mask = vec![self.no_op];
+        for (slot, &card) in self.cards.iter().enumerate() {
+            let slot_tiles = self.hand_tiles.get(slot).unwrap_or(&self.tiles_flat);
+            mask.extend((0..tiles).map(|tile| card && slot_tiles.get(tile).copied().unwrap_or(false)));
+        }
+        mask
+    }
+}
+
+fn default_no_op() -> bool {
+    true
+}
+
+fn default_grid_w() -> usize {
+    crate::dims::PLACEMENT_GRID_W
+}
+
+fn default_grid_h() -> usize {
+    crate::dims::PLACEMENT_GRID_H
+}
+
+/// A crown taken during the match, with what earned it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Crown {
+    pub owner: String,   // who took it: "ALLY" or "ENEMY"
+    pub tick: u64,
+    pub tower: String,   // "King", "LeftPrincess" or "RightPrincess"
+    /// Crowns the tower was worth (a King makes up the rest of three).
+    pub crowns: u32,
+    /// Entity id and card of whatever landed the last hit, when known.
+    #[serde(default)]
+    pub source_id: Option<u32>,
+    #[serde(default)]
+    pub card: Option<String>,
+}
+
+/// Summary of a finished match, attached to terminal frames only.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchResult {
+    pub winner: String,      // "ALLY", "ENEMY" or "DRAW"
+    pub ally_crowns: u32,
+    pub enemy_crowns: u32,
+    pub end_reason: String,  // "king_destroyed", "time_up", "first_crown" or "timeout"
+    /// Final tower HP in King, LeftPrincess, RightPrincess order.
+    pub ally_tower_hp: Vec<f32>,
+    pub enemy_tower_hp: Vec<f32>,
+    pub duration_s: f32,
+    /// Every crown of the match, in the order taken.
+    #[serde(default)]
+    pub crowns: Vec<Crown>,
+}
+
+/// Everything needed to reconstruct an episode, attached to its first frame.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MatchMetadata {
+    pub seed: u64,
+    pub patch_id: String,
+    pub game_mode: String,
+    /// Order-independent hashes of the starting decks (0 if none was set).
+    pub ally_deck_hash: u64,
+    pub enemy_deck_hash: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CRState {
     pub t_ms: u64,
     pub ally_elixir: f32,
+    /// The opponent's elixir as inferred from regen and their observed plays.
+    #[serde(default)]
+    pub estimated_enemy_elixir: f32,
     pub time_left: f32,
     pub overtime: bool,
 
@@ -34,6 +203,21 @@ pub struct CRState {
     pub enemy_towers: Vec<Tower>,
     pub ally_units: Vec<Unit>,
     pub enemy_units: Vec<Unit>,
+    #[serde(default)]
+    pub pending_spells: Vec<PendingSpell>,
+
+    #[serde(default)]
+    pub ally_cycle: CardCycle,
+    /// The opponent's hand and cycle, only when the match exports it
+    /// (full-information experiments).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enemy_cycle: Option<CardCycle>,
+    /// The teammate's elixir, in 2v2 only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teammate_elixir: Option<f32>,
+    /// The teammate's hand and cycle, in 2v2 only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teammate_cycle: Option<CardCycle>,
 
     pub legal: LegalMasks,
 
@@ -42,4 +226,21 @@ pub struct CRState {
 
     pub enemy_tower_hp_drop: f32,
     pub ally_tower_hp_drop: f32,
+    /// Crowns taken so far, in order; new entries mark crown checkpoints.
+    #[serde(default)]
+    pub crowns: Vec<Crown>,
+
+    /// Present only once the match is over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<MatchResult>,
+
+    /// Present only on the episode's first frame: the match at tick 0,
+    /// before any tick has run (as a reset returns it).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MatchMetadata>,
+
+    /// The exported side's reward for the step that led here, on frames
+    /// returned from a step (the engine's `reward` module).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward: Option<f32>,
 }
\ No newline at end of file
@@ -0,0 +1,33 @@
+//! Grid, arena, and deck dimensions shared by the engine and the exported
+//! agent state.
+//!
+//! Every consumer reads these instead of hardcoding its own copy, so
+//! changing one value cannot silently desync the engine from `CRState`.
+
+/// Arena width in tiles.
+pub const ARENA_WIDTH: u32 = 32;
+
+/// Arena height in tiles.
+pub const ARENA_HEIGHT: u32 = 18;
+
+/// Default width of the agent placement grid (`tiles_flat` is `W * H`,
+/// row-major); a match may configure another size.
+pub const PLACEMENT_GRID_W: usize = 16;
+
+/// Default height of the agent placement grid.
+pub const PLACEMENT_GRID_H: usize = 9;
+
+/// Number of cards in a deck.
+pub const DECK_SIZE: usize = 8;
+
+/// Number of cards in hand.
+pub const HAND_SIZE: usize = 4;
+
+/// Length of the card legality mask (`LegalMasks::cards`).
+pub const HAND_SLOTS: usize = 8;
+
+/// Starting HP of the King tower.
+pub const KING_TOWER_HP: f32 = 2400.0;
+
+/// Starting HP of each Princess tower.
+pub const PRINCESS_TOWER_HP: f32 = 1400.0;
@@ -22,6 +22,46 @@ impl Position {
         (dx * dx + dy * dy).sqrt()
     }
 
+    /// Squared distance to another position. Cheaper than [`distance_to`]
+    /// when only comparing distances.
+    ///
+    /// [`distance_to`]: Position::distance_to
+    pub fn distance_squared(&self, other: &Position) -> f32 {
+        let dx = self.x - other.x;
+        let dy = self.y - other.y;
+        dx * dx + dy * dy
+    }
+
+    /// Component-wise sum.
+    pub fn add(&self, other: &Position) -> Position {
+        Position::new(self.x + other.x, self.y + other.y)
+    }
+
+    /// Returns this position offset by `velocity` applied for `dt` seconds.
+    pub fn advance(&self, velocity: &Velocity, dt: f32) -> Position {
+        Position::new(self.x + velocity.x * dt, self.y + velocity.y * dt)
+    }
+
+    /// Multiplies both components by `factor`.
+    pub fn scale(&self, factor: f32) -> Position {
+        Position::new(self.x * factor, self.y * factor)
+    }
+
+    /// Linear interpolation: `t = 0` gives `self`, `t = 1` gives `other`.
+    pub fn lerp(&self, other: &Position, t: f32) -> Position {
+        Position::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+
+    /// Dot product, treating both positions as vectors.
+    pub fn dot(&self, other: &Position) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Clamps this position into the rectangle spanned by `min` and `max`.
+    pub fn clamp_to_rect(&self, min: &Position, max: &Position) -> Position {
+        Position::new(self.x.clamp(min.x, max.x), self.y.clamp(min.y, max.y))
+    }
+
     /// Returns the direction vector (normalized) from this position to another.
     /// Returns (0, 0) if positions are the same.
     pub fn direction_to(&self, other: &Position) -> (f32, f32) {
@@ -69,22 +109,77 @@ impl Velocity {
     pub fn zero() -> Self {
         Self { x: 0.0, y: 0.0 }
     }
+
+    /// Component-wise sum.
+    pub fn add(&self, other: &Velocity) -> Velocity {
+        Velocity::new(self.x + other.x, self.y + other.y)
+    }
+
+    /// Multiplies both components by `factor`.
+    pub fn scale(&self, factor: f32) -> Velocity {
+        Velocity::new(self.x * factor, self.y * factor)
+    }
+
+    /// Linear interpolation: `t = 0` gives `self`, `t = 1` gives `other`.
+    pub fn lerp(&self, other: &Velocity, t: f32) -> Velocity {
+        Velocity::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+
+    pub fn dot(&self, other: &Velocity) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length_squared(&self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
 }
 
 /// Player identifier.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// A 1v1 match has Player1 (left half) and Player2 (right half). In 2v2,
+/// Player3 joins Player1's team and Player4 joins Player2's: teammates
+/// share their side's towers and half of the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum PlayerId {
     Player1,
     Player2,
+    Player3,
+    Player4,
 }
 
 impl PlayerId {
+    /// The lead of the opposing team (Player1 or Player2), who owns its
+    /// towers.
     pub fn opponent(&self) -> Self {
         match self {
-            PlayerId::Player1 => PlayerId::Player2,
-            PlayerId::Player2 => PlayerId::Player1,
+            PlayerId::Player1 | PlayerId::Player3 => PlayerId::Player2,
+            PlayerId::Player2 | PlayerId::Player4 => PlayerId::Player1,
+        }
+    }
+
+    /// The lead of this player's own team (Player1 or Player2).
+    pub fn side(&self) -> Self {
+        self.opponent().opponent()
+    }
+
+    /// The other player on this player's team in 2v2.
+    pub fn teammate(&self) -> Self {
+        match self {
+            PlayerId::Player1 => PlayerId::Player3,
+            PlayerId::Player2 => PlayerId::Player4,
+            PlayerId::Player3 => PlayerId::Player1,
+            PlayerId::Player4 => PlayerId::Player2,
         }
     }
+
+    /// True for the same player or a teammate.
+    pub fn is_ally_of(&self, other: PlayerId) -> bool {
+        self.side() == other.side()
+    }
 }
 
 /// Common result type for engine operations.
@@ -101,9 +196,34 @@ pub enum Error {
 
     #[error("Configuration error: {0}")]
     Configuration(String),
+
+    /// An internal invariant failed (a panic caught at an API boundary).
+    #[error("Internal error: {0}")]
+    Internal(String),
 }
 
 
 pub mod cr_state;
-
-pub use cr_state::{CRState, Tower, Unit, LegalMasks};
\ No newline at end of file
+pub mod dims;
+pub mod transform;
+
+pub use cr_state::{CardCycle, CRState, Crown, HandCard, Tower, Unit, LegalMasks, MatchMetadata, MatchResult, PendingSpell};
+pub use transform::{Homography, ScreenTransform};
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_math() {
+        let a = Position::new(1.0, 2.0);
+        let b = Position::new(4.0, 6.0);
+        assert_eq!(a.distance_squared(&b), 25.0);
+        assert_eq!(a.lerp(&b, 0.5), Position::new(2.5, 4.0));
+        assert_eq!(a.add(&b).scale(2.0), Position::new(10.0, 16.0));
+        assert_eq!(a.dot(&b), 16.0);
+        assert_eq!(a.advance(&Velocity::new(2.0, -2.0), 0.5), Position::new(2.0, 1.0));
+
+        let clamped = Position::new(-3.0, 40.0).clamp_to_rect(&Position::new(0.0, 0.0), &Position::new(32.0, 18.0));
+        assert_eq!(clamped, Position::new(0.0, 18.0));
+    }
+}
@@ -0,0 +1,200 @@
+//! Coordinate transforms between engine tiles and normalized screen space.
+//!
+//! Engine positions are in tiles (`0..ARENA_WIDTH` along the lane,
+//! `0..ARENA_HEIGHT` across it). Vision pipelines usually work in normalized
+//! `[0, 1]²` image coordinates, with the arena seen in perspective. The
+//! transform goes through normalized arena coordinates and a 3x3
+//! homography, which covers scaling, rotation, cropping and camera tilt.
+
+use crate::dims::{ARENA_HEIGHT, ARENA_WIDTH};
+use crate::Position;
+use serde::{Deserialize, Serialize};
+
+/// Below this the projective divisor is treated as zero (point at infinity).
+const DEGENERATE_EPSILON: f64 = 1e-9;
+
+/// Tile coordinates to normalized arena coordinates (`[0, 1]²` over the
+/// arena's width and height).
+pub fn tile_to_normalized(pos: &Position) -> (f32, f32) {
+    (pos.x / ARENA_WIDTH as f32, pos.y / ARENA_HEIGHT as f32)
+}
+
+/// Normalized arena coordinates back to tile coordinates.
+pub fn normalized_to_tile(u: f32, v: f32) -> Position {
+    Position::new(u * ARENA_WIDTH as f32, v * ARENA_HEIGHT as f32)
+}
+
+/// A planar projective transform, stored row-major.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Homography {
+    pub m: [[f64; 3]; 3],
+}
+
+impl Homography {
+    pub fn identity() -> Self {
+        Self {
+            m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+
+    /// Portrait view as in the live game: the screen's `u` runs across the
+    /// arena and `v` runs down it, with the arena's far end (`x = 1`) at the
+    /// top of the screen. Maps screen to normalized arena coordinates.
+    pub fn portrait() -> Self {
+        Self {
+            m: [[0.0, -1.0, 1.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+
+    /// Solves for the homography sending each `src[i]` to `dst[i]`.
+    /// Returns `None` if three of the points are collinear.
+    pub fn from_correspondences(src: [(f32, f32); 4], dst: [(f32, f32); 4]) -> Option<Self> {
+        // Eight equations in the eight unknowns h00..h21 (h22 = 1)
+        let mut a = [[0.0f64; 9]; 8];
+        for (i, (&(x, y), &(u, v))) in src.iter().zip(dst.iter()).enumerate() {
+            let (x, y, u, v) = (x as f64, y as f64, u as f64, v as f64);
+            a[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+            a[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+        }
+
+        // Gaussian elimination with partial pivoting
+        for col in 0..8 {
+            let pivot = (col..8).max_by(|&r, &s| a[r][col].abs().total_cmp(&a[s][col].abs()))?;
+            if a[pivot][col].abs() < DEGENERATE_EPSILON {
+                return None;
+            }
+            a.swap(col, pivot);
+            let pivot_row = a[col];
+            for (r, row) in a.iter_mut().enumerate() {
+                if r != col {
+                    let factor = row[col] / pivot_row[col];
+                    for (value, p) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                        *value -= factor * p;
+                    }
+                }
+            }
+        }
+        let h: Vec<f64> = a.iter().enumerate().map(|(i, row)| row[8] / row[i]).collect();
+
+        Some(Self {
+            m: [[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]],
+        })
+    }
+
+    /// Applies the transform. Returns `None` for points sent to infinity.
+    pub fn apply(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (x, y) = (x as f64, y as f64);
+        let row = |r: [f64; 3]| r[0] * x + r[1] * y + r[2];
+        let w = row(self.m[2]);
+        if w.abs() < DEGENERATE_EPSILON {
+            return None;
+        }
+        Some(((row(self.m[0]) / w) as f32, (row(self.m[1]) / w) as f32))
+    }
+
+    /// The inverse transform, or `None` if this one is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let m = &self.m;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+        let adj = [
+            [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+            [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+            [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+        ];
+        let det = m[0][0] * adj[0][0] + m[0][1] * adj[1][0] + m[0][2] * adj[2][0];
+        if det.abs() < DEGENERATE_EPSILON {
+            return None;
+        }
+        Some(Self {
+            m: adj.map(|row| row.map(|v| v / det)),
+        })
+    }
+}
+
+impl Default for Homography {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Maps between screen coordinates (normalized `[0, 1]²` image space) and
+/// engine tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScreenTransform {
+    /// Screen to normalized arena coordinates.
+    pub screen_to_arena: Homography,
+}
+
+impl ScreenTransform {
+    pub fn new(screen_to_arena: Homography) -> Self {
+        Self { screen_to_arena }
+    }
+
+    /// Fits the transform from four screen points (e.g. detected tower
+    /// centers) and the tile positions they correspond to.
+    pub fn from_landmarks(screen: [(f32, f32); 4], tiles: [Position; 4]) -> Option<Self> {
+        let arena = tiles.map(|p| tile_to_normalized(&p));
+        Homography::from_correspondences(screen, arena).map(Self::new)
+    }
+
+    /// Screen point to tile position.
+    pub fn screen_to_tile(&self, u: f32, v: f32) -> Option<Position> {
+        self.screen_to_arena.apply(u, v).map(|(x, y)| normalized_to_tile(x, y))
+    }
+
+    /// Tile position to screen point.
+    pub fn tile_to_screen(&self, pos: &Position) -> Option<(f32, f32)> {
+        let (x, y) = tile_to_normalized(pos);
+        self.screen_to_arena.inverse()?.apply(x, y)
+    }
+}
+
+impl Default for ScreenTransform {
+    fn default() -> Self {
+        Self::new(Homography::identity())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4
+    }
+
+    #[test]
+    fn test_portrait_round_trip() {
+        let transform = ScreenTransform::new(Homography::portrait());
+        // Bottom-left of the screen is the near end of the arena, y = 0
+        let near = transform.screen_to_tile(0.0, 1.0).unwrap();
+        assert!(close((near.x, near.y), (0.0, 0.0)));
+
+        let king = Position::new(3.0, 9.0);
+        let screen = transform.tile_to_screen(&king).unwrap();
+        assert!(close(screen, (0.5, 1.0 - 3.0 / 32.0)));
+        let back = transform.screen_to_tile(screen.0, screen.1).unwrap();
+        assert!(close((back.x, back.y), (king.x, king.y)));
+    }
+
+    #[test]
+    fn test_fit_from_landmarks() {
+        // A tilted camera: the far end of the arena appears narrower
+        let screen = [(0.1, 0.9), (0.9, 0.9), (0.3, 0.1), (0.7, 0.1)];
+        let tiles = [
+            Position::new(0.0, 0.0),
+            Position::new(0.0, 18.0),
+            Position::new(32.0, 0.0),
+            Position::new(32.0, 18.0),
+        ];
+        let transform = ScreenTransform::from_landmarks(screen, tiles).unwrap();
+        for (s, t) in screen.iter().zip(tiles.iter()) {
+            let tile = transform.screen_to_tile(s.0, s.1).unwrap();
+            assert!(close((tile.x, tile.y), (t.x, t.y)), "{:?} -> {:?}", s, tile);
+            assert!(close(transform.tile_to_screen(t).unwrap(), *s));
+        }
+
+        let collinear = [(0.0, 0.0), (0.5, 0.5), (1.0, 1.0), (0.0, 1.0)];
+        assert!(ScreenTransform::from_landmarks(collinear, tiles).is_none());
+    }
+}